@@ -0,0 +1,119 @@
+//! Compares command throughput with the old global `Mutex<PostgresService>` against
+//! sharing the service directly, using the real data-access code.
+//!
+//! Needs a throwaway Postgres (migrations run automatically), e.g.
+//!
+//!     docker run -d --rm -p 5432:5432 -e POSTGRES_PASSWORD=postgres postgres:16
+//!     LOAD_TEST_PG="host=localhost user=postgres password=postgres" \
+//!         cargo run --release --example load_test
+//!
+//! LOAD_TEST_WORKERS and LOAD_TEST_OPS (per worker) tune the run. Every run seeds
+//! its own guild and deletes it again afterwards.
+#![allow(dead_code)]
+
+#[path = "../src/dataaccess/mod.rs"]
+mod dataaccess;
+
+use std::env;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use tokio::sync::Mutex;
+
+use dataaccess::bbp_store::BbpStore;
+use dataaccess::models::{HistoryFilter, Page, PointKind, Standings, TimeWindow};
+use dataaccess::postgres_service::PostgresService;
+
+const USERS: i64 = 50;
+
+#[derive(Clone)]
+enum Shared {
+    // How Data.db used to look: every command held the lock for its whole run
+    Locked(Arc<Mutex<PostgresService>>),
+    Direct(Arc<PostgresService>),
+}
+
+impl Shared {
+    async fn run_op(&self, guild_id: i64, op: u64) {
+        match self {
+            Shared::Locked(db) => {
+                let db = db.lock().await;
+                command(&*db, guild_id, op).await;
+            }
+            Shared::Direct(db) => command(db.as_ref(), guild_id, op).await,
+        }
+    }
+}
+
+// A rough mix of what the bot sees: mostly reads, some issuances.
+async fn command(db: &dyn BbpStore, guild_id: i64, op: u64) {
+    let user = (op % USERS as u64) as i64 + 1;
+    let other = ((op / 7) % USERS as u64) as i64 + 1;
+
+    let page = Page { offset: 0, limit: 10 };
+    let result = match op % 4 {
+        0 => db.get_leaderboard(guild_id, Standings::Live(TimeWindow::default()), page).await.map(|_| ()),
+        1 => db.get_user_history(guild_id, user, HistoryFilter::default(), page).await.map(|_| ()),
+        2 => db.get_user_by_discord_id_with_rank(guild_id, user).await.map(|_| ()),
+        _ => db.issue_point(guild_id, PointKind::Bbp, 1, user, other, "load test").await.map(|_| ()),
+    };
+
+    if let Err(e) = result {
+        panic!("Operation {} failed: {}", op, e);
+    }
+}
+
+async fn run(shared: Shared, guild_id: i64, workers: u64, ops: u64) -> Duration {
+    let started = Instant::now();
+
+    let handles: Vec<_> = (0..workers).map(|worker| {
+        let shared = shared.clone();
+        tokio::spawn(async move {
+            // Cheap LCG so each worker gets a different but repeatable sequence
+            let mut op = worker.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+            for _ in 0..ops {
+                shared.run_op(guild_id, op >> 16).await;
+                op = op.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+            }
+        })
+    }).collect();
+
+    for handle in handles {
+        handle.await.expect("Worker panicked");
+    }
+
+    started.elapsed()
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    env_logger::init();
+    let constr = env::var("LOAD_TEST_PG").expect("Expected LOAD_TEST_PG to point at a throwaway postgres");
+    let workers: u64 = env::var("LOAD_TEST_WORKERS").map(|v| v.parse().unwrap()).unwrap_or(15);
+    let ops: u64 = env::var("LOAD_TEST_OPS").map(|v| v.parse().unwrap()).unwrap_or(200);
+
+    let db = PostgresService::new(&constr).await?;
+    let guild_id = -(chrono::Utc::now().timestamp_millis());
+    for user in 1..=USERS {
+        db.add_user(guild_id, user, &format!("load{}", user), &format!("Load {}", user)).await?;
+    }
+
+    let db = Arc::new(db);
+    let locked = run(Shared::Locked(Arc::new(Mutex::new(PostgresService::clone(&db)))), guild_id, workers, ops).await;
+    let direct = run(Shared::Direct(db.clone()), guild_id, workers, ops).await;
+
+    let total = (workers * ops) as f64;
+    println!("{} workers x {} operations", workers, ops);
+    println!("global mutex: {:>8.1} ops/s ({:?})", total / locked.as_secs_f64(), locked);
+    println!("shared pool:  {:>8.1} ops/s ({:?})", total / direct.as_secs_f64(), direct);
+    println!("speedup:      {:>8.2}x", locked.as_secs_f64() / direct.as_secs_f64());
+
+    let conn = db.pool.get().await?;
+    conn.batch_execute(&format!(
+        "DELETE FROM public.\"Bbps\" WHERE \"GuildID\" = {id};
+         DELETE FROM public.\"Gbps\" WHERE \"GuildID\" = {id};
+         DELETE FROM public.\"Users\" WHERE \"GuildID\" = {id};",
+        id = guild_id)).await?;
+
+    Ok(())
+}
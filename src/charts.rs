@@ -0,0 +1,185 @@
+//! PNG charts rendered in-process. Everything here is pure: the same input
+//! always gives the same bytes, since the font is embedded instead of looked up
+//! on the host.
+use std::sync::OnceLock;
+
+use image::codecs::png::PngEncoder;
+use image::{ColorType, ImageEncoder};
+use plotters::prelude::*;
+use plotters::style::{register_font, FontStyle};
+
+pub type ChartError = Box<dyn std::error::Error + Send + Sync>;
+
+const WIDTH: u32 = 800;
+const HEIGHT: u32 = 480;
+
+const FONT: &str = "sans-serif";
+static FONT_BYTES: &[u8] = include_bytes!("../assets/fonts/DejaVuSans.ttf");
+/// Whether registering the font worked, it's only tried once
+static FONT_REGISTERED: OnceLock<bool> = OnceLock::new();
+
+const BBP_RED: RGBColor = RGBColor(0xdd, 0x2e, 0x44);
+const GBP_GREEN: RGBColor = RGBColor(0x78, 0xb1, 0x59);
+
+/// One user's bar on the leaderboard chart.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Bar {
+    pub label: String,
+    pub value: f64,
+}
+
+/// Net scores as bars, best first, red above zero and green below.
+pub fn leaderboard_png(title: &str, bars: &[Bar]) -> Result<Vec<u8>, ChartError> {
+    let (low, high) = value_range(bars.iter().map(|bar| bar.value));
+
+    render(|root| {
+        let mut chart = ChartBuilder::on(&root)
+            .caption(title, (FONT, 24))
+            .margin(16)
+            .x_label_area_size(40)
+            .y_label_area_size(48)
+            .build_cartesian_2d((0..bars.len().max(1) - 1).into_segmented(), low..high)?;
+
+        chart.configure_mesh()
+            .disable_x_mesh()
+            .x_label_formatter(&|segment| match segment {
+                SegmentValue::CenterOf(i) => bars.get(*i).map(|bar| bar.label.clone()).unwrap_or_default(),
+                _ => String::new(),
+            })
+            .label_style((FONT, 14))
+            .draw()?;
+
+        chart.draw_series(bars.iter().enumerate().map(|(i, bar)| {
+            let colour = if bar.value >= 0.0 { BBP_RED } else { GBP_GREEN };
+            let mut rect = Rectangle::new([(SegmentValue::Exact(i), 0.0), (SegmentValue::Exact(i + 1), bar.value)], colour.filled());
+            rect.set_margin(0, 0, 8, 8);
+            rect
+        }))?;
+
+        Ok(())
+    })
+}
+
+/// A user's running net score, one point per day.
+pub fn trend_png(title: &str, days: &[(chrono::NaiveDate, i32)]) -> Result<Vec<u8>, ChartError> {
+    let (first, last) = match (days.first(), days.last()) {
+        (Some(first), Some(last)) => (first.0, last.0.max(first.0 + chrono::Duration::days(1))),
+        _ => return Err("A trend needs at least one day".into()),
+    };
+    let (low, high) = value_range(days.iter().map(|(_, score)| *score as f64));
+
+    render(|root| {
+        let mut chart = ChartBuilder::on(&root)
+            .caption(title, (FONT, 24))
+            .margin(16)
+            .margin_right(40)
+            .x_label_area_size(40)
+            .y_label_area_size(48)
+            .build_cartesian_2d(first..last, low..high)?;
+
+        chart.configure_mesh()
+            .x_labels(6)
+            .x_label_formatter(&|day| day.format("%Y-%m-%d").to_string())
+            .label_style((FONT, 14))
+            .draw()?;
+
+        // A single day still gets a line, held flat across the axis
+        let held = days.last().filter(|_| days.len() == 1).map(|(_, score)| (last, *score));
+        let points = days.iter().copied().chain(held).map(|(day, score)| (day, score as f64));
+        chart.draw_series(LineSeries::new(points, BBP_RED.stroke_width(3)))?;
+
+        Ok(())
+    })
+}
+
+/// Always includes zero, with some headroom so bars don't touch the frame.
+fn value_range(values: impl Iterator<Item = f64>) -> (f64, f64) {
+    let (low, high) = values.fold((0.0_f64, 0.0_f64), |(low, high), value| (low.min(value), high.max(value)));
+    let pad = ((high - low) * 0.1).max(1.0);
+
+    (if low < 0.0 { low - pad } else { 0.0 }, high + pad)
+}
+
+fn render(draw: impl FnOnce(DrawingArea<BitMapBackend, plotters::coord::Shift>) -> Result<(), ChartError>) -> Result<Vec<u8>, ChartError> {
+    if !*FONT_REGISTERED.get_or_init(|| register_font(FONT, FontStyle::Normal, FONT_BYTES).is_ok()) {
+        return Err("The embedded font couldn't be loaded".into());
+    }
+
+    let mut pixels = vec![0; (WIDTH * HEIGHT * 3) as usize];
+    {
+        let root = BitMapBackend::with_buffer(&mut pixels, (WIDTH, HEIGHT)).into_drawing_area();
+        root.fill(&WHITE)?;
+        draw(root.clone())?;
+        root.present()?;
+    }
+
+    let mut png = Vec::new();
+    PngEncoder::new(&mut png).write_image(&pixels, WIDTH, HEIGHT, ColorType::Rgb8)?;
+
+    Ok(png)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Compares decoded pixels, so a different PNG encoder doesn't fail the test.
+    /// Run with `UPDATE_GOLDEN=1` to write the golden file instead.
+    fn assert_golden(png: &[u8], name: &str) {
+        let path = format!("{}/tests/golden/{}", env!("CARGO_MANIFEST_DIR"), name);
+        if std::env::var_os("UPDATE_GOLDEN").is_some() {
+            std::fs::write(&path, png).unwrap();
+        }
+        let golden = std::fs::read(&path).unwrap_or_else(|e| panic!("Couldn't read {}: {}", path, e));
+
+        let decode = |png: &[u8]| image::load_from_memory_with_format(png, image::ImageFormat::Png).unwrap().to_rgb8();
+        let (actual, expected) = (decode(png), decode(&golden));
+        assert_eq!(actual.dimensions(), expected.dimensions());
+        assert!(actual.as_raw() == expected.as_raw(), "{} doesn't match the rendered chart", name);
+    }
+
+    fn day(day: u32) -> chrono::NaiveDate {
+        chrono::NaiveDate::from_ymd_opt(2024, 5, day).unwrap()
+    }
+
+    #[test]
+    fn leaderboard_matches_the_golden_chart() {
+        let bars = [
+            Bar { label: "Alice".to_string(), value: 5.0 },
+            Bar { label: "Bob".to_string(), value: 2.5 },
+            Bar { label: "Carol".to_string(), value: -3.0 },
+        ];
+
+        let png = leaderboard_png("Leaderboard, this week", &bars).unwrap();
+
+        assert_golden(&png, "leaderboard.png");
+    }
+
+    #[test]
+    fn trend_matches_the_golden_chart() {
+        let days = [(day(1), 1), (day(2), 3), (day(3), 3), (day(4), 2), (day(5), -1)];
+
+        let png = trend_png("Net score of Bob this season", &days).unwrap();
+
+        assert_golden(&png, "trend.png");
+    }
+
+    #[test]
+    fn single_day_trend_matches_the_golden_chart() {
+        let png = trend_png("Net score of Bob today", &[(day(1), 2)]).unwrap();
+
+        assert_golden(&png, "trend_single_day.png");
+    }
+
+    #[test]
+    fn rendering_is_deterministic() {
+        let bars = [Bar { label: "Alice".to_string(), value: 1.0 }];
+
+        assert_eq!(leaderboard_png("Leaderboard", &bars).unwrap(), leaderboard_png("Leaderboard", &bars).unwrap());
+    }
+
+    #[test]
+    fn empty_trend_fails() {
+        assert!(trend_png("Nothing", &[]).is_err());
+    }
+}
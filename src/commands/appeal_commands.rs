@@ -0,0 +1,254 @@
+use log::error;
+use poise::serenity_prelude as serenity;
+
+use crate::{Context, Data, Error};
+use crate::commands::bbp_commands::{a_point, bbp_choices};
+use crate::commands::registration_commands::reply_privately;
+use crate::commands::responses::{error_message, respond};
+use crate::dataaccess::bbp_store::BbpStore;
+use crate::dataaccess::models::{Appeal, AppealStatus, PointKind};
+use crate::dataaccess::store_error::StoreError;
+
+// Verdict buttons carry the appeal, e.g. "appeal:overturn:7"
+const BUTTON_PREFIX: &str = "appeal:";
+
+// How many of their bbps the autocomplete offers
+const SUGGESTIONS: i64 = 25;
+
+#[poise::command(slash_command, guild_only, rename = "appeal")]
+pub async fn appeal_command(
+    ctx: Context<'_>,
+    #[description = "The bbp you want to contest"] #[autocomplete = "autocomplete_bbp"] bbp: i32,
+    #[description = "Why it should be overturned"] statement: String,
+) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().unwrap().get() as i64;
+    let db = ctx.data().db.as_ref();
+
+    let appeal = match appeal(db, guild_id, bbp, ctx.author().id.get() as i64, &statement).await {
+        Ok(appeal) => appeal,
+        Err(e) => return respond(ctx, Err(e)).await,
+    };
+
+    let reply = poise::CreateReply::default()
+        .content(open_text(&appeal))
+        .components(verdict_buttons(appeal.appeal_id));
+    let handle = ctx.send(reply).await?;
+    let message = handle.message().await?;
+
+    // Only kept so the message can be found again, the buttons work without it
+    let saved = db.set_appeal_message(appeal.appeal_id, message.channel_id.get() as i64, message.id.get() as i64).await;
+    if let Err(e) = saved {
+        error!("Couldn't remember the message of appeal {}: {}", appeal.appeal_id, e);
+    }
+
+    Ok(())
+}
+
+/// Offers the caller's bbps that can still be appealed, newest first.
+async fn autocomplete_bbp(ctx: Context<'_>, partial: &str) -> Vec<serenity::AutocompleteChoice> {
+    let Some(guild_id) = ctx.guild_id() else {
+        return Vec::new();
+    };
+
+    match ctx.data().db.get_recent_bbps(guild_id.get() as i64, ctx.author().id.get() as i64, None, false, SUGGESTIONS).await {
+        Ok(bbps) => bbp_choices(&bbps, partial),
+        Err(e) => {
+            error!("Listing bbps to appeal failed: {}", e);
+            Vec::new()
+        }
+    }
+}
+
+/// Opens an appeal of one of the appellant's bbps. The community gets a say
+/// when the guild has a jury, otherwise only moderators decide.
+pub async fn appeal(db: &dyn BbpStore, guild_id: i64, bbp_id: i32, appellant: i64, statement: &str) -> Result<Appeal, StoreError> {
+    let settings = db.get_guild_settings(guild_id).await?;
+
+    db.open_appeal(guild_id, bbp_id, appellant, statement, settings.jury.quorum()).await
+}
+
+/// Records a press of the verdict buttons. Moderators decide the appeal there
+/// and then, everyone else casts a vote.
+pub async fn appeal_verdict(db: &dyn BbpStore, appeal_id: i32, presser: i64, moderator: bool, overturn: bool) -> Result<Appeal, StoreError> {
+    if moderator {
+        db.resolve_appeal(appeal_id, presser, overturn).await
+    } else {
+        db.vote_on_appeal(appeal_id, presser, overturn).await
+    }
+}
+
+/// Handles presses of the verdict buttons. Like the registration buttons they
+/// outlive the command that posted them, and keep working across restarts.
+pub async fn handle_button(ctx: &serenity::Context, press: &serenity::ComponentInteraction, data: &Data, owners: &std::collections::HashSet<serenity::UserId>) -> Result<(), Error> {
+    let Some((overturn, appeal_id)) = parse_button(&press.data.custom_id) else {
+        return Ok(());
+    };
+    let Some(guild_id) = press.guild_id.map(|id| id.get() as i64) else {
+        return Ok(());
+    };
+    let db = data.db.as_ref();
+
+    let roles: Vec<i64> = press.member.iter().flat_map(|m| m.roles.iter().map(|role| role.get() as i64)).collect();
+    let moderator = match db.get_guild_settings(guild_id).await {
+        Ok(settings) => owners.contains(&press.user.id) || settings.is_moderator(&roles),
+        Err(e) => return reply_privately(ctx, press, &error_message(&e)).await,
+    };
+
+    match appeal_verdict(db, appeal_id, press.user.id.get() as i64, moderator, overturn).await {
+        Ok(appeal) => {
+            let mut update = serenity::CreateInteractionResponseMessage::new().content(appeal_text(&appeal));
+            if appeal.status != AppealStatus::Open {
+                update = update.components(vec![]);
+            }
+            press.create_response(ctx, serenity::CreateInteractionResponse::UpdateMessage(update)).await?;
+            Ok(())
+        }
+        Err(e) => {
+            error!("Deciding appeal {} failed: {}", appeal_id, e);
+            reply_privately(ctx, press, &error_message(&e)).await
+        }
+    }
+}
+
+fn appeal_text(appeal: &Appeal) -> String {
+    match appeal.status {
+        AppealStatus::Open => open_text(appeal),
+        AppealStatus::Upheld | AppealStatus::Overturned => closed_text(appeal),
+    }
+}
+
+/// "<@2> is appealing a bbp (B12) from <@1>." with both sides, and how it gets decided.
+fn open_text(appeal: &Appeal) -> String {
+    let how = if appeal.quorum > 0 {
+        format!("A moderator can decide it, or {} votes either way settle it. Overturn {} Uphold {}.", appeal.quorum, appeal.votes_overturn, appeal.votes_uphold)
+    } else {
+        "A moderator will decide it.".to_string()
+    };
+
+    format!(
+        "<@{}> is appealing {} ({}) from <@{}>: {}\n\n> {}\n\n{}",
+        appeal.bbp.target_discord_id,
+        a_point(PointKind::Bbp, appeal.bbp.value),
+        PointKind::Bbp.public_id(appeal.bbp.bbp_id),
+        appeal.bbp.issuer_discord_id,
+        appeal.bbp.description,
+        appeal.statement,
+        how)
+}
+
+fn closed_text(appeal: &Appeal) -> String {
+    let verdict = match appeal.status {
+        AppealStatus::Overturned => "overturned, the bbp is forgiven",
+        AppealStatus::Upheld | AppealStatus::Open => "upheld, the bbp stands",
+    };
+    let decided_by = match appeal.resolved_by_discord_id {
+        Some(moderator) => format!("by <@{}>", moderator),
+        None => format!("by vote (Overturn {} Uphold {})", appeal.votes_overturn, appeal.votes_uphold),
+    };
+
+    format!(
+        "<@{}> appealed {} ({}) from <@{}>: {}\n\n> {}\n\nThe appeal was {} {}.",
+        appeal.bbp.target_discord_id,
+        a_point(PointKind::Bbp, appeal.bbp.value),
+        PointKind::Bbp.public_id(appeal.bbp.bbp_id),
+        appeal.bbp.issuer_discord_id,
+        appeal.bbp.description,
+        appeal.statement,
+        verdict,
+        decided_by)
+}
+
+fn verdict_buttons(appeal_id: i32) -> Vec<serenity::CreateActionRow> {
+    let overturn = format!("{}overturn:{}", BUTTON_PREFIX, appeal_id);
+    let uphold = format!("{}uphold:{}", BUTTON_PREFIX, appeal_id);
+
+    vec![serenity::CreateActionRow::Buttons(vec![
+        serenity::CreateButton::new(overturn).label("Overturn").style(serenity::ButtonStyle::Success),
+        serenity::CreateButton::new(uphold).label("Uphold").style(serenity::ButtonStyle::Danger),
+    ])]
+}
+
+/// `Some((overturn, appeal_id))` for the buttons from `verdict_buttons`.
+fn parse_button(custom_id: &str) -> Option<(bool, i32)> {
+    let (action, appeal_id) = custom_id.strip_prefix(BUTTON_PREFIX)?.split_once(':')?;
+    let overturn = match action {
+        "overturn" => true,
+        "uphold" => false,
+        _ => return None,
+    };
+
+    Some((overturn, appeal_id.parse().ok()?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dataaccess::memory_store::MemoryStore;
+    use crate::dataaccess::models::{GuildSettings, JuryPolicy};
+
+    const GUILD: i64 = 1;
+    const ALICE: i64 = 10;
+    const BOB: i64 = 20;
+    const CAROL: i64 = 30;
+    const DAVE: i64 = 40;
+
+    /// Alice has given Bob B1, in a guild with the given jury.
+    async fn store(jury: JuryPolicy) -> MemoryStore {
+        let db = MemoryStore::new();
+        for (discord_id, name) in [(ALICE, "Alice"), (BOB, "Bob"), (CAROL, "Carol"), (DAVE, "Dave")] {
+            db.add_user(GUILD, discord_id, &name.to_lowercase(), name).await.unwrap();
+        }
+        db.save_guild_settings(GuildSettings { guild_id: GUILD, jury, ..GuildSettings::default() }).await.unwrap();
+        db.issue_point(GUILD, PointKind::Bbp, 1, ALICE, BOB, "late").await.unwrap();
+        db
+    }
+
+    #[tokio::test]
+    async fn without_a_jury_only_moderators_decide() {
+        let db = store(JuryPolicy::Off).await;
+
+        let opened = appeal(&db, GUILD, 1, BOB, "the bus was late").await.unwrap();
+        let vote = appeal_verdict(&db, opened.appeal_id, CAROL, false, true).await;
+
+        assert_eq!(opened.quorum, 0);
+        assert_eq!(open_text(&opened), "<@20> is appealing a bbp (B1) from <@10>: late\n\n> the bus was late\n\nA moderator will decide it.");
+        assert!(matches!(vote, Err(StoreError::NoCommunityVote)), "{:?}", vote);
+        assert_eq!(error_message(&vote.unwrap_err()), "Only moderators decide appeals here.");
+    }
+
+    #[tokio::test]
+    async fn a_moderator_decides_straight_away() {
+        let db = store(JuryPolicy::On { quorum: 2, hours: 24 }).await;
+        let opened = appeal(&db, GUILD, 1, BOB, "the bus was late").await.unwrap();
+
+        let decided = appeal_verdict(&db, opened.appeal_id, CAROL, true, true).await.unwrap();
+
+        assert_eq!(decided.status, AppealStatus::Overturned);
+        assert_eq!(appeal_text(&decided), "<@20> appealed a bbp (B1) from <@10>: late\n\n> the bus was late\n\nThe appeal was overturned, the bbp is forgiven by <@30>.");
+        assert_eq!(db.get_user_by_discord_id(GUILD, BOB).await.unwrap().unwrap().points, 0);
+    }
+
+    #[tokio::test]
+    async fn the_community_settles_it_at_the_jury_quorum() {
+        let db = store(JuryPolicy::On { quorum: 2, hours: 24 }).await;
+        let opened = appeal(&db, GUILD, 1, BOB, "the bus was late").await.unwrap();
+
+        let first = appeal_verdict(&db, opened.appeal_id, CAROL, false, false).await.unwrap();
+        let decided = appeal_verdict(&db, opened.appeal_id, DAVE, false, false).await.unwrap();
+
+        assert_eq!(opened.quorum, 2);
+        assert_eq!(appeal_text(&first), "<@20> is appealing a bbp (B1) from <@10>: late\n\n> the bus was late\n\nA moderator can decide it, or 2 votes either way settle it. Overturn 0 Uphold 1.");
+        assert_eq!(decided.status, AppealStatus::Upheld);
+        assert_eq!(appeal_text(&decided), "<@20> appealed a bbp (B1) from <@10>: late\n\n> the bus was late\n\nThe appeal was upheld, the bbp stands by vote (Overturn 0 Uphold 2).");
+        assert_eq!(db.get_user_by_discord_id(GUILD, BOB).await.unwrap().unwrap().points, 1);
+    }
+
+    #[test]
+    fn parse_button_reads_the_verdict_buttons() {
+        assert_eq!(parse_button("appeal:overturn:7"), Some((true, 7)));
+        assert_eq!(parse_button("appeal:uphold:7"), Some((false, 7)));
+        assert_eq!(parse_button("appeal:dismiss:7"), None);
+        assert_eq!(parse_button("appeal:uphold:seven"), None);
+        assert_eq!(parse_button("jury:for:7"), None);
+    }
+}
@@ -1,213 +1,173 @@
-use log::{error};
+use async_trait::async_trait;
+use log::error;
+use poise::serenity_prelude as serenity;
 use crate::{Context, Error};
-use crate::dataaccess::postgres_service;
+use crate::commands::jury_commands::post_accusation;
+use crate::commands::pagination::{paginate, PageReply, Pages, PAGE_SIZE};
+use crate::commands::responses::{reject, respond};
+use crate::commands::settings_commands::decay_description;
+use crate::dataaccess::bbp_store::BbpStore;
+use crate::dataaccess::models::{net_score, AppealStatus, Bbp, DecayPolicy, HistoryFilter, HistoryRecord, JuryPolicy, Page, PendingBbp, PointKind, Season, Standings, TimeWindow, User};
+use chrono::{Duration, Months, NaiveDate, NaiveDateTime, NaiveTime};
+use crate::dataaccess::store_error::{StoreError, UserRole};
 use std::fmt::Write;
 
-#[poise::command(slash_command, rename = "bbp", user_cooldown = 30)]
+// How many bbps the autocomplete of `/forgive` and `/unforgive` offers
+const SUGGESTIONS: i64 = 25;
+
+#[poise::command(slash_command, guild_only, rename = "bbp", user_cooldown = 30)]
 pub async fn bbp_add_command(
     ctx: Context<'_>,
     target: poise::serenity_prelude::User,
-    description: String
+    description: String,
+    #[description = "How many points it's worth, 1 by default"] #[min = 1] value: Option<i32>,
 ) -> Result<(), Error> {
     let issuer = ctx.author().id.get() as i64;
     let target = target.id.get() as i64;
-    let db = ctx.data().db.lock().await;
-
-    let issuing_user = match db.get_user_by_discord_id(issuer).await {
-        Ok(Some(user)) => user,
-        Ok(None) => {
-            let msg = "Issuing user not found";
-            error!("{}", msg);
-            ctx.say(msg).await?;
-            return Ok(());
-        }
-        Err(e) => {
-            error!("Error fetching issuing user: {}", e);
-            ctx.say("Error fetching issuing user").await?;
-            return Ok(());
-        }
-    };
+    let guild_id = ctx.guild_id().unwrap().get() as i64;
+    let roles = member_role_ids(ctx).await;
+    let db = ctx.data().db.as_ref();
 
-    let target_user = match db.get_user_by_discord_id(target).await {
-        Ok(Some(user)) => user,
-        Ok(None) => {
-            let msg = "Target user not found";
-            error!("{}", msg);
-            ctx.say(msg).await?;
-            return Ok(());
-        }
-        Err(e) => {
-            error!("Error fetching target user: {}", e);
-            ctx.say("Error fetching target user").await?;
-            return Ok(());
-        }
-    };
-
-    db.add_bbp_to_user(&target_user, &issuing_user, &description).await?;
-    
-    let ranked_user = match db.get_user_by_discord_id_with_rank(target).await {
-        Ok(Some(user)) => user,
-        Ok(None) => {
-            let msg = "Ranked user not found";
-            error!("{}", msg);
-            ctx.say(msg).await?;
-            return Ok(());
-        }
-        Err(e) => {
-            error!("Error adding BBP: {}", e);
-            ctx.say("Error adding BBP").await?;
-            return Ok(());
-        }
-    };
-    
-    let msg = format!(
-        "{} has given {} a bbp.\n\n{}\n\n{}(#{}) now has {} bbps.",
-        issuing_user.friendly_name.unwrap(),
-        target_user.discord_mention.unwrap(),
-        description,
-        ranked_user.friendly_name.unwrap(),
-        ranked_user.rank.unwrap(),
-        ranked_user.points
-    );    
-    
-    ctx.say(msg).await?;
-
-    Ok(())
+    match bbp_add(db, guild_id, issuer, target, &description, value.unwrap_or(1), &roles).await {
+        Ok(BbpOutcome::Issued(announcement)) => announce(ctx, guild_id, announcement).await,
+        Ok(BbpOutcome::Accused(pending)) => post_accusation(ctx, &pending).await,
+        Err(e) => respond(ctx, Err(e)).await,
+    }
 }
 
-#[poise::command(slash_command, rename = "gbp", user_cooldown = 30)]
+#[poise::command(slash_command, guild_only, rename = "gbp", user_cooldown = 30)]
 pub async fn gbp_add_command(
     ctx: Context<'_>,
     target: poise::serenity_prelude::User,
-    description: String
+    description: String,
+    #[description = "How many points it's worth, 1 by default"] #[min = 1] value: Option<i32>,
 ) -> Result<(), Error> {
     let issuer = ctx.author().id.get() as i64;
     let target = target.id.get() as i64;
+    let guild_id = ctx.guild_id().unwrap().get() as i64;
+    let roles = member_role_ids(ctx).await;
+    let db = ctx.data().db.as_ref();
 
-    if issuer == target {
-        let ranked_user = match add_bbp(&ctx, target.clone(), target, "Attempting to give themselves a GBP 😡".to_string()).await {
-            Ok(Some(user)) => user,
-            Ok(None) => {
-                let msg = "Ranked user not found";
-                error!("{}", msg);
-                ctx.say(msg).await?;
-                return Ok(());
-            }
-            Err(e) => {
-                error!("Error adding GBP: {}", e);
-                ctx.say("Error adding GBP").await?;
-                return Ok(());
-            }
-        };
+    match gbp_add(db, guild_id, issuer, target, &description, value.unwrap_or(1), &roles).await {
+        Ok(announcement) => announce(ctx, guild_id, announcement).await,
+        Err(e) => respond(ctx, Err(e)).await,
+    }
+}
 
-        ctx.say(format!("😡 trying to give yourself a gbp? That's a bbp for you. {}(#{}) now has {} bbps.",
-            &ranked_user.friendly_name.unwrap(),
-            &ranked_user.rank.unwrap(),
-            &ranked_user.points)).await?;
-    } else {
-        match add_gbp(&ctx, issuer, target, &description).await {
-            Ok((issuing_user, target_user, ranked_user)) => {
-                let msg = format!(
-                    "{} has given {} a bbp.\n\n{}\n\n{}(#{}) now has {} bbps.",
-                    issuing_user.friendly_name.unwrap(),
-                    target_user.discord_mention.unwrap(),
-                    description,
-                    ranked_user.friendly_name.unwrap(),
-                    ranked_user.rank.unwrap(),
-                    ranked_user.points
-                );
-                ctx.say(msg).await?;
-            },
-            Err(e) => {
-                error!("Error adding GBP: {}", e);
-                ctx.say("Error adding GBP").await?;
-                return Ok(());
-            }
-        };
+/// Replies with a bbp/gbp that was just given, and remembers the message so
+/// `/show` can link to it.
+async fn announce(ctx: Context<'_>, guild_id: i64, announcement: Announcement) -> Result<(), Error> {
+    let handle = ctx.say(announcement.text).await?;
+    let message = handle.message().await?;
+
+    // The point counts without it, `/show` just won't link the message
+    let saved = ctx.data().db.set_point_message(guild_id, announcement.kind, announcement.point_id, message.channel_id.get() as i64, message.id.get() as i64).await;
+    if let Err(e) = saved {
+        error!("Couldn't remember the message of {}: {}", announcement.kind.public_id(announcement.point_id), e);
     }
 
     Ok(())
 }
 
-#[poise::command(slash_command, rename = "forgive")]
+/// The roles of whoever ran the command, which decide how much their points can
+/// be worth.
+pub(crate) async fn member_role_ids(ctx: Context<'_>) -> Vec<i64> {
+    match ctx.author_member().await {
+        Some(member) => member.roles.iter().map(|role| role.get() as i64).collect(),
+        None => Vec::new(),
+    }
+}
+
+#[poise::command(slash_command, guild_only, rename = "forgive")]
 pub async fn bbp_forgive_command(
     ctx: Context<'_>,
-    target: poise::serenity_prelude::User
+    target: poise::serenity_prelude::User,
+    #[description = "Which of their bbps, the most recent by default"] #[autocomplete = "autocomplete_forgivable"] bbp: Option<i32>,
+) -> Result<(), Error> {
+    let forgiver = ctx.author().id.get() as i64;
+    let target = target.id.get() as i64;
+    let guild_id = ctx.guild_id().unwrap().get() as i64;
+    let owner = ctx.framework().options().owners.contains(&ctx.author().id);
+    let db = ctx.data().db.as_ref();
+
+    respond(ctx, bbp_forgive(db, guild_id, forgiver, target, bbp, owner).await).await
+}
+
+#[poise::command(slash_command, guild_only, rename = "unforgive")]
+pub async fn bbp_unforgive_command(
+    ctx: Context<'_>,
+    target: poise::serenity_prelude::User,
+    #[description = "Which of their forgiven bbps, the most recently given by default"] #[autocomplete = "autocomplete_unforgivable"] bbp: Option<i32>,
 ) -> Result<(), Error> {
     let issuer = ctx.author().id.get() as i64;
     let target = target.id.get() as i64;
-    let db = ctx.data().db.lock().await;
-
-    let issuing_user = match db.get_user_by_discord_id(issuer).await {
-        Ok(Some(user)) => user,
-        Ok(None) => {
-            let msg = "Issuing user not found";
-            error!("{}", msg);
-            ctx.say(msg).await?;
-            return Ok(());
-        }
-        Err(e) => {
-            error!("Error fetching issuing user: {}", e);
-            ctx.say("Error fetching issuing user").await?;
-            return Ok(());
-        }
-    };
+    let guild_id = ctx.guild_id().unwrap().get() as i64;
+    let owner = ctx.framework().options().owners.contains(&ctx.author().id);
+    let db = ctx.data().db.as_ref();
 
-    let target_user = match db.get_user_by_discord_id(target).await {
-        Ok(Some(user)) => user,
-        Ok(None) => {
-            let msg = "Target user not found";
-            error!("{}", msg);
-            ctx.say(msg).await?;
-            return Ok(());
-        }
-        Err(e) => {
-            error!("Error fetching target user: {}", e);
-            ctx.say("Error fetching target user").await?;
-            return Ok(());
-        }
-    };
+    respond(ctx, bbp_unforgive(db, guild_id, issuer, target, bbp, owner).await).await
+}
 
-    let description = match db.forgive_user(&target_user, &issuing_user).await {
-        Ok(Some(desc)) => desc,
-        Ok(None) => {
-            let msg = "There is nothing to forgive";
-            error!("{}", msg);
-            ctx.say(msg).await?;
-            return Ok(());
-        }
-        Err(e) => {
-            error!("Error forgiving user: {}", e);
-            ctx.say("Error forgiving user").await?;
-            return Ok(());
-        }
+async fn autocomplete_forgivable(ctx: Context<'_>, partial: &str) -> Vec<serenity::AutocompleteChoice> {
+    target_bbp_choices(ctx, partial, false).await
+}
+
+async fn autocomplete_unforgivable(ctx: Context<'_>, partial: &str) -> Vec<serenity::AutocompleteChoice> {
+    target_bbp_choices(ctx, partial, true).await
+}
+
+/// The bbps the caller could forgive (or unforgive) for the `target` picked so
+/// far: the ones they gave, or anyone's for owners.
+async fn target_bbp_choices(ctx: Context<'_>, partial: &str, forgiven: bool) -> Vec<serenity::AutocompleteChoice> {
+    let (Some(guild_id), Some(target)) = (ctx.guild_id(), user_option(ctx, "target")) else {
+        return Vec::new();
     };
+    let owner = ctx.framework().options().owners.contains(&ctx.author().id);
+    let issuer = (!owner).then_some(ctx.author().id.get() as i64);
 
-    let ranked_user = match db.get_user_by_discord_id_with_rank(target).await {
-        Ok(Some(user)) => user,
-        Ok(None) => {
-            let msg = "Ranked user not found";
-            error!("{}", msg);
-            ctx.say(msg).await?;
-            return Ok(());
-        }
+    match ctx.data().db.get_recent_bbps(guild_id.get() as i64, target, issuer, forgiven, SUGGESTIONS).await {
+        Ok(bbps) => bbp_choices(&bbps, partial),
         Err(e) => {
-            error!("Error fetching ranked user: {}", e);
-            ctx.say("Error fetching ranked user").await?;
-            return Ok(());
+            error!("Listing bbps to choose from failed: {}", e);
+            Vec::new()
         }
+    }
+}
+
+/// The user given for another option of the command being autocompleted, which
+/// Discord only sends unresolved.
+fn user_option(ctx: Context<'_>, name: &str) -> Option<i64> {
+    let poise::Context::Application(ctx) = ctx else {
+        return None;
     };
 
-    ctx.say(format!("{}(#{}) was forgiven for '{}' and now only has {} bbps.",
-        &ranked_user.friendly_name.unwrap(),
-        &ranked_user.rank.unwrap(),
-        &description,
-        &ranked_user.points)).await?;
+    ctx.args.iter().find(|option| option.name == name).and_then(|option| match option.value {
+        serenity::ResolvedValue::User(user, _) => Some(user.id.get() as i64),
+        serenity::ResolvedValue::Unresolved(serenity::Unresolved::User(id)) => Some(id.get() as i64),
+        _ => None,
+    })
+}
+
+/// Autocomplete choices for the bbps matching what was typed so far, by ID,
+/// issuer or description.
+pub(crate) fn bbp_choices(bbps: &[Bbp], partial: &str) -> Vec<serenity::AutocompleteChoice> {
+    let partial = partial.to_lowercase();
 
-    Ok(())
+    bbps.iter()
+        .map(|bbp| (bbp_choice(bbp), bbp.bbp_id))
+        .filter(|(choice, _)| choice.to_lowercase().contains(&partial))
+        .map(|(choice, bbp_id)| serenity::AutocompleteChoice::new(choice, bbp_id))
+        .collect()
+}
+
+/// "B12 from Bob: stole the last donut (2024-05-01)", cut to fit a choice.
+fn bbp_choice(bbp: &Bbp) -> String {
+    let choice = format!("{} from {}: {} ({})", PointKind::Bbp.public_id(bbp.bbp_id), bbp.issuer_friendly_name, bbp.description, bbp.timestamp.format("%Y-%m-%d"));
+
+    choice.chars().take(100).collect()
 }
 
-#[poise::command(slash_command, rename = "add-user", owners_only)]
+#[poise::command(slash_command, guild_only, rename = "add-user", owners_only)]
 pub async fn add_user_command(
     ctx: Context<'_>,
     target: poise::serenity_prelude::User,
@@ -215,179 +175,869 @@ pub async fn add_user_command(
 ) -> Result<(), Error> {
     let user_id = target.id.get() as i64;
     let user_name = target.name.clone();
-    let db = ctx.data().db.lock().await;
-
-    // Check if the user already exists
-    if let Ok(Some(_)) = db.get_user_by_discord_id(user_id).await {
-        let msg = "User already exists";
-        error!("{}", msg);
-        ctx.say(msg).await?;
-        return Ok(());
-    }
-
-    let added_user = match db.add_user(user_id, &user_name, &friendly_name).await {
-        Ok(Some(user)) => user,
-        Ok(None) => {
-            let msg = "Ranked user not found";
-            error!("{}", msg);
-            ctx.say(msg).await?;
-            return Ok(());
-        }
-        Err(e) => {
-            error!("Error adding user: {}", e);
-            ctx.say("Error adding user").await?;
-            return Ok(());
-        }
-    };
-    
-    ctx.say(format!("Added user '{}'({}/{})",
-        &added_user.friendly_name.unwrap(),
-        &added_user.discord_username.unwrap(),
-        &added_user.user_id)).await?;
+    let guild_id = ctx.guild_id().unwrap().get() as i64;
+    let db = ctx.data().db.as_ref();
 
-    Ok(())
+    respond(ctx, add_user(db, guild_id, user_id, &user_name, &friendly_name).await).await
 }
 
-#[poise::command(slash_command, rename = "leaderboard", user_cooldown = 30)]
-pub async fn leaderboard_command(ctx: Context<'_>) -> Result<(), Error> {
-    let db = ctx.data().db.lock().await;
+#[derive(Debug, Clone, Copy, PartialEq, Eq, poise::ChoiceParameter)]
+pub enum PeriodChoice {
+    #[name = "week"]
+    Week,
+    #[name = "month"]
+    Month,
+    #[name = "year"]
+    Year,
+    #[name = "all"]
+    All,
+    #[name = "custom range"]
+    Custom,
+    #[name = "season"]
+    Season,
+}
 
-    let leaderboard = match db.get_leaderboard().await {
-        Ok(users) => users,
-        Err(e) => {
-            error!("Error fetching leaderboard: {}", e);
-            ctx.say("Error fetching leaderboard").await?;
-            return Ok(());
-        }
+#[poise::command(slash_command, guild_only, rename = "leaderboard", user_cooldown = 30)]
+pub async fn leaderboard_command(
+    ctx: Context<'_>,
+    #[description = "Only count bbps and gbps from this period"] period: Option<PeriodChoice>,
+    #[description = "Start of a custom range, YYYY-MM-DD"] from: Option<String>,
+    #[description = "Last day of a custom range, YYYY-MM-DD"] to: Option<String>,
+    #[description = "Show the final standings of this season"] season: Option<i32>,
+) -> Result<(), Error> {
+    let standings = match season {
+        Some(_) if period.is_some() || from.is_some() || to.is_some() =>
+            return reject(ctx, "A past season can't be combined with a period.").await,
+        Some(number) => Standings::Season(number),
+        None => match period_window(period, from.as_deref(), to.as_deref(), ctx.data().db.now()) {
+            Ok(window) => Standings::Live(window),
+            Err(msg) => return reject(ctx, &msg).await,
+        },
     };
+    let guild_id = ctx.guild_id().unwrap().get() as i64;
+    let db = ctx.data().db.as_ref();
 
-    let mut response = String::new();
-    for user in leaderboard {
-        let _ = writeln!(
-            response,
-            "{}. {} ({} points, {} bbps given, {} gbps given)",
-            user.rank,
-            user.friendly_name.unwrap(),
-            user.points,
-            user.bbps_issued,
-            user.gbps_issued
-        );
-    }
+    paginate(ctx, LeaderboardPages { db, guild_id, standings }).await
+}
 
-    ctx.say(response).await?;
-    Ok(())
+#[derive(Debug, Clone, Copy, poise::ChoiceParameter)]
+pub enum KindChoice {
+    #[name = "bbp"]
+    Bbp,
+    #[name = "gbp"]
+    Gbp,
 }
 
-#[poise::command(slash_command, rename = "history", user_cooldown = 30)]
+impl From<KindChoice> for PointKind {
+    fn from(choice: KindChoice) -> Self {
+        match choice {
+            KindChoice::Bbp => PointKind::Bbp,
+            KindChoice::Gbp => PointKind::Gbp,
+        }
+    }
+}
+
+#[poise::command(slash_command, guild_only, rename = "history", user_cooldown = 30)]
 pub async fn history_command(
     ctx: Context<'_>,
-    user: Option<poise::serenity_prelude::User>
+    user: Option<poise::serenity_prelude::User>,
+    #[description = "Only show bbps or gbps"] kind: Option<KindChoice>,
+    #[description = "Only show points given by this user"] issuer: Option<poise::serenity_prelude::User>,
+    #[description = "Only show points from this season"] season: Option<i32>,
 ) -> Result<(), Error> {
     let target_user = match user {
         Some(u) => u,
         None => ctx.author().clone(),
     };
 
-    let db = ctx.data().db.lock().await;
+    let filter = HistoryFilter {
+        kind: kind.map(PointKind::from),
+        issuer_discord_id: issuer.map(|u| u.id.get() as i64),
+        season,
+    };
+    let guild_id = ctx.guild_id().unwrap().get() as i64;
+    let db = ctx.data().db.as_ref();
 
-    let user_id = target_user.id.get() as i64;
+    paginate(ctx, HistoryPages { db, guild_id, user_id: target_user.id.get() as i64, filter }).await
+}
 
-    // Fetch the target user information
-    let target_user_info = match db.get_user_by_discord_id(user_id).await {
-        Ok(Some(user)) => user,
-        Ok(None) => {
-            let msg = "Target user not found";
-            error!("{}", msg);
-            ctx.say(msg).await?;
-            return Ok(());
-        }
-        Err(e) => {
-            error!("Error fetching target user: {}", e);
-            ctx.say("Error fetching target user").await?;
-            return Ok(());
-        }
-    };
+pub(crate) struct LeaderboardPages<'a> {
+    pub db: &'a dyn BbpStore,
+    pub guild_id: i64,
+    pub standings: Standings,
+}
 
-    let history = match db.get_user_history(user_id).await {
-        Ok(records) => records,
-        Err(e) => {
-            error!("Error fetching user history: {}", e);
-            ctx.say("Error fetching user history").await?;
-            return Ok(());
+#[async_trait]
+impl Pages for LeaderboardPages<'_> {
+    async fn page(&mut self, offset: i64) -> Result<PageReply, StoreError> {
+        leaderboard(self.db, self.guild_id, self.standings, offset).await
+    }
+
+    async fn jump_to(&mut self, discord_id: i64) -> Result<PageReply, StoreError> {
+        let position = self.db.get_leaderboard_position(self.guild_id, self.standings, discord_id).await?
+            .ok_or(StoreError::UserNotFound(UserRole::Issuer))?;
+
+        leaderboard(self.db, self.guild_id, self.standings, position / PAGE_SIZE * PAGE_SIZE).await
+    }
+}
+
+struct HistoryPages<'a> {
+    db: &'a dyn BbpStore,
+    guild_id: i64,
+    user_id: i64,
+    filter: HistoryFilter,
+}
+
+#[async_trait]
+impl Pages for HistoryPages<'_> {
+    async fn page(&mut self, offset: i64) -> Result<PageReply, StoreError> {
+        history(self.db, self.guild_id, self.user_id, self.filter, offset).await
+    }
+
+    /// Switches over to the presser's own history.
+    async fn jump_to(&mut self, discord_id: i64) -> Result<PageReply, StoreError> {
+        if self.db.get_user_by_discord_id(self.guild_id, discord_id).await?.is_none() {
+            return Err(StoreError::UserNotFound(UserRole::Issuer));
         }
-    };
 
-    let mut response = format!("History for {}\n\n", target_user_info.friendly_name.unwrap_or("Unknown".to_string()));
-    for record in history {
-        let date = record.timestamp.format("%Y-%m-%d").to_string();
+        self.user_id = discord_id;
+        self.page(0).await
+    }
+}
+
+// The functions below hold the command logic. They only depend on a `BbpStore`
+// and return the reply text, so they can be driven without Discord or Postgres.
+
+/// A bbp/gbp that was just given, and the reply that announces it.
+pub struct Announcement {
+    pub kind: PointKind,
+    pub point_id: i32,
+    pub text: String,
+}
+
+pub enum BbpOutcome {
+    Issued(Announcement),
+    /// Put before the jury, it only counts once they confirm it
+    Accused(PendingBbp),
+}
+
+/// Issues the bbp, or puts it to a vote when the guild has a jury.
+pub async fn bbp_add(db: &dyn BbpStore, guild_id: i64, issuer: i64, target: i64, description: &str, value: i32, issuer_roles: &[i64]) -> Result<BbpOutcome, StoreError> {
+    check_value(db, guild_id, value, issuer_roles).await?;
+
+    if let JuryPolicy::On { quorum, hours } = db.get_guild_settings(guild_id).await?.jury {
+        let pending = db.create_pending_bbp(guild_id, value, issuer, target, description, quorum, Duration::hours(hours as i64)).await?;
+        return Ok(BbpOutcome::Accused(pending));
+    }
+    let issued = db.issue_point(guild_id, PointKind::Bbp, value, issuer, target, description).await?;
+
+    Ok(BbpOutcome::Issued(Announcement {
+        kind: PointKind::Bbp,
+        point_id: issued.point_id,
+        text: format!(
+            "{} has given {} {} ({}).\n\n{}\n\n{}",
+            display_name(&issued.issuer),
+            mention(&issued.target),
+            a_point(PointKind::Bbp, value),
+            PointKind::Bbp.public_id(issued.point_id),
+            description,
+            standing(&issued.ranked)?
+        ),
+    }))
+}
+
+pub async fn gbp_add(db: &dyn BbpStore, guild_id: i64, issuer: i64, target: i64, description: &str, value: i32, issuer_roles: &[i64]) -> Result<Announcement, StoreError> {
+    if issuer == target {
+        let issued = db.issue_point(guild_id, PointKind::Bbp, 1, target, target, "Attempting to give themselves a GBP 😡").await?;
+
+        Ok(Announcement {
+            kind: PointKind::Bbp,
+            point_id: issued.point_id,
+            text: format!("😡 trying to give yourself a gbp? That's a bbp ({}) for you. {}",
+                PointKind::Bbp.public_id(issued.point_id),
+                standing(&issued.ranked)?),
+        })
+    } else {
+        check_value(db, guild_id, value, issuer_roles).await?;
+        let issued = db.issue_point(guild_id, PointKind::Gbp, value, issuer, target, description).await?;
+
+        Ok(Announcement {
+            kind: PointKind::Gbp,
+            point_id: issued.point_id,
+            text: format!(
+                "{} has given {} {} ({}) 😇\n\n{}\n\n{}",
+                display_name(&issued.issuer),
+                mention(&issued.target),
+                a_point(PointKind::Gbp, value),
+                PointKind::Gbp.public_id(issued.point_id),
+                description,
+                standing(&issued.ranked)?
+            ),
+        })
+    }
+}
+
+/// Forgives one of the target's bbps from the forgiver, or from anyone when an
+/// owner forgives it.
+pub async fn bbp_forgive(db: &dyn BbpStore, guild_id: i64, forgiver: i64, target: i64, bbp_id: Option<i32>, owner: bool) -> Result<String, StoreError> {
+    let issuer = forgiveness_issuer(db, guild_id, forgiver, target, owner).await?;
+
+    let bbp = db.forgive_bbp(guild_id, target, issuer, bbp_id, forgiver).await?;
+
+    let ranked_user = db.get_user_by_discord_id_with_rank(guild_id, target).await?
+        .ok_or_else(|| StoreError::Integrity("Ranked user not found".to_string()))?;
+
+    Ok(format!("{} was forgiven for '{}' ({}). {}",
+        display_name(&ranked_user),
+        &bbp.description,
+        PointKind::Bbp.public_id(bbp.bbp_id),
+        standing(&ranked_user)?))
+}
+
+/// Takes back the forgiveness of one of the target's bbps, picked like in `bbp_forgive`.
+pub async fn bbp_unforgive(db: &dyn BbpStore, guild_id: i64, issuer: i64, target: i64, bbp_id: Option<i32>, owner: bool) -> Result<String, StoreError> {
+    let issuer = forgiveness_issuer(db, guild_id, issuer, target, owner).await?;
+
+    let bbp = db.unforgive_bbp(guild_id, target, issuer, bbp_id).await?;
+
+    let ranked_user = db.get_user_by_discord_id_with_rank(guild_id, target).await?
+        .ok_or_else(|| StoreError::Integrity("Ranked user not found".to_string()))?;
+
+    Ok(format!("The bbp {} got for '{}' ({}) counts again. {}",
+        display_name(&ranked_user),
+        &bbp.description,
+        PointKind::Bbp.public_id(bbp.bbp_id),
+        standing(&ranked_user)?))
+}
+
+/// Whose bbps the caller may (un)forgive: their own, or anyone's (`None`) for
+/// owners. Fails unless both users are registered and the target is active.
+async fn forgiveness_issuer(db: &dyn BbpStore, guild_id: i64, caller: i64, target: i64, owner: bool) -> Result<Option<i64>, StoreError> {
+    if !owner && db.get_user_by_discord_id(guild_id, caller).await?.is_none() {
+        return Err(StoreError::UserNotFound(UserRole::Issuer));
+    }
+    let target_user = db.get_user_by_discord_id(guild_id, target).await?
+        .ok_or(StoreError::UserNotFound(UserRole::Target))?;
+    if !target_user.active {
+        return Err(StoreError::UserInactive(UserRole::Target));
+    }
+
+    Ok((!owner).then_some(caller))
+}
+
+pub async fn add_user(db: &dyn BbpStore, guild_id: i64, user_id: i64, user_name: &str, friendly_name: &str) -> Result<String, StoreError> {
+    let added_user = db.add_user(guild_id, user_id, user_name, friendly_name).await?;
+
+    Ok(format!("Added user '{}'({}/{})",
+        display_name(&added_user),
+        added_user.discord_username.as_deref().unwrap_or_default(),
+        &added_user.user_id))
+}
+
+pub async fn leaderboard(db: &dyn BbpStore, guild_id: i64, standings: Standings, offset: i64) -> Result<PageReply, StoreError> {
+    // One extra row tells whether there is a next page
+    let mut leaderboard = db.get_leaderboard(guild_id, standings, Page { offset, limit: PAGE_SIZE + 1 }).await?;
+    let has_next = leaderboard.len() as i64 > PAGE_SIZE;
+    leaderboard.truncate(PAGE_SIZE as usize);
+
+    let title = match standings {
+        Standings::Live(window) => window_title(window),
+        Standings::Season(number) => Some(season_line(&db.get_season(guild_id, number).await?)),
+    };
+    let mut response = match title {
+        Some(title) => format!("{}\n\n", title),
+        None => String::new(),
+    };
+    for user in &leaderboard {
         let _ = writeln!(
             response,
-            "{} -> {} ({})",
-            record.issuer_friendly_name,
-            record.description,
-            date
+            "{}. {} ({} points{}, {} bbps given, {} gbps given)",
+            user.rank,
+            user.friendly_name.as_deref().unwrap_or_default(),
+            user.points,
+            after_decay(user.points, Some(user.score)),
+            user.bbps_issued,
+            user.gbps_issued
         );
     }
 
-    if response.is_empty() {
-        response = "No history found".to_string();
+    if leaderboard.is_empty() {
+        response.push_str("Nobody is on the leaderboard yet");
+    }
+    Ok(PageReply { text: response, offset, has_next })
+}
+
+pub async fn history(db: &dyn BbpStore, guild_id: i64, user_id: i64, filter: HistoryFilter, offset: i64) -> Result<PageReply, StoreError> {
+    // Fetch the target user information
+    let target_user_info = db.get_user_by_discord_id_with_rank(guild_id, user_id).await?
+        .ok_or(StoreError::UserNotFound(UserRole::Target))?;
+    let decay = db.get_guild_settings(guild_id).await?.decay;
+
+    let mut history = db.get_user_history(guild_id, user_id, filter, Page { offset, limit: PAGE_SIZE + 1 }).await?;
+    let has_next = history.len() as i64 > PAGE_SIZE;
+    history.truncate(PAGE_SIZE as usize);
+
+    let mut response = format!("History for {}", display_name(&target_user_info));
+    if let Some(number) = filter.season {
+        let _ = write!(response, " in season {}", number);
+    }
+    if decay != DecayPolicy::Off {
+        let _ = write!(response, "\nNet score {}, {:.1} after decay ({})",
+            target_user_info.points,
+            target_user_info.score.unwrap_or_default(),
+            decay_description(decay));
     }
-    ctx.say(response).await?;
+    response.push_str("\n\n");
+    if history.is_empty() {
+        response.push_str("Nothing here yet");
+    }
+    for record in history {
+        let _ = writeln!(response, "{}", history_line(&record));
+    }
+
+    Ok(PageReply { text: response, offset, has_next })
+}
+
+/// Fails unless someone with `issuer_roles` may give a bbp/gbp worth `value`
+/// in this guild. Single points are always allowed, a point worth nothing or
+/// less never is.
+async fn check_value(db: &dyn BbpStore, guild_id: i64, value: i32, issuer_roles: &[i64]) -> Result<(), StoreError> {
+    match value {
+        ..=0 => return Err(StoreError::ValueTooLow),
+        1 => return Ok(()),
+        _ => {}
+    }
+
+    let settings = db.get_guild_settings(guild_id).await?;
+    let max = settings.max_value_for(settings.is_moderator(issuer_roles));
+    if value > max {
+        return Err(StoreError::ValueNotAllowed { max });
+    }
+
     Ok(())
 }
-async fn add_bbp(ctx: &Context<'_>, issuer: i64, target: i64, description: String) -> Result<Option<postgres_service::User>, Box<dyn std::error::Error + Send + Sync>> {
-    let db = ctx.data().db.lock().await;
 
-    let issuing_user = match db.get_user_by_discord_id(issuer).await {
-        Ok(Some(user)) => user,
-        Ok(None) => return Err("Issuing user not found".into()),
-        Err(e) => return Err(e.into()),
-    };
+/// Works out the window for `/leaderboard`. A custom range covers both days it
+/// names; `from`/`to` without a period imply a custom range. Without any period
+/// only the running season counts, every other period reaches into past ones.
+pub fn period_window(period: Option<PeriodChoice>, from: Option<&str>, to: Option<&str>, now: NaiveDateTime) -> Result<TimeWindow, String> {
+    let custom = from.is_some() || to.is_some();
+    match period {
+        Some(PeriodChoice::Custom) | None if custom => {}
+        Some(PeriodChoice::Custom) => return Err("A custom range needs a `from` or `to` date.".to_string()),
+        Some(_) if custom => return Err("`from` and `to` only apply to a custom range.".to_string()),
+        _ => {}
+    }
+
+    let months_ago = |months| now.checked_sub_months(Months::new(months));
+    let window = match period {
+        Some(PeriodChoice::Week) => TimeWindow { from: Some(now - Duration::days(7)), to: None, all_seasons: true },
+        Some(PeriodChoice::Month) => TimeWindow { from: months_ago(1), to: None, all_seasons: true },
+        Some(PeriodChoice::Year) => TimeWindow { from: months_ago(12), to: None, all_seasons: true },
+        Some(PeriodChoice::All) => TimeWindow { all_seasons: true, ..TimeWindow::default() },
+        Some(PeriodChoice::Season) => TimeWindow::default(),
+        None if !custom => TimeWindow::default(),
+        Some(PeriodChoice::Custom) | None => {
+            let from = from.map(parse_date).transpose()?;
+            let to = to.map(parse_date).transpose()?;
+            if let (Some(from), Some(to)) = (from, to) {
+                if from > to {
+                    return Err("The range ends before it starts.".to_string());
+                }
+            }
 
-    let target_user = match db.get_user_by_discord_id(target).await {
-        Ok(Some(user)) => user,
-        Ok(None) => return Err("Target user not found".into()),
-        Err(e) => return Err(e.into()),
+            TimeWindow {
+                from: from.map(|day| day.and_time(NaiveTime::MIN)),
+                to: to.map(|day| (day + Duration::days(1)).and_time(NaiveTime::MIN)),
+                all_seasons: true,
+            }
+        }
     };
 
-    db.add_bbp_to_user(&target_user, &issuing_user, &description).await?;
+    Ok(window)
+}
 
-    let ranked_user = match db.get_user_by_discord_id_with_rank(target).await {
-        Ok(Some(user)) => user,
-        Ok(None) => return Err("Ranked user not found".into()),
-        Err(e) => return Err(e.into()),
-    };
+fn parse_date(date: &str) -> Result<NaiveDate, String> {
+    NaiveDate::parse_from_str(date.trim(), "%Y-%m-%d").map_err(|_| format!("'{}' isn't a YYYY-MM-DD date.", date))
+}
 
-    Ok(Some(ranked_user))
+pub(crate) fn window_title(window: TimeWindow) -> Option<String> {
+    let day = |timestamp: NaiveDateTime| timestamp.format("%Y-%m-%d").to_string();
+    // `to` is exclusive, show the last day that's included
+    let last_day = |timestamp: NaiveDateTime| day(timestamp - Duration::days(1));
+
+    match (window.from, window.to) {
+        (None, None) if window.all_seasons => Some("All time".to_string()),
+        (None, None) => None,
+        (Some(from), None) => Some(format!("Since {}", day(from))),
+        (None, Some(to)) => Some(format!("Up to {}", last_day(to))),
+        (Some(from), Some(to)) => Some(format!("From {} to {}", day(from), last_day(to))),
+    }
 }
 
-async fn add_gbp(
-    ctx: &Context<'_>, 
-    issuer: i64, 
-    target: i64, 
-    description: &str
-) -> Result<(postgres_service::User, postgres_service::User, postgres_service::User), Box<dyn std::error::Error + Send + Sync>> {
-    let db = ctx.data().db.lock().await;
+/// "Season 2: 2024-01-01 to 2024-03-31", or "(running)" without an end.
+pub(crate) fn season_line(season: &Season) -> String {
+    let started = season.started_at.format("%Y-%m-%d");
 
-    let issuing_user = match db.get_user_by_discord_id(issuer).await {
-        Ok(Some(user)) => user,
-        Ok(None) => return Err("Issuing user not found".into()),
-        Err(e) => return Err(e.into()),
-    };
+    match season.ended_at {
+        Some(ended) => format!("Season {}: {} to {}", season.number, started, ended.format("%Y-%m-%d")),
+        None => format!("Season {}: {} (running)", season.number, started),
+    }
+}
 
-    let target_user = match db.get_user_by_discord_id(target).await {
-        Ok(Some(user)) => user,
-        Ok(None) => return Err("Target user not found".into()),
-        Err(e) => return Err(e.into()),
+/// "🔴 `B12` bbp from Alice -> late again (2024-05-01)", struck through once forgiven.
+pub(crate) fn history_line(record: &HistoryRecord) -> String {
+    let icon = match record.kind {
+        PointKind::Bbp => "🔴",
+        PointKind::Gbp => "🟢",
     };
+    let value = if record.value == 1 { String::new() } else { format!(" x{}", record.value) };
+    let line = format!("{} `{}` {}{} from {} -> {}",
+        icon,
+        record.kind.public_id(record.point_id),
+        record.kind.as_str(),
+        value,
+        record.issuer_friendly_name,
+        record.description);
+    let date = record.timestamp.format("%Y-%m-%d");
 
-    db.add_gbp_to_user(&target_user, &issuing_user, &description).await?;
+    match (record.forgiven, record.appeal) {
+        (true, Some(AppealStatus::Overturned)) => format!("~~{}~~ ({}, overturned on appeal)", line, date),
+        (true, _) => format!("~~{}~~ ({}, forgiven)", line, date),
+        (false, Some(AppealStatus::Open)) => format!("{} ({}, under appeal)", line, date),
+        (false, Some(AppealStatus::Upheld)) => format!("{} ({}, appeal upheld)", line, date),
+        (false, _) => format!("{} ({})", line, date),
+    }
+}
 
-    let ranked_user = match db.get_user_by_discord_id_with_rank(target).await {
-        Ok(Some(user)) => user,
-        Ok(None) => return Err("Ranked user not found".into()),
-        Err(e) => return Err(e.into()),
-    };
+/// "a bbp", or "a 3-point bbp" when it's worth more.
+pub(crate) fn a_point(kind: PointKind, value: i32) -> String {
+    if value == 1 {
+        format!("a {}", kind.as_str())
+    } else {
+        format!("a {}-point {}", value, kind.as_str())
+    }
+}
+
+pub(crate) fn display_name(user: &User) -> &str {
+    user.friendly_name.as_deref().unwrap_or("Unknown")
+}
+
+fn mention(user: &User) -> String {
+    user.discord_mention.clone().unwrap_or_else(|| format!("<@{}>", user.discord_id))
+}
+
+/// "Name(#rank) now has 3 bbps and 1 gbp, a net score of 2."
+pub(crate) fn standing(user: &User) -> Result<String, StoreError> {
+    let points = net_score(user.bbp_total, user.gbp_total);
+
+    Ok(format!("{}(#{}) now has {} and {}, a net score of {}{}.",
+        display_name(user),
+        rank(user)?,
+        plural(user.bbp_total, "bbp"),
+        plural(user.gbp_total, "gbp"),
+        points,
+        after_decay(points, user.score)))
+}
+
+/// " (1.4 after decay)" when decay changed the score, otherwise nothing.
+pub(crate) fn after_decay(points: i32, score: Option<f64>) -> String {
+    match score {
+        Some(score) if (score - points as f64).abs() >= 0.05 => format!(" ({:.1} after decay)", score),
+        _ => String::new(),
+    }
+}
+
+pub(crate) fn plural(count: i32, noun: &str) -> String {
+    if count == 1 {
+        format!("{} {}", count, noun)
+    } else {
+        format!("{} {}s", count, noun)
+    }
+}
+
+/// Users handed back for a reply are always ranked; a missing rank means the
+/// store returned the wrong row.
+pub(crate) fn rank(user: &User) -> Result<i64, StoreError> {
+    user.rank.ok_or_else(|| StoreError::Integrity(format!("User {} has no rank", user.user_id)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use crate::dataaccess::clock::FixedClock;
+    use crate::dataaccess::memory_store::MemoryStore;
+    use crate::dataaccess::models::GuildSettings;
+
+    const GUILD: i64 = 1;
+    const ALICE: i64 = 10;
+    const BOB: i64 = 20;
+    const CAROL: i64 = 30;
+
+    async fn store() -> MemoryStore {
+        let db = MemoryStore::new();
+        for (discord_id, name) in [(ALICE, "Alice"), (BOB, "Bob"), (CAROL, "Carol")] {
+            db.add_user(GUILD, discord_id, &name.to_lowercase(), name).await.unwrap();
+        }
+        db
+    }
+
+    async fn issued(db: &MemoryStore, issuer: i64, target: i64, description: &str) -> String {
+        match bbp_add(db, GUILD, issuer, target, description, 1, &[]).await.unwrap() {
+            BbpOutcome::Issued(announcement) => announcement.text,
+            BbpOutcome::Accused(_) => panic!("there is no jury"),
+        }
+    }
+
+    #[tokio::test]
+    async fn bbp_add_issues_the_bbp() {
+        let db = store().await;
+
+        let msg = issued(&db, ALICE, BOB, "late again").await;
+
+        assert_eq!(msg, "Alice has given <@20> a bbp (B1).\n\nlate again\n\nBob(#1) now has 1 bbp and 0 gbps, a net score of 1.");
+        let bob = db.get_user_by_discord_id(GUILD, BOB).await.unwrap().unwrap();
+        assert_eq!((bob.bbp_total, bob.points), (1, 1));
+    }
+
+    #[tokio::test]
+    async fn gbp_add_lowers_the_net_score() {
+        let db = store().await;
+        issued(&db, ALICE, BOB, "late again").await;
+
+        let msg = gbp_add(&db, GUILD, ALICE, BOB, "brought donuts", 1, &[]).await.unwrap().text;
+
+        assert_eq!(msg, "Alice has given <@20> a gbp (G1) 😇\n\nbrought donuts\n\nBob(#1) now has 1 bbp and 1 gbp, a net score of 0.");
+    }
+
+    #[tokio::test]
+    async fn gbp_reply_shows_both_totals_and_the_net() {
+        let db = store().await;
+        for description in ["one", "two", "three"] {
+            issued(&db, ALICE, BOB, description).await;
+        }
+
+        let msg = gbp_add(&db, GUILD, CAROL, BOB, "fixed the build", 1, &[]).await.unwrap().text;
+
+        assert!(msg.ends_with("Bob(#1) now has 3 bbps and 1 gbp, a net score of 2."), "{}", msg);
+    }
+
+    #[tokio::test]
+    async fn gbp_to_yourself_is_a_bbp() {
+        let db = store().await;
+
+        let announcement = gbp_add(&db, GUILD, ALICE, ALICE, "I'm great", 1, &[]).await.unwrap();
+
+        assert_eq!((announcement.kind, announcement.point_id), (PointKind::Bbp, 1));
+        let msg = announcement.text;
+        assert!(msg.starts_with("😡 trying to give yourself a gbp? That's a bbp (B1) for you."), "{}", msg);
+        let alice = db.get_user_by_discord_id(GUILD, ALICE).await.unwrap().unwrap();
+        assert_eq!((alice.bbp_total, alice.gbp_total), (1, 0));
+    }
+
+    #[tokio::test]
+    async fn point_for_unregistered_target_fails() {
+        let db = store().await;
+
+        let bbp = bbp_add(&db, GUILD, ALICE, 99, "who?", 1, &[]).await;
+        let gbp = gbp_add(&db, GUILD, ALICE, 99, "who?", 1, &[]).await;
+
+        assert!(matches!(bbp, Err(StoreError::UserNotFound(UserRole::Target))));
+        assert!(matches!(gbp, Err(StoreError::UserNotFound(UserRole::Target))));
+    }
+
+    const MODERATOR_ROLE: i64 = 99;
+
+    async fn limited_store() -> MemoryStore {
+        let db = store().await;
+        db.save_guild_settings(GuildSettings { guild_id: GUILD, max_value: 3, moderator_max_value: 5, moderator_role_id: Some(MODERATOR_ROLE), ..GuildSettings::default() }).await.unwrap();
+        db
+    }
+
+    #[tokio::test]
+    async fn points_worth_nothing_or_less_are_refused() {
+        let db = limited_store().await;
+
+        for value in [0, -2] {
+            let bbp = bbp_add(&db, GUILD, ALICE, BOB, "late", value, &[]).await;
+            let gbp = gbp_add(&db, GUILD, ALICE, BOB, "donuts", value, &[]).await;
+            assert!(matches!(bbp, Err(StoreError::ValueTooLow)));
+            assert!(matches!(gbp, Err(StoreError::ValueTooLow)));
+        }
+        let bob = db.get_user_by_discord_id(GUILD, BOB).await.unwrap().unwrap();
+        assert_eq!((bob.bbp_total, bob.gbp_total), (0, 0));
+    }
+
+    #[tokio::test]
+    async fn points_up_to_the_guilds_max_value_are_allowed() {
+        let db = limited_store().await;
+
+        bbp_add(&db, GUILD, ALICE, BOB, "late", 3, &[]).await.unwrap();
+        let over = bbp_add(&db, GUILD, ALICE, BOB, "late", 4, &[]).await;
+        let over_gbp = gbp_add(&db, GUILD, ALICE, BOB, "donuts", 4, &[]).await;
+
+        assert!(matches!(over, Err(StoreError::ValueNotAllowed { max: 3 })));
+        assert!(matches!(over_gbp, Err(StoreError::ValueNotAllowed { max: 3 })));
+        assert_eq!(db.get_user_by_discord_id(GUILD, BOB).await.unwrap().unwrap().points, 3);
+    }
+
+    #[tokio::test]
+    async fn moderators_get_the_higher_cap() {
+        let db = limited_store().await;
 
-    Ok((issuing_user, target_user, ranked_user))
-}
\ No newline at end of file
+        bbp_add(&db, GUILD, ALICE, BOB, "late", 5, &[1, MODERATOR_ROLE]).await.unwrap();
+        let over = bbp_add(&db, GUILD, ALICE, BOB, "late", 6, &[MODERATOR_ROLE]).await;
+        let other_role = bbp_add(&db, GUILD, ALICE, BOB, "late", 5, &[1]).await;
+
+        assert!(matches!(over, Err(StoreError::ValueNotAllowed { max: 5 })));
+        assert!(matches!(other_role, Err(StoreError::ValueNotAllowed { max: 3 })));
+    }
+
+    #[tokio::test]
+    async fn single_points_ignore_the_limits() {
+        let db = store().await;
+        db.save_guild_settings(GuildSettings { guild_id: GUILD, max_value: 1, ..GuildSettings::default() }).await.unwrap();
+
+        issued(&db, ALICE, BOB, "late").await;
+        let double = bbp_add(&db, GUILD, ALICE, BOB, "late", 2, &[]).await;
+
+        assert!(matches!(double, Err(StoreError::ValueNotAllowed { max: 1 })));
+    }
+
+    #[tokio::test]
+    async fn forgive_takes_the_most_recent_bbp_from_the_forgiver() {
+        let db = store().await;
+        issued(&db, ALICE, BOB, "late again").await;
+        issued(&db, CAROL, BOB, "ate my lunch").await;
+        issued(&db, ALICE, BOB, "missed standup").await;
+
+        let msg = bbp_forgive(&db, GUILD, ALICE, BOB, None, false).await.unwrap();
+
+        assert_eq!(msg, "Bob was forgiven for 'missed standup' (B3). Bob(#1) now has 2 bbps and 0 gbps, a net score of 2.");
+    }
+
+    #[tokio::test]
+    async fn forgive_without_bbps_from_the_forgiver_fails() {
+        let db = store().await;
+        issued(&db, CAROL, BOB, "ate my lunch").await;
+
+        let result = bbp_forgive(&db, GUILD, ALICE, BOB, None, false).await;
+
+        assert!(matches!(result, Err(StoreError::NothingToForgive)));
+    }
+
+    #[tokio::test]
+    async fn forgive_an_unregistered_target_fails() {
+        let db = store().await;
+
+        let result = bbp_forgive(&db, GUILD, ALICE, 99, None, false).await;
+
+        assert!(matches!(result, Err(StoreError::UserNotFound(UserRole::Target))));
+    }
+
+    #[tokio::test]
+    async fn forgive_by_id_picks_that_bbp() {
+        let db = store().await;
+        issued(&db, ALICE, BOB, "late again").await;
+        issued(&db, ALICE, BOB, "missed standup").await;
+
+        let msg = bbp_forgive(&db, GUILD, ALICE, BOB, Some(1), false).await.unwrap();
+
+        assert_eq!(msg, "Bob was forgiven for 'late again' (B1). Bob(#1) now has 1 bbp and 0 gbps, a net score of 1.");
+    }
+
+    #[tokio::test]
+    async fn only_the_issuer_or_an_owner_may_forgive() {
+        let db = store().await;
+        issued(&db, CAROL, BOB, "ate my lunch").await;
+
+        let by_alice = bbp_forgive(&db, GUILD, ALICE, BOB, Some(1), false).await;
+        // Owners needn't be registered, and may forgive anyone's bbp
+        let by_owner = bbp_forgive(&db, GUILD, 99, BOB, Some(1), true).await.unwrap();
+
+        assert!(matches!(by_alice, Err(StoreError::NothingToForgive)), "{:?}", by_alice);
+        assert_eq!(by_owner, "Bob was forgiven for 'ate my lunch' (B1). Bob(#1) now has 0 bbps and 0 gbps, a net score of 0.");
+        let bbp = db.get_point(GUILD, PointKind::Bbp, 1).await.unwrap().unwrap();
+        assert_eq!(bbp.forgiven_by_discord_id, Some(99));
+    }
+
+    #[tokio::test]
+    async fn forgive_by_an_unregistered_member_fails() {
+        let db = store().await;
+        issued(&db, ALICE, BOB, "late again").await;
+
+        let result = bbp_forgive(&db, GUILD, 99, BOB, None, false).await;
+
+        assert!(matches!(result, Err(StoreError::UserNotFound(UserRole::Issuer))), "{:?}", result);
+    }
+
+    #[tokio::test]
+    async fn forgive_a_deactivated_target_fails() {
+        let db = store().await;
+        issued(&db, ALICE, BOB, "late again").await;
+        db.set_user_active(GUILD, BOB, false).await.unwrap();
+
+        let result = bbp_forgive(&db, GUILD, ALICE, BOB, None, true).await;
+
+        assert!(matches!(result, Err(StoreError::UserInactive(UserRole::Target))), "{:?}", result);
+    }
+
+    #[tokio::test]
+    async fn unforgive_makes_the_bbp_count_again() {
+        let db = store().await;
+        issued(&db, ALICE, BOB, "late again").await;
+        issued(&db, ALICE, BOB, "missed standup").await;
+        bbp_forgive(&db, GUILD, ALICE, BOB, Some(1), false).await.unwrap();
+        bbp_forgive(&db, GUILD, ALICE, BOB, Some(2), false).await.unwrap();
+
+        let msg = bbp_unforgive(&db, GUILD, ALICE, BOB, Some(1), false).await.unwrap();
+
+        assert_eq!(msg, "The bbp Bob got for 'late again' (B1) counts again. Bob(#1) now has 1 bbp and 0 gbps, a net score of 1.");
+        let nothing_left = bbp_unforgive(&db, GUILD, ALICE, BOB, Some(1), false).await;
+        assert!(matches!(nothing_left, Err(StoreError::NothingToUnforgive)), "{:?}", nothing_left);
+    }
+
+    #[tokio::test]
+    async fn only_the_issuer_or_an_owner_may_unforgive() {
+        let db = store().await;
+        issued(&db, ALICE, BOB, "late again").await;
+        bbp_forgive(&db, GUILD, ALICE, BOB, None, false).await.unwrap();
+
+        let by_carol = bbp_unforgive(&db, GUILD, CAROL, BOB, None, false).await;
+        let by_owner = bbp_unforgive(&db, GUILD, CAROL, BOB, None, true).await;
+
+        assert!(matches!(by_carol, Err(StoreError::NothingToUnforgive)), "{:?}", by_carol);
+        assert!(by_owner.is_ok(), "{:?}", by_owner);
+        assert_eq!(db.get_user_by_discord_id(GUILD, BOB).await.unwrap().unwrap().points, 1);
+    }
+
+    #[tokio::test]
+    async fn leaderboard_puts_the_most_bbps_first() {
+        let db = store().await;
+        issued(&db, ALICE, CAROL, "one").await;
+        issued(&db, ALICE, CAROL, "two").await;
+        issued(&db, CAROL, BOB, "three").await;
+
+        let page = leaderboard(&db, GUILD, Standings::Live(TimeWindow::default()), 0).await.unwrap();
+
+        let lines: Vec<&str> = page.text.lines().collect();
+        assert_eq!(lines, [
+            "1. Carol (2 points, 1 bbps given, 0 gbps given)",
+            "2. Bob (1 points, 0 bbps given, 0 gbps given)",
+            "3. Alice (0 points, 2 bbps given, 0 gbps given)",
+        ]);
+        assert!(!page.has_next);
+    }
+
+    #[tokio::test]
+    async fn history_lists_newest_first() {
+        let db = store().await;
+        issued(&db, ALICE, BOB, "late again").await;
+        gbp_add(&db, GUILD, CAROL, BOB, "brought donuts", 1, &[]).await.unwrap();
+
+        let page = history(&db, GUILD, BOB, HistoryFilter::default(), 0).await.unwrap();
+
+        let lines: Vec<&str> = page.text.lines().collect();
+        assert_eq!(lines[0], "History for Bob");
+        assert!(lines[2].starts_with("🟢 `G1` gbp from Carol -> brought donuts ("), "{}", lines[2]);
+        assert!(lines[3].starts_with("🔴 `B1` bbp from Alice -> late again ("), "{}", lines[3]);
+    }
+
+    #[tokio::test]
+    async fn history_shows_the_raw_and_decayed_score() {
+        let clock = Arc::new(FixedClock::at(NaiveDate::from_ymd_opt(2024, 5, 1).unwrap().and_hms_opt(12, 0, 0).unwrap()));
+        let db = store().await.with_clock(clock.clone());
+        db.save_guild_settings(GuildSettings { guild_id: GUILD, decay: DecayPolicy::HalfLife { days: 2 }, ..GuildSettings::default() }).await.unwrap();
+        issued(&db, ALICE, BOB, "late again").await;
+        issued(&db, CAROL, BOB, "ate my lunch").await;
+
+        clock.advance(Duration::days(2));
+        let page = history(&db, GUILD, BOB, HistoryFilter::default(), 0).await.unwrap();
+
+        assert_eq!(page.text.lines().nth(1), Some("Net score 2, 1.0 after decay (bbps lose half their weight every 2 days)"));
+    }
+
+    #[tokio::test]
+    async fn history_of_an_unregistered_user_fails() {
+        let db = store().await;
+
+        let result = history(&db, GUILD, 99, HistoryFilter::default(), 0).await;
+
+        assert!(matches!(result, Err(StoreError::UserNotFound(UserRole::Target))));
+    }
+
+    fn at(year: i32, month: u32, day: u32, hour: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap().and_hms_opt(hour, 0, 0).unwrap()
+    }
+
+    #[test]
+    fn custom_range_covers_both_days() {
+        let window = period_window(Some(PeriodChoice::Custom), Some("2024-05-01"), Some(" 2024-05-03 "), at(2024, 6, 1, 0)).unwrap();
+
+        assert_eq!((window.from, window.to, window.all_seasons), (Some(at(2024, 5, 1, 0)), Some(at(2024, 5, 4, 0)), true));
+        assert_eq!(period_window(None, None, Some("2024-05-03"), at(2024, 6, 1, 0)).unwrap().from, None);
+    }
+
+    #[test]
+    fn custom_range_ending_before_it_starts_is_refused() {
+        let backwards = period_window(Some(PeriodChoice::Custom), Some("2024-05-03"), Some("2024-05-01"), at(2024, 6, 1, 0));
+        let one_day = period_window(None, Some("2024-05-03"), Some("2024-05-03"), at(2024, 6, 1, 0));
+
+        assert_eq!(backwards.unwrap_err(), "The range ends before it starts.");
+        assert_eq!(one_day.unwrap().to, Some(at(2024, 5, 4, 0)));
+    }
+
+    #[test]
+    fn malformed_dates_are_refused() {
+        for date in ["2024-13-01", "2024-02-30", "01/05/2024", "yesterday", ""] {
+            let result = period_window(None, Some(date), None, at(2024, 6, 1, 0));
+            assert_eq!(result.unwrap_err(), format!("'{}' isn't a YYYY-MM-DD date.", date));
+        }
+    }
+
+    #[test]
+    fn periods_and_dates_dont_mix() {
+        let now = at(2024, 6, 1, 0);
+
+        assert_eq!(period_window(Some(PeriodChoice::Week), Some("2024-05-01"), None, now).unwrap_err(), "`from` and `to` only apply to a custom range.");
+        assert_eq!(period_window(Some(PeriodChoice::Custom), None, None, now).unwrap_err(), "A custom range needs a `from` or `to` date.");
+        assert_eq!(period_window(None, None, None, now).unwrap(), TimeWindow::default());
+    }
+
+    #[tokio::test]
+    async fn month_and_year_clamp_to_the_end_of_shorter_months() {
+        let clock = Arc::new(FixedClock::at(at(2024, 3, 31, 15)));
+        let db = store().await.with_clock(clock.clone());
+
+        let month = period_window(Some(PeriodChoice::Month), None, None, db.now()).unwrap();
+        assert_eq!(month.from, Some(at(2024, 2, 29, 15)));
+
+        clock.advance(Duration::days(335));
+        assert_eq!(db.now(), at(2025, 3, 1, 15));
+        let year = period_window(Some(PeriodChoice::Year), None, None, db.now()).unwrap();
+        let week = period_window(Some(PeriodChoice::Week), None, None, db.now()).unwrap();
+        assert_eq!(year.from, Some(at(2024, 3, 1, 15)));
+        assert_eq!(week.from, Some(at(2025, 2, 22, 15)));
+    }
+
+    #[tokio::test]
+    async fn month_window_counts_points_from_exactly_a_month_ago() {
+        let clock = Arc::new(FixedClock::at(at(2024, 4, 30, 9)));
+        let db = store().await.with_clock(clock.clone());
+        issued(&db, ALICE, BOB, "too old").await;
+        clock.advance(Duration::hours(1));
+        issued(&db, ALICE, CAROL, "just in time").await;
+        clock.advance(Duration::days(31));
+
+        let window = period_window(Some(PeriodChoice::Month), None, None, db.now()).unwrap();
+        let page = leaderboard(&db, GUILD, Standings::Live(window), 0).await.unwrap();
+
+        // May 31st goes back to the end of April
+        assert_eq!(window.from, Some(at(2024, 4, 30, 10)));
+        let lines: Vec<&str> = page.text.lines().collect();
+        assert_eq!(lines[0], "Since 2024-04-30");
+        assert!(lines.contains(&"1. Carol (1 points, 0 bbps given, 0 gbps given)"), "{}", page.text);
+        assert!(lines.contains(&"2. Bob (0 points, 0 bbps given, 0 gbps given)"), "{}", page.text);
+    }
+}
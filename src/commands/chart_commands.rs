@@ -0,0 +1,193 @@
+use crate::{Context, Error};
+use crate::charts::{self, Bar};
+use crate::commands::bbp_commands::{display_name, period_window, window_title, PeriodChoice};
+use crate::commands::responses::{reject, respond};
+use crate::dataaccess::bbp_store::BbpStore;
+use crate::dataaccess::models::{HistoryRecord, Page, PointKind, Standings, TimeWindow};
+use crate::dataaccess::store_error::{StoreError, UserRole};
+use chrono::{Duration, NaiveDate, NaiveDateTime};
+use poise::serenity_prelude as serenity;
+
+/// How many users the leaderboard chart shows
+const CHART_USERS: i64 = 10;
+
+#[poise::command(
+    slash_command,
+    guild_only,
+    rename = "chart",
+    subcommands("chart_leaderboard_command", "chart_trend_command"),
+    subcommand_required
+)]
+pub async fn chart_command(_ctx: Context<'_>) -> Result<(), Error> {
+    Ok(())
+}
+
+#[poise::command(slash_command, guild_only, rename = "leaderboard", user_cooldown = 30)]
+pub async fn chart_leaderboard_command(
+    ctx: Context<'_>,
+    #[description = "Only count bbps and gbps from this period"] period: Option<PeriodChoice>,
+    #[description = "Start of a custom range, YYYY-MM-DD"] from: Option<String>,
+    #[description = "Last day of a custom range, YYYY-MM-DD"] to: Option<String>,
+) -> Result<(), Error> {
+    let db = ctx.data().db.as_ref();
+    let now = db.now();
+    let window = match period_window(period, from.as_deref(), to.as_deref(), now) {
+        Ok(window) => window,
+        Err(msg) => return reject(ctx, &msg).await,
+    };
+    let guild_id = ctx.guild_id().unwrap().get() as i64;
+
+    match leaderboard_bars(db, guild_id, window).await {
+        Ok((_, bars)) if bars.is_empty() => reject(ctx, "Nobody is on the leaderboard yet.").await,
+        Ok((title, bars)) => send_png(ctx, charts::leaderboard_png(&title, &bars)?, "leaderboard.png").await,
+        Err(e) => respond(ctx, Err(e)).await,
+    }
+}
+
+#[poise::command(slash_command, guild_only, rename = "trend", user_cooldown = 30)]
+pub async fn chart_trend_command(
+    ctx: Context<'_>,
+    user: Option<serenity::User>,
+    #[description = "Only count bbps and gbps from this period"] period: Option<PeriodChoice>,
+    #[description = "Start of a custom range, YYYY-MM-DD"] from: Option<String>,
+    #[description = "Last day of a custom range, YYYY-MM-DD"] to: Option<String>,
+) -> Result<(), Error> {
+    let db = ctx.data().db.as_ref();
+    let now = db.now();
+    let window = match period_window(period, from.as_deref(), to.as_deref(), now) {
+        Ok(window) => window,
+        Err(msg) => return reject(ctx, &msg).await,
+    };
+    let target_user = match user {
+        Some(u) => u,
+        None => ctx.author().clone(),
+    };
+    let guild_id = ctx.guild_id().unwrap().get() as i64;
+
+    match trend_days(db, guild_id, target_user.id.get() as i64, window, now).await {
+        Ok((title, days)) => send_png(ctx, charts::trend_png(&title, &days)?, "trend.png").await,
+        Err(e) => respond(ctx, Err(e)).await,
+    }
+}
+
+async fn send_png(ctx: Context<'_>, png: Vec<u8>, file_name: &str) -> Result<(), Error> {
+    let reply = poise::CreateReply::default().attachment(serenity::CreateAttachment::bytes(png, file_name));
+    ctx.send(reply).await?;
+
+    Ok(())
+}
+
+/// The chart title and the top of the leaderboard as bars of their score.
+pub async fn leaderboard_bars(db: &dyn BbpStore, guild_id: i64, window: TimeWindow) -> Result<(String, Vec<Bar>), StoreError> {
+    let users = db.get_leaderboard(guild_id, Standings::Live(window), Page { offset: 0, limit: CHART_USERS }).await?;
+
+    let title = match window_title(window) {
+        Some(period) => format!("Leaderboard, {}", period.to_lowercase()),
+        None => "Leaderboard".to_string(),
+    };
+    let bars = users.into_iter()
+        .map(|user| Bar { label: user.friendly_name.unwrap_or_default(), value: user.score })
+        .collect();
+
+    Ok((title, bars))
+}
+
+/// The chart title and the user's running net score over the window, as of `now`.
+pub async fn trend_days(db: &dyn BbpStore, guild_id: i64, user_id: i64, window: TimeWindow, now: NaiveDateTime) -> Result<(String, Vec<(NaiveDate, i32)>), StoreError> {
+    let user = db.get_user_by_discord_id(guild_id, user_id).await?
+        .ok_or(StoreError::UserNotFound(UserRole::Target))?;
+    let timeline = db.get_point_timeline(guild_id, user_id, window).await?;
+
+    let title = match window_title(window) {
+        Some(period) => format!("Net score of {}, {}", display_name(&user), period.to_lowercase()),
+        None => format!("Net score of {} this season", display_name(&user)),
+    };
+    // `to` is exclusive, the last day shown is the one before it
+    let last_day = window.to.map(|to| (to - Duration::days(1)).date()).unwrap_or(now.date()).min(now.date());
+
+    Ok((title, running_score(&timeline, window.from.map(|from| from.date()), last_day)))
+}
+
+/// The net score at the end of every day from `first_day` (or the first record)
+/// through `last_day`, counting only the given records. Forgiven bbps don't count.
+pub fn running_score(timeline: &[HistoryRecord], first_day: Option<NaiveDate>, last_day: NaiveDate) -> Vec<(NaiveDate, i32)> {
+    let first_day = first_day
+        .or_else(|| timeline.first().map(|record| record.timestamp.date()))
+        .unwrap_or(last_day)
+        .min(last_day);
+
+    let mut records = timeline.iter().filter(|record| !record.forgiven).peekable();
+    let mut score = 0;
+    first_day.iter_days()
+        .take_while(|day| *day <= last_day)
+        .map(|day| {
+            while let Some(record) = records.next_if(|record| record.timestamp.date() <= day) {
+                score += match record.kind {
+                    PointKind::Bbp => record.value,
+                    PointKind::Gbp => -record.value,
+                };
+            }
+            (day, score)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn day(day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2024, 5, day).unwrap()
+    }
+
+    fn record(kind: PointKind, value: i32, day: u32, forgiven: bool) -> HistoryRecord {
+        HistoryRecord {
+            kind,
+            point_id: 1,
+            value,
+            forgiven,
+            issuer_discord_id: 10,
+            issuer_friendly_name: "Alice".to_string(),
+            description: "late".to_string(),
+            timestamp: self::day(day).and_hms_opt(12, 0, 0).unwrap(),
+            appeal: None,
+        }
+    }
+
+    #[test]
+    fn empty_timeline_is_a_single_day_at_zero() {
+        assert_eq!(running_score(&[], None, day(5)), [(day(5), 0)]);
+    }
+
+    #[test]
+    fn single_day_sums_that_days_points() {
+        let timeline = [record(PointKind::Bbp, 2, 3, false), record(PointKind::Gbp, 1, 3, false)];
+
+        assert_eq!(running_score(&timeline, None, day(3)), [(day(3), 1)]);
+    }
+
+    #[test]
+    fn every_day_carries_the_score_forward() {
+        let timeline = [
+            record(PointKind::Bbp, 2, 1, false),
+            record(PointKind::Bbp, 5, 2, true),
+            record(PointKind::Gbp, 3, 3, false),
+        ];
+
+        assert_eq!(running_score(&timeline, None, day(4)), [(day(1), 2), (day(2), 2), (day(3), -1), (day(4), -1)]);
+    }
+
+    #[test]
+    fn custom_range_starts_on_its_first_day() {
+        let timeline = [record(PointKind::Bbp, 1, 4, false), record(PointKind::Bbp, 1, 6, false)];
+
+        assert_eq!(running_score(&timeline, Some(day(3)), day(6)), [(day(3), 0), (day(4), 1), (day(5), 1), (day(6), 2)]);
+    }
+
+    #[test]
+    fn range_starting_after_its_last_day_is_just_the_last_day() {
+        let timeline = [record(PointKind::Bbp, 1, 2, false)];
+
+        assert_eq!(running_score(&timeline, Some(day(9)), day(4)), [(day(4), 1)]);
+    }
+}
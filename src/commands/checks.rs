@@ -0,0 +1,28 @@
+use crate::{Context, Error};
+use crate::commands::bbp_commands::member_role_ids;
+use crate::commands::responses::{error_message, reject};
+
+/// Lets bot owners and members with the guild's moderator role through, and
+/// tells everyone else why the command didn't run.
+pub async fn owner_or_moderator(ctx: Context<'_>) -> Result<bool, Error> {
+    if ctx.framework().options().owners.contains(&ctx.author().id) {
+        return Ok(true);
+    }
+    let Some(guild_id) = ctx.guild_id() else {
+        return Ok(false);
+    };
+
+    let settings = match ctx.data().db.get_guild_settings(guild_id.get() as i64).await {
+        Ok(settings) => settings,
+        Err(e) => {
+            reject(ctx, &error_message(&e)).await?;
+            return Ok(false);
+        }
+    };
+    if settings.is_moderator(&member_role_ids(ctx).await) {
+        return Ok(true);
+    }
+
+    reject(ctx, "Only owners and moderators can do that.").await?;
+    Ok(false)
+}
@@ -0,0 +1,229 @@
+use std::fmt::Write;
+
+use crate::{Context, Error};
+use crate::commands::bbp_commands::{a_point, standing};
+use crate::commands::pagination::PAGE_SIZE;
+use crate::commands::responses::{reject, respond};
+use crate::commands::show_commands::discord_time;
+use crate::dataaccess::bbp_store::BbpStore;
+use crate::dataaccess::models::{AuditAction, AuditEntry, Page, PointKind};
+use crate::dataaccess::store_error::StoreError;
+
+#[poise::command(slash_command, guild_only, rename = "bbp-edit")]
+pub async fn point_edit_command(
+    ctx: Context<'_>,
+    #[description = "The bbp or gbp you gave, e.g. B12 or G7"] id: String,
+    #[description = "What it should say instead"] description: String,
+) -> Result<(), Error> {
+    let Some((kind, point_id)) = PointKind::parse_public_id(&id) else {
+        return reject(ctx, "That isn't a bbp or gbp ID, they look like B12 or G7.").await;
+    };
+    let issuer = ctx.author().id.get() as i64;
+    let guild_id = ctx.guild_id().unwrap().get() as i64;
+    let db = ctx.data().db.as_ref();
+
+    respond(ctx, point_edit(db, guild_id, issuer, kind, point_id, &description).await).await
+}
+
+#[poise::command(slash_command, guild_only, rename = "retract")]
+pub async fn point_retract_command(
+    ctx: Context<'_>,
+    #[description = "The bbp or gbp you gave, e.g. B12 or G7"] id: String,
+) -> Result<(), Error> {
+    let Some((kind, point_id)) = PointKind::parse_public_id(&id) else {
+        return reject(ctx, "That isn't a bbp or gbp ID, they look like B12 or G7.").await;
+    };
+    let issuer = ctx.author().id.get() as i64;
+    let guild_id = ctx.guild_id().unwrap().get() as i64;
+    let db = ctx.data().db.as_ref();
+
+    respond(ctx, point_retract(db, guild_id, issuer, kind, point_id).await).await
+}
+
+#[poise::command(slash_command, guild_only, rename = "audit", owners_only)]
+pub async fn audit_command(
+    ctx: Context<'_>,
+    #[description = "Which page of older entries, 1 by default"] #[min = 1] page: Option<i64>,
+) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().unwrap().get() as i64;
+    let db = ctx.data().db.as_ref();
+
+    respond(ctx, audit(db, guild_id, page.unwrap_or(1)).await).await
+}
+
+/// Replaces the description of a bbp/gbp the issuer gave within the guild's
+/// grace period.
+pub async fn point_edit(db: &dyn BbpStore, guild_id: i64, issuer: i64, kind: PointKind, point_id: i32, description: &str) -> Result<String, StoreError> {
+    let grace = db.get_guild_settings(guild_id).await?.grace_period();
+
+    let entry = db.edit_point(guild_id, kind, point_id, issuer, description, grace).await?;
+
+    Ok(format!("{} for <@{}> now reads:\n\n{}",
+        kind.public_id(entry.point_id),
+        entry.target_discord_id,
+        description))
+}
+
+/// Takes back a bbp/gbp the issuer gave within the guild's grace period. Unlike
+/// forgiving, it's gone for good and only the audit log remembers it.
+pub async fn point_retract(db: &dyn BbpStore, guild_id: i64, issuer: i64, kind: PointKind, point_id: i32) -> Result<String, StoreError> {
+    let grace = db.get_guild_settings(guild_id).await?.grace_period();
+
+    let entry = db.retract_point(guild_id, kind, point_id, issuer, grace).await?;
+
+    let ranked_user = db.get_user_by_discord_id_with_rank(guild_id, entry.target_discord_id).await?
+        .ok_or_else(|| StoreError::Integrity("Ranked user not found".to_string()))?;
+    let mut response = format!("<@{}> took back {} ({}) for '{}'.",
+        issuer,
+        a_point(kind, entry.value),
+        kind.public_id(entry.point_id),
+        entry.old_description);
+    // Deactivated users have no standing to report
+    if ranked_user.rank.is_some() {
+        let _ = write!(response, " {}", standing(&ranked_user)?);
+    }
+
+    Ok(response)
+}
+
+/// One page of the guild's edits and retractions, newest first.
+pub async fn audit(db: &dyn BbpStore, guild_id: i64, page: i64) -> Result<String, StoreError> {
+    let offset = (page.max(1) - 1) * PAGE_SIZE;
+    let entries = db.get_audit_log(guild_id, Page { offset, limit: PAGE_SIZE }).await?;
+
+    let mut response = format!("Audit log, page {}\n\n", page.max(1));
+    if entries.is_empty() {
+        response.push_str("Nothing here yet");
+    }
+    for entry in &entries {
+        let _ = writeln!(response, "{}", audit_line(entry));
+    }
+
+    Ok(response)
+}
+
+/// "`B12` bbp for <@2> retracted by <@1> <t:..:f>: 'late again'"
+fn audit_line(entry: &AuditEntry) -> String {
+    let point = format!("`{}` {} for <@{}>", entry.kind.public_id(entry.point_id), entry.kind.as_str(), entry.target_discord_id);
+
+    match entry.action {
+        AuditAction::Edit => format!("{} edited by <@{}> {}: '{}' -> '{}'",
+            point,
+            entry.actor_discord_id,
+            discord_time(entry.at),
+            entry.old_description,
+            entry.new_description.as_deref().unwrap_or_default()),
+        AuditAction::Retract => format!("{} retracted by <@{}> {}: '{}'",
+            point,
+            entry.actor_discord_id,
+            discord_time(entry.at),
+            entry.old_description),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use crate::commands::responses::error_message;
+    use crate::dataaccess::clock::FixedClock;
+    use crate::dataaccess::memory_store::MemoryStore;
+    use crate::dataaccess::models::GuildSettings;
+
+    const GUILD: i64 = 1;
+    const ALICE: i64 = 10;
+    const BOB: i64 = 20;
+    const CAROL: i64 = 30;
+
+    fn start() -> chrono::NaiveDateTime {
+        chrono::NaiveDate::from_ymd_opt(2024, 5, 1).unwrap().and_hms_opt(12, 0, 0).unwrap()
+    }
+
+    /// A guild with a 5 minute grace period where Alice just gave Bob B1.
+    async fn store() -> (MemoryStore, Arc<FixedClock>) {
+        let clock = Arc::new(FixedClock::at(start()));
+        let db = MemoryStore::new().with_clock(clock.clone());
+        for (discord_id, name) in [(ALICE, "Alice"), (BOB, "Bob"), (CAROL, "Carol")] {
+            db.add_user(GUILD, discord_id, &name.to_lowercase(), name).await.unwrap();
+        }
+        db.save_guild_settings(GuildSettings { guild_id: GUILD, grace_minutes: 5, ..GuildSettings::default() }).await.unwrap();
+        db.issue_point(GUILD, PointKind::Bbp, 1, ALICE, BOB, "late").await.unwrap();
+        (db, clock)
+    }
+
+    #[tokio::test]
+    async fn edit_replaces_the_description() {
+        let (db, _clock) = store().await;
+
+        let msg = point_edit(&db, GUILD, ALICE, PointKind::Bbp, 1, "late again").await.unwrap();
+
+        assert_eq!(msg, "B1 for <@20> now reads:\n\nlate again");
+        assert_eq!(db.get_point(GUILD, PointKind::Bbp, 1).await.unwrap().unwrap().description, "late again");
+    }
+
+    #[tokio::test]
+    async fn the_grace_period_comes_from_the_guild_settings() {
+        let (db, clock) = store().await;
+
+        clock.advance(chrono::Duration::minutes(5) - chrono::Duration::seconds(1));
+        assert!(point_edit(&db, GUILD, ALICE, PointKind::Bbp, 1, "late again").await.is_ok());
+        clock.advance(chrono::Duration::seconds(1));
+        let edit = point_edit(&db, GUILD, ALICE, PointKind::Bbp, 1, "very late").await;
+        let retract = point_retract(&db, GUILD, ALICE, PointKind::Bbp, 1).await;
+
+        assert!(matches!(retract, Err(StoreError::GracePeriodOver { minutes: 5 })), "{:?}", retract);
+        assert_eq!(error_message(&edit.unwrap_err()), "Bbps and gbps can only be edited or retracted within 5 minutes of giving them.");
+    }
+
+    #[tokio::test]
+    async fn only_the_issuer_can_retract() {
+        let (db, _clock) = store().await;
+
+        let by_bob = point_retract(&db, GUILD, BOB, PointKind::Bbp, 1).await;
+        let by_carol = point_edit(&db, GUILD, CAROL, PointKind::Bbp, 1, "not late").await;
+
+        assert!(matches!(by_bob, Err(StoreError::NotEditable)), "{:?}", by_bob);
+        assert!(matches!(by_carol, Err(StoreError::NotEditable)), "{:?}", by_carol);
+        assert!(db.get_point(GUILD, PointKind::Bbp, 1).await.unwrap().is_some());
+    }
+
+    #[tokio::test]
+    async fn retract_removes_the_point_and_reports_the_standing() {
+        let (db, clock) = store().await;
+        db.issue_point(GUILD, PointKind::Bbp, 1, CAROL, BOB, "ate my lunch").await.unwrap();
+        clock.advance(chrono::Duration::minutes(1));
+
+        let msg = point_retract(&db, GUILD, ALICE, PointKind::Bbp, 1).await.unwrap();
+
+        assert_eq!(msg, "<@10> took back a bbp (B1) for 'late'. Bob(#1) now has 1 bbp and 0 gbps, a net score of 1.");
+        let log = audit(&db, GUILD, 1).await.unwrap();
+        assert_eq!(log, "Audit log, page 1\n\n`B1` bbp for <@20> retracted by <@10> <t:1714564860:f>: 'late'\n");
+    }
+
+    #[tokio::test]
+    async fn retracting_from_a_deactivated_user_leaves_out_the_standing() {
+        let (db, _clock) = store().await;
+        db.set_user_active(GUILD, BOB, false).await.unwrap();
+
+        let msg = point_retract(&db, GUILD, ALICE, PointKind::Bbp, 1).await.unwrap();
+
+        assert_eq!(msg, "<@10> took back a bbp (B1) for 'late'.");
+    }
+
+    #[tokio::test]
+    async fn audit_lists_edits_newest_first() {
+        let (db, clock) = store().await;
+        point_edit(&db, GUILD, ALICE, PointKind::Bbp, 1, "late again").await.unwrap();
+        clock.advance(chrono::Duration::minutes(1));
+        point_edit(&db, GUILD, ALICE, PointKind::Bbp, 1, "very late").await.unwrap();
+
+        let log = audit(&db, GUILD, 1).await.unwrap();
+        let empty = audit(&db, GUILD, 2).await.unwrap();
+
+        assert_eq!(log.lines().skip(2).collect::<Vec<_>>(), [
+            "`B1` bbp for <@20> edited by <@10> <t:1714564860:f>: 'late again' -> 'very late'",
+            "`B1` bbp for <@20> edited by <@10> <t:1714564800:f>: 'late' -> 'late again'",
+        ]);
+        assert_eq!(empty, "Audit log, page 2\n\nNothing here yet");
+    }
+}
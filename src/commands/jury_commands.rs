@@ -0,0 +1,261 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use log::error;
+use poise::serenity_prelude as serenity;
+
+use crate::{Context, Data, Error};
+use crate::commands::bbp_commands::{a_point, standing};
+use crate::commands::registration_commands::reply_privately;
+use crate::commands::responses::error_message;
+use crate::dataaccess::bbp_store::BbpStore;
+use crate::dataaccess::models::{JuryStatus, JuryVote, PendingBbp, PointKind};
+use crate::dataaccess::store_error::StoreError;
+
+// Vote buttons carry the pending bbp, e.g. "jury:for:12"
+const BUTTON_PREFIX: &str = "jury:";
+
+// How often the sweeper looks for pending bbps whose time ran out
+const SWEEP_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Replies to `/bbp` with the pending bbp and its vote buttons, and remembers
+/// the message so the sweeper can close it once voting runs out.
+pub async fn post_accusation(ctx: Context<'_>, pending: &PendingBbp) -> Result<(), Error> {
+    let reply = poise::CreateReply::default()
+        .content(pending_text(pending))
+        .components(vote_buttons(pending.pending_id));
+    let handle = ctx.send(reply).await?;
+    let message = handle.message().await?;
+
+    // The vote works without it, the message just won't say when it lapsed
+    let saved = ctx.data().db.set_pending_bbp_message(pending.pending_id, message.channel_id.get() as i64, message.id.get() as i64).await;
+    if let Err(e) = saved {
+        error!("Couldn't remember the message of pending bbp {}: {}", pending.pending_id, e);
+    }
+
+    Ok(())
+}
+
+/// Casts a vote and returns the new text for the accusation message.
+pub async fn jury_vote(db: &dyn BbpStore, pending_id: i32, voter: i64, in_favour: bool) -> Result<(String, JuryStatus), StoreError> {
+    let vote = db.vote_on_pending_bbp(pending_id, voter, in_favour).await?;
+
+    Ok((vote_text(&vote)?, vote.pending.status))
+}
+
+/// Handles presses of the vote buttons. Like the registration buttons they
+/// outlive the command that posted them, and keep working across restarts.
+pub async fn handle_button(ctx: &serenity::Context, press: &serenity::ComponentInteraction, data: &Data) -> Result<(), Error> {
+    let Some((in_favour, pending_id)) = parse_button(&press.data.custom_id) else {
+        return Ok(());
+    };
+
+    match jury_vote(data.db.as_ref(), pending_id, press.user.id.get() as i64, in_favour).await {
+        Ok((text, status)) => {
+            let mut update = serenity::CreateInteractionResponseMessage::new().content(text);
+            if status != JuryStatus::Pending {
+                update = update.components(vec![]);
+            }
+            press.create_response(ctx, serenity::CreateInteractionResponse::UpdateMessage(update)).await?;
+            Ok(())
+        }
+        Err(e) => {
+            error!("Voting on pending bbp {} failed: {}", pending_id, e);
+            reply_privately(ctx, press, &error_message(&e)).await
+        }
+    }
+}
+
+/// Expires pending bbps whose time ran out and takes the buttons off their
+/// messages, every minute for as long as the bot runs.
+pub async fn sweep_expired(http: Arc<serenity::Http>, db: Arc<dyn BbpStore>) {
+    let mut interval = tokio::time::interval(SWEEP_INTERVAL);
+
+    loop {
+        interval.tick().await;
+
+        let expired = match db.expire_pending_bbps().await {
+            Ok(expired) => expired,
+            Err(e) => {
+                error!("Expiring pending bbps failed: {}", e);
+                continue;
+            }
+        };
+        for pending in expired {
+            let (Some(channel_id), Some(message_id)) = (pending.channel_id, pending.message_id) else {
+                continue;
+            };
+            let edit = serenity::EditMessage::new().content(closed_text(&pending)).components(vec![]);
+            let edited = serenity::ChannelId::new(channel_id as u64)
+                .edit_message(&http, serenity::MessageId::new(message_id as u64), edit)
+                .await;
+            if let Err(e) = edited {
+                error!("Couldn't close the message of pending bbp {}: {}", pending.pending_id, e);
+            }
+        }
+    }
+}
+
+fn vote_text(vote: &JuryVote) -> Result<String, StoreError> {
+    match (&vote.issued, vote.pending.status) {
+        (Some(issued), _) => Ok(format!("{}\n\n{}", closed_text(&vote.pending), standing(&issued.ranked)?)),
+        (None, JuryStatus::Pending) => Ok(pending_text(&vote.pending)),
+        (None, _) => Ok(closed_text(&vote.pending)),
+    }
+}
+
+/// "<@1> wants to give <@2> a bbp, if the jury agrees." with the tally and when voting closes.
+fn pending_text(pending: &PendingBbp) -> String {
+    format!(
+        "<@{}> wants to give <@{}> {}, if the jury agrees.\n\n{}\n\n👍 {} 👎 {}, {} votes either way settle it. Voting closes <t:{}:R>.",
+        pending.issuer_discord_id,
+        pending.target_discord_id,
+        a_point(PointKind::Bbp, pending.value),
+        pending.description,
+        pending.votes_for,
+        pending.votes_against,
+        pending.quorum,
+        pending.expires_at.and_utc().timestamp())
+}
+
+fn closed_text(pending: &PendingBbp) -> String {
+    let verdict = match (pending.status, pending.bbp_id) {
+        (JuryStatus::Confirmed, Some(bbp_id)) => format!("The jury agreed, it's {}", PointKind::Bbp.public_id(bbp_id)),
+        (JuryStatus::Confirmed, None) => "The jury agreed".to_string(),
+        (JuryStatus::Vetoed, _) => "The jury threw it out".to_string(),
+        (JuryStatus::Expired | JuryStatus::Pending, _) => "The jury didn't decide in time, so it lapsed".to_string(),
+    };
+
+    format!(
+        "<@{}> wanted to give <@{}> {}. {} (👍 {} 👎 {}).\n\n{}",
+        pending.issuer_discord_id,
+        pending.target_discord_id,
+        a_point(PointKind::Bbp, pending.value),
+        verdict,
+        pending.votes_for,
+        pending.votes_against,
+        pending.description)
+}
+
+fn vote_buttons(pending_id: i32) -> Vec<serenity::CreateActionRow> {
+    let vote_for = format!("{}for:{}", BUTTON_PREFIX, pending_id);
+    let vote_against = format!("{}against:{}", BUTTON_PREFIX, pending_id);
+
+    vec![serenity::CreateActionRow::Buttons(vec![
+        serenity::CreateButton::new(vote_for).label("👍").style(serenity::ButtonStyle::Success),
+        serenity::CreateButton::new(vote_against).label("👎").style(serenity::ButtonStyle::Danger),
+    ])]
+}
+
+/// `Some((in_favour, pending_id))` for the buttons from `vote_buttons`.
+fn parse_button(custom_id: &str) -> Option<(bool, i32)> {
+    let (action, pending_id) = custom_id.strip_prefix(BUTTON_PREFIX)?.split_once(':')?;
+    let in_favour = match action {
+        "for" => true,
+        "against" => false,
+        _ => return None,
+    };
+
+    Some((in_favour, pending_id.parse().ok()?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::bbp_commands::{bbp_add, BbpOutcome};
+    use crate::dataaccess::clock::FixedClock;
+    use crate::dataaccess::memory_store::MemoryStore;
+    use crate::dataaccess::models::{GuildSettings, JuryPolicy};
+
+    const GUILD: i64 = 1;
+    const ALICE: i64 = 10;
+    const BOB: i64 = 20;
+    const CAROL: i64 = 30;
+    const DAVE: i64 = 40;
+
+    fn start() -> chrono::NaiveDateTime {
+        chrono::NaiveDate::from_ymd_opt(2024, 5, 1).unwrap().and_hms_opt(12, 0, 0).unwrap()
+    }
+
+    /// Alice accuses Bob in a guild whose jury needs 2 votes within 2 hours.
+    async fn accused() -> (MemoryStore, Arc<FixedClock>, PendingBbp) {
+        let clock = Arc::new(FixedClock::at(start()));
+        let db = MemoryStore::new().with_clock(clock.clone());
+        for (discord_id, name) in [(ALICE, "Alice"), (BOB, "Bob"), (CAROL, "Carol"), (DAVE, "Dave")] {
+            db.add_user(GUILD, discord_id, &name.to_lowercase(), name).await.unwrap();
+        }
+        let jury = JuryPolicy::On { quorum: 2, hours: 2 };
+        db.save_guild_settings(GuildSettings { guild_id: GUILD, jury, ..GuildSettings::default() }).await.unwrap();
+
+        match bbp_add(&db, GUILD, ALICE, BOB, "late", 1, &[]).await.unwrap() {
+            BbpOutcome::Accused(pending) => (db, clock, pending),
+            BbpOutcome::Issued(_) => panic!("the jury should get a say"),
+        }
+    }
+
+    #[tokio::test]
+    async fn bbp_add_waits_for_the_jury() {
+        let (db, _clock, pending) = accused().await;
+
+        assert_eq!((pending.quorum, pending.expires_at), (2, start() + chrono::Duration::hours(2)));
+        assert_eq!(pending_text(&pending), "<@10> wants to give <@20> a bbp, if the jury agrees.\n\nlate\n\n👍 0 👎 0, 2 votes either way settle it. Voting closes <t:1714572000:R>.");
+        assert_eq!(db.get_user_by_discord_id(GUILD, BOB).await.unwrap().unwrap().points, 0);
+    }
+
+    #[tokio::test]
+    async fn reaching_the_quorum_issues_the_bbp() {
+        let (db, _clock, pending) = accused().await;
+
+        let (first, first_status) = jury_vote(&db, pending.pending_id, CAROL, true).await.unwrap();
+        let (text, status) = jury_vote(&db, pending.pending_id, DAVE, true).await.unwrap();
+
+        assert_eq!(first_status, JuryStatus::Pending);
+        assert!(first.contains("👍 1 👎 0, 2 votes either way settle it."), "{}", first);
+        assert_eq!(status, JuryStatus::Confirmed);
+        assert_eq!(text, "<@10> wanted to give <@20> a bbp. The jury agreed, it's B1 (👍 2 👎 0).\n\nlate\n\nBob(#1) now has 1 bbp and 0 gbps, a net score of 1.");
+    }
+
+    #[tokio::test]
+    async fn voting_it_down_vetoes_the_bbp() {
+        let (db, _clock, pending) = accused().await;
+
+        jury_vote(&db, pending.pending_id, CAROL, false).await.unwrap();
+        let (text, status) = jury_vote(&db, pending.pending_id, DAVE, false).await.unwrap();
+
+        assert_eq!(status, JuryStatus::Vetoed);
+        assert_eq!(text, "<@10> wanted to give <@20> a bbp. The jury threw it out (👍 0 👎 2).\n\nlate");
+        assert_eq!(db.get_user_by_discord_id(GUILD, BOB).await.unwrap().unwrap().points, 0);
+    }
+
+    #[tokio::test]
+    async fn the_accused_cannot_vote() {
+        let (db, _clock, pending) = accused().await;
+
+        let vote = jury_vote(&db, pending.pending_id, BOB, false).await;
+
+        assert!(matches!(vote, Err(StoreError::OwnAccusation)), "{:?}", vote);
+    }
+
+    #[tokio::test]
+    async fn an_undecided_bbp_lapses_when_voting_closes() {
+        let (db, clock, pending) = accused().await;
+        jury_vote(&db, pending.pending_id, CAROL, true).await.unwrap();
+
+        clock.advance(chrono::Duration::hours(2));
+        let late = jury_vote(&db, pending.pending_id, DAVE, true).await;
+        let expired = db.expire_pending_bbps().await.unwrap();
+
+        assert!(matches!(late, Err(StoreError::VotingClosed)), "{:?}", late);
+        assert_eq!(expired.len(), 1);
+        assert_eq!(closed_text(&expired[0]), "<@10> wanted to give <@20> a bbp. The jury didn't decide in time, so it lapsed (👍 1 👎 0).\n\nlate");
+    }
+
+    #[test]
+    fn parse_button_reads_the_vote_buttons() {
+        assert_eq!(parse_button("jury:for:12"), Some((true, 12)));
+        assert_eq!(parse_button("jury:against:3"), Some((false, 3)));
+        assert_eq!(parse_button("jury:maybe:3"), None);
+        assert_eq!(parse_button("jury:for:"), None);
+        assert_eq!(parse_button("register:approve:3"), None);
+    }
+}
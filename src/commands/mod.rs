@@ -1 +1,14 @@
-pub mod bbp_commands;
\ No newline at end of file
+pub mod appeal_commands;
+pub mod bbp_commands;
+pub mod chart_commands;
+pub mod checks;
+pub mod edit_commands;
+pub mod jury_commands;
+pub mod pagination;
+pub mod profile_commands;
+pub mod registration_commands;
+pub mod responses;
+pub mod season_commands;
+pub mod settings_commands;
+pub mod show_commands;
+pub mod user_commands;
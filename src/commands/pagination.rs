@@ -0,0 +1,102 @@
+use std::time::Duration;
+
+use async_trait::async_trait;
+use log::error;
+use poise::serenity_prelude as serenity;
+use poise::CreateReply;
+
+use crate::{Context, Error};
+use crate::commands::responses::{error_message, respond};
+use crate::dataaccess::store_error::StoreError;
+
+pub const PAGE_SIZE: i64 = 10;
+
+// The buttons stop working this long after the last press
+const NAVIGATION_TIMEOUT: Duration = Duration::from_secs(300);
+
+/// One page of a reply. `offset` is where the page starts, which isn't known up
+/// front when jumping to a user.
+pub struct PageReply {
+    pub text: String,
+    pub offset: i64,
+    pub has_next: bool,
+}
+
+/// A reply that can be browsed with the Prev/Next/Jump to me buttons.
+#[async_trait]
+pub trait Pages: Send {
+    async fn page(&mut self, offset: i64) -> Result<PageReply, StoreError>;
+
+    /// The page for whoever pressed "Jump to me".
+    async fn jump_to(&mut self, discord_id: i64) -> Result<PageReply, StoreError>;
+}
+
+/// Sends the first page and edits it in place as the buttons are pressed. Anyone
+/// can browse; failures only go to the person who pressed the button.
+pub async fn paginate(ctx: Context<'_>, mut pages: impl Pages) -> Result<(), Error> {
+    let mut current = match pages.page(0).await {
+        Ok(page) => page,
+        Err(e) => return respond(ctx, Err(e)).await,
+    };
+
+    let ctx_id = ctx.id();
+    let prev_id = format!("{}prev", ctx_id);
+    let next_id = format!("{}next", ctx_id);
+    let me_id = format!("{}me", ctx_id);
+
+    let reply = CreateReply::default()
+        .content(content(&current))
+        .components(buttons(&current, &prev_id, &next_id, &me_id));
+    let handle = ctx.send(reply).await?;
+
+    while let Some(press) = serenity::ComponentInteractionCollector::new(ctx)
+        .filter(move |press| press.data.custom_id.starts_with(&ctx_id.to_string()))
+        .timeout(NAVIGATION_TIMEOUT)
+        .await
+    {
+        let result = if press.data.custom_id == prev_id {
+            pages.page((current.offset - PAGE_SIZE).max(0)).await
+        } else if press.data.custom_id == next_id {
+            pages.page(current.offset + PAGE_SIZE).await
+        } else if press.data.custom_id == me_id {
+            pages.jump_to(press.user.id.get() as i64).await
+        } else {
+            continue;
+        };
+
+        let response = match result {
+            Ok(page) => {
+                current = page;
+                serenity::CreateInteractionResponse::UpdateMessage(
+                    serenity::CreateInteractionResponseMessage::new()
+                        .content(content(&current))
+                        .components(buttons(&current, &prev_id, &next_id, &me_id)))
+            }
+            Err(e) => {
+                error!("/{} navigation failed: {}", ctx.command().name, e);
+                serenity::CreateInteractionResponse::Message(
+                    serenity::CreateInteractionResponseMessage::new()
+                        .content(error_message(&e))
+                        .ephemeral(true))
+            }
+        };
+        press.create_response(ctx.serenity_context(), response).await?;
+    }
+
+    // Take the buttons away once nothing listens to them anymore
+    handle.edit(ctx, CreateReply::default().content(content(&current)).components(vec![])).await?;
+
+    Ok(())
+}
+
+fn content(page: &PageReply) -> String {
+    format!("{}\nPage {}", page.text, page.offset / PAGE_SIZE + 1)
+}
+
+fn buttons(page: &PageReply, prev_id: &str, next_id: &str, me_id: &str) -> Vec<serenity::CreateActionRow> {
+    vec![serenity::CreateActionRow::Buttons(vec![
+        serenity::CreateButton::new(prev_id).label("Prev").disabled(page.offset == 0),
+        serenity::CreateButton::new(next_id).label("Next").disabled(!page.has_next),
+        serenity::CreateButton::new(me_id).label("Jump to me").style(serenity::ButtonStyle::Secondary),
+    ])]
+}
@@ -0,0 +1,299 @@
+use crate::{Context, Error};
+use crate::commands::bbp_commands::{after_decay, display_name, history_line, plural, rank};
+use crate::commands::responses::{reject, respond, respond_embed};
+use crate::dataaccess::bbp_store::BbpStore;
+use crate::dataaccess::models::{Exchange, PointTally, User, UserStats};
+use crate::dataaccess::store_error::{StoreError, UserRole};
+use chrono::{Duration, NaiveDateTime};
+use std::fmt::Write;
+use poise::serenity_prelude::CreateEmbed;
+
+#[poise::command(slash_command, guild_only, rename = "profile", user_cooldown = 30)]
+pub async fn profile_command(
+    ctx: Context<'_>,
+    #[description = "Whose profile to show, yourself by default"] user: Option<poise::serenity_prelude::User>,
+) -> Result<(), Error> {
+    let target_user = match user {
+        Some(u) => u,
+        None => ctx.author().clone(),
+    };
+    let guild_id = ctx.guild_id().unwrap().get() as i64;
+    let db = ctx.data().db.as_ref();
+
+    respond_embed(ctx, profile(db, guild_id, target_user.id.get() as i64, db.now()).await).await
+}
+
+#[poise::command(slash_command, guild_only, rename = "versus", user_cooldown = 30)]
+pub async fn versus_command(
+    ctx: Context<'_>,
+    #[description = "One side of the rivalry"] a: poise::serenity_prelude::User,
+    #[description = "The other side"] b: poise::serenity_prelude::User,
+) -> Result<(), Error> {
+    if a.id == b.id {
+        return reject(ctx, "Pick two different users.").await;
+    }
+    let guild_id = ctx.guild_id().unwrap().get() as i64;
+    let db = ctx.data().db.as_ref();
+
+    respond(ctx, versus(db, guild_id, a.id.get() as i64, b.id.get() as i64).await).await
+}
+
+pub async fn profile(db: &dyn BbpStore, guild_id: i64, user_id: i64, now: NaiveDateTime) -> Result<CreateEmbed, StoreError> {
+    let user = db.get_user_by_discord_id_with_rank(guild_id, user_id).await?
+        .ok_or(StoreError::UserNotFound(UserRole::Target))?;
+    let stats = db.get_user_stats(guild_id, user_id).await?;
+
+    let top_issuers = if stats.top_issuers.is_empty() {
+        "Nobody yet".to_string()
+    } else {
+        stats.top_issuers.iter().map(tally_line).collect::<Vec<_>>().join("\n")
+    };
+    let (first, last) = match (stats.offences.first(), stats.offences.last()) {
+        (Some(first), Some(last)) => (first.format("%Y-%m-%d").to_string(), last.format("%Y-%m-%d").to_string()),
+        _ => ("Never".to_string(), "Never".to_string()),
+    };
+
+    Ok(CreateEmbed::new()
+        .title(format!("Profile for {}", display_name(&user)))
+        .field("Rank", rank_text(&user)?, true)
+        .field("Net score", net_score_text(&user), true)
+        .field("Forgiven", plural(stats.forgiven, "bbp"), true)
+        .field("Received", format!("{}\n{}", plural(user.bbp_total, "bbp"), plural(user.gbp_total, "gbp")), true)
+        .field("Issued", format!("{}\n{}", plural(user.bbps_issued, "bbp"), plural(user.gbps_issued, "gbp")), true)
+        .field("Longest clean streak", clean_streak_text(&stats, now), true)
+        .field("Most bbps from", top_issuers, true)
+        .field("Favourite target", stats.favourite_target.as_ref().map(tally_line).unwrap_or_else(|| "Nobody yet".to_string()), true)
+        .field("Offences", format!("First: {}\nLast: {}", first, last), true))
+}
+
+/// How many of the latest exchanges `/versus` lists
+const RECENT_EXCHANGES: i64 = 5;
+
+pub async fn versus(db: &dyn BbpStore, guild_id: i64, a_id: i64, b_id: i64) -> Result<String, StoreError> {
+    let a = db.get_user_by_discord_id(guild_id, a_id).await?
+        .ok_or(StoreError::UserNotFound(UserRole::Target))?;
+    let b = db.get_user_by_discord_id(guild_id, b_id).await?
+        .ok_or(StoreError::UserNotFound(UserRole::Target))?;
+    let rivalry = db.get_rivalry(guild_id, a_id, b_id, RECENT_EXCHANGES).await?;
+
+    let mut response = format!("{} vs {}\n\n", display_name(&a), display_name(&b));
+    let _ = writeln!(response, "{} -> {}: {}", display_name(&a), display_name(&b), exchange_line(rivalry.a_to_b));
+    let _ = writeln!(response, "{} -> {}: {}", display_name(&b), display_name(&a), exchange_line(rivalry.b_to_a));
+
+    if !rivalry.recent.is_empty() {
+        response.push_str("\nRecent exchanges\n");
+        for record in &rivalry.recent {
+            let _ = writeln!(response, "{}", history_line(record));
+        }
+    }
+
+    let _ = write!(response, "\n{}", verdict(&a, rivalry.a_to_b, &b, rivalry.b_to_a));
+
+    Ok(response)
+}
+
+/// "5 bbps (2 forgiven, 40%) and 1 gbp"
+fn exchange_line(exchange: Exchange) -> String {
+    let forgiven = match exchange.forgiveness_rate() {
+        Some(rate) if exchange.forgiven > 0 => format!(" ({} forgiven, {:.0}%)", exchange.forgiven, rate * 100.0),
+        Some(_) => " (none forgiven)".to_string(),
+        None => String::new(),
+    };
+
+    format!("{}{} and {}", plural(exchange.bbps, "bbp"), forgiven, plural(exchange.gbps, "gbp"))
+}
+
+/// Whoever has more unforgiven bbp points on the other comes out on top.
+fn verdict(a: &User, a_to_b: Exchange, b: &User, b_to_a: Exchange) -> String {
+    let (given, received) = (a_to_b.standing_bbps(), b_to_a.standing_bbps());
+    if given == 0 && received == 0 {
+        return format!("Verdict: peace, neither {} nor {} has a bbp standing against the other.", display_name(a), display_name(b));
+    }
+
+    match given.cmp(&received) {
+        std::cmp::Ordering::Greater => format!("Verdict: {} bbps {} more, {} to {}.", display_name(a), display_name(b), given, received),
+        std::cmp::Ordering::Less => format!("Verdict: {} bbps {} more, {} to {}.", display_name(b), display_name(a), received, given),
+        std::cmp::Ordering::Equal => format!("Verdict: dead even at {} each.", given),
+    }
+}
+
+/// The longest stretch without an unforgiven bbp, counting up to `now`. `None`
+/// when the user has never offended.
+pub fn longest_clean_streak(offences: &[NaiveDateTime], now: NaiveDateTime) -> Option<Duration> {
+    let last = offences.last()?;
+    let gaps = offences.windows(2).map(|pair| pair[1] - pair[0]);
+
+    gaps.chain(std::iter::once(now - *last)).max()
+}
+
+/// "4 (1.4 after decay)", the raw net score and what's left of it once bbps decay.
+fn net_score_text(user: &User) -> String {
+    format!("{}{}", user.points, after_decay(user.points, user.score))
+}
+
+/// "#3", deactivated users aren't ranked.
+fn rank_text(user: &User) -> Result<String, StoreError> {
+    if !user.active {
+        return Ok("Deactivated".to_string());
+    }
+
+    Ok(format!("#{}", rank(user)?))
+}
+
+fn clean_streak_text(stats: &UserStats, now: NaiveDateTime) -> String {
+    match longest_clean_streak(&stats.offences, now) {
+        Some(streak) => plural(streak.num_days() as i32, "day"),
+        None => "Never offended".to_string(),
+    }
+}
+
+fn tally_line(tally: &PointTally) -> String {
+    format!("{} ({})", tally.friendly_name.as_deref().unwrap_or("Unknown"), plural(tally.points, "point"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use crate::dataaccess::clock::FixedClock;
+    use crate::dataaccess::memory_store::MemoryStore;
+    use crate::dataaccess::models::{DecayPolicy, GuildSettings, PointKind};
+
+    const GUILD: i64 = 1;
+    const ALICE: i64 = 10;
+    const BOB: i64 = 20;
+
+    fn at(day: u32, hour: u32) -> NaiveDateTime {
+        chrono::NaiveDate::from_ymd_opt(2024, 5, day).unwrap().and_hms_opt(hour, 0, 0).unwrap()
+    }
+
+    async fn store() -> (MemoryStore, Arc<FixedClock>) {
+        let clock = Arc::new(FixedClock::at(at(1, 12)));
+        let db = MemoryStore::new().with_clock(clock.clone());
+        db.add_user(GUILD, ALICE, "alice", "Alice").await.unwrap();
+        db.add_user(GUILD, BOB, "bob", "Bob").await.unwrap();
+        (db, clock)
+    }
+
+    #[tokio::test]
+    async fn net_score_shows_the_decayed_score_once_it_differs() {
+        let (db, clock) = store().await;
+        db.save_guild_settings(GuildSettings { guild_id: GUILD, decay: DecayPolicy::HalfLife { days: 1 }, ..GuildSettings::default() }).await.unwrap();
+        db.issue_point(GUILD, PointKind::Bbp, 3, ALICE, BOB, "late").await.unwrap();
+        let bob = || async { db.get_user_by_discord_id_with_rank(GUILD, BOB).await.unwrap().unwrap() };
+
+        assert_eq!(net_score_text(&bob().await), "3");
+
+        clock.advance(Duration::days(1));
+        assert_eq!(net_score_text(&bob().await), "3 (1.5 after decay)");
+
+        clock.advance(Duration::days(1));
+        assert_eq!(net_score_text(&bob().await), "3 (0.8 after decay)");
+    }
+
+    #[tokio::test]
+    async fn versus_lists_both_sides_and_the_recent_exchanges() {
+        let (db, clock) = store().await;
+        db.issue_point(GUILD, PointKind::Bbp, 1, ALICE, BOB, "late").await.unwrap();
+        clock.advance(Duration::days(1));
+        db.issue_point(GUILD, PointKind::Bbp, 3, ALICE, BOB, "ate my lunch").await.unwrap();
+        clock.advance(Duration::days(1));
+        db.issue_point(GUILD, PointKind::Gbp, 1, BOB, ALICE, "donuts").await.unwrap();
+        db.forgive_bbp(GUILD, BOB, None, Some(1), ALICE).await.unwrap();
+
+        let msg = versus(&db, GUILD, ALICE, BOB).await.unwrap();
+
+        assert_eq!(msg, "Alice vs Bob\n\n\
+            Alice -> Bob: 4 bbps (1 forgiven, 25%) and 0 gbps\n\
+            Bob -> Alice: 0 bbps and 1 gbp\n\
+            \nRecent exchanges\n\
+            🟢 `G1` gbp from Bob -> donuts (2024-05-03)\n\
+            🔴 `B2` bbp x3 from Alice -> ate my lunch (2024-05-02)\n\
+            ~~🔴 `B1` bbp from Alice -> late~~ (2024-05-01, forgiven)\n\
+            \nVerdict: Alice bbps Bob more, 3 to 0.");
+    }
+
+    #[tokio::test]
+    async fn versus_verdict_goes_to_whoever_has_more_standing_bbps() {
+        let (db, _) = store().await;
+        db.issue_point(GUILD, PointKind::Bbp, 1, ALICE, BOB, "late").await.unwrap();
+        db.issue_point(GUILD, PointKind::Bbp, 2, BOB, ALICE, "rude").await.unwrap();
+
+        let msg = versus(&db, GUILD, ALICE, BOB).await.unwrap();
+
+        assert!(msg.contains("Alice -> Bob: 1 bbp (none forgiven) and 0 gbps\n"), "{}", msg);
+        assert!(msg.ends_with("\nVerdict: Bob bbps Alice more, 2 to 1."), "{}", msg);
+    }
+
+    #[tokio::test]
+    async fn versus_verdict_can_be_even() {
+        let (db, _) = store().await;
+        db.issue_point(GUILD, PointKind::Bbp, 2, ALICE, BOB, "late").await.unwrap();
+        db.issue_point(GUILD, PointKind::Bbp, 2, BOB, ALICE, "rude").await.unwrap();
+
+        let msg = versus(&db, GUILD, BOB, ALICE).await.unwrap();
+
+        assert!(msg.ends_with("\nVerdict: dead even at 2 each."), "{}", msg);
+    }
+
+    #[tokio::test]
+    async fn versus_is_peaceful_once_every_bbp_is_forgiven() {
+        let (db, _) = store().await;
+        db.issue_point(GUILD, PointKind::Bbp, 2, ALICE, BOB, "late").await.unwrap();
+        db.forgive_bbp(GUILD, BOB, None, Some(1), ALICE).await.unwrap();
+
+        let msg = versus(&db, GUILD, ALICE, BOB).await.unwrap();
+
+        assert!(msg.contains("Alice -> Bob: 2 bbps (2 forgiven, 100%) and 0 gbps\n"), "{}", msg);
+        assert!(msg.ends_with("\nVerdict: peace, neither Alice nor Bob has a bbp standing against the other."), "{}", msg);
+    }
+
+    #[tokio::test]
+    async fn versus_without_exchanges_skips_the_recent_list() {
+        let (db, _) = store().await;
+
+        let msg = versus(&db, GUILD, ALICE, BOB).await.unwrap();
+
+        assert_eq!(msg, "Alice vs Bob\n\n\
+            Alice -> Bob: 0 bbps and 0 gbps\n\
+            Bob -> Alice: 0 bbps and 0 gbps\n\
+            \nVerdict: peace, neither Alice nor Bob has a bbp standing against the other.");
+    }
+
+    #[tokio::test]
+    async fn versus_with_an_unregistered_user_fails() {
+        let (db, _) = store().await;
+
+        assert!(matches!(versus(&db, GUILD, ALICE, 99).await, Err(StoreError::UserNotFound(UserRole::Target))));
+    }
+
+    #[test]
+    fn never_offending_has_no_streak() {
+        assert_eq!(longest_clean_streak(&[], at(9, 12)), None);
+    }
+
+    #[test]
+    fn single_offence_measures_up_to_now() {
+        assert_eq!(longest_clean_streak(&[at(1, 12)], at(4, 18)), Some(Duration::days(3) + Duration::hours(6)));
+        assert_eq!(longest_clean_streak(&[at(4, 18)], at(4, 18)), Some(Duration::zero()));
+    }
+
+    #[test]
+    fn longest_gap_beats_a_shorter_tail() {
+        let offences = [at(1, 12), at(2, 12), at(9, 12), at(10, 0)];
+
+        assert_eq!(longest_clean_streak(&offences, at(12, 0)), Some(Duration::days(7)));
+        assert_eq!(longest_clean_streak(&offences, at(20, 0)), Some(Duration::days(10)));
+    }
+
+    #[tokio::test]
+    async fn profile_counts_the_clean_streak_in_whole_days() {
+        let (db, clock) = store().await;
+        db.issue_point(GUILD, PointKind::Bbp, 1, ALICE, BOB, "late").await.unwrap();
+        clock.advance(Duration::days(2) + Duration::hours(23));
+        let stats = db.get_user_stats(GUILD, BOB).await.unwrap();
+
+        assert_eq!(clean_streak_text(&stats, db.now()), "2 days");
+        assert_eq!(clean_streak_text(&UserStats::default(), db.now()), "Never offended");
+    }
+}
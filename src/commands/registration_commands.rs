@@ -0,0 +1,393 @@
+use log::{error, info};
+use poise::serenity_prelude as serenity;
+
+use crate::{Context, Data, Error};
+use crate::commands::bbp_commands::display_name;
+use crate::commands::responses::{error_message, reject, respond};
+use crate::dataaccess::bbp_store::BbpStore;
+use crate::dataaccess::models::{RegistrationPolicy, RegistrationRequest, User};
+use crate::dataaccess::store_error::StoreError;
+
+// Approve/deny buttons carry the requester, e.g. "registration:approve:1234"
+const BUTTON_PREFIX: &str = "registration:";
+
+#[poise::command(slash_command, guild_only, rename = "join")]
+pub async fn join_command(
+    ctx: Context<'_>,
+    #[description = "What the bot should call you"] friendly_name: String,
+) -> Result<(), Error> {
+    let author = ctx.author();
+    let guild_id = ctx.guild_id().unwrap().get() as i64;
+    let db = ctx.data().db.as_ref();
+
+    match join(db, guild_id, author.id.get() as i64, &author.name, &friendly_name).await {
+        Ok(JoinOutcome::Registered(user)) => respond(ctx, Ok(format!("Welcome, {}!", display_name(&user)))).await,
+        Ok(JoinOutcome::Pending { request, channel_id }) => {
+            if !post_request(ctx, db, &request, channel_id).await? {
+                return reject(ctx, "Your request couldn't be posted to the moderators, ask an owner to check the moderator channel.").await;
+            }
+
+            let reply = poise::CreateReply::default()
+                .content("Your request to join was sent to the moderators.")
+                .ephemeral(true);
+            ctx.send(reply).await?;
+            Ok(())
+        }
+        Ok(JoinOutcome::Refused(msg)) => reject(ctx, msg).await,
+        Err(e) => respond(ctx, Err(e)).await,
+    }
+}
+
+pub enum JoinOutcome {
+    Registered(User),
+    /// Waiting for a moderator, who gets asked in the given channel
+    Pending { request: RegistrationRequest, channel_id: i64 },
+    /// The guild's policy doesn't let people join by themselves
+    Refused(&'static str),
+}
+
+/// Registers the user or queues their request, depending on the guild's `RegistrationPolicy`.
+pub async fn join(db: &dyn BbpStore, guild_id: i64, discord_id: i64, discord_username: &str, friendly_name: &str) -> Result<JoinOutcome, StoreError> {
+    let settings = db.get_guild_settings(guild_id).await?;
+
+    match (settings.registration, settings.moderator_channel_id) {
+        (RegistrationPolicy::Open, _) => {
+            let user = db.add_user(guild_id, discord_id, discord_username, friendly_name).await?;
+            Ok(JoinOutcome::Registered(user))
+        }
+        (RegistrationPolicy::Approval, Some(channel_id)) => {
+            let request = db.request_registration(guild_id, discord_id, discord_username, friendly_name).await?;
+            Ok(JoinOutcome::Pending { request, channel_id })
+        }
+        (RegistrationPolicy::Approval, None) =>
+            Ok(JoinOutcome::Refused("Requests to join need a moderator channel, ask an owner to set one up.")),
+        (RegistrationPolicy::OwnerOnly, _) =>
+            Ok(JoinOutcome::Refused("Only owners can register people here, ask one to `/add-user` you.")),
+    }
+}
+
+/// Posts a request to join to the moderator channel, and returns whether that
+/// worked. Nobody could ever approve a request that didn't get posted, so it's
+/// dropped again rather than left blocking a retry.
+pub(crate) async fn post_request(cache_http: impl serenity::CacheHttp, db: &dyn BbpStore, request: &RegistrationRequest, channel_id: i64) -> Result<bool, StoreError> {
+    let post = serenity::ChannelId::new(channel_id as u64)
+        .send_message(cache_http, request_message(request))
+        .await;
+    if let Err(e) = post {
+        db.take_registration_request(request.guild_id, request.discord_id).await?;
+        error!("Couldn't post the request to join to channel {}: {}", channel_id, e);
+        return Ok(false);
+    }
+
+    Ok(true)
+}
+
+/// Registers whoever ran a command if the guild allows it and they aren't yet,
+/// so they can be bbp'd without running `/join` first.
+pub async fn auto_register(db: &dyn BbpStore, guild_id: i64, discord_id: i64, discord_username: &str, friendly_name: &str) -> Result<Option<User>, StoreError> {
+    let settings = db.get_guild_settings(guild_id).await?;
+    if settings.registration != RegistrationPolicy::Open || !settings.auto_register {
+        return Ok(None);
+    }
+    if db.get_user_by_discord_id(guild_id, discord_id).await?.is_some() {
+        return Ok(None);
+    }
+
+    match db.add_user(guild_id, discord_id, discord_username, friendly_name).await {
+        Ok(user) => Ok(Some(user)),
+        // Registered by a command running at the same time
+        Err(StoreError::Duplicate(_)) => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+/// Runs before every command. `/join` is left alone so people still get to
+/// pick their own name.
+pub async fn auto_register_author(ctx: Context<'_>) {
+    let Some(guild_id) = ctx.guild_id() else {
+        return;
+    };
+    if ctx.command().name == "join" {
+        return;
+    }
+    let author = ctx.author();
+    let friendly_name = author.global_name.clone().unwrap_or_else(|| author.name.clone());
+
+    match auto_register(ctx.data().db.as_ref(), guild_id.get() as i64, author.id.get() as i64, &author.name, &friendly_name).await {
+        Ok(Some(user)) => info!("Registered {} in guild {} on their first command", display_name(&user), guild_id),
+        Ok(None) => {}
+        Err(e) => error!("Couldn't auto-register {}: {}", author.name, e),
+    }
+}
+
+/// Someone joined the server: refreshes their username wherever they're
+/// registered and enrols them if the guild wants new members registered.
+/// Where joining needs approval, enrolling them asks the moderators like
+/// `/join` would.
+pub async fn member_joined(db: &dyn BbpStore, guild_id: i64, discord_id: i64, discord_username: &str, display_name: &str) -> Result<Option<JoinOutcome>, StoreError> {
+    db.update_discord_username(discord_id, discord_username).await?;
+
+    let settings = db.get_guild_settings(guild_id).await?;
+    if !settings.enrol_new_members {
+        return Ok(None);
+    }
+    let enrolled = match (settings.registration, settings.moderator_channel_id) {
+        (RegistrationPolicy::Approval, Some(channel_id)) => db.request_registration(guild_id, discord_id, discord_username, display_name).await
+            .map(|request| JoinOutcome::Pending { request, channel_id }),
+        // Nobody could approve them, they can /join once there's a channel
+        (RegistrationPolicy::Approval, None) => return Ok(None),
+        // Owners turned enrolling on, which stands in for adding them by hand
+        (RegistrationPolicy::Open | RegistrationPolicy::OwnerOnly, _) => db.add_user(guild_id, discord_id, discord_username, display_name).await
+            .map(JoinOutcome::Registered),
+    };
+
+    match enrolled {
+        Ok(outcome) => Ok(Some(outcome)),
+        // Back after leaving, their standing (or their request) is still there
+        Err(StoreError::Duplicate(_)) => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+/// Approves or denies a request to join, returning the new text for the request message.
+pub async fn resolve_registration(db: &dyn BbpStore, guild_id: i64, discord_id: i64, approve: bool, moderator_id: i64) -> Result<String, StoreError> {
+    let Some(request) = db.take_registration_request(guild_id, discord_id).await? else {
+        return Ok(format!("The request from <@{}> was already handled.", discord_id));
+    };
+
+    if !approve {
+        return Ok(format!("<@{}> was turned away as '{}' by <@{}>.", discord_id, request.friendly_name, moderator_id));
+    }
+
+    let user = db.add_user(guild_id, discord_id, &request.discord_username, &request.friendly_name).await?;
+    Ok(format!("<@{}> joined as '{}', approved by <@{}>.", discord_id, display_name(&user), moderator_id))
+}
+
+/// Handles presses of the buttons under a request to join. They outlive the
+/// command that posted them, so they go through the event handler rather than
+/// a collector.
+pub async fn handle_button(ctx: &serenity::Context, press: &serenity::ComponentInteraction, data: &Data, owners: &std::collections::HashSet<serenity::UserId>) -> Result<(), Error> {
+    let Some((approve, discord_id)) = parse_button(&press.data.custom_id) else {
+        return Ok(());
+    };
+    let Some(guild_id) = press.guild_id.map(|id| id.get() as i64) else {
+        return Ok(());
+    };
+    let db = data.db.as_ref();
+
+    let roles: Vec<i64> = press.member.iter().flat_map(|m| m.roles.iter().map(|role| role.get() as i64)).collect();
+    let allowed = match db.get_guild_settings(guild_id).await {
+        Ok(settings) => owners.contains(&press.user.id) || settings.is_moderator(&roles),
+        Err(e) => return reply_privately(ctx, press, &error_message(&e)).await,
+    };
+    if !allowed {
+        return reply_privately(ctx, press, "Only moderators can handle requests to join.").await;
+    }
+
+    match resolve_registration(db, guild_id, discord_id, approve, press.user.id.get() as i64).await {
+        Ok(text) => {
+            let update = serenity::CreateInteractionResponseMessage::new().content(text).components(vec![]);
+            press.create_response(ctx, serenity::CreateInteractionResponse::UpdateMessage(update)).await?;
+            Ok(())
+        }
+        Err(e) => {
+            error!("Resolving the request to join of {} failed: {}", discord_id, e);
+            reply_privately(ctx, press, &error_message(&e)).await
+        }
+    }
+}
+
+pub(crate) async fn reply_privately(ctx: &serenity::Context, press: &serenity::ComponentInteraction, text: &str) -> Result<(), Error> {
+    let reply = serenity::CreateInteractionResponseMessage::new().content(text).ephemeral(true);
+    press.create_response(ctx, serenity::CreateInteractionResponse::Message(reply)).await?;
+
+    Ok(())
+}
+
+fn request_message(request: &RegistrationRequest) -> serenity::CreateMessage {
+    let approve = format!("{}approve:{}", BUTTON_PREFIX, request.discord_id);
+    let deny = format!("{}deny:{}", BUTTON_PREFIX, request.discord_id);
+
+    serenity::CreateMessage::new()
+        .content(format!("<@{}> would like to join as '{}'.", request.discord_id, request.friendly_name))
+        .allowed_mentions(serenity::CreateAllowedMentions::new())
+        .components(vec![serenity::CreateActionRow::Buttons(vec![
+            serenity::CreateButton::new(approve).label("Approve").style(serenity::ButtonStyle::Success),
+            serenity::CreateButton::new(deny).label("Deny").style(serenity::ButtonStyle::Danger),
+        ])])
+}
+
+/// `Some((approve, discord_id))` for the buttons from `request_message`.
+fn parse_button(custom_id: &str) -> Option<(bool, i64)> {
+    let (action, discord_id) = custom_id.strip_prefix(BUTTON_PREFIX)?.split_once(':')?;
+    let approve = match action {
+        "approve" => true,
+        "deny" => false,
+        _ => return None,
+    };
+
+    Some((approve, discord_id.parse().ok()?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dataaccess::memory_store::MemoryStore;
+    use crate::dataaccess::models::GuildSettings;
+
+    const GUILD: i64 = 1;
+    const CHANNEL: i64 = 500;
+    const MODERATOR: i64 = 10;
+    const ALICE: i64 = 20;
+
+    async fn store(registration: RegistrationPolicy, moderator_channel_id: Option<i64>) -> MemoryStore {
+        let db = MemoryStore::new();
+        db.save_guild_settings(GuildSettings { guild_id: GUILD, registration, moderator_channel_id, ..GuildSettings::default() }).await.unwrap();
+        db
+    }
+
+    async fn registered(db: &MemoryStore, discord_id: i64) -> bool {
+        db.get_user_by_discord_id(GUILD, discord_id).await.unwrap().is_some()
+    }
+
+    #[tokio::test]
+    async fn open_registration_lets_anyone_join() {
+        let db = store(RegistrationPolicy::Open, None).await;
+
+        let joined = join(&db, GUILD, ALICE, "alice", "Alice").await.unwrap();
+        let again = join(&db, GUILD, ALICE, "alice", "Alice").await;
+
+        assert!(matches!(joined, JoinOutcome::Registered(ref user) if user.discord_id == ALICE));
+        assert!(matches!(again, Err(StoreError::Duplicate(_))));
+    }
+
+    #[tokio::test]
+    async fn approval_queues_the_request() {
+        let db = store(RegistrationPolicy::Approval, Some(CHANNEL)).await;
+
+        let joined = join(&db, GUILD, ALICE, "alice", "Alice").await.unwrap();
+        let again = join(&db, GUILD, ALICE, "alice", "Ally").await;
+
+        let JoinOutcome::Pending { request, channel_id } = joined else {
+            panic!("the request should wait for a moderator");
+        };
+        assert_eq!((request.discord_id, request.friendly_name.as_str(), channel_id), (ALICE, "Alice", CHANNEL));
+        assert!(matches!(again, Err(StoreError::Duplicate(_))));
+        assert!(!registered(&db, ALICE).await);
+    }
+
+    #[tokio::test]
+    async fn approval_without_a_channel_and_owner_only_refuse() {
+        for (registration, expected) in [
+            (RegistrationPolicy::Approval, "Requests to join need a moderator channel, ask an owner to set one up."),
+            (RegistrationPolicy::OwnerOnly, "Only owners can register people here, ask one to `/add-user` you."),
+        ] {
+            let db = store(registration, None).await;
+
+            let joined = join(&db, GUILD, ALICE, "alice", "Alice").await.unwrap();
+
+            assert!(matches!(joined, JoinOutcome::Refused(msg) if msg == expected));
+            assert!(!registered(&db, ALICE).await);
+        }
+    }
+
+    #[tokio::test]
+    async fn approving_a_request_registers_them_once() {
+        let db = store(RegistrationPolicy::Approval, Some(CHANNEL)).await;
+        join(&db, GUILD, ALICE, "alice", "Alice").await.unwrap();
+
+        let approved = resolve_registration(&db, GUILD, ALICE, true, MODERATOR).await.unwrap();
+        let again = resolve_registration(&db, GUILD, ALICE, false, MODERATOR).await.unwrap();
+
+        assert_eq!(approved, "<@20> joined as 'Alice', approved by <@10>.");
+        assert_eq!(again, "The request from <@20> was already handled.");
+        assert!(registered(&db, ALICE).await);
+    }
+
+    #[tokio::test]
+    async fn denying_a_request_lets_them_ask_again() {
+        let db = store(RegistrationPolicy::Approval, Some(CHANNEL)).await;
+        join(&db, GUILD, ALICE, "alice", "Alice").await.unwrap();
+
+        let denied = resolve_registration(&db, GUILD, ALICE, false, MODERATOR).await.unwrap();
+
+        assert_eq!(denied, "<@20> was turned away as 'Alice' by <@10>.");
+        assert!(!registered(&db, ALICE).await);
+        assert!(matches!(join(&db, GUILD, ALICE, "alice", "Alice").await, Ok(JoinOutcome::Pending { .. })));
+    }
+
+    #[tokio::test]
+    async fn auto_register_only_under_open_registration() {
+        let open = store(RegistrationPolicy::Open, None).await;
+        open.save_guild_settings(GuildSettings { auto_register: true, ..open.get_guild_settings(GUILD).await.unwrap() }).await.unwrap();
+        let approval = store(RegistrationPolicy::Approval, Some(CHANNEL)).await;
+        approval.save_guild_settings(GuildSettings { auto_register: true, ..approval.get_guild_settings(GUILD).await.unwrap() }).await.unwrap();
+
+        assert!(auto_register(&open, GUILD, ALICE, "alice", "Alice").await.unwrap().is_some());
+        assert!(auto_register(&open, GUILD, ALICE, "alice", "Alice").await.unwrap().is_none());
+        assert!(auto_register(&approval, GUILD, ALICE, "alice", "Alice").await.unwrap().is_none());
+        assert!(!registered(&approval, ALICE).await);
+    }
+
+    async fn enrolling(registration: RegistrationPolicy, moderator_channel_id: Option<i64>) -> MemoryStore {
+        let db = store(registration, moderator_channel_id).await;
+        db.save_guild_settings(GuildSettings { enrol_new_members: true, ..db.get_guild_settings(GUILD).await.unwrap() }).await.unwrap();
+        db
+    }
+
+    #[tokio::test]
+    async fn new_members_are_only_enrolled_when_the_guild_wants_it() {
+        let db = store(RegistrationPolicy::Open, None).await;
+
+        let joined = member_joined(&db, GUILD, ALICE, "alice", "Alice").await.unwrap();
+
+        assert!(joined.is_none());
+        assert!(!registered(&db, ALICE).await);
+    }
+
+    #[tokio::test]
+    async fn enrolling_registers_new_members_without_approval() {
+        for registration in [RegistrationPolicy::Open, RegistrationPolicy::OwnerOnly] {
+            let db = enrolling(registration, None).await;
+
+            let joined = member_joined(&db, GUILD, ALICE, "alice", "Alice").await.unwrap();
+            let rejoined = member_joined(&db, GUILD, ALICE, "alice", "Alice").await.unwrap();
+
+            assert!(matches!(joined, Some(JoinOutcome::Registered(ref user)) if user.discord_id == ALICE), "{:?}", registration);
+            assert!(rejoined.is_none());
+        }
+    }
+
+    #[tokio::test]
+    async fn enrolling_under_approval_asks_the_moderators() {
+        let db = enrolling(RegistrationPolicy::Approval, Some(CHANNEL)).await;
+
+        let joined = member_joined(&db, GUILD, ALICE, "alice", "Alice").await.unwrap();
+        let rejoined = member_joined(&db, GUILD, ALICE, "alice", "Alice").await.unwrap();
+
+        assert!(matches!(joined, Some(JoinOutcome::Pending { ref request, channel_id: CHANNEL }) if request.discord_id == ALICE));
+        assert!(rejoined.is_none());
+        assert!(!registered(&db, ALICE).await);
+        resolve_registration(&db, GUILD, ALICE, true, MODERATOR).await.unwrap();
+        assert!(registered(&db, ALICE).await);
+    }
+
+    #[tokio::test]
+    async fn enrolling_under_approval_without_a_channel_waits_for_join() {
+        let db = enrolling(RegistrationPolicy::Approval, None).await;
+
+        let joined = member_joined(&db, GUILD, ALICE, "alice", "Alice").await.unwrap();
+
+        assert!(joined.is_none());
+        assert!(db.take_registration_request(GUILD, ALICE).await.unwrap().is_none());
+        assert!(!registered(&db, ALICE).await);
+    }
+
+    #[test]
+    fn parse_button_reads_the_request_buttons() {
+        assert_eq!(parse_button("registration:approve:1234"), Some((true, 1234)));
+        assert_eq!(parse_button("registration:deny:1234"), Some((false, 1234)));
+        assert_eq!(parse_button("registration:ignore:1234"), None);
+        assert_eq!(parse_button("jury:for:1234"), None);
+    }
+}
@@ -0,0 +1,86 @@
+use log::error;
+use poise::serenity_prelude::CreateEmbed;
+use crate::{Context, Error};
+use crate::dataaccess::store_error::{Existing, StoreError, UserRole};
+
+/// Sends the reply for a command. Failures are logged and shown only to the
+/// person who ran the command.
+pub async fn respond(ctx: Context<'_>, result: Result<String, StoreError>) -> Result<(), Error> {
+    match result {
+        Ok(msg) => {
+            ctx.say(msg).await?;
+        }
+        Err(e) => {
+            error!("/{} failed: {}", ctx.command().name, e);
+            let reply = poise::CreateReply::default()
+                .content(error_message(&e))
+                .ephemeral(true);
+            ctx.send(reply).await?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Like `respond`, for commands that reply with an embed.
+pub async fn respond_embed(ctx: Context<'_>, result: Result<CreateEmbed, StoreError>) -> Result<(), Error> {
+    match result {
+        Ok(embed) => {
+            ctx.send(poise::CreateReply::default().embed(embed)).await?;
+        }
+        Err(e) => {
+            error!("/{} failed: {}", ctx.command().name, e);
+            let reply = poise::CreateReply::default()
+                .content(error_message(&e))
+                .ephemeral(true);
+            ctx.send(reply).await?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Turns down a command whose arguments don't make sense, only visible to the
+/// person who ran it.
+pub async fn reject(ctx: Context<'_>, msg: &str) -> Result<(), Error> {
+    ctx.send(poise::CreateReply::default().content(msg).ephemeral(true)).await?;
+
+    Ok(())
+}
+
+/// The user-facing text for a store error.
+pub fn error_message(e: &StoreError) -> String {
+    match e {
+        StoreError::UserNotFound(UserRole::Issuer) => "You aren't registered with the bot yet, ask an owner to add you.".to_string(),
+        StoreError::UserNotFound(UserRole::Target) => "That user isn't registered with the bot yet.".to_string(),
+        StoreError::UserNotFound(UserRole::MergeFrom) => "The user to merge isn't registered with the bot.".to_string(),
+        StoreError::UserNotFound(UserRole::MergeInto) => "The user to merge into isn't registered with the bot.".to_string(),
+        StoreError::UserInactive(UserRole::Issuer) => "You were deactivated, ask a moderator to reactivate you.".to_string(),
+        StoreError::UserInactive(_) => "That user was deactivated.".to_string(),
+        StoreError::UserHasHistory => "That user has bbps or gbps on record, deactivate or merge them instead.".to_string(),
+        StoreError::VotingClosed => "Voting on that bbp is already over.".to_string(),
+        StoreError::OwnAccusation => "You can't vote on a bbp you gave or got.".to_string(),
+        StoreError::BbpNotFound => "There is no such bbp.".to_string(),
+        StoreError::PointNotFound => "There is no such bbp or gbp.".to_string(),
+        StoreError::NotEditable => "You can only change bbps and gbps you gave someone else, and not once a jury or an appeal was involved or their season ended.".to_string(),
+        StoreError::GracePeriodOver { minutes: 0 } => "Bbps and gbps can't be edited or retracted here.".to_string(),
+        StoreError::GracePeriodOver { minutes: 1 } => "Bbps and gbps can only be edited or retracted within a minute of giving them.".to_string(),
+        StoreError::GracePeriodOver { minutes } => format!("Bbps and gbps can only be edited or retracted within {} minutes of giving them.", minutes),
+        StoreError::NotAppealable => "You can only appeal a bbp you got this season that still counts, and only once.".to_string(),
+        StoreError::NoCommunityVote => "Only moderators decide appeals here.".to_string(),
+        StoreError::Duplicate(Existing::User) => "That user is already registered.".to_string(),
+        StoreError::Duplicate(Existing::RunningSeason) => "A season is already running.".to_string(),
+        StoreError::Duplicate(Existing::JoinRequest) => "A request to join is already waiting.".to_string(),
+        StoreError::Duplicate(Existing::Row) => "That already exists.".to_string(),
+        StoreError::NothingToForgive => "There is nothing to forgive.".to_string(),
+        StoreError::NothingToUnforgive => "There is nothing to unforgive.".to_string(),
+        StoreError::ValueNotAllowed { max: 1 } => "You can only give single points.".to_string(),
+        StoreError::ValueNotAllowed { max } => format!("You can give at most {} points at once.", max),
+        StoreError::ValueTooLow => "Bbps and gbps are worth at least 1 point.".to_string(),
+        StoreError::NoOpenSeason => "No season is running, start one with `/season start`.".to_string(),
+        StoreError::SeasonNotFound(number) => format!("There is no season {}.", number),
+        StoreError::Pool(_) => "The database is unavailable right now, try again in a bit.".to_string(),
+        StoreError::Query(_) => "Something went wrong talking to the database.".to_string(),
+        StoreError::Integrity(_) => "The stored data looks inconsistent, an owner should take a look.".to_string(),
+    }
+}
@@ -0,0 +1,157 @@
+use crate::{Context, Error};
+use crate::commands::bbp_commands::{season_line, LeaderboardPages};
+use crate::commands::pagination::paginate;
+use crate::commands::responses::respond;
+use crate::dataaccess::bbp_store::BbpStore;
+use crate::dataaccess::models::Standings;
+use crate::dataaccess::store_error::StoreError;
+use std::fmt::Write;
+
+#[poise::command(
+    slash_command,
+    guild_only,
+    rename = "season",
+    subcommands("season_start_command", "season_end_command", "season_list_command", "season_show_command"),
+    subcommand_required
+)]
+pub async fn season_command(_ctx: Context<'_>) -> Result<(), Error> {
+    Ok(())
+}
+
+#[poise::command(slash_command, guild_only, rename = "start", owners_only)]
+pub async fn season_start_command(ctx: Context<'_>) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().unwrap().get() as i64;
+    let db = ctx.data().db.as_ref();
+
+    respond(ctx, season_start(db, guild_id).await).await
+}
+
+#[poise::command(slash_command, guild_only, rename = "end", owners_only)]
+pub async fn season_end_command(ctx: Context<'_>) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().unwrap().get() as i64;
+    let db = ctx.data().db.as_ref();
+
+    respond(ctx, season_end(db, guild_id).await).await
+}
+
+#[poise::command(slash_command, guild_only, rename = "list")]
+pub async fn season_list_command(ctx: Context<'_>) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().unwrap().get() as i64;
+    let db = ctx.data().db.as_ref();
+
+    respond(ctx, season_list(db, guild_id).await).await
+}
+
+#[poise::command(slash_command, guild_only, rename = "show")]
+pub async fn season_show_command(
+    ctx: Context<'_>,
+    #[description = "The season number"] number: i32,
+) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().unwrap().get() as i64;
+    let db = ctx.data().db.as_ref();
+
+    paginate(ctx, LeaderboardPages { db, guild_id, standings: Standings::Season(number) }).await
+}
+
+pub async fn season_start(db: &dyn BbpStore, guild_id: i64) -> Result<String, StoreError> {
+    let season = db.start_season(guild_id).await?;
+
+    Ok(format!("Season {} has started, good luck everyone!", season.number))
+}
+
+/// Closes the running season and announces how it went.
+pub async fn season_end(db: &dyn BbpStore, guild_id: i64) -> Result<String, StoreError> {
+    let closed = db.end_season(guild_id).await?;
+
+    let mut response = format!("Season {} is over, the standings have been reset.\n", closed.season.number);
+    match closed.champion() {
+        Some(champion) => {
+            let _ = write!(response, "\n🏆 Champion: {} with a net score of {}",
+                champion.friendly_name.as_deref().unwrap_or("Unknown"),
+                champion.points);
+        }
+        None => response.push_str("\nNobody played this season."),
+    }
+    if let Some(winner) = closed.most_bbps() {
+        let _ = write!(response, "\n😈 Most bbps: {} with {}",
+            winner.friendly_name.as_deref().unwrap_or("Unknown"),
+            winner.bbp_total);
+    }
+
+    Ok(response)
+}
+
+pub async fn season_list(db: &dyn BbpStore, guild_id: i64) -> Result<String, StoreError> {
+    let seasons = db.get_seasons(guild_id).await?;
+    if seasons.is_empty() {
+        return Ok("There haven't been any seasons yet.".to_string());
+    }
+
+    let mut response = String::from("Seasons\n\n");
+    for season in &seasons {
+        let _ = writeln!(response, "{}", season_line(season));
+    }
+
+    Ok(response)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dataaccess::memory_store::MemoryStore;
+    use crate::dataaccess::models::PointKind;
+
+    const GUILD: i64 = 1;
+    const ALICE: i64 = 10;
+    const BOB: i64 = 20;
+
+    async fn store() -> MemoryStore {
+        let db = MemoryStore::new();
+        db.add_user(GUILD, ALICE, "alice", "Alice").await.unwrap();
+        db.add_user(GUILD, BOB, "bob", "Bob").await.unwrap();
+        db
+    }
+
+    #[tokio::test]
+    async fn season_end_announces_the_champion_and_most_bbps() {
+        let db = store().await;
+        season_start(&db, GUILD).await.unwrap();
+        db.issue_point(GUILD, PointKind::Bbp, 2, ALICE, BOB, "late").await.unwrap();
+        db.issue_point(GUILD, PointKind::Gbp, 1, BOB, ALICE, "donuts").await.unwrap();
+
+        let msg = season_end(&db, GUILD).await.unwrap();
+
+        assert_eq!(msg, "Season 1 is over, the standings have been reset.\n\n🏆 Champion: Alice with a net score of -1\n😈 Most bbps: Bob with 2");
+    }
+
+    #[tokio::test]
+    async fn season_end_without_bbps_has_no_most_bbps() {
+        let db = store().await;
+        season_start(&db, GUILD).await.unwrap();
+
+        let msg = season_end(&db, GUILD).await.unwrap();
+
+        assert_eq!(msg, "Season 1 is over, the standings have been reset.\n\n🏆 Champion: Alice with a net score of 0");
+    }
+
+    #[tokio::test]
+    async fn season_end_without_a_running_season_fails() {
+        let db = store().await;
+
+        assert!(matches!(season_end(&db, GUILD).await, Err(StoreError::NoOpenSeason)));
+    }
+
+    #[tokio::test]
+    async fn seasons_are_numbered_per_guild() {
+        let db = store().await;
+        season_start(&db, GUILD).await.unwrap();
+        season_end(&db, GUILD).await.unwrap();
+
+        let msg = season_start(&db, GUILD).await.unwrap();
+        let elsewhere = season_start(&db, 2).await.unwrap();
+
+        assert_eq!(msg, "Season 2 has started, good luck everyone!");
+        assert_eq!(elsewhere, "Season 1 has started, good luck everyone!");
+        assert!(season_list(&db, GUILD).await.unwrap().starts_with("Seasons\n\n"));
+    }
+}
@@ -0,0 +1,382 @@
+use crate::{Context, Error};
+use crate::commands::responses::{reject, respond};
+use crate::dataaccess::bbp_store::BbpStore;
+use crate::dataaccess::models::{DecayPolicy, GuildSettings, JuryPolicy, RegistrationPolicy};
+use crate::dataaccess::store_error::StoreError;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, poise::ChoiceParameter)]
+pub enum DecayChoice {
+    #[name = "off"]
+    Off,
+    #[name = "half-life"]
+    HalfLife,
+    #[name = "expiry"]
+    Expiry,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, poise::ChoiceParameter)]
+pub enum RegistrationChoice {
+    #[name = "open"]
+    Open,
+    #[name = "approval"]
+    Approval,
+    #[name = "owner-only"]
+    OwnerOnly,
+}
+
+impl From<RegistrationChoice> for RegistrationPolicy {
+    fn from(choice: RegistrationChoice) -> Self {
+        match choice {
+            RegistrationChoice::Open => RegistrationPolicy::Open,
+            RegistrationChoice::Approval => RegistrationPolicy::Approval,
+            RegistrationChoice::OwnerOnly => RegistrationPolicy::OwnerOnly,
+        }
+    }
+}
+
+#[poise::command(
+    slash_command,
+    guild_only,
+    rename = "settings",
+    subcommands(
+        "settings_show_command",
+        "settings_decay_command",
+        "settings_limits_command",
+        "settings_moderator_role_command",
+        "settings_registration_command",
+        "settings_jury_command",
+        "settings_grace_command"
+    ),
+    subcommand_required
+)]
+pub async fn settings_command(_ctx: Context<'_>) -> Result<(), Error> {
+    Ok(())
+}
+
+#[poise::command(slash_command, guild_only, rename = "show")]
+pub async fn settings_show_command(ctx: Context<'_>) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().unwrap().get() as i64;
+    let db = ctx.data().db.as_ref();
+
+    respond(ctx, settings_show(db, guild_id).await).await
+}
+
+#[poise::command(slash_command, guild_only, rename = "decay", owners_only)]
+pub async fn settings_decay_command(
+    ctx: Context<'_>,
+    #[description = "How old bbps lose weight"] mode: DecayChoice,
+    #[description = "The half-life or the age at which bbps expire"] days: Option<i32>,
+) -> Result<(), Error> {
+    let decay = match decay_policy(mode, days) {
+        Ok(decay) => decay,
+        Err(msg) => return reject(ctx, &msg).await,
+    };
+    let guild_id = ctx.guild_id().unwrap().get() as i64;
+    let db = ctx.data().db.as_ref();
+
+    respond(ctx, settings_decay(db, guild_id, decay).await).await
+}
+
+#[poise::command(slash_command, guild_only, rename = "limits", owners_only)]
+pub async fn settings_limits_command(
+    ctx: Context<'_>,
+    #[description = "The most a bbp or gbp from anyone can be worth"] #[min = 1] everyone: Option<i32>,
+    #[description = "The most a bbp or gbp from a moderator can be worth"] #[min = 1] moderators: Option<i32>,
+) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().unwrap().get() as i64;
+    let db = ctx.data().db.as_ref();
+
+    respond(ctx, settings_limits(db, guild_id, everyone, moderators).await).await
+}
+
+#[poise::command(slash_command, guild_only, rename = "moderator-role", owners_only)]
+pub async fn settings_moderator_role_command(
+    ctx: Context<'_>,
+    #[description = "Leave out to have no moderators"] role: Option<poise::serenity_prelude::Role>,
+) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().unwrap().get() as i64;
+    let db = ctx.data().db.as_ref();
+
+    respond(ctx, settings_moderator_role(db, guild_id, role.map(|r| r.id.get() as i64)).await).await
+}
+
+#[poise::command(slash_command, guild_only, rename = "registration", owners_only)]
+pub async fn settings_registration_command(
+    ctx: Context<'_>,
+    #[description = "Who can register with /join"] policy: Option<RegistrationChoice>,
+    #[description = "Where requests to join are posted for approval"] #[channel_types("Text")] channel: Option<poise::serenity_prelude::GuildChannel>,
+    #[description = "Register members on their first command, when registration is open"] auto_register: Option<bool>,
+    #[description = "Register members as soon as they join the server, or ask for approval if it's required"] enrol_new_members: Option<bool>,
+) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().unwrap().get() as i64;
+    let db = ctx.data().db.as_ref();
+    let change = RegistrationChange {
+        policy: policy.map(RegistrationPolicy::from),
+        channel_id: channel.map(|c| c.id.get() as i64),
+        auto_register,
+        enrol_new_members,
+    };
+
+    let settings = match db.get_guild_settings(guild_id).await {
+        Ok(settings) => settings,
+        Err(e) => return respond(ctx, Err(e)).await,
+    };
+    let settings = match registration_settings(settings, change) {
+        Ok(settings) => settings,
+        Err(msg) => return reject(ctx, &msg).await,
+    };
+
+    respond(ctx, settings_registration(db, settings).await).await
+}
+
+#[poise::command(slash_command, guild_only, rename = "jury", owners_only)]
+pub async fn settings_jury_command(
+    ctx: Context<'_>,
+    #[description = "Whether bbps need the jury to agree before they count"] enabled: bool,
+    #[description = "Votes either way it takes to settle a bbp, 3 by default"] #[min = 1] quorum: Option<i32>,
+    #[description = "Hours the jury has to vote, 24 by default"] #[min = 1] hours: Option<i32>,
+) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().unwrap().get() as i64;
+    let db = ctx.data().db.as_ref();
+    let jury = match enabled {
+        true => JuryPolicy::On { quorum: quorum.unwrap_or(3), hours: hours.unwrap_or(24) },
+        false => JuryPolicy::Off,
+    };
+
+    respond(ctx, settings_jury(db, guild_id, jury).await).await
+}
+
+#[poise::command(slash_command, guild_only, rename = "grace", owners_only)]
+pub async fn settings_grace_command(
+    ctx: Context<'_>,
+    #[description = "Minutes issuers have to edit or retract what they gave, 0 for never"] #[min = 0] minutes: i32,
+) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().unwrap().get() as i64;
+    let db = ctx.data().db.as_ref();
+
+    respond(ctx, settings_grace(db, guild_id, minutes).await).await
+}
+
+pub async fn settings_show(db: &dyn BbpStore, guild_id: i64) -> Result<String, StoreError> {
+    let settings = db.get_guild_settings(guild_id).await?;
+
+    Ok(format!("Settings\n\nDecay: {}\n{}\nRegistration: {}\nJury: {}\nGrace: {}",
+        decay_description(settings.decay),
+        limits_description(&settings),
+        registration_description(&settings),
+        jury_description(settings.jury),
+        grace_description(settings.grace_minutes)))
+}
+
+pub async fn settings_decay(db: &dyn BbpStore, guild_id: i64, decay: DecayPolicy) -> Result<String, StoreError> {
+    let settings = db.get_guild_settings(guild_id).await?;
+    let settings = db.save_guild_settings(GuildSettings { decay, ..settings }).await?;
+
+    Ok(format!("Decay updated, {} from now on.", decay_description(settings.decay)))
+}
+
+/// Leaves out limits that weren't given as they are.
+pub async fn settings_limits(db: &dyn BbpStore, guild_id: i64, everyone: Option<i32>, moderators: Option<i32>) -> Result<String, StoreError> {
+    let settings = db.get_guild_settings(guild_id).await?;
+    let settings = db.save_guild_settings(GuildSettings {
+        max_value: everyone.unwrap_or(settings.max_value),
+        moderator_max_value: moderators.unwrap_or(settings.moderator_max_value),
+        ..settings
+    }).await?;
+
+    Ok(format!("Limits updated.\n{}", limits_description(&settings)))
+}
+
+pub async fn settings_moderator_role(db: &dyn BbpStore, guild_id: i64, role_id: Option<i64>) -> Result<String, StoreError> {
+    let settings = db.get_guild_settings(guild_id).await?;
+    let settings = db.save_guild_settings(GuildSettings { moderator_role_id: role_id, ..settings }).await?;
+
+    Ok(format!("Moderator role updated.\n{}", limits_description(&settings)))
+}
+
+/// The `/settings registration` options, `None` keeps what is set.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RegistrationChange {
+    pub policy: Option<RegistrationPolicy>,
+    pub channel_id: Option<i64>,
+    pub auto_register: Option<bool>,
+    pub enrol_new_members: Option<bool>,
+}
+
+/// Saves settings checked by `registration_settings`.
+pub async fn settings_registration(db: &dyn BbpStore, settings: GuildSettings) -> Result<String, StoreError> {
+    let settings = db.save_guild_settings(settings).await?;
+
+    Ok(format!("Registration updated, {}.", registration_description(&settings)))
+}
+
+/// Checks the `/settings registration` arguments against the current settings.
+/// Approval can't be turned on without a channel to post requests to.
+pub fn registration_settings(settings: GuildSettings, change: RegistrationChange) -> Result<GuildSettings, String> {
+    let registration = change.policy.unwrap_or(settings.registration);
+    let moderator_channel_id = change.channel_id.or(settings.moderator_channel_id);
+    if registration == RegistrationPolicy::Approval && moderator_channel_id.is_none() {
+        return Err("Pick a `channel` for requests to join before requiring approval.".to_string());
+    }
+
+    Ok(GuildSettings {
+        registration,
+        moderator_channel_id,
+        auto_register: change.auto_register.unwrap_or(settings.auto_register),
+        enrol_new_members: change.enrol_new_members.unwrap_or(settings.enrol_new_members),
+        ..settings
+    })
+}
+
+pub async fn settings_jury(db: &dyn BbpStore, guild_id: i64, jury: JuryPolicy) -> Result<String, StoreError> {
+    let settings = db.get_guild_settings(guild_id).await?;
+    let settings = db.save_guild_settings(GuildSettings { jury, ..settings }).await?;
+
+    Ok(format!("Jury updated, {}. Pending bbps keep the rules they were put up with.", jury_description(settings.jury)))
+}
+
+/// 0 turns edits and retractions off, negative numbers count as 0.
+pub async fn settings_grace(db: &dyn BbpStore, guild_id: i64, minutes: i32) -> Result<String, StoreError> {
+    let settings = db.get_guild_settings(guild_id).await?;
+    let settings = db.save_guild_settings(GuildSettings { grace_minutes: minutes.max(0), ..settings }).await?;
+
+    Ok(format!("Grace period updated, {}.", grace_description(settings.grace_minutes)))
+}
+
+/// Checks the `/settings decay` arguments. Only turning decay off goes without
+/// a number of days.
+pub fn decay_policy(mode: DecayChoice, days: Option<i32>) -> Result<DecayPolicy, String> {
+    match (mode, days) {
+        (DecayChoice::Off, _) => Ok(DecayPolicy::Off),
+        (_, None) => Err("Say after how many `days` bbps should decay.".to_string()),
+        (_, Some(days)) if days <= 0 => Err("`days` has to be at least 1.".to_string()),
+        (DecayChoice::HalfLife, Some(days)) => Ok(DecayPolicy::HalfLife { days }),
+        (DecayChoice::Expiry, Some(days)) => Ok(DecayPolicy::Expiry { days }),
+    }
+}
+
+/// "Anyone can give up to 1 point at once, <@&role> up to 3."
+fn limits_description(settings: &GuildSettings) -> String {
+    let everyone = format!("Anyone can give up to {} at once", points(settings.max_value));
+
+    match settings.moderator_role_id {
+        Some(role_id) => format!("{}, <@&{}> up to {}.", everyone, role_id, settings.max_value_for(true)),
+        None => format!("{}, there is no moderator role.", everyone),
+    }
+}
+
+/// "anyone can /join, members are registered on their first command"
+fn registration_description(settings: &GuildSettings) -> String {
+    let policy = policy_description(settings);
+
+    match (settings.enrol_new_members, settings.registration) {
+        (false, _) => policy,
+        (true, RegistrationPolicy::Approval) => format!("{}, new members are asked for when they join the server", policy),
+        (true, _) => format!("{}, new members are registered when they join the server", policy),
+    }
+}
+
+fn policy_description(settings: &GuildSettings) -> String {
+    let policy = match (settings.registration, settings.moderator_channel_id) {
+        (RegistrationPolicy::Open, _) => "anyone can /join".to_string(),
+        (RegistrationPolicy::Approval, Some(channel_id)) => format!("requests to /join are approved in <#{}>", channel_id),
+        (RegistrationPolicy::Approval, None) => "requests to /join need approval, but there is no channel for them".to_string(),
+        (RegistrationPolicy::OwnerOnly, _) => "only owners add users".to_string(),
+    };
+
+    if settings.registration == RegistrationPolicy::Open && settings.auto_register {
+        format!("{}, members are registered on their first command", policy)
+    } else {
+        policy
+    }
+}
+
+fn points(count: i32) -> String {
+    if count == 1 {
+        "1 point".to_string()
+    } else {
+        format!("{} points", count)
+    }
+}
+
+/// "bbps lose half their weight every 30 days"
+pub fn decay_description(decay: DecayPolicy) -> String {
+    match decay {
+        DecayPolicy::Off => "bbps never decay".to_string(),
+        DecayPolicy::HalfLife { days: 1 } => "bbps lose half their weight every day".to_string(),
+        DecayPolicy::HalfLife { days } => format!("bbps lose half their weight every {} days", days),
+        DecayPolicy::Expiry { days: 1 } => "bbps expire after a day".to_string(),
+        DecayPolicy::Expiry { days } => format!("bbps expire after {} days", days),
+    }
+}
+
+/// "bbps count once 3 members vote for them within 24 hours"
+fn jury_description(jury: JuryPolicy) -> String {
+    match jury {
+        JuryPolicy::Off => "bbps count right away".to_string(),
+        JuryPolicy::On { quorum, hours } => format!("bbps count once {} vote for them within {}",
+            if quorum == 1 { "1 member".to_string() } else { format!("{} members", quorum) },
+            if hours == 1 { "an hour".to_string() } else { format!("{} hours", hours) }),
+    }
+}
+
+/// "issuers can edit or retract what they gave for 10 minutes"
+fn grace_description(minutes: i32) -> String {
+    match minutes {
+        m if m <= 0 => "bbps and gbps can't be edited or retracted".to_string(),
+        1 => "issuers can edit or retract what they gave for a minute".to_string(),
+        m => format!("issuers can edit or retract what they gave for {} minutes", m),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dataaccess::memory_store::MemoryStore;
+
+    const GUILD: i64 = 1;
+    const CHANNEL: i64 = 500;
+
+    #[test]
+    fn approval_needs_a_channel() {
+        let change = RegistrationChange { policy: Some(RegistrationPolicy::Approval), ..RegistrationChange::default() };
+
+        let refused = registration_settings(GuildSettings::default(), change);
+        let with_channel = registration_settings(GuildSettings::default(), RegistrationChange { channel_id: Some(CHANNEL), ..change }).unwrap();
+        let channel_set_before = registration_settings(GuildSettings { moderator_channel_id: Some(CHANNEL), ..GuildSettings::default() }, change).unwrap();
+
+        assert_eq!(refused.unwrap_err(), "Pick a `channel` for requests to join before requiring approval.");
+        assert_eq!((with_channel.registration, with_channel.moderator_channel_id), (RegistrationPolicy::Approval, Some(CHANNEL)));
+        assert_eq!((channel_set_before.registration, channel_set_before.moderator_channel_id), (RegistrationPolicy::Approval, Some(CHANNEL)));
+    }
+
+    #[test]
+    fn options_left_out_stay_as_they_are() {
+        let settings = GuildSettings { registration: RegistrationPolicy::Open, auto_register: true, enrol_new_members: true, ..GuildSettings::default() };
+
+        let changed = registration_settings(settings, RegistrationChange { auto_register: Some(false), ..RegistrationChange::default() }).unwrap();
+
+        assert_eq!((changed.registration, changed.auto_register, changed.enrol_new_members), (RegistrationPolicy::Open, false, true));
+    }
+
+    #[test]
+    fn enrolling_under_approval_is_described_as_asking() {
+        let approval = GuildSettings { registration: RegistrationPolicy::Approval, moderator_channel_id: Some(CHANNEL), enrol_new_members: true, ..GuildSettings::default() };
+        let owner_only = GuildSettings { enrol_new_members: true, ..GuildSettings::default() };
+
+        assert_eq!(registration_description(&approval), "requests to /join are approved in <#500>, new members are asked for when they join the server");
+        assert_eq!(registration_description(&owner_only), "only owners add users, new members are registered when they join the server");
+    }
+
+    #[tokio::test]
+    async fn settings_registration_saves_and_describes_the_policy() {
+        let db = MemoryStore::new();
+        let change = RegistrationChange { policy: Some(RegistrationPolicy::Open), auto_register: Some(true), ..RegistrationChange::default() };
+        let settings = registration_settings(db.get_guild_settings(GUILD).await.unwrap(), change).unwrap();
+
+        let msg = settings_registration(&db, settings).await.unwrap();
+
+        assert_eq!(msg, "Registration updated, anyone can /join, members are registered on their first command.");
+        assert_eq!(db.get_guild_settings(GUILD).await.unwrap().registration, RegistrationPolicy::Open);
+    }
+}
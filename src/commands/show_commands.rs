@@ -0,0 +1,176 @@
+use crate::{Context, Error};
+use crate::commands::bbp_commands::a_point;
+use crate::commands::responses::{reject, respond_embed};
+use crate::dataaccess::bbp_store::BbpStore;
+use crate::dataaccess::models::{Appeal, AppealStatus, JuryStatus, PendingBbp, PointDetails, PointKind};
+use crate::dataaccess::store_error::StoreError;
+use poise::serenity_prelude::CreateEmbed;
+
+#[poise::command(slash_command, guild_only, rename = "show")]
+pub async fn show_command(
+    ctx: Context<'_>,
+    #[description = "The bbp or gbp, e.g. B12 or G7"] id: String,
+) -> Result<(), Error> {
+    let Some((kind, point_id)) = PointKind::parse_public_id(&id) else {
+        return reject(ctx, "That isn't a bbp or gbp ID, they look like B12 or G7.").await;
+    };
+    let guild_id = ctx.guild_id().unwrap().get() as i64;
+    let db = ctx.data().db.as_ref();
+
+    match show(db, guild_id, kind, point_id).await {
+        Ok(Some(embed)) => respond_embed(ctx, Ok(embed)).await,
+        Ok(None) => reject(ctx, &format!("There is no {} here.", kind.public_id(point_id))).await,
+        Err(e) => respond_embed(ctx, Err(e)).await,
+    }
+}
+
+/// Everything about one bbp/gbp, or `None` if the guild has no such point.
+pub async fn show(db: &dyn BbpStore, guild_id: i64, kind: PointKind, point_id: i32) -> Result<Option<CreateEmbed>, StoreError> {
+    let Some(point) = db.get_point(guild_id, kind, point_id).await? else {
+        return Ok(None);
+    };
+
+    let mut embed = CreateEmbed::new()
+        .title(format!("{}: {}", kind.public_id(point_id), a_point(kind, point.value)))
+        .description(point.description.clone())
+        .field("From", format!("<@{}>", point.issuer_discord_id), true)
+        .field("To", format!("<@{}>", point.target_discord_id), true)
+        .field("When", when_text(&point), true)
+        .field("Status", status_text(&point), false);
+    if let Some(pending) = &point.jury {
+        embed = embed.field("Jury", jury_text(pending), false);
+    }
+    if let Some(appeal) = &point.appeal {
+        embed = embed.field("Appeal", appeal_text(appeal), false);
+    }
+
+    Ok(Some(embed))
+}
+
+/// When it was given, with a link to the reply that announced it.
+fn when_text(point: &PointDetails) -> String {
+    format!("{}{}", discord_time(point.timestamp), message_link(point.guild_id, point.channel_id, point.message_id))
+}
+
+/// Whether it still counts, and if not, who forgave it when.
+fn status_text(point: &PointDetails) -> String {
+    let overturned = point.appeal.as_ref().is_some_and(|appeal| appeal.status == AppealStatus::Overturned);
+    let mut status = match (point.forgiven, point.forgiven_by_discord_id, point.forgiven_at) {
+        (false, _, _) => "Counts".to_string(),
+        (true, _, _) if overturned => "Overturned on appeal".to_string(),
+        (true, Some(forgiver), Some(at)) => format!("Forgiven by <@{}> {}", forgiver, discord_time(at)),
+        (true, _, _) => "Forgiven".to_string(),
+    };
+    if point.season_id.is_some() {
+        status.push_str(", archived when its season ended");
+    }
+
+    status
+}
+
+fn jury_text(pending: &PendingBbp) -> String {
+    let verdict = match pending.status {
+        JuryStatus::Confirmed => "Confirmed",
+        JuryStatus::Vetoed => "Vetoed",
+        JuryStatus::Expired => "Lapsed",
+        JuryStatus::Pending => "Voting",
+    };
+
+    format!("{} (👍 {} 👎 {}){}",
+        verdict,
+        pending.votes_for,
+        pending.votes_against,
+        message_link(pending.guild_id, pending.channel_id, pending.message_id))
+}
+
+fn appeal_text(appeal: &Appeal) -> String {
+    let verdict = match (appeal.status, appeal.resolved_by_discord_id) {
+        (AppealStatus::Open, _) => "Open".to_string(),
+        (AppealStatus::Upheld, Some(moderator)) => format!("Upheld by <@{}>", moderator),
+        (AppealStatus::Overturned, Some(moderator)) => format!("Overturned by <@{}>", moderator),
+        (AppealStatus::Upheld, None) => "Upheld by vote".to_string(),
+        (AppealStatus::Overturned, None) => "Overturned by vote".to_string(),
+    };
+    let votes = if appeal.quorum > 0 {
+        format!(" (Overturn {} Uphold {})", appeal.votes_overturn, appeal.votes_uphold)
+    } else {
+        String::new()
+    };
+
+    format!("{}{}{}\n> {}",
+        verdict,
+        votes,
+        message_link(appeal.bbp.guild_id, appeal.channel_id, appeal.message_id),
+        appeal.statement)
+}
+
+/// " - [message](...)" pointing at a message the bot posted, if it was remembered.
+fn message_link(guild_id: i64, channel_id: Option<i64>, message_id: Option<i64>) -> String {
+    match (channel_id, message_id) {
+        (Some(channel_id), Some(message_id)) => format!(" - [message](https://discord.com/channels/{}/{}/{})", guild_id, channel_id, message_id),
+        _ => String::new(),
+    }
+}
+
+/// A timestamp every reader sees in their own timezone.
+pub(crate) fn discord_time(timestamp: chrono::NaiveDateTime) -> String {
+    format!("<t:{}:f>", timestamp.and_utc().timestamp())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dataaccess::memory_store::MemoryStore;
+
+    const GUILD: i64 = 1;
+    const ALICE: i64 = 10;
+    const BOB: i64 = 20;
+
+    async fn store() -> MemoryStore {
+        let db = MemoryStore::new();
+        for (discord_id, name) in [(ALICE, "Alice"), (BOB, "Bob")] {
+            db.add_user(GUILD, discord_id, &name.to_lowercase(), name).await.unwrap();
+        }
+        db
+    }
+
+    #[test]
+    fn public_ids_round_trip() {
+        for (kind, point_id) in [(PointKind::Bbp, 1), (PointKind::Bbp, 12), (PointKind::Gbp, 7), (PointKind::Gbp, i32::MAX)] {
+            assert_eq!(PointKind::parse_public_id(&kind.public_id(point_id)), Some((kind, point_id)));
+        }
+        assert_eq!(PointKind::parse_public_id(" b12 "), Some((PointKind::Bbp, 12)));
+        assert_eq!(PointKind::parse_public_id("g7"), Some((PointKind::Gbp, 7)));
+    }
+
+    #[test]
+    fn malformed_public_ids_are_refused() {
+        for id in ["", "B", "12", "X12", "B0", "B-3", "B+3", "B1.5", "B 12", "BB12", "G12a", "B99999999999", "É12"] {
+            assert_eq!(PointKind::parse_public_id(id), None, "{}", id);
+        }
+    }
+
+    #[tokio::test]
+    async fn when_links_the_announcement() {
+        let db = store().await;
+        db.issue_point(GUILD, PointKind::Bbp, 1, ALICE, BOB, "late").await.unwrap();
+        db.issue_point(GUILD, PointKind::Gbp, 1, ALICE, BOB, "donuts").await.unwrap();
+        db.set_point_message(GUILD, PointKind::Gbp, 1, 500, 600).await.unwrap();
+
+        let bbp = db.get_point(GUILD, PointKind::Bbp, 1).await.unwrap().unwrap();
+        let gbp = db.get_point(GUILD, PointKind::Gbp, 1).await.unwrap().unwrap();
+
+        assert_eq!(when_text(&bbp), discord_time(bbp.timestamp));
+        assert_eq!(when_text(&gbp), format!("{} - [message](https://discord.com/channels/1/500/600)", discord_time(gbp.timestamp)));
+    }
+
+    #[tokio::test]
+    async fn show_only_finds_points_of_the_guild() {
+        let db = store().await;
+        db.issue_point(GUILD, PointKind::Bbp, 1, ALICE, BOB, "late").await.unwrap();
+
+        assert!(show(&db, GUILD, PointKind::Bbp, 1).await.unwrap().is_some());
+        assert!(show(&db, GUILD, PointKind::Gbp, 1).await.unwrap().is_none());
+        assert!(show(&db, 2, PointKind::Bbp, 1).await.unwrap().is_none());
+    }
+}
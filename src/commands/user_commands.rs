@@ -0,0 +1,180 @@
+use crate::{Context, Error};
+use crate::commands::bbp_commands::display_name;
+use crate::commands::checks::owner_or_moderator;
+use crate::commands::responses::{reject, respond};
+use crate::dataaccess::bbp_store::BbpStore;
+use crate::dataaccess::store_error::StoreError;
+use poise::serenity_prelude as serenity;
+
+#[poise::command(
+    slash_command,
+    guild_only,
+    rename = "user",
+    check = "owner_or_moderator",
+    subcommands(
+        "user_rename_command",
+        "user_deactivate_command",
+        "user_reactivate_command",
+        "user_merge_command",
+        "user_remove_command"
+    ),
+    subcommand_required
+)]
+pub async fn user_command(_ctx: Context<'_>) -> Result<(), Error> {
+    Ok(())
+}
+
+#[poise::command(slash_command, guild_only, rename = "rename")]
+pub async fn user_rename_command(
+    ctx: Context<'_>,
+    user: serenity::User,
+    #[description = "What the bot should call them"] friendly_name: String,
+) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().unwrap().get() as i64;
+    let db = ctx.data().db.as_ref();
+
+    respond(ctx, user_rename(db, guild_id, user.id.get() as i64, &friendly_name).await).await
+}
+
+#[poise::command(slash_command, guild_only, rename = "deactivate")]
+pub async fn user_deactivate_command(
+    ctx: Context<'_>,
+    #[description = "Who to take off the leaderboard, their history is kept"] user: serenity::User,
+) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().unwrap().get() as i64;
+    let db = ctx.data().db.as_ref();
+
+    respond(ctx, user_set_active(db, guild_id, user.id.get() as i64, false).await).await
+}
+
+#[poise::command(slash_command, guild_only, rename = "reactivate")]
+pub async fn user_reactivate_command(
+    ctx: Context<'_>,
+    user: serenity::User,
+) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().unwrap().get() as i64;
+    let db = ctx.data().db.as_ref();
+
+    respond(ctx, user_set_active(db, guild_id, user.id.get() as i64, true).await).await
+}
+
+#[poise::command(slash_command, guild_only, rename = "merge")]
+pub async fn user_merge_command(
+    ctx: Context<'_>,
+    #[description = "The duplicate account, removed after the merge"] from: serenity::User,
+    #[description = "The account that keeps everything"] into: serenity::User,
+) -> Result<(), Error> {
+    if from.id == into.id {
+        return reject(ctx, "Pick two different users.").await;
+    }
+    let guild_id = ctx.guild_id().unwrap().get() as i64;
+    let db = ctx.data().db.as_ref();
+
+    respond(ctx, user_merge(db, guild_id, from.id.get() as i64, into.id.get() as i64).await).await
+}
+
+#[poise::command(slash_command, guild_only, rename = "remove")]
+pub async fn user_remove_command(
+    ctx: Context<'_>,
+    #[description = "Someone registered by mistake, who never gave or got a bbp or gbp"] user: serenity::User,
+) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().unwrap().get() as i64;
+    let db = ctx.data().db.as_ref();
+
+    respond(ctx, user_remove(db, guild_id, user.id.get() as i64).await).await
+}
+
+pub async fn user_rename(db: &dyn BbpStore, guild_id: i64, user_id: i64, friendly_name: &str) -> Result<String, StoreError> {
+    let user = db.rename_user(guild_id, user_id, friendly_name).await?;
+
+    Ok(format!("<@{}> is now called '{}'.", user.discord_id, display_name(&user)))
+}
+
+pub async fn user_set_active(db: &dyn BbpStore, guild_id: i64, user_id: i64, active: bool) -> Result<String, StoreError> {
+    let user = db.set_user_active(guild_id, user_id, active).await?;
+
+    Ok(if active {
+        format!("{} is back on the leaderboard.", display_name(&user))
+    } else {
+        format!("{} was deactivated, their history is kept but they're off the leaderboard.", display_name(&user))
+    })
+}
+
+pub async fn user_merge(db: &dyn BbpStore, guild_id: i64, from_id: i64, into_id: i64) -> Result<String, StoreError> {
+    let merged = db.merge_users(guild_id, from_id, into_id).await?;
+
+    Ok(format!("Merged <@{}> into {}, who now has a net score of {}.", from_id, display_name(&merged), merged.points))
+}
+
+pub async fn user_remove(db: &dyn BbpStore, guild_id: i64, user_id: i64) -> Result<String, StoreError> {
+    let removed = db.remove_user(guild_id, user_id).await?;
+
+    Ok(format!("Removed {}.", display_name(&removed)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dataaccess::memory_store::MemoryStore;
+    use crate::dataaccess::models::PointKind;
+    use crate::dataaccess::store_error::UserRole;
+
+    const GUILD: i64 = 1;
+    const ALICE: i64 = 10;
+    const BOB: i64 = 20;
+    const BOB_AGAIN: i64 = 21;
+
+    async fn store() -> MemoryStore {
+        let db = MemoryStore::new();
+        for (discord_id, name) in [(ALICE, "Alice"), (BOB, "Bob"), (BOB_AGAIN, "Bobby")] {
+            db.add_user(GUILD, discord_id, &name.to_lowercase(), name).await.unwrap();
+        }
+        db
+    }
+
+    #[tokio::test]
+    async fn merge_reports_the_combined_net_score() {
+        let db = store().await;
+        db.issue_point(GUILD, PointKind::Bbp, 2, ALICE, BOB, "late").await.unwrap();
+        db.issue_point(GUILD, PointKind::Bbp, 1, ALICE, BOB_AGAIN, "late again").await.unwrap();
+
+        let msg = user_merge(&db, GUILD, BOB_AGAIN, BOB).await.unwrap();
+
+        assert_eq!(msg, "Merged <@21> into Bob, who now has a net score of 3.");
+    }
+
+    #[tokio::test]
+    async fn merge_says_which_user_is_missing() {
+        let db = store().await;
+
+        assert!(matches!(user_merge(&db, GUILD, 99, BOB).await, Err(StoreError::UserNotFound(UserRole::MergeFrom))));
+        assert!(matches!(user_merge(&db, GUILD, BOB, 99).await, Err(StoreError::UserNotFound(UserRole::MergeInto))));
+    }
+
+    #[tokio::test]
+    async fn remove_only_takes_users_without_history() {
+        let db = store().await;
+        db.issue_point(GUILD, PointKind::Gbp, 1, ALICE, BOB, "donuts").await.unwrap();
+
+        let msg = user_remove(&db, GUILD, BOB_AGAIN).await.unwrap();
+        let with_history = user_remove(&db, GUILD, BOB).await;
+        let issuer = user_remove(&db, GUILD, ALICE).await;
+
+        assert_eq!(msg, "Removed Bobby.");
+        assert!(db.get_user_by_discord_id(GUILD, BOB_AGAIN).await.unwrap().is_none());
+        assert!(matches!(with_history, Err(StoreError::UserHasHistory)));
+        assert!(matches!(issuer, Err(StoreError::UserHasHistory)));
+    }
+
+    #[tokio::test]
+    async fn deactivating_takes_the_user_off_the_leaderboard() {
+        let db = store().await;
+        db.issue_point(GUILD, PointKind::Bbp, 1, ALICE, BOB, "late").await.unwrap();
+
+        let msg = user_set_active(&db, GUILD, BOB, false).await.unwrap();
+        let back = user_set_active(&db, GUILD, BOB, true).await.unwrap();
+
+        assert_eq!(msg, "Bob was deactivated, their history is kept but they're off the leaderboard.");
+        assert_eq!(back, "Bob is back on the leaderboard.");
+    }
+}
@@ -0,0 +1,175 @@
+use async_trait::async_trait;
+
+use crate::dataaccess::models::{Appeal, AuditEntry, Bbp, ClosedSeason, GuildSettings, HistoryFilter, HistoryRecord, IssuedPoint, JuryVote, LeaderboardUser, Page, PendingBbp, PointDetails, PointKind, RegistrationRequest, Rivalry, Season, Standings, TimeWindow, User, UserStats};
+use crate::dataaccess::store_error::StoreError;
+
+/// Everything the commands need from storage. `PostgresService` is the production
+/// implementation, `MemoryStore` keeps everything in process.
+///
+/// Users are scoped to a guild, so the same Discord account has an independent
+/// standing in every server the bot is in. Bbps and gbps belong to the guild of
+/// the users involved.
+///
+/// Ranks order users by their score, the net score with the guild's
+/// `DecayPolicy` applied as of the store's `Clock`.
+#[async_trait]
+pub trait BbpStore: Send + Sync {
+    /// The current time according to the store's `Clock`. Commands work out
+    /// their periods from it, so they agree with the store on what "now" is.
+    fn now(&self) -> chrono::NaiveDateTime;
+
+    async fn get_user_by_discord_id(&self, guild_id: i64, discord_id: i64) -> Result<Option<User>, StoreError>;
+
+    /// Deactivated users come back without a rank or score.
+    async fn get_user_by_discord_id_with_rank(&self, guild_id: i64, discord_id: i64) -> Result<Option<User>, StoreError>;
+
+    /// Fails with `StoreError::Duplicate` if the user is already registered in the guild.
+    async fn add_user(&self, guild_id: i64, discord_id: i64, discord_username: &str, friendly_name: &str) -> Result<User, StoreError>;
+
+    /// Fails with `StoreError::UserNotFound` if the user isn't registered in the guild.
+    async fn rename_user(&self, guild_id: i64, discord_id: i64, friendly_name: &str) -> Result<User, StoreError>;
+
+    /// Deactivating leaves the user's bbps and gbps alone, it only drops them
+    /// from the standings.
+    async fn set_user_active(&self, guild_id: i64, discord_id: i64, active: bool) -> Result<User, StoreError>;
+
+    /// Moves every bbp and gbp the first user gave or got, their pending bbps,
+    /// their votes and their archived season standings, over to the second user
+    /// and removes the first, in a single transaction. Returns the merged user
+    /// with their totals recomputed. A missing user fails with
+    /// `StoreError::UserNotFound` naming which of the two it was.
+    async fn merge_users(&self, guild_id: i64, from_discord_id: i64, into_discord_id: i64) -> Result<User, StoreError>;
+
+    /// Deletes a user registered by mistake. Fails with `StoreError::UserHasHistory`
+    /// if they gave or got any bbps or gbps, or took part in a jury.
+    async fn remove_user(&self, guild_id: i64, discord_id: i64) -> Result<User, StoreError>;
+
+    /// Looks up both users, inserts the bbp/gbp worth `value` points and re-ranks
+    /// the target in a single transaction, so concurrent issuances can't report a
+    /// stale standing. Limits on `value` are up to the caller. Fails with
+    /// `StoreError::UserInactive` if either user was deactivated.
+    async fn issue_point(&self, guild_id: i64, kind: PointKind, value: i32, issuer_discord_id: i64, target_discord_id: i64, description: &str) -> Result<IssuedPoint, StoreError>;
+
+    /// Remembers the reply that announced a bbp/gbp, so `/show` can link to it.
+    async fn set_point_message(&self, guild_id: i64, kind: PointKind, point_id: i32, channel_id: i64, message_id: i64) -> Result<(), StoreError>;
+
+    /// Puts a bbp before the jury instead of issuing it. Checks the users like
+    /// `issue_point`; voting closes `lifetime` from now.
+    #[allow(clippy::too_many_arguments)]
+    async fn create_pending_bbp(&self, guild_id: i64, value: i32, issuer_discord_id: i64, target_discord_id: i64, description: &str, quorum: i32, lifetime: chrono::Duration) -> Result<PendingBbp, StoreError>;
+
+    /// Remembers where the vote buttons were posted, so the message can be
+    /// updated once the pending bbp expires.
+    async fn set_pending_bbp_message(&self, pending_id: i32, channel_id: i64, message_id: i64) -> Result<(), StoreError>;
+
+    /// Casts or changes the voter's vote and settles the pending bbp if that
+    /// reached a verdict, issuing it when confirmed, all in one transaction.
+    /// Fails with `StoreError::VotingClosed` once it's settled or expired.
+    async fn vote_on_pending_bbp(&self, pending_id: i32, voter_discord_id: i64, in_favour: bool) -> Result<JuryVote, StoreError>;
+
+    /// Marks every pending bbp in any guild whose time ran out as expired and
+    /// returns them.
+    async fn expire_pending_bbps(&self) -> Result<Vec<PendingBbp>, StoreError>;
+
+    /// The target's bbps from the running season, newest first and at most `limit`
+    /// of them. Only those from the given issuer if there is one, and either
+    /// the ones that still count or the forgiven ones that could be unforgiven.
+    async fn get_recent_bbps(&self, guild_id: i64, target_discord_id: i64, issuer_discord_id: Option<i64>, forgiven: bool, limit: i64) -> Result<Vec<Bbp>, StoreError>;
+
+    /// Opens an appeal of a bbp by its target. Fails with `StoreError::NotAppealable`
+    /// if someone else got it, it no longer counts or it was appealed before.
+    async fn open_appeal(&self, guild_id: i64, bbp_id: i32, appellant_discord_id: i64, statement: &str, quorum: i32) -> Result<Appeal, StoreError>;
+
+    /// Remembers where the appeal's buttons were posted.
+    async fn set_appeal_message(&self, appeal_id: i32, channel_id: i64, message_id: i64) -> Result<(), StoreError>;
+
+    /// Casts or changes a community vote on an appeal and settles it if that
+    /// reached a verdict, all in one transaction. Fails with `StoreError::NoCommunityVote`
+    /// when the appeal was opened without a quorum.
+    async fn vote_on_appeal(&self, appeal_id: i32, voter_discord_id: i64, overturn: bool) -> Result<Appeal, StoreError>;
+
+    /// A moderator's decision, which settles the appeal right away. Overturning
+    /// forgives the bbp. Appeals of bbps archived by the end of a season can't be
+    /// decided any more and fail with `StoreError::VotingClosed`, like settled ones.
+    async fn resolve_appeal(&self, appeal_id: i32, moderator_discord_id: i64, overturn: bool) -> Result<Appeal, StoreError>;
+
+    /// Forgives one of the target's bbps from the running season, the given one
+    /// or else the most recent that still counts, and records who forgave it.
+    /// With an issuer only their bbps qualify. Fails with `StoreError::NothingToForgive`
+    /// if none does.
+    async fn forgive_bbp(&self, guild_id: i64, target_discord_id: i64, issuer_discord_id: Option<i64>, bbp_id: Option<i32>, forgiver_discord_id: i64) -> Result<Bbp, StoreError>;
+
+    /// Makes a forgiven bbp count again, picked like in `forgive_bbp` among the
+    /// forgiven ones. Bbps overturned on appeal stay forgiven.
+    async fn unforgive_bbp(&self, guild_id: i64, target_discord_id: i64, issuer_discord_id: Option<i64>, bbp_id: Option<i32>) -> Result<Bbp, StoreError>;
+
+    /// One bbp/gbp of the guild with its jury vote and appeal, if any.
+    async fn get_point(&self, guild_id: i64, kind: PointKind, point_id: i32) -> Result<Option<PointDetails>, StoreError>;
+
+    /// Replaces the description of a bbp/gbp the issuer gave less than `grace`
+    /// ago and logs the change, in one transaction. Fails with `StoreError::NotEditable`
+    /// if it isn't theirs to change, and `StoreError::GracePeriodOver` once it's too late.
+    async fn edit_point(&self, guild_id: i64, kind: PointKind, point_id: i32, issuer_discord_id: i64, description: &str, grace: chrono::Duration) -> Result<AuditEntry, StoreError>;
+
+    /// Deletes a bbp/gbp, checked like in `edit_point`. Unlike forgiving, it
+    /// no longer counts for either user at all; the audit log keeps what it was.
+    async fn retract_point(&self, guild_id: i64, kind: PointKind, point_id: i32, issuer_discord_id: i64, grace: chrono::Duration) -> Result<AuditEntry, StoreError>;
+
+    /// The guild's edits and retractions, newest first.
+    async fn get_audit_log(&self, guild_id: i64, page: Page) -> Result<Vec<AuditEntry>, StoreError>;
+
+    /// Active users ranked by the score of the bbps and gbps they got within the window,
+    /// ties broken by who registered first. Totals and issued counts are limited to the window too.
+    /// A closed season gives its archived standings, the running one the live ones.
+    async fn get_leaderboard(&self, guild_id: i64, standings: Standings, page: Page) -> Result<Vec<LeaderboardUser>, StoreError>;
+
+    /// The user's zero-based position in `get_leaderboard`'s ordering, or `None`
+    /// if they aren't on it.
+    async fn get_leaderboard_position(&self, guild_id: i64, standings: Standings, discord_id: i64) -> Result<Option<i64>, StoreError>;
+
+    /// The bbps and gbps the user received, newest first.
+    async fn get_user_history(&self, guild_id: i64, discord_id: i64, filter: HistoryFilter, page: Page) -> Result<Vec<HistoryRecord>, StoreError>;
+
+    async fn get_user_stats(&self, guild_id: i64, discord_id: i64) -> Result<UserStats, StoreError>;
+
+    /// What the users `a` and `b` gave each other, with up to `recent` of their
+    /// latest exchanges.
+    async fn get_rivalry(&self, guild_id: i64, a_discord_id: i64, b_discord_id: i64, recent: i64) -> Result<Rivalry, StoreError>;
+
+    /// The bbps and gbps the user received within the window, oldest first.
+    async fn get_point_timeline(&self, guild_id: i64, discord_id: i64, window: TimeWindow) -> Result<Vec<HistoryRecord>, StoreError>;
+
+    /// Fails with `StoreError::Duplicate` while another season is running.
+    async fn start_season(&self, guild_id: i64) -> Result<Season, StoreError>;
+
+    /// Archives the running season's standings and its bbps and gbps, which
+    /// resets everyone's points. Like the live leaderboard, the standings only
+    /// rank active users: a deactivated user's points are archived with the
+    /// season but they get no standing in it.
+    async fn end_season(&self, guild_id: i64) -> Result<ClosedSeason, StoreError>;
+
+    async fn get_seasons(&self, guild_id: i64) -> Result<Vec<Season>, StoreError>;
+
+    async fn get_season(&self, guild_id: i64, number: i32) -> Result<Season, StoreError>;
+
+    async fn get_guild_settings(&self, guild_id: i64) -> Result<GuildSettings, StoreError>;
+
+    /// Creates or replaces the guild's settings.
+    async fn save_guild_settings(&self, settings: GuildSettings) -> Result<GuildSettings, StoreError>;
+
+    /// Refreshes the username and mention of the Discord account in every guild
+    /// it's registered in. Returns the number of users that changed.
+    async fn update_discord_username(&self, discord_id: i64, discord_username: &str) -> Result<u64, StoreError>;
+
+    /// Queues a `/join` for approval. Fails with `StoreError::Duplicate` if the
+    /// user is already registered or already waiting.
+    async fn request_registration(&self, guild_id: i64, discord_id: i64, discord_username: &str, friendly_name: &str) -> Result<RegistrationRequest, StoreError>;
+
+    /// Removes the user's pending request and returns it, `None` if there was
+    /// none. Whoever takes it decides what happens with it.
+    async fn take_registration_request(&self, guild_id: i64, discord_id: i64) -> Result<Option<RegistrationRequest>, StoreError>;
+
+    /// Moves users, bbps and gbps created before guild scoping existed into the
+    /// given guild. Returns the number of users claimed.
+    async fn claim_legacy_rows(&self, guild_id: i64) -> Result<u64, StoreError>;
+}
@@ -0,0 +1,36 @@
+/// Where the stores get the current time from. Scores depend on it once bbps
+/// decay, so pinning it makes them reproducible.
+pub trait Clock: Send + Sync {
+    fn now(&self) -> chrono::NaiveDateTime;
+}
+
+/// The wall clock, in UTC like every timestamp in the database.
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> chrono::NaiveDateTime {
+        chrono::Utc::now().naive_utc()
+    }
+}
+
+/// A clock that only moves when told to, for tests.
+#[cfg(test)]
+pub struct FixedClock(std::sync::Mutex<chrono::NaiveDateTime>);
+
+#[cfg(test)]
+impl FixedClock {
+    pub fn at(now: chrono::NaiveDateTime) -> FixedClock {
+        FixedClock(std::sync::Mutex::new(now))
+    }
+
+    pub fn advance(&self, by: chrono::Duration) {
+        *self.0.lock().unwrap() += by;
+    }
+}
+
+#[cfg(test)]
+impl Clock for FixedClock {
+    fn now(&self) -> chrono::NaiveDateTime {
+        *self.0.lock().unwrap()
+    }
+}
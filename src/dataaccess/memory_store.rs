@@ -0,0 +1,1254 @@
+use std::sync::{Arc, Mutex};
+
+use async_trait::async_trait;
+
+use crate::dataaccess::bbp_store::BbpStore;
+use crate::dataaccess::clock::{Clock, SystemClock};
+use crate::dataaccess::models::{net_score, Appeal, AppealStatus, AuditAction, AuditEntry, Bbp, ClosedSeason, Exchange, GuildSettings, HistoryFilter, HistoryRecord, IssuedPoint, JuryStatus, JuryVote, LeaderboardUser, Page, PendingBbp, PointDetails, PointKind, PointTally, RegistrationRequest, Rivalry, Season, Standings, TimeWindow, User, UserStats};
+use crate::dataaccess::store_error::{Existing, StoreError, UserRole};
+
+/// Process-local `BbpStore`. Nothing survives a restart, which makes it handy for
+/// trying commands out and for exercising command logic without a database.
+pub struct MemoryStore {
+    state: Mutex<MemoryState>,
+    clock: Arc<dyn Clock>,
+}
+
+#[derive(Default)]
+struct MemoryState {
+    users: Vec<User>,
+    bbps: Vec<PointRow>,
+    gbps: Vec<PointRow>,
+    seasons: Vec<Season>,
+    season_standings: Vec<(i32, LeaderboardUser)>,
+    settings: Vec<GuildSettings>,
+    registration_requests: Vec<RegistrationRequest>,
+    pending_bbps: Vec<PendingRow>,
+    pending_votes: Vec<VoteRow>,
+    appeals: Vec<AppealRow>,
+    appeal_votes: Vec<AppealVoteRow>,
+    audit_log: Vec<AuditEntry>,
+}
+
+struct PointRow {
+    point_id: i32,
+    guild_id: i64,
+    user_id: i32,
+    issuer_id: i32,
+    value: i32,
+    description: String,
+    timestamp: chrono::NaiveDateTime,
+    forgiven: bool,
+    forgiven_by_discord_id: Option<i64>,
+    forgiven_at: Option<chrono::NaiveDateTime>,
+    season_id: Option<i32>,
+    channel_id: Option<i64>,
+    message_id: Option<i64>,
+}
+
+struct PendingRow {
+    pending_id: i32,
+    guild_id: i64,
+    user_id: i32,
+    issuer_id: i32,
+    value: i32,
+    description: String,
+    quorum: i32,
+    status: JuryStatus,
+    created_at: chrono::NaiveDateTime,
+    expires_at: chrono::NaiveDateTime,
+    channel_id: Option<i64>,
+    message_id: Option<i64>,
+    bbp_id: Option<i32>,
+}
+
+struct VoteRow {
+    pending_id: i32,
+    voter_id: i32,
+    in_favour: bool,
+}
+
+struct AppealRow {
+    appeal_id: i32,
+    bbp_id: i32,
+    statement: String,
+    status: AppealStatus,
+    quorum: i32,
+    opened_at: chrono::NaiveDateTime,
+    resolved_at: Option<chrono::NaiveDateTime>,
+    resolved_by_discord_id: Option<i64>,
+    channel_id: Option<i64>,
+    message_id: Option<i64>,
+}
+
+struct AppealVoteRow {
+    appeal_id: i32,
+    voter_id: i32,
+    overturn: bool,
+}
+
+impl MemoryStore {
+    pub fn new() -> MemoryStore {
+        MemoryStore { state: Mutex::default(), clock: Arc::new(SystemClock) }
+    }
+
+    pub fn with_clock(self, clock: Arc<dyn Clock>) -> MemoryStore {
+        MemoryStore { clock, ..self }
+    }
+}
+
+impl MemoryState {
+    // Mirrors the points trigger on the Postgres side.
+    fn recalculate_user_points(&mut self, user_id: i32) {
+        let bbp_total: i32 = self.bbps.iter()
+            .filter(|b| b.user_id == user_id && !b.forgiven && b.season_id.is_none())
+            .map(|b| b.value)
+            .sum();
+        let gbp_total: i32 = self.gbps.iter()
+            .filter(|g| g.user_id == user_id && g.season_id.is_none())
+            .map(|g| g.value)
+            .sum();
+        let bbps_issued = self.bbps.iter().filter(|b| b.issuer_id == user_id && b.season_id.is_none()).count() as i32;
+        let gbps_issued = self.gbps.iter().filter(|g| g.issuer_id == user_id && g.season_id.is_none()).count() as i32;
+
+        if let Some(user) = self.users.iter_mut().find(|u| u.user_id == user_id) {
+            user.bbp_total = bbp_total;
+            user.gbp_total = gbp_total;
+            user.points = net_score(bbp_total, gbp_total);
+            user.bbps_issued = bbps_issued;
+            user.gbps_issued = gbps_issued;
+        }
+    }
+
+    fn guild_settings(&self, guild_id: i64) -> GuildSettings {
+        self.settings.iter()
+            .find(|s| s.guild_id == guild_id)
+            .copied()
+            .unwrap_or(GuildSettings { guild_id, ..GuildSettings::default() })
+    }
+
+    // Mirrors RANKED_USERS_IN_WINDOW on the SQL side.
+    fn ranked_in_window(&self, guild_id: i64, window: TimeWindow, now: chrono::NaiveDateTime) -> Vec<LeaderboardUser> {
+        let decay = self.guild_settings(guild_id).decay;
+        let mut leaderboard: Vec<LeaderboardUser> = self.users.iter().filter(|u| u.guild_id == guild_id && u.active).map(|u| {
+            let bbps: Vec<&PointRow> = self.bbps.iter()
+                .filter(|b| b.user_id == u.user_id && !b.forgiven && window.contains(b.timestamp, b.season_id))
+                .collect();
+            let bbp_total = bbps.iter().map(|b| b.value).sum();
+            // Summing f64s starts from -0.0, which SQL never reports
+            let decayed_bbp_total = bbps.iter().fold(0.0, |total, b| total + b.value as f64 * decay.weight(b.timestamp, now));
+            let gbp_total = self.gbps.iter()
+                .filter(|g| g.user_id == u.user_id && window.contains(g.timestamp, g.season_id))
+                .map(|g| g.value)
+                .sum();
+
+            LeaderboardUser {
+                user_id: u.user_id,
+                discord_username: u.discord_username.clone(),
+                discord_mention: u.discord_mention.clone(),
+                discord_id: u.discord_id,
+                friendly_name: u.friendly_name.clone(),
+                points: net_score(bbp_total, gbp_total),
+                score: decayed_bbp_total - gbp_total as f64,
+                bbp_total,
+                gbp_total,
+                bbps_issued: self.bbps.iter().filter(|b| b.issuer_id == u.user_id && window.contains(b.timestamp, b.season_id)).count() as i32,
+                gbps_issued: self.gbps.iter().filter(|g| g.issuer_id == u.user_id && window.contains(g.timestamp, g.season_id)).count() as i32,
+                rank: 0,
+            }
+        }).collect();
+
+        // Same tie semantics as RANK(): equal scores share a rank and leave a gap
+        let scores: Vec<f64> = leaderboard.iter().map(|u| u.score).collect();
+        for user in leaderboard.iter_mut() {
+            user.rank = scores.iter().filter(|s| **s > user.score).count() as i64 + 1;
+        }
+        leaderboard.sort_by_key(|u| (u.rank, u.user_id));
+
+        leaderboard
+    }
+
+    // Mirrors RERANK_SEASON_STANDINGS on the SQL side.
+    fn rerank_season_standings(&mut self, guild_id: i64) {
+        let seasons: Vec<i32> = self.seasons.iter().filter(|s| s.guild_id == guild_id).map(|s| s.season_id).collect();
+        for season_id in seasons {
+            let scores: Vec<f64> = self.season_standings.iter().filter(|(id, _)| *id == season_id).map(|(_, u)| u.score).collect();
+            for (_, line) in self.season_standings.iter_mut().filter(|(id, _)| *id == season_id) {
+                line.rank = scores.iter().filter(|s| **s > line.score).count() as i64 + 1;
+            }
+        }
+    }
+
+    fn find_season(&self, guild_id: i64, number: i32) -> Result<&Season, StoreError> {
+        self.seasons.iter()
+            .find(|s| s.guild_id == guild_id && s.number == number)
+            .ok_or(StoreError::SeasonNotFound(number))
+    }
+
+    // A running season has no snapshot yet, its standings are the live ones.
+    fn standings(&self, guild_id: i64, standings: Standings, now: chrono::NaiveDateTime) -> Result<Vec<LeaderboardUser>, StoreError> {
+        match standings {
+            Standings::Live(window) => Ok(self.ranked_in_window(guild_id, window, now)),
+            Standings::Season(number) => {
+                let season = self.find_season(guild_id, number)?;
+                if season.ended_at.is_none() {
+                    return Ok(self.ranked_in_window(guild_id, TimeWindow::default(), now));
+                }
+
+                Ok(self.season_standings.iter()
+                    .filter(|(season_id, _)| *season_id == season.season_id)
+                    .map(|(_, user)| user.clone())
+                    .collect())
+            }
+        }
+    }
+
+    fn find_user(&self, guild_id: i64, discord_id: i64) -> Option<&User> {
+        self.users.iter().find(|u| u.guild_id == guild_id && u.discord_id == discord_id)
+    }
+
+    // Bbps and gbps matching `keep` as history records, newest first
+    fn timeline(&self, keep: impl Fn(PointKind, &PointRow) -> bool) -> Vec<HistoryRecord> {
+        let bbps = self.bbps.iter().map(|row| (PointKind::Bbp, row));
+        let gbps = self.gbps.iter().map(|row| (PointKind::Gbp, row));
+        let mut history: Vec<HistoryRecord> = bbps.chain(gbps)
+            .filter(|(kind, row)| keep(*kind, row))
+            .filter_map(|(kind, row)| {
+                let issuer = self.users.iter().find(|u| u.user_id == row.issuer_id)?;
+                Some(HistoryRecord {
+                    kind,
+                    point_id: row.point_id,
+                    value: row.value,
+                    forgiven: row.forgiven,
+                    issuer_discord_id: issuer.discord_id,
+                    issuer_friendly_name: issuer.friendly_name.clone().unwrap_or_default(),
+                    description: row.description.clone(),
+                    timestamp: row.timestamp,
+                    appeal: match kind {
+                        PointKind::Bbp => self.appeals.iter().find(|a| a.bbp_id == row.point_id).map(|a| a.status),
+                        PointKind::Gbp => None,
+                    },
+                })
+            })
+            .collect();
+        // Newest first like the SQL, ties broken by kind and then the newest ID
+        history.sort_by(|a, b| b.timestamp.cmp(&a.timestamp).then(a.kind.as_str().cmp(b.kind.as_str())).then(b.point_id.cmp(&a.point_id)));
+
+        history
+    }
+
+    // Sums the points per user, most first and ties broken by UserID.
+    fn tally(&self, points: impl Iterator<Item = (i32, i32)>) -> Vec<PointTally> {
+        let mut totals: Vec<(i32, i32)> = Vec::new();
+        for (user_id, value) in points {
+            match totals.iter_mut().find(|(id, _)| *id == user_id) {
+                Some((_, total)) => *total += value,
+                None => totals.push((user_id, value)),
+            }
+        }
+        totals.sort_by_key(|(user_id, total)| (std::cmp::Reverse(*total), *user_id));
+
+        totals.into_iter()
+            .filter_map(|(user_id, points)| {
+                let user = self.users.iter().find(|u| u.user_id == user_id)?;
+                Some(PointTally { discord_id: user.discord_id, friendly_name: user.friendly_name.clone(), points })
+            })
+            .collect()
+    }
+
+    // Deactivated users come back without a rank, like the LEFT JOIN on the SQL side
+    fn find_ranked_user(&self, guild_id: i64, discord_id: i64, now: chrono::NaiveDateTime) -> Option<User> {
+        let user = self.find_user(guild_id, discord_id)?;
+        let ranked = self.ranked_in_window(guild_id, TimeWindow::default(), now)
+            .into_iter()
+            .find(|u| u.user_id == user.user_id);
+
+        Some(User { rank: ranked.as_ref().map(|r| r.rank), score: ranked.map(|r| r.score), ..user.clone() })
+    }
+
+    fn find_user_mut(&mut self, guild_id: i64, discord_id: i64) -> Result<&mut User, StoreError> {
+        self.users.iter_mut()
+            .find(|u| u.guild_id == guild_id && u.discord_id == discord_id)
+            .ok_or(StoreError::UserNotFound(UserRole::Target))
+    }
+
+    fn users_for_point(&self, guild_id: i64, issuer_discord_id: i64, target_discord_id: i64) -> Result<(User, User), StoreError> {
+        let issuer = match self.find_user(guild_id, issuer_discord_id) {
+            Some(user) => user.clone(),
+            None => return Err(StoreError::UserNotFound(UserRole::Issuer)),
+        };
+        let target = match self.find_user(guild_id, target_discord_id) {
+            Some(user) => user.clone(),
+            None => return Err(StoreError::UserNotFound(UserRole::Target)),
+        };
+        if !issuer.active {
+            return Err(StoreError::UserInactive(UserRole::Issuer));
+        }
+        if !target.active {
+            return Err(StoreError::UserInactive(UserRole::Target));
+        }
+
+        Ok((issuer, target))
+    }
+
+    // Inserts the point and reads back the target's live rank
+    #[allow(clippy::too_many_arguments)]
+    fn issue(&mut self, guild_id: i64, kind: PointKind, value: i32, issuer: User, target: User, description: &str, now: chrono::NaiveDateTime) -> Result<IssuedPoint, StoreError> {
+        let point_id = self.insert_point(kind, value, &target, &issuer, description, now);
+
+        let ranked = match self.find_ranked_user(guild_id, target.discord_id, now) {
+            Some(user) => user,
+            None => return Err(StoreError::Integrity("Ranked user not found".to_string())),
+        };
+
+        Ok(IssuedPoint { point_id, issuer, target, ranked })
+    }
+
+    // Mirrors PENDING_BBPS on the SQL side
+    fn pending_bbp(&self, pending_id: i32) -> Result<PendingBbp, StoreError> {
+        let row = self.pending_bbps.iter()
+            .find(|p| p.pending_id == pending_id)
+            .ok_or_else(|| StoreError::Integrity("Pending bbp not found".to_string()))?;
+        let discord_id = |user_id: i32| self.users.iter().find(|u| u.user_id == user_id).map(|u| u.discord_id).unwrap_or_default();
+        let votes = |in_favour: bool| self.pending_votes.iter().filter(|v| v.pending_id == pending_id && v.in_favour == in_favour).count() as i32;
+
+        Ok(PendingBbp {
+            pending_id,
+            guild_id: row.guild_id,
+            issuer_discord_id: discord_id(row.issuer_id),
+            target_discord_id: discord_id(row.user_id),
+            value: row.value,
+            description: row.description.clone(),
+            quorum: row.quorum,
+            votes_for: votes(true),
+            votes_against: votes(false),
+            status: row.status,
+            created_at: row.created_at,
+            expires_at: row.expires_at,
+            channel_id: row.channel_id,
+            message_id: row.message_id,
+            bbp_id: row.bbp_id,
+        })
+    }
+
+    // Mirrors BBPS on the SQL side
+    fn bbp(&self, bbp_id: i32) -> Option<Bbp> {
+        let row = self.bbps.iter().find(|b| b.point_id == bbp_id)?;
+        let target = self.users.iter().find(|u| u.user_id == row.user_id)?;
+        let issuer = self.users.iter().find(|u| u.user_id == row.issuer_id)?;
+
+        Some(Bbp {
+            bbp_id,
+            guild_id: row.guild_id,
+            target_discord_id: target.discord_id,
+            issuer_discord_id: issuer.discord_id,
+            issuer_friendly_name: issuer.friendly_name.clone().unwrap_or_default(),
+            value: row.value,
+            description: row.description.clone(),
+            timestamp: row.timestamp,
+            forgiven: row.forgiven,
+            forgiven_by_discord_id: row.forgiven_by_discord_id,
+            forgiven_at: row.forgiven_at,
+            season_id: row.season_id,
+        })
+    }
+
+    // Mirrors RECENT_BBPS on the SQL side, newest first
+    fn recent_bbps(&self, guild_id: i64, target_discord_id: i64, issuer_discord_id: Option<i64>, forgiven: bool) -> Vec<Bbp> {
+        let overturned = |bbp_id: i32| self.appeals.iter().any(|a| a.bbp_id == bbp_id && a.status == AppealStatus::Overturned);
+        let mut bbps: Vec<Bbp> = self.bbps.iter()
+            .filter(|b| b.guild_id == guild_id && b.forgiven == forgiven && b.season_id.is_none() && !overturned(b.point_id))
+            .filter_map(|b| self.bbp(b.point_id))
+            .filter(|b| b.target_discord_id == target_discord_id && issuer_discord_id.is_none_or(|id| id == b.issuer_discord_id))
+            .collect();
+        bbps.sort_by_key(|b| std::cmp::Reverse((b.timestamp, b.bbp_id)));
+
+        bbps
+    }
+
+    // `Some(forgiver)` forgives the picked bbp, `None` unforgives it
+    fn change_forgiven(&mut self, guild_id: i64, target_discord_id: i64, issuer_discord_id: Option<i64>, bbp_id: Option<i32>, forgiver_discord_id: Option<i64>, now: chrono::NaiveDateTime) -> Option<Bbp> {
+        let picked = self.recent_bbps(guild_id, target_discord_id, issuer_discord_id, forgiver_discord_id.is_none())
+            .into_iter()
+            .find(|b| bbp_id.is_none_or(|id| id == b.bbp_id))?;
+
+        let row = self.bbps.iter_mut().find(|b| b.point_id == picked.bbp_id)?;
+        row.forgiven = forgiver_discord_id.is_some();
+        row.forgiven_by_discord_id = forgiver_discord_id;
+        row.forgiven_at = forgiver_discord_id.map(|_| now);
+        let user_id = row.user_id;
+        self.recalculate_user_points(user_id);
+
+        self.bbp(picked.bbp_id)
+    }
+
+    // Mirrors APPEALS on the SQL side
+    fn appeal(&self, appeal_id: i32) -> Result<Appeal, StoreError> {
+        let row = self.appeals.iter()
+            .find(|a| a.appeal_id == appeal_id)
+            .ok_or(StoreError::VotingClosed)?;
+        let bbp = self.bbp(row.bbp_id)
+            .ok_or_else(|| StoreError::Integrity("Appealed bbp not found".to_string()))?;
+        let votes = |overturn: bool| self.appeal_votes.iter().filter(|v| v.appeal_id == appeal_id && v.overturn == overturn).count() as i32;
+
+        Ok(Appeal {
+            appeal_id,
+            bbp,
+            statement: row.statement.clone(),
+            status: row.status,
+            quorum: row.quorum,
+            votes_overturn: votes(true),
+            votes_uphold: votes(false),
+            opened_at: row.opened_at,
+            resolved_at: row.resolved_at,
+            resolved_by_discord_id: row.resolved_by_discord_id,
+            channel_id: row.channel_id,
+            message_id: row.message_id,
+        })
+    }
+
+    // Settled appeals and those of archived bbps are closed
+    fn open_appeal(&self, appeal_id: i32) -> Result<Appeal, StoreError> {
+        match self.appeal(appeal_id)? {
+            appeal if appeal.status == AppealStatus::Open && appeal.bbp.season_id.is_none() => Ok(appeal),
+            _ => Err(StoreError::VotingClosed),
+        }
+    }
+
+    // Mirrors settle_appeal on the SQL side
+    fn settle_appeal(&mut self, appeal: &mut Appeal, status: AppealStatus, resolved_by: Option<i64>, now: chrono::NaiveDateTime) {
+        if let Some(row) = self.appeals.iter_mut().find(|a| a.appeal_id == appeal.appeal_id) {
+            row.status = status;
+            row.resolved_at = Some(now);
+            row.resolved_by_discord_id = resolved_by;
+        }
+        if status == AppealStatus::Overturned {
+            let mut target = None;
+            if let Some(row) = self.bbps.iter_mut().find(|b| b.point_id == appeal.bbp.bbp_id) {
+                row.forgiven = true;
+                row.forgiven_by_discord_id = resolved_by;
+                row.forgiven_at = Some(now);
+                target = Some(row.user_id);
+            }
+            if let Some(user_id) = target {
+                self.recalculate_user_points(user_id);
+            }
+            appeal.bbp.forgiven = true;
+            appeal.bbp.forgiven_by_discord_id = resolved_by;
+            appeal.bbp.forgiven_at = Some(now);
+        }
+
+        appeal.status = status;
+        appeal.resolved_at = Some(now);
+        appeal.resolved_by_discord_id = resolved_by;
+    }
+
+    // Mirrors `change_own_point` on the SQL side.
+    #[allow(clippy::too_many_arguments)]
+    fn change_own_point(&mut self, guild_id: i64, kind: PointKind, point_id: i32, issuer_discord_id: i64, description: Option<&str>, grace: chrono::Duration, now: chrono::NaiveDateTime) -> Result<AuditEntry, StoreError> {
+        let rows = match kind {
+            PointKind::Bbp => &self.bbps,
+            PointKind::Gbp => &self.gbps,
+        };
+        let index = rows.iter()
+            .position(|r| r.point_id == point_id && r.guild_id == guild_id)
+            .ok_or(StoreError::PointNotFound)?;
+        let row = &rows[index];
+        let discord_id = |user_id: i32| self.users.iter()
+            .find(|u| u.user_id == user_id)
+            .map(|u| u.discord_id)
+            .ok_or_else(|| StoreError::Integrity("Point without its user".to_string()));
+        let (target_discord_id, issuer_id, target_id) = (discord_id(row.user_id)?, row.issuer_id, row.user_id);
+        let contested = kind == PointKind::Bbp
+            && (self.pending_bbps.iter().any(|p| p.bbp_id == Some(point_id)) || self.appeals.iter().any(|a| a.bbp_id == point_id));
+        if discord_id(issuer_id)? != issuer_discord_id || target_discord_id == issuer_discord_id || contested || row.season_id.is_some() {
+            return Err(StoreError::NotEditable);
+        }
+        if now - row.timestamp >= grace {
+            return Err(StoreError::GracePeriodOver { minutes: grace.num_minutes() as i32 });
+        }
+
+        let rows = match kind {
+            PointKind::Bbp => &mut self.bbps,
+            PointKind::Gbp => &mut self.gbps,
+        };
+        let (action, value, old_description) = match description {
+            Some(description) => {
+                let row = &mut rows[index];
+                let old_description = std::mem::replace(&mut row.description, description.to_string());
+                (AuditAction::Edit, row.value, old_description)
+            }
+            None => {
+                let row = rows.remove(index);
+                (AuditAction::Retract, row.value, row.description)
+            }
+        };
+        self.recalculate_user_points(target_id);
+        self.recalculate_user_points(issuer_id);
+
+        let entry = AuditEntry {
+            audit_id: self.audit_log.iter().map(|e| e.audit_id).max().unwrap_or(0) + 1,
+            guild_id,
+            action,
+            kind,
+            point_id,
+            actor_discord_id: issuer_discord_id,
+            target_discord_id,
+            value,
+            old_description,
+            new_description: description.map(str::to_string),
+            at: now,
+        };
+        self.audit_log.push(entry.clone());
+
+        Ok(entry)
+    }
+
+    fn insert_point(&mut self, kind: PointKind, value: i32, target: &User, issuer: &User, description: &str, timestamp: chrono::NaiveDateTime) -> i32 {
+        let rows = match kind {
+            PointKind::Bbp => &mut self.bbps,
+            PointKind::Gbp => &mut self.gbps,
+        };
+        // Retracted IDs stay taken, like with AUTOINCREMENT
+        let retracted = self.audit_log.iter()
+            .filter(|e| e.kind == kind && e.action == AuditAction::Retract)
+            .map(|e| e.point_id);
+        let point_id = rows.iter().map(|r| r.point_id).chain(retracted).max().unwrap_or(0) + 1;
+        rows.push(PointRow {
+            point_id,
+            guild_id: target.guild_id,
+            user_id: target.user_id,
+            issuer_id: issuer.user_id,
+            value,
+            description: description.to_string(),
+            timestamp,
+            forgiven: false,
+            forgiven_by_discord_id: None,
+            forgiven_at: None,
+            season_id: None,
+            channel_id: None,
+            message_id: None,
+        });
+
+        self.recalculate_user_points(target.user_id);
+        self.recalculate_user_points(issuer.user_id);
+
+        point_id
+    }
+}
+
+fn paginate<T>(items: Vec<T>, page: Page) -> Vec<T> {
+    items.into_iter().skip(page.offset.max(0) as usize).take(page.limit.max(0) as usize).collect()
+}
+
+#[async_trait]
+impl BbpStore for MemoryStore {
+    fn now(&self) -> chrono::NaiveDateTime {
+        self.clock.now()
+    }
+
+    async fn get_user_by_discord_id(&self, guild_id: i64, discord_id: i64) -> Result<Option<User>, StoreError> {
+        let state = self.state.lock().unwrap();
+
+        Ok(state.find_user(guild_id, discord_id).cloned())
+    }
+
+    async fn get_user_by_discord_id_with_rank(&self, guild_id: i64, discord_id: i64) -> Result<Option<User>, StoreError> {
+        let state = self.state.lock().unwrap();
+
+        Ok(state.find_ranked_user(guild_id, discord_id, self.clock.now()))
+    }
+
+    async fn add_user(&self, guild_id: i64, discord_id: i64, discord_username: &str, friendly_name: &str) -> Result<User, StoreError> {
+        let mut state = self.state.lock().unwrap();
+        if state.find_user(guild_id, discord_id).is_some() {
+            return Err(StoreError::Duplicate(Existing::User));
+        }
+
+        let user = User {
+            user_id: state.users.iter().map(|u| u.user_id).max().unwrap_or(0) + 1,
+            guild_id,
+            username: None,
+            discord_username: Some(discord_username.to_string()),
+            discord_mention: Some(format!("<@{}>", discord_id)),
+            discord_id,
+            friendly_name: Some(friendly_name.to_string()),
+            points: 0,
+            bbp_total: 0,
+            gbp_total: 0,
+            bbps_issued: 0,
+            gbps_issued: 0,
+            rank: None,
+            score: None,
+            active: true,
+        };
+        state.users.push(user.clone());
+
+        Ok(user)
+    }
+
+    async fn rename_user(&self, guild_id: i64, discord_id: i64, friendly_name: &str) -> Result<User, StoreError> {
+        let mut state = self.state.lock().unwrap();
+
+        let user = state.find_user_mut(guild_id, discord_id)?;
+        user.friendly_name = Some(friendly_name.to_string());
+
+        Ok(user.clone())
+    }
+
+    async fn set_user_active(&self, guild_id: i64, discord_id: i64, active: bool) -> Result<User, StoreError> {
+        let mut state = self.state.lock().unwrap();
+
+        let user = state.find_user_mut(guild_id, discord_id)?;
+        user.active = active;
+
+        Ok(user.clone())
+    }
+
+    async fn merge_users(&self, guild_id: i64, from_discord_id: i64, into_discord_id: i64) -> Result<User, StoreError> {
+        if from_discord_id == into_discord_id {
+            return Err(StoreError::Integrity("Can't merge a user into themselves".to_string()));
+        }
+        let mut guard = self.state.lock().unwrap();
+        let state = &mut *guard;
+
+        let from = state.find_user(guild_id, from_discord_id).ok_or(StoreError::UserNotFound(UserRole::MergeFrom))?.user_id;
+        let into = state.find_user(guild_id, into_discord_id).ok_or(StoreError::UserNotFound(UserRole::MergeInto))?.user_id;
+
+        for row in state.bbps.iter_mut().chain(state.gbps.iter_mut()) {
+            if row.user_id == from {
+                row.user_id = into;
+            }
+            if row.issuer_id == from {
+                row.issuer_id = into;
+            }
+        }
+        for row in state.pending_bbps.iter_mut() {
+            if row.user_id == from {
+                row.user_id = into;
+            }
+            if row.issuer_id == from {
+                row.issuer_id = into;
+            }
+        }
+
+        // Where both users voted on the same pending bbp or appeal only the second user's vote counts
+        let into_votes: Vec<i32> = state.pending_votes.iter().filter(|v| v.voter_id == into).map(|v| v.pending_id).collect();
+        state.pending_votes.retain(|v| v.voter_id != from || !into_votes.contains(&v.pending_id));
+        for vote in state.pending_votes.iter_mut().filter(|v| v.voter_id == from) {
+            vote.voter_id = into;
+        }
+        let into_votes: Vec<i32> = state.appeal_votes.iter().filter(|v| v.voter_id == into).map(|v| v.appeal_id).collect();
+        state.appeal_votes.retain(|v| v.voter_id != from || !into_votes.contains(&v.appeal_id));
+        for vote in state.appeal_votes.iter_mut().filter(|v| v.voter_id == from) {
+            vote.voter_id = into;
+        }
+
+        // Seasons both users were ranked in get one combined line, then re-ranked
+        let (merged, moved): (Vec<_>, Vec<_>) = std::mem::take(&mut state.season_standings)
+            .into_iter()
+            .partition(|(_, u)| u.user_id != from);
+        state.season_standings = merged;
+        let into_user = state.find_user(guild_id, into_discord_id).cloned();
+        for (season_id, from_line) in moved {
+            match state.season_standings.iter_mut().find(|(id, u)| *id == season_id && u.user_id == into) {
+                Some((_, line)) => {
+                    line.points += from_line.points;
+                    line.score += from_line.score;
+                    line.bbp_total += from_line.bbp_total;
+                    line.gbp_total += from_line.gbp_total;
+                    line.bbps_issued += from_line.bbps_issued;
+                    line.gbps_issued += from_line.gbps_issued;
+                }
+                None => state.season_standings.push((season_id, LeaderboardUser {
+                    user_id: into,
+                    discord_username: into_user.as_ref().and_then(|u| u.discord_username.clone()),
+                    discord_mention: into_user.as_ref().and_then(|u| u.discord_mention.clone()),
+                    discord_id: into_discord_id,
+                    friendly_name: into_user.as_ref().and_then(|u| u.friendly_name.clone()),
+                    ..from_line
+                })),
+            }
+        }
+        state.rerank_season_standings(guild_id);
+
+        state.users.retain(|u| u.user_id != from);
+        state.recalculate_user_points(into);
+
+        state.find_user(guild_id, into_discord_id).cloned()
+            .ok_or_else(|| StoreError::Integrity("Merged user not found".to_string()))
+    }
+
+    async fn remove_user(&self, guild_id: i64, discord_id: i64) -> Result<User, StoreError> {
+        let mut state = self.state.lock().unwrap();
+
+        let user = match state.find_user(guild_id, discord_id) {
+            Some(user) => user.clone(),
+            None => return Err(StoreError::UserNotFound(UserRole::Target)),
+        };
+        let involved = |row: &PointRow| row.user_id == user.user_id || row.issuer_id == user.user_id;
+        if state.bbps.iter().chain(state.gbps.iter()).any(involved)
+            || state.pending_bbps.iter().any(|p| p.user_id == user.user_id || p.issuer_id == user.user_id)
+            || state.pending_votes.iter().any(|v| v.voter_id == user.user_id)
+            || state.appeal_votes.iter().any(|v| v.voter_id == user.user_id) {
+            return Err(StoreError::UserHasHistory);
+        }
+
+        // Without any bbps or gbps their archived lines are all zeroes
+        state.season_standings.retain(|(_, u)| u.user_id != user.user_id);
+        state.rerank_season_standings(guild_id);
+        state.users.retain(|u| u.user_id != user.user_id);
+
+        Ok(user)
+    }
+
+    async fn issue_point(&self, guild_id: i64, kind: PointKind, value: i32, issuer_discord_id: i64, target_discord_id: i64, description: &str) -> Result<IssuedPoint, StoreError> {
+        let mut state = self.state.lock().unwrap();
+
+        let (issuer, target) = state.users_for_point(guild_id, issuer_discord_id, target_discord_id)?;
+        state.issue(guild_id, kind, value, issuer, target, description, self.clock.now())
+    }
+
+    async fn set_point_message(&self, guild_id: i64, kind: PointKind, point_id: i32, channel_id: i64, message_id: i64) -> Result<(), StoreError> {
+        let mut state = self.state.lock().unwrap();
+
+        let rows = match kind {
+            PointKind::Bbp => &mut state.bbps,
+            PointKind::Gbp => &mut state.gbps,
+        };
+        if let Some(row) = rows.iter_mut().find(|r| r.point_id == point_id && r.guild_id == guild_id) {
+            row.channel_id = Some(channel_id);
+            row.message_id = Some(message_id);
+        }
+
+        Ok(())
+    }
+
+    async fn create_pending_bbp(&self, guild_id: i64, value: i32, issuer_discord_id: i64, target_discord_id: i64, description: &str, quorum: i32, lifetime: chrono::Duration) -> Result<PendingBbp, StoreError> {
+        let mut state = self.state.lock().unwrap();
+
+        let (issuer, target) = state.users_for_point(guild_id, issuer_discord_id, target_discord_id)?;
+        let now = self.clock.now();
+        let pending_id = state.pending_bbps.iter().map(|p| p.pending_id).max().unwrap_or(0) + 1;
+        state.pending_bbps.push(PendingRow {
+            pending_id,
+            guild_id,
+            user_id: target.user_id,
+            issuer_id: issuer.user_id,
+            value,
+            description: description.to_string(),
+            quorum,
+            status: JuryStatus::Pending,
+            created_at: now,
+            expires_at: now + lifetime,
+            channel_id: None,
+            message_id: None,
+            bbp_id: None,
+        });
+
+        state.pending_bbp(pending_id)
+    }
+
+    async fn set_pending_bbp_message(&self, pending_id: i32, channel_id: i64, message_id: i64) -> Result<(), StoreError> {
+        let mut state = self.state.lock().unwrap();
+
+        if let Some(row) = state.pending_bbps.iter_mut().find(|p| p.pending_id == pending_id) {
+            row.channel_id = Some(channel_id);
+            row.message_id = Some(message_id);
+        }
+
+        Ok(())
+    }
+
+    async fn vote_on_pending_bbp(&self, pending_id: i32, voter_discord_id: i64, in_favour: bool) -> Result<JuryVote, StoreError> {
+        let mut state = self.state.lock().unwrap();
+        let now = self.clock.now();
+
+        let (guild_id, accused, accuser) = match state.pending_bbps.iter().find(|p| p.pending_id == pending_id) {
+            Some(row) if row.status == JuryStatus::Pending && now < row.expires_at => (row.guild_id, row.user_id, row.issuer_id),
+            _ => return Err(StoreError::VotingClosed),
+        };
+        let voter = match state.find_user(guild_id, voter_discord_id) {
+            Some(user) => user.clone(),
+            None => return Err(StoreError::UserNotFound(UserRole::Issuer)),
+        };
+        if !voter.active {
+            return Err(StoreError::UserInactive(UserRole::Issuer));
+        }
+        if voter.user_id == accused || voter.user_id == accuser {
+            return Err(StoreError::OwnAccusation);
+        }
+
+        match state.pending_votes.iter_mut().find(|v| v.pending_id == pending_id && v.voter_id == voter.user_id) {
+            Some(vote) => vote.in_favour = in_favour,
+            None => state.pending_votes.push(VoteRow { pending_id, voter_id: voter.user_id, in_favour }),
+        }
+
+        let mut pending = state.pending_bbp(pending_id)?;
+        let verdict = pending.verdict();
+        let issued = match verdict {
+            JuryStatus::Confirmed => {
+                let (issuer, target) = state.users_for_point(guild_id, pending.issuer_discord_id, pending.target_discord_id)?;
+                Some(state.issue(guild_id, PointKind::Bbp, pending.value, issuer, target, &pending.description, now)?)
+            },
+            _ => None,
+        };
+        if verdict != JuryStatus::Pending {
+            let bbp_id = issued.as_ref().map(|issued| issued.point_id);
+            if let Some(row) = state.pending_bbps.iter_mut().find(|p| p.pending_id == pending_id) {
+                row.status = verdict;
+                row.bbp_id = bbp_id;
+            }
+            pending.status = verdict;
+            pending.bbp_id = bbp_id;
+        }
+
+        Ok(JuryVote { pending, issued })
+    }
+
+    async fn expire_pending_bbps(&self) -> Result<Vec<PendingBbp>, StoreError> {
+        let mut state = self.state.lock().unwrap();
+        let now = self.clock.now();
+
+        let mut expired = Vec::new();
+        for row in state.pending_bbps.iter_mut().filter(|p| p.status == JuryStatus::Pending && p.expires_at <= now) {
+            row.status = JuryStatus::Expired;
+            expired.push(row.pending_id);
+        }
+
+        expired.into_iter().map(|pending_id| state.pending_bbp(pending_id)).collect()
+    }
+
+    async fn get_recent_bbps(&self, guild_id: i64, target_discord_id: i64, issuer_discord_id: Option<i64>, forgiven: bool, limit: i64) -> Result<Vec<Bbp>, StoreError> {
+        let state = self.state.lock().unwrap();
+
+        let mut bbps = state.recent_bbps(guild_id, target_discord_id, issuer_discord_id, forgiven);
+        bbps.truncate(limit.max(0) as usize);
+
+        Ok(bbps)
+    }
+
+    async fn open_appeal(&self, guild_id: i64, bbp_id: i32, appellant_discord_id: i64, statement: &str, quorum: i32) -> Result<Appeal, StoreError> {
+        let mut state = self.state.lock().unwrap();
+
+        let bbp = match state.bbp(bbp_id) {
+            Some(bbp) if bbp.guild_id == guild_id => bbp,
+            _ => return Err(StoreError::BbpNotFound),
+        };
+        if bbp.target_discord_id != appellant_discord_id || bbp.forgiven || bbp.season_id.is_some()
+            || state.appeals.iter().any(|a| a.bbp_id == bbp_id) {
+            return Err(StoreError::NotAppealable);
+        }
+
+        let appeal_id = state.appeals.iter().map(|a| a.appeal_id).max().unwrap_or(0) + 1;
+        state.appeals.push(AppealRow {
+            appeal_id,
+            bbp_id,
+            statement: statement.to_string(),
+            status: AppealStatus::Open,
+            quorum,
+            opened_at: self.clock.now(),
+            resolved_at: None,
+            resolved_by_discord_id: None,
+            channel_id: None,
+            message_id: None,
+        });
+
+        state.appeal(appeal_id)
+    }
+
+    async fn set_appeal_message(&self, appeal_id: i32, channel_id: i64, message_id: i64) -> Result<(), StoreError> {
+        let mut state = self.state.lock().unwrap();
+
+        if let Some(row) = state.appeals.iter_mut().find(|a| a.appeal_id == appeal_id) {
+            row.channel_id = Some(channel_id);
+            row.message_id = Some(message_id);
+        }
+
+        Ok(())
+    }
+
+    async fn vote_on_appeal(&self, appeal_id: i32, voter_discord_id: i64, overturn: bool) -> Result<Appeal, StoreError> {
+        let mut state = self.state.lock().unwrap();
+        let now = self.clock.now();
+
+        let appeal = state.open_appeal(appeal_id)?;
+        if appeal.quorum <= 0 {
+            return Err(StoreError::NoCommunityVote);
+        }
+        let voter = match state.find_user(appeal.bbp.guild_id, voter_discord_id) {
+            Some(user) => user.clone(),
+            None => return Err(StoreError::UserNotFound(UserRole::Issuer)),
+        };
+        if !voter.active {
+            return Err(StoreError::UserInactive(UserRole::Issuer));
+        }
+        if voter.discord_id == appeal.bbp.target_discord_id || voter.discord_id == appeal.bbp.issuer_discord_id {
+            return Err(StoreError::OwnAccusation);
+        }
+
+        match state.appeal_votes.iter_mut().find(|v| v.appeal_id == appeal_id && v.voter_id == voter.user_id) {
+            Some(vote) => vote.overturn = overturn,
+            None => state.appeal_votes.push(AppealVoteRow { appeal_id, voter_id: voter.user_id, overturn }),
+        }
+
+        let mut appeal = state.appeal(appeal_id)?;
+        let verdict = appeal.verdict();
+        if verdict != AppealStatus::Open {
+            state.settle_appeal(&mut appeal, verdict, None, now);
+        }
+
+        Ok(appeal)
+    }
+
+    async fn resolve_appeal(&self, appeal_id: i32, moderator_discord_id: i64, overturn: bool) -> Result<Appeal, StoreError> {
+        let mut state = self.state.lock().unwrap();
+
+        let mut appeal = state.open_appeal(appeal_id)?;
+        if moderator_discord_id == appeal.bbp.target_discord_id || moderator_discord_id == appeal.bbp.issuer_discord_id {
+            return Err(StoreError::OwnAccusation);
+        }
+        let status = if overturn { AppealStatus::Overturned } else { AppealStatus::Upheld };
+        state.settle_appeal(&mut appeal, status, Some(moderator_discord_id), self.clock.now());
+
+        Ok(appeal)
+    }
+
+    async fn forgive_bbp(&self, guild_id: i64, target_discord_id: i64, issuer_discord_id: Option<i64>, bbp_id: Option<i32>, forgiver_discord_id: i64) -> Result<Bbp, StoreError> {
+        let mut state = self.state.lock().unwrap();
+
+        state.change_forgiven(guild_id, target_discord_id, issuer_discord_id, bbp_id, Some(forgiver_discord_id), self.clock.now())
+            .ok_or(StoreError::NothingToForgive)
+    }
+
+    async fn unforgive_bbp(&self, guild_id: i64, target_discord_id: i64, issuer_discord_id: Option<i64>, bbp_id: Option<i32>) -> Result<Bbp, StoreError> {
+        let mut state = self.state.lock().unwrap();
+
+        state.change_forgiven(guild_id, target_discord_id, issuer_discord_id, bbp_id, None, self.clock.now())
+            .ok_or(StoreError::NothingToUnforgive)
+    }
+
+    async fn get_point(&self, guild_id: i64, kind: PointKind, point_id: i32) -> Result<Option<PointDetails>, StoreError> {
+        let state = self.state.lock().unwrap();
+
+        let rows = match kind {
+            PointKind::Bbp => &state.bbps,
+            PointKind::Gbp => &state.gbps,
+        };
+        let Some(row) = rows.iter().find(|r| r.point_id == point_id && r.guild_id == guild_id) else {
+            return Ok(None);
+        };
+        let user = |user_id: i32| state.users.iter()
+            .find(|u| u.user_id == user_id)
+            .ok_or_else(|| StoreError::Integrity("Point without its user".to_string()));
+        let (target, issuer) = (user(row.user_id)?, user(row.issuer_id)?);
+
+        let (jury, appeal) = match kind {
+            PointKind::Bbp => (
+                state.pending_bbps.iter()
+                    .find(|p| p.bbp_id == Some(point_id))
+                    .map(|p| state.pending_bbp(p.pending_id))
+                    .transpose()?,
+                state.appeals.iter()
+                    .find(|a| a.bbp_id == point_id)
+                    .map(|a| state.appeal(a.appeal_id))
+                    .transpose()?,
+            ),
+            PointKind::Gbp => (None, None),
+        };
+
+        Ok(Some(PointDetails {
+            kind,
+            point_id,
+            guild_id,
+            target_discord_id: target.discord_id,
+            target_friendly_name: target.friendly_name.clone().unwrap_or_default(),
+            issuer_discord_id: issuer.discord_id,
+            issuer_friendly_name: issuer.friendly_name.clone().unwrap_or_default(),
+            value: row.value,
+            description: row.description.clone(),
+            timestamp: row.timestamp,
+            forgiven: row.forgiven,
+            forgiven_by_discord_id: row.forgiven_by_discord_id,
+            forgiven_at: row.forgiven_at,
+            season_id: row.season_id,
+            channel_id: row.channel_id,
+            message_id: row.message_id,
+            jury,
+            appeal,
+        }))
+    }
+
+    async fn edit_point(&self, guild_id: i64, kind: PointKind, point_id: i32, issuer_discord_id: i64, description: &str, grace: chrono::Duration) -> Result<AuditEntry, StoreError> {
+        let mut state = self.state.lock().unwrap();
+
+        state.change_own_point(guild_id, kind, point_id, issuer_discord_id, Some(description), grace, self.clock.now())
+    }
+
+    async fn retract_point(&self, guild_id: i64, kind: PointKind, point_id: i32, issuer_discord_id: i64, grace: chrono::Duration) -> Result<AuditEntry, StoreError> {
+        let mut state = self.state.lock().unwrap();
+
+        state.change_own_point(guild_id, kind, point_id, issuer_discord_id, None, grace, self.clock.now())
+    }
+
+    async fn get_audit_log(&self, guild_id: i64, page: Page) -> Result<Vec<AuditEntry>, StoreError> {
+        let state = self.state.lock().unwrap();
+
+        let mut entries: Vec<AuditEntry> = state.audit_log.iter().filter(|e| e.guild_id == guild_id).cloned().collect();
+        entries.sort_by(|a, b| b.at.cmp(&a.at).then(b.audit_id.cmp(&a.audit_id)));
+
+        Ok(paginate(entries, page))
+    }
+
+    async fn get_leaderboard(&self, guild_id: i64, standings: Standings, page: Page) -> Result<Vec<LeaderboardUser>, StoreError> {
+        let state = self.state.lock().unwrap();
+
+        Ok(paginate(state.standings(guild_id, standings, self.clock.now())?, page))
+    }
+
+    async fn get_leaderboard_position(&self, guild_id: i64, standings: Standings, discord_id: i64) -> Result<Option<i64>, StoreError> {
+        let state = self.state.lock().unwrap();
+
+        Ok(state.standings(guild_id, standings, self.clock.now())?.iter().position(|u| u.discord_id == discord_id).map(|p| p as i64))
+    }
+
+    async fn get_user_history(&self, guild_id: i64, discord_id: i64, filter: HistoryFilter, page: Page) -> Result<Vec<HistoryRecord>, StoreError> {
+        let state = self.state.lock().unwrap();
+
+        let user = match state.find_user(guild_id, discord_id) {
+            Some(user) => user,
+            None => return Err(StoreError::UserNotFound(UserRole::Target)),
+        };
+
+        // A running season's rows aren't tagged yet
+        let season_id = match filter.season {
+            Some(number) => Some(state.find_season(guild_id, number)?)
+                .filter(|s| s.ended_at.is_some())
+                .map(|s| s.season_id),
+            None => None,
+        };
+
+        let history = state.timeline(|kind, row| {
+            row.user_id == user.user_id
+                && filter.kind.is_none_or(|k| k == kind)
+                && (filter.season.is_none() || row.season_id == season_id)
+        });
+        let history = history.into_iter()
+            .filter(|h| filter.issuer_discord_id.is_none_or(|id| id == h.issuer_discord_id))
+            .collect();
+
+        Ok(paginate(history, page))
+    }
+
+    async fn get_user_stats(&self, guild_id: i64, discord_id: i64) -> Result<UserStats, StoreError> {
+        let state = self.state.lock().unwrap();
+
+        let user_id = match state.find_user(guild_id, discord_id) {
+            Some(user) => user.user_id,
+            None => return Err(StoreError::UserNotFound(UserRole::Target)),
+        };
+
+        let unforgiven = || state.bbps.iter().filter(|b| !b.forgiven);
+        let mut top_issuers = state.tally(unforgiven().filter(|b| b.user_id == user_id).map(|b| (b.issuer_id, b.value)));
+        top_issuers.truncate(3);
+        let favourite_target = state.tally(unforgiven().filter(|b| b.issuer_id == user_id).map(|b| (b.user_id, b.value)))
+            .into_iter()
+            .next();
+
+        let mut offences: Vec<_> = unforgiven().filter(|b| b.user_id == user_id).map(|b| b.timestamp).collect();
+        offences.sort();
+
+        Ok(UserStats {
+            forgiven: state.bbps.iter().filter(|b| b.user_id == user_id && b.forgiven).count() as i32,
+            top_issuers,
+            favourite_target,
+            offences,
+        })
+    }
+
+    async fn get_rivalry(&self, guild_id: i64, a_discord_id: i64, b_discord_id: i64, recent: i64) -> Result<Rivalry, StoreError> {
+        let state = self.state.lock().unwrap();
+
+        let (a, b) = match (state.find_user(guild_id, a_discord_id), state.find_user(guild_id, b_discord_id)) {
+            (Some(a), Some(b)) => (a.user_id, b.user_id),
+            _ => return Err(StoreError::UserNotFound(UserRole::Target)),
+        };
+
+        let exchange = |issuer_id: i32, user_id: i32| {
+            let between = |row: &&PointRow| row.issuer_id == issuer_id && row.user_id == user_id;
+            Exchange {
+                bbps: state.bbps.iter().filter(between).map(|row| row.value).sum(),
+                forgiven: state.bbps.iter().filter(between).filter(|row| row.forgiven).map(|row| row.value).sum(),
+                gbps: state.gbps.iter().filter(between).map(|row| row.value).sum(),
+            }
+        };
+
+        let exchanges = state.timeline(|_, row| {
+            (row.issuer_id == a && row.user_id == b) || (row.issuer_id == b && row.user_id == a)
+        });
+
+        Ok(Rivalry { a_to_b: exchange(a, b), b_to_a: exchange(b, a), recent: paginate(exchanges, Page { offset: 0, limit: recent }) })
+    }
+
+    async fn get_point_timeline(&self, guild_id: i64, discord_id: i64, window: TimeWindow) -> Result<Vec<HistoryRecord>, StoreError> {
+        let state = self.state.lock().unwrap();
+
+        let user_id = match state.find_user(guild_id, discord_id) {
+            Some(user) => user.user_id,
+            None => return Err(StoreError::UserNotFound(UserRole::Target)),
+        };
+
+        let mut timeline = state.timeline(|_, row| row.user_id == user_id && window.contains(row.timestamp, row.season_id));
+        timeline.reverse();
+
+        Ok(timeline)
+    }
+
+    async fn start_season(&self, guild_id: i64) -> Result<Season, StoreError> {
+        let mut state = self.state.lock().unwrap();
+        let guild_seasons = || state.seasons.iter().filter(|s| s.guild_id == guild_id);
+        if guild_seasons().any(|s| s.ended_at.is_none()) {
+            return Err(StoreError::Duplicate(Existing::RunningSeason));
+        }
+
+        let season = Season {
+            season_id: state.seasons.iter().map(|s| s.season_id).max().unwrap_or(0) + 1,
+            guild_id,
+            number: guild_seasons().map(|s| s.number).max().unwrap_or(0) + 1,
+            started_at: self.clock.now(),
+            ended_at: None,
+        };
+        state.seasons.push(season.clone());
+
+        Ok(season)
+    }
+
+    async fn end_season(&self, guild_id: i64) -> Result<ClosedSeason, StoreError> {
+        let mut guard = self.state.lock().unwrap();
+        let state = &mut *guard;
+
+        let season = match state.seasons.iter_mut().find(|s| s.guild_id == guild_id && s.ended_at.is_none()) {
+            Some(season) => season,
+            None => return Err(StoreError::NoOpenSeason),
+        };
+        let now = self.clock.now();
+        season.ended_at = Some(now);
+        let season = season.clone();
+
+        let standings = state.ranked_in_window(guild_id, TimeWindow::default(), now);
+        state.season_standings.extend(standings.iter().map(|u| (season.season_id, u.clone())));
+
+        for row in state.bbps.iter_mut().chain(state.gbps.iter_mut()).filter(|r| r.guild_id == guild_id && r.season_id.is_none()) {
+            row.season_id = Some(season.season_id);
+        }
+        let user_ids: Vec<i32> = state.users.iter().filter(|u| u.guild_id == guild_id).map(|u| u.user_id).collect();
+        for user_id in user_ids {
+            state.recalculate_user_points(user_id);
+        }
+
+        Ok(ClosedSeason { season, standings })
+    }
+
+    async fn get_seasons(&self, guild_id: i64) -> Result<Vec<Season>, StoreError> {
+        let state = self.state.lock().unwrap();
+
+        let mut seasons: Vec<Season> = state.seasons.iter().filter(|s| s.guild_id == guild_id).cloned().collect();
+        seasons.sort_by_key(|s| s.number);
+
+        Ok(seasons)
+    }
+
+    async fn get_season(&self, guild_id: i64, number: i32) -> Result<Season, StoreError> {
+        let state = self.state.lock().unwrap();
+
+        state.find_season(guild_id, number).cloned()
+    }
+
+    async fn get_guild_settings(&self, guild_id: i64) -> Result<GuildSettings, StoreError> {
+        let state = self.state.lock().unwrap();
+
+        Ok(state.guild_settings(guild_id))
+    }
+
+    async fn save_guild_settings(&self, settings: GuildSettings) -> Result<GuildSettings, StoreError> {
+        let mut state = self.state.lock().unwrap();
+
+        state.settings.retain(|s| s.guild_id != settings.guild_id);
+        state.settings.push(settings);
+
+        Ok(settings)
+    }
+
+    async fn update_discord_username(&self, discord_id: i64, discord_username: &str) -> Result<u64, StoreError> {
+        let mut state = self.state.lock().unwrap();
+        let discord_mention = format!("<@{}>", discord_id);
+
+        let mut updated = 0;
+        for user in state.users.iter_mut().filter(|u| u.discord_id == discord_id) {
+            if user.discord_username.as_deref() != Some(discord_username) || user.discord_mention.as_deref() != Some(&discord_mention) {
+                user.discord_username = Some(discord_username.to_string());
+                user.discord_mention = Some(discord_mention.clone());
+                updated += 1;
+            }
+        }
+
+        Ok(updated)
+    }
+
+    async fn request_registration(&self, guild_id: i64, discord_id: i64, discord_username: &str, friendly_name: &str) -> Result<RegistrationRequest, StoreError> {
+        let mut state = self.state.lock().unwrap();
+        if state.find_user(guild_id, discord_id).is_some() {
+            return Err(StoreError::Duplicate(Existing::User));
+        }
+        if state.registration_requests.iter().any(|r| r.guild_id == guild_id && r.discord_id == discord_id) {
+            return Err(StoreError::Duplicate(Existing::JoinRequest));
+        }
+
+        let request = RegistrationRequest {
+            guild_id,
+            discord_id,
+            discord_username: discord_username.to_string(),
+            friendly_name: friendly_name.to_string(),
+            requested_at: self.clock.now(),
+        };
+        state.registration_requests.push(request.clone());
+
+        Ok(request)
+    }
+
+    async fn take_registration_request(&self, guild_id: i64, discord_id: i64) -> Result<Option<RegistrationRequest>, StoreError> {
+        let mut state = self.state.lock().unwrap();
+
+        let index = state.registration_requests.iter().position(|r| r.guild_id == guild_id && r.discord_id == discord_id);
+
+        Ok(index.map(|index| state.registration_requests.remove(index)))
+    }
+
+    async fn claim_legacy_rows(&self, guild_id: i64) -> Result<u64, StoreError> {
+        let mut guard = self.state.lock().unwrap();
+        let state = &mut *guard;
+
+        let mut claimed = 0;
+        for user in state.users.iter_mut().filter(|u| u.guild_id == 0) {
+            user.guild_id = guild_id;
+            claimed += 1;
+        }
+        for row in state.bbps.iter_mut().chain(state.gbps.iter_mut()).filter(|r| r.guild_id == 0) {
+            row.guild_id = guild_id;
+        }
+
+        Ok(claimed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dataaccess::store_cases::store_cases;
+
+    async fn open(clock: Arc<dyn Clock>) -> MemoryStore {
+        MemoryStore::new().with_clock(clock)
+    }
+
+    store_cases!(open);
+}
@@ -0,0 +1,234 @@
+use log::info;
+use tokio_postgres::Client;
+
+pub struct Migration {
+    pub version: i32,
+    pub name: &'static str,
+    pub sql: &'static str,
+}
+
+// Append new migrations to the end, never edit one that has shipped.
+pub const POSTGRES_MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        name: "initial_schema",
+        sql: include_str!("postgres/0001_initial_schema.sql"),
+    },
+    Migration {
+        version: 2,
+        name: "guild_scoping",
+        sql: include_str!("postgres/0002_guild_scoping.sql"),
+    },
+    Migration {
+        version: 3,
+        name: "point_totals",
+        sql: include_str!("postgres/0003_point_totals.sql"),
+    },
+    Migration {
+        version: 4,
+        name: "seasons",
+        sql: include_str!("postgres/0004_seasons.sql"),
+    },
+    Migration {
+        version: 5,
+        name: "guild_settings",
+        sql: include_str!("postgres/0005_guild_settings.sql"),
+    },
+    Migration {
+        version: 6,
+        name: "point_values",
+        sql: include_str!("postgres/0006_point_values.sql"),
+    },
+    Migration {
+        version: 7,
+        name: "registration",
+        sql: include_str!("postgres/0007_registration.sql"),
+    },
+    Migration {
+        version: 8,
+        name: "member_sync",
+        sql: include_str!("postgres/0008_member_sync.sql"),
+    },
+    Migration {
+        version: 9,
+        name: "user_management",
+        sql: include_str!("postgres/0009_user_management.sql"),
+    },
+    Migration {
+        version: 10,
+        name: "jury",
+        sql: include_str!("postgres/0010_jury.sql"),
+    },
+    Migration {
+        version: 11,
+        name: "appeals",
+        sql: include_str!("postgres/0011_appeals.sql"),
+    },
+    Migration {
+        version: 12,
+        name: "forgiveness",
+        sql: include_str!("postgres/0012_forgiveness.sql"),
+    },
+    Migration {
+        version: 13,
+        name: "point_links",
+        sql: include_str!("postgres/0013_point_links.sql"),
+    },
+    Migration {
+        version: 14,
+        name: "retractions",
+        sql: include_str!("postgres/0014_retractions.sql"),
+    },
+    Migration {
+        version: 15,
+        name: "point_messages",
+        sql: include_str!("postgres/0015_point_messages.sql"),
+    },
+];
+
+#[cfg(feature = "sqlite")]
+pub const SQLITE_MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        name: "initial_schema",
+        sql: include_str!("sqlite/0001_initial_schema.sql"),
+    },
+    Migration {
+        version: 2,
+        name: "guild_scoping",
+        sql: include_str!("sqlite/0002_guild_scoping.sql"),
+    },
+    Migration {
+        version: 3,
+        name: "point_totals",
+        sql: include_str!("sqlite/0003_point_totals.sql"),
+    },
+    Migration {
+        version: 4,
+        name: "seasons",
+        sql: include_str!("sqlite/0004_seasons.sql"),
+    },
+    Migration {
+        version: 5,
+        name: "guild_settings",
+        sql: include_str!("sqlite/0005_guild_settings.sql"),
+    },
+    Migration {
+        version: 6,
+        name: "point_values",
+        sql: include_str!("sqlite/0006_point_values.sql"),
+    },
+    Migration {
+        version: 7,
+        name: "registration",
+        sql: include_str!("sqlite/0007_registration.sql"),
+    },
+    Migration {
+        version: 8,
+        name: "member_sync",
+        sql: include_str!("sqlite/0008_member_sync.sql"),
+    },
+    Migration {
+        version: 9,
+        name: "user_management",
+        sql: include_str!("sqlite/0009_user_management.sql"),
+    },
+    Migration {
+        version: 10,
+        name: "jury",
+        sql: include_str!("sqlite/0010_jury.sql"),
+    },
+    Migration {
+        version: 11,
+        name: "appeals",
+        sql: include_str!("sqlite/0011_appeals.sql"),
+    },
+    Migration {
+        version: 12,
+        name: "forgiveness",
+        sql: include_str!("sqlite/0012_forgiveness.sql"),
+    },
+    Migration {
+        version: 13,
+        name: "point_links",
+        sql: include_str!("sqlite/0013_point_links.sql"),
+    },
+    Migration {
+        version: 14,
+        name: "retractions",
+        sql: include_str!("sqlite/0014_retractions.sql"),
+    },
+    Migration {
+        version: 15,
+        name: "point_messages",
+        sql: include_str!("sqlite/0015_point_messages.sql"),
+    },
+];
+
+// Arbitrary key so that two bot instances starting at once don't both migrate.
+const MIGRATION_LOCK_KEY: i64 = 0x0062_6270_5f62_6f74;
+
+pub async fn run_postgres_migrations(client: &mut Client) -> Result<Vec<i32>, tokio_postgres::Error> {
+    let tx = client.transaction().await?;
+
+    tx.execute("SELECT pg_advisory_xact_lock($1)", &[&MIGRATION_LOCK_KEY]).await?;
+    tx.batch_execute(
+        "CREATE TABLE IF NOT EXISTS public.\"SchemaMigrations\" (
+            \"Version\" INTEGER PRIMARY KEY,
+            \"Name\" TEXT NOT NULL,
+            \"AppliedAt\" TIMESTAMP NOT NULL DEFAULT (now() AT TIME ZONE 'utc')
+        )")
+        .await?;
+
+    let applied: Vec<i32> = tx
+        .query("SELECT \"Version\" FROM public.\"SchemaMigrations\"", &[])
+        .await?
+        .iter()
+        .map(|row| row.get("Version"))
+        .collect();
+
+    let mut newly_applied = Vec::new();
+    for migration in POSTGRES_MIGRATIONS.iter().filter(|m| !applied.contains(&m.version)) {
+        info!("Applying migration {} ({})", migration.version, migration.name);
+        tx.batch_execute(migration.sql).await?;
+        tx.execute(
+            "INSERT INTO public.\"SchemaMigrations\" (\"Version\", \"Name\") VALUES ($1, $2)",
+            &[&migration.version, &migration.name])
+            .await?;
+        newly_applied.push(migration.version);
+    }
+
+    tx.commit().await?;
+    Ok(newly_applied)
+}
+
+#[cfg(feature = "sqlite")]
+pub fn run_sqlite_migrations(conn: &mut rusqlite::Connection) -> Result<Vec<i32>, rusqlite::Error> {
+    // IMMEDIATE takes the write lock up front, like the advisory lock on Postgres
+    let tx = conn.transaction_with_behavior(rusqlite::TransactionBehavior::Immediate)?;
+
+    tx.execute_batch(
+        "CREATE TABLE IF NOT EXISTS \"SchemaMigrations\" (
+            \"Version\" INTEGER PRIMARY KEY,
+            \"Name\" TEXT NOT NULL,
+            \"AppliedAt\" TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+        )")?;
+
+    let applied: Vec<i32> = tx
+        .prepare("SELECT \"Version\" FROM \"SchemaMigrations\"")?
+        .query_map([], |row| row.get("Version"))?
+        .collect::<Result<_, _>>()?;
+
+    let mut newly_applied = Vec::new();
+    for migration in SQLITE_MIGRATIONS.iter().filter(|m| !applied.contains(&m.version)) {
+        info!("Applying migration {} ({})", migration.version, migration.name);
+        tx.execute_batch(migration.sql)?;
+        tx.execute(
+            "INSERT INTO \"SchemaMigrations\" (\"Version\", \"Name\") VALUES (?1, ?2)",
+            rusqlite::params![migration.version, migration.name])?;
+        newly_applied.push(migration.version);
+    }
+
+    tx.commit()?;
+    Ok(newly_applied)
+}
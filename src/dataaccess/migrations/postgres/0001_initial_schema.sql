@@ -0,0 +1,120 @@
+-- Base tables as used by PostgresService. IF NOT EXISTS lets an existing
+-- production database adopt the migration history. Two things change on such a
+-- database: users registered more than once under the same DiscordID are merged
+-- into their oldest row, and any trigger it already had to maintain Points is
+-- replaced by the ones below so bbps and gbps aren't counted twice.
+CREATE TABLE IF NOT EXISTS public."Users" (
+    "UserID" SERIAL PRIMARY KEY,
+    "Username" TEXT,
+    "DiscordUsername" TEXT,
+    "DiscordMention" TEXT,
+    "DiscordID" BIGINT NOT NULL,
+    "FriendlyName" TEXT,
+    "Points" INTEGER NOT NULL DEFAULT 0,
+    "BbpsIssued" INTEGER NOT NULL DEFAULT 0,
+    "GbpsIssued" INTEGER NOT NULL DEFAULT 0
+);
+
+CREATE TABLE IF NOT EXISTS public."Bbps" (
+    "BbpID" SERIAL PRIMARY KEY,
+    "UserID" INTEGER NOT NULL REFERENCES public."Users" ("UserID"),
+    "Value" INTEGER NOT NULL DEFAULT 1,
+    "Description" TEXT NOT NULL,
+    "Timestamp" TIMESTAMP NOT NULL,
+    "IssuerID" INTEGER NOT NULL REFERENCES public."Users" ("UserID"),
+    "Forgiven" BOOLEAN NOT NULL DEFAULT false
+);
+
+CREATE TABLE IF NOT EXISTS public."Gbps" (
+    "GbpID" SERIAL PRIMARY KEY,
+    "UserID" INTEGER NOT NULL REFERENCES public."Users" ("UserID"),
+    "Value" INTEGER NOT NULL DEFAULT 1,
+    "Description" TEXT NOT NULL,
+    "Timestamp" TIMESTAMP NOT NULL,
+    "IssuerID" INTEGER NOT NULL REFERENCES public."Users" ("UserID")
+);
+
+-- Older versions allowed duplicate registrations. Their bbps and gbps move to
+-- the oldest row, the recalculation at the end fixes its totals.
+CREATE TEMPORARY TABLE "DuplicateUsers" ON COMMIT DROP AS
+    SELECT u."UserID", keep."UserID" AS "KeepID"
+    FROM public."Users" u
+    JOIN (SELECT "DiscordID", MIN("UserID") AS "UserID" FROM public."Users" GROUP BY "DiscordID") keep
+        ON keep."DiscordID" = u."DiscordID" AND keep."UserID" <> u."UserID";
+UPDATE public."Bbps" b SET "UserID" = d."KeepID" FROM "DuplicateUsers" d WHERE b."UserID" = d."UserID";
+UPDATE public."Bbps" b SET "IssuerID" = d."KeepID" FROM "DuplicateUsers" d WHERE b."IssuerID" = d."UserID";
+UPDATE public."Gbps" g SET "UserID" = d."KeepID" FROM "DuplicateUsers" d WHERE g."UserID" = d."UserID";
+UPDATE public."Gbps" g SET "IssuerID" = d."KeepID" FROM "DuplicateUsers" d WHERE g."IssuerID" = d."UserID";
+DELETE FROM public."Users" u USING "DuplicateUsers" d WHERE u."UserID" = d."UserID";
+
+CREATE UNIQUE INDEX IF NOT EXISTS "IX_Users_DiscordID" ON public."Users" ("DiscordID");
+CREATE INDEX IF NOT EXISTS "IX_Users_Points" ON public."Users" ("Points" DESC);
+CREATE INDEX IF NOT EXISTS "IX_Bbps_UserID_Timestamp" ON public."Bbps" ("UserID", "Timestamp" DESC);
+CREATE INDEX IF NOT EXISTS "IX_Bbps_IssuerID_UserID" ON public."Bbps" ("IssuerID", "UserID") WHERE "Forgiven" = false;
+CREATE INDEX IF NOT EXISTS "IX_Gbps_UserID_Timestamp" ON public."Gbps" ("UserID", "Timestamp" DESC);
+CREATE INDEX IF NOT EXISTS "IX_Gbps_IssuerID" ON public."Gbps" ("IssuerID");
+
+-- Points = unforgiven bbps - gbps, same as postgres/queries.sql
+CREATE OR REPLACE FUNCTION public.recalculate_user_points(target_user_id INTEGER) RETURNS VOID AS $$
+BEGIN
+    UPDATE public."Users" u
+    SET "Points" = COALESCE((SELECT SUM(b."Value") FROM public."Bbps" b
+                             WHERE b."UserID" = u."UserID" AND b."Forgiven" = false), 0)
+                 - COALESCE((SELECT SUM(g."Value") FROM public."Gbps" g
+                             WHERE g."UserID" = u."UserID"), 0),
+        "BbpsIssued" = (SELECT COUNT(*) FROM public."Bbps" b WHERE b."IssuerID" = u."UserID"),
+        "GbpsIssued" = (SELECT COUNT(*) FROM public."Gbps" g WHERE g."IssuerID" = u."UserID")
+    WHERE u."UserID" = target_user_id;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE OR REPLACE FUNCTION public.maintain_user_points() RETURNS TRIGGER AS $$
+BEGIN
+    IF TG_OP IN ('INSERT', 'UPDATE') THEN
+        PERFORM public.recalculate_user_points(NEW."UserID");
+        PERFORM public.recalculate_user_points(NEW."IssuerID");
+    END IF;
+    IF TG_OP IN ('UPDATE', 'DELETE') THEN
+        PERFORM public.recalculate_user_points(OLD."UserID");
+        PERFORM public.recalculate_user_points(OLD."IssuerID");
+    END IF;
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+-- A points trigger production already has, under whatever name, would count
+-- every bbp and gbp a second time.
+DO $$
+DECLARE
+    existing RECORD;
+BEGIN
+    FOR existing IN
+        SELECT t.tgname, c.relname
+        FROM pg_trigger t
+        JOIN pg_class c ON c.oid = t.tgrelid
+        JOIN pg_namespace n ON n.oid = c.relnamespace
+        JOIN pg_proc p ON p.oid = t.tgfoid
+        WHERE n.nspname = 'public'
+          AND c.relname IN ('Bbps', 'Gbps')
+          AND NOT t.tgisinternal
+          AND t.tgname NOT IN ('TR_Bbps_MaintainPoints', 'TR_Gbps_MaintainPoints')
+          AND p.prosrc LIKE '%Points%'
+    LOOP
+        RAISE NOTICE 'Dropping points trigger % on %', existing.tgname, existing.relname;
+        EXECUTE format('DROP TRIGGER %I ON public.%I', existing.tgname, existing.relname);
+    END LOOP;
+END;
+$$;
+
+DROP TRIGGER IF EXISTS "TR_Bbps_MaintainPoints" ON public."Bbps";
+CREATE TRIGGER "TR_Bbps_MaintainPoints"
+    AFTER INSERT OR UPDATE OR DELETE ON public."Bbps"
+    FOR EACH ROW EXECUTE FUNCTION public.maintain_user_points();
+
+DROP TRIGGER IF EXISTS "TR_Gbps_MaintainPoints" ON public."Gbps";
+CREATE TRIGGER "TR_Gbps_MaintainPoints"
+    AFTER INSERT OR UPDATE OR DELETE ON public."Gbps"
+    FOR EACH ROW EXECUTE FUNCTION public.maintain_user_points();
+
+-- Bring any pre-existing rows in line with the triggers.
+SELECT public.recalculate_user_points("UserID") FROM public."Users";
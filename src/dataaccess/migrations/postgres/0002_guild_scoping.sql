@@ -0,0 +1,17 @@
+-- Scope users, bbps and gbps to a Discord guild. Rows created before this
+-- migration get GuildID 0 until claimed through LEGACY_GUILD_ID.
+ALTER TABLE public."Users" ADD COLUMN IF NOT EXISTS "GuildID" BIGINT NOT NULL DEFAULT 0;
+ALTER TABLE public."Bbps" ADD COLUMN IF NOT EXISTS "GuildID" BIGINT NOT NULL DEFAULT 0;
+ALTER TABLE public."Gbps" ADD COLUMN IF NOT EXISTS "GuildID" BIGINT NOT NULL DEFAULT 0;
+
+ALTER TABLE public."Users" ALTER COLUMN "GuildID" DROP DEFAULT;
+ALTER TABLE public."Bbps" ALTER COLUMN "GuildID" DROP DEFAULT;
+ALTER TABLE public."Gbps" ALTER COLUMN "GuildID" DROP DEFAULT;
+
+-- The same person can hold a separate standing in every guild
+DROP INDEX IF EXISTS public."IX_Users_DiscordID";
+DROP INDEX IF EXISTS public."IX_Users_Points";
+CREATE UNIQUE INDEX IF NOT EXISTS "IX_Users_GuildID_DiscordID" ON public."Users" ("GuildID", "DiscordID");
+CREATE INDEX IF NOT EXISTS "IX_Users_GuildID_Points" ON public."Users" ("GuildID", "Points" DESC);
+CREATE INDEX IF NOT EXISTS "IX_Bbps_GuildID_Timestamp" ON public."Bbps" ("GuildID", "Timestamp" DESC);
+CREATE INDEX IF NOT EXISTS "IX_Gbps_GuildID_Timestamp" ON public."Gbps" ("GuildID", "Timestamp" DESC);
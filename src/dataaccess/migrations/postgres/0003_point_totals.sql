@@ -0,0 +1,24 @@
+-- Keep the bbps and gbps a user has received next to their net Points, so
+-- replies can show both sides. Points stays BbpTotal - GbpTotal.
+ALTER TABLE public."Users" ADD COLUMN IF NOT EXISTS "BbpTotal" INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE public."Users" ADD COLUMN IF NOT EXISTS "GbpTotal" INTEGER NOT NULL DEFAULT 0;
+
+CREATE OR REPLACE FUNCTION public.recalculate_user_points(target_user_id INTEGER) RETURNS VOID AS $$
+BEGIN
+    UPDATE public."Users" u
+    SET "BbpTotal" = t.bbp_total,
+        "GbpTotal" = t.gbp_total,
+        "Points" = t.bbp_total - t.gbp_total,
+        "BbpsIssued" = (SELECT COUNT(*) FROM public."Bbps" b WHERE b."IssuerID" = u."UserID"),
+        "GbpsIssued" = (SELECT COUNT(*) FROM public."Gbps" g WHERE g."IssuerID" = u."UserID")
+    FROM (
+        SELECT COALESCE((SELECT SUM(b."Value") FROM public."Bbps" b
+                         WHERE b."UserID" = target_user_id AND b."Forgiven" = false), 0) AS bbp_total,
+               COALESCE((SELECT SUM(g."Value") FROM public."Gbps" g
+                         WHERE g."UserID" = target_user_id), 0) AS gbp_total
+    ) t
+    WHERE u."UserID" = target_user_id;
+END;
+$$ LANGUAGE plpgsql;
+
+SELECT public.recalculate_user_points("UserID") FROM public."Users";
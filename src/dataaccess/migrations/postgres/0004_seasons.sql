@@ -0,0 +1,49 @@
+-- Owners can close a season: its standings are copied into SeasonStandings and
+-- its bbps and gbps are tagged with the SeasonID. Rows without a SeasonID make
+-- up the running season, which is all the cached totals count.
+CREATE TABLE IF NOT EXISTS public."Seasons" (
+    "SeasonID" SERIAL PRIMARY KEY,
+    "GuildID" BIGINT NOT NULL,
+    "Number" INTEGER NOT NULL,
+    "StartedAt" TIMESTAMP NOT NULL,
+    "EndedAt" TIMESTAMP
+);
+
+CREATE UNIQUE INDEX IF NOT EXISTS "IX_Seasons_GuildID_Number" ON public."Seasons" ("GuildID", "Number");
+-- At most one running season per guild
+CREATE UNIQUE INDEX IF NOT EXISTS "IX_Seasons_GuildID_Running" ON public."Seasons" ("GuildID") WHERE "EndedAt" IS NULL;
+
+CREATE TABLE IF NOT EXISTS public."SeasonStandings" (
+    "SeasonID" INTEGER NOT NULL REFERENCES public."Seasons" ("SeasonID"),
+    "UserID" INTEGER NOT NULL REFERENCES public."Users" ("UserID"),
+    "Rank" BIGINT NOT NULL,
+    "Points" INTEGER NOT NULL,
+    "BbpTotal" INTEGER NOT NULL,
+    "GbpTotal" INTEGER NOT NULL,
+    "BbpsIssued" INTEGER NOT NULL,
+    "GbpsIssued" INTEGER NOT NULL,
+    PRIMARY KEY ("SeasonID", "UserID")
+);
+
+ALTER TABLE public."Bbps" ADD COLUMN IF NOT EXISTS "SeasonID" INTEGER REFERENCES public."Seasons" ("SeasonID");
+ALTER TABLE public."Gbps" ADD COLUMN IF NOT EXISTS "SeasonID" INTEGER REFERENCES public."Seasons" ("SeasonID");
+
+CREATE OR REPLACE FUNCTION public.recalculate_user_points(target_user_id INTEGER) RETURNS VOID AS $$
+BEGIN
+    UPDATE public."Users" u
+    SET "BbpTotal" = t.bbp_total,
+        "GbpTotal" = t.gbp_total,
+        "Points" = t.bbp_total - t.gbp_total,
+        "BbpsIssued" = (SELECT COUNT(*) FROM public."Bbps" b
+                        WHERE b."IssuerID" = u."UserID" AND b."SeasonID" IS NULL),
+        "GbpsIssued" = (SELECT COUNT(*) FROM public."Gbps" g
+                        WHERE g."IssuerID" = u."UserID" AND g."SeasonID" IS NULL)
+    FROM (
+        SELECT COALESCE((SELECT SUM(b."Value") FROM public."Bbps" b
+                         WHERE b."UserID" = target_user_id AND b."Forgiven" = false AND b."SeasonID" IS NULL), 0) AS bbp_total,
+               COALESCE((SELECT SUM(g."Value") FROM public."Gbps" g
+                         WHERE g."UserID" = target_user_id AND g."SeasonID" IS NULL), 0) AS gbp_total
+    ) t
+    WHERE u."UserID" = target_user_id;
+END;
+$$ LANGUAGE plpgsql;
@@ -0,0 +1,23 @@
+-- Per-guild configuration, starting with how bbps decay. Guilds without a row
+-- use the defaults.
+CREATE TABLE IF NOT EXISTS public."GuildSettings" (
+    "GuildID" BIGINT PRIMARY KEY,
+    "DecayMode" TEXT NOT NULL DEFAULT 'off',
+    "DecayDays" INTEGER NOT NULL DEFAULT 0
+);
+
+-- What a bbp issued at issued_at is worth at now_at under the given policy.
+-- Mirrors DecayPolicy::weight.
+CREATE OR REPLACE FUNCTION public.decay_weight(mode TEXT, days INTEGER, issued_at TIMESTAMP, now_at TIMESTAMP) RETURNS DOUBLE PRECISION AS $$
+    SELECT CASE
+        WHEN mode = 'half_life' AND days > 0
+            THEN power(0.5, GREATEST(EXTRACT(EPOCH FROM (now_at - issued_at)), 0) / (days * 86400.0))
+        WHEN mode = 'expiry' AND days > 0
+            THEN CASE WHEN now_at - issued_at < make_interval(days => days) THEN 1.0 ELSE 0.0 END
+        ELSE 1.0
+    END::DOUBLE PRECISION
+$$ LANGUAGE sql IMMUTABLE;
+
+-- Archived standings keep the score they were ranked by
+ALTER TABLE public."SeasonStandings" ADD COLUMN IF NOT EXISTS "Score" DOUBLE PRECISION NOT NULL DEFAULT 0;
+UPDATE public."SeasonStandings" SET "Score" = "Points";
@@ -0,0 +1,5 @@
+-- Bbps and gbps can be worth more than one point. Everyone may issue up to
+-- MaxValue, members with the moderator role up to ModeratorMaxValue.
+ALTER TABLE public."GuildSettings" ADD COLUMN IF NOT EXISTS "MaxValue" INTEGER NOT NULL DEFAULT 1;
+ALTER TABLE public."GuildSettings" ADD COLUMN IF NOT EXISTS "ModeratorMaxValue" INTEGER NOT NULL DEFAULT 3;
+ALTER TABLE public."GuildSettings" ADD COLUMN IF NOT EXISTS "ModeratorRoleID" BIGINT;
@@ -0,0 +1,15 @@
+-- Members can register themselves with /join, as far as the guild's
+-- RegistrationPolicy allows. Under 'approval' their requests wait in
+-- RegistrationRequests until a moderator approves or denies them.
+ALTER TABLE public."GuildSettings" ADD COLUMN IF NOT EXISTS "RegistrationPolicy" TEXT NOT NULL DEFAULT 'owner_only';
+ALTER TABLE public."GuildSettings" ADD COLUMN IF NOT EXISTS "ModeratorChannelID" BIGINT;
+ALTER TABLE public."GuildSettings" ADD COLUMN IF NOT EXISTS "AutoRegister" BOOLEAN NOT NULL DEFAULT false;
+
+CREATE TABLE IF NOT EXISTS public."RegistrationRequests" (
+    "GuildID" BIGINT NOT NULL,
+    "DiscordID" BIGINT NOT NULL,
+    "DiscordUsername" TEXT NOT NULL,
+    "FriendlyName" TEXT NOT NULL,
+    "RequestedAt" TIMESTAMP NOT NULL,
+    PRIMARY KEY ("GuildID", "DiscordID")
+);
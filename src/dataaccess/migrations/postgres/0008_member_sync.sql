@@ -0,0 +1,2 @@
+-- Guilds can have members registered as soon as they join the server.
+ALTER TABLE public."GuildSettings" ADD COLUMN IF NOT EXISTS "EnrolNewMembers" BOOLEAN NOT NULL DEFAULT false;
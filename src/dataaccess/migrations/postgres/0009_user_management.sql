@@ -0,0 +1,2 @@
+-- Deactivated users keep their bbps and gbps but are left out of the standings.
+ALTER TABLE public."Users" ADD COLUMN IF NOT EXISTS "Active" BOOLEAN NOT NULL DEFAULT true;
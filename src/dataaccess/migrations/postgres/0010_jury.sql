@@ -0,0 +1,30 @@
+-- Guilds can have a jury confirm every bbp. A JuryQuorum of 0 means no jury.
+ALTER TABLE public."GuildSettings" ADD COLUMN IF NOT EXISTS "JuryQuorum" INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE public."GuildSettings" ADD COLUMN IF NOT EXISTS "JuryHours" INTEGER NOT NULL DEFAULT 0;
+
+-- Bbps waiting on the jury. A confirmed one is copied into Bbps, the row stays
+-- behind with its votes.
+CREATE TABLE IF NOT EXISTS public."PendingBbps" (
+    "PendingID" SERIAL PRIMARY KEY,
+    "GuildID" BIGINT NOT NULL,
+    "UserID" INTEGER NOT NULL REFERENCES public."Users" ("UserID"),
+    "IssuerID" INTEGER NOT NULL REFERENCES public."Users" ("UserID"),
+    "Value" INTEGER NOT NULL,
+    "Description" TEXT NOT NULL,
+    "Quorum" INTEGER NOT NULL,
+    "Status" TEXT NOT NULL DEFAULT 'pending',
+    "CreatedAt" TIMESTAMP NOT NULL,
+    "ExpiresAt" TIMESTAMP NOT NULL,
+    "ChannelID" BIGINT,
+    "MessageID" BIGINT
+);
+
+CREATE INDEX IF NOT EXISTS "IX_PendingBbps_ExpiresAt" ON public."PendingBbps" ("ExpiresAt") WHERE "Status" = 'pending';
+
+CREATE TABLE IF NOT EXISTS public."PendingBbpVotes" (
+    "PendingID" INTEGER NOT NULL REFERENCES public."PendingBbps" ("PendingID"),
+    "VoterID" INTEGER NOT NULL REFERENCES public."Users" ("UserID"),
+    "InFavour" BOOLEAN NOT NULL,
+    "VotedAt" TIMESTAMP NOT NULL,
+    PRIMARY KEY ("PendingID", "VoterID")
+);
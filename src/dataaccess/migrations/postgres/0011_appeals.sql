@@ -0,0 +1,23 @@
+-- Targets contesting a bbp. Overturning one forgives the bbp, the appeal stays
+-- behind for the history. A bbp can only be appealed once.
+CREATE TABLE IF NOT EXISTS public."Appeals" (
+    "AppealID" SERIAL PRIMARY KEY,
+    "BbpID" INTEGER NOT NULL UNIQUE REFERENCES public."Bbps" ("BbpID"),
+    "Statement" TEXT NOT NULL,
+    "Status" TEXT NOT NULL DEFAULT 'open',
+    "Quorum" INTEGER NOT NULL,
+    "OpenedAt" TIMESTAMP NOT NULL,
+    "ResolvedAt" TIMESTAMP,
+    -- The moderator who decided it, NULL when the community did
+    "ResolvedByDiscordID" BIGINT,
+    "ChannelID" BIGINT,
+    "MessageID" BIGINT
+);
+
+CREATE TABLE IF NOT EXISTS public."AppealVotes" (
+    "AppealID" INTEGER NOT NULL REFERENCES public."Appeals" ("AppealID"),
+    "VoterID" INTEGER NOT NULL REFERENCES public."Users" ("UserID"),
+    "Overturn" BOOLEAN NOT NULL,
+    "VotedAt" TIMESTAMP NOT NULL,
+    PRIMARY KEY ("AppealID", "VoterID")
+);
@@ -0,0 +1,4 @@
+-- Who forgave a bbp and when. Both stay NULL for bbps forgiven before this, and
+-- the forgiver is NULL when the community overturned it on appeal.
+ALTER TABLE public."Bbps" ADD COLUMN IF NOT EXISTS "ForgivenByDiscordID" BIGINT;
+ALTER TABLE public."Bbps" ADD COLUMN IF NOT EXISTS "ForgivenAt" TIMESTAMP;
@@ -0,0 +1,5 @@
+-- The bbp a pending bbp became once the jury confirmed it, so the bbp can point
+-- back at its vote.
+ALTER TABLE public."PendingBbps" ADD COLUMN IF NOT EXISTS "BbpID" INTEGER REFERENCES public."Bbps" ("BbpID");
+
+CREATE UNIQUE INDEX IF NOT EXISTS "IX_PendingBbps_BbpID" ON public."PendingBbps" ("BbpID") WHERE "BbpID" IS NOT NULL;
@@ -0,0 +1,23 @@
+-- Issuers can edit or retract their own bbps and gbps for this many minutes
+-- after giving them. 0 turns it off.
+ALTER TABLE public."GuildSettings" ADD COLUMN IF NOT EXISTS "GraceMinutes" INTEGER NOT NULL DEFAULT 10;
+
+-- Every edit and retraction. Retracted points are deleted, so the entry keeps
+-- what they were. Users are referred to by Discord ID so merging or removing
+-- them leaves the trail alone.
+CREATE TABLE IF NOT EXISTS public."AuditLog" (
+    "AuditID" SERIAL PRIMARY KEY,
+    "GuildID" BIGINT NOT NULL,
+    "Action" TEXT NOT NULL,
+    "Kind" TEXT NOT NULL,
+    "PointID" INTEGER NOT NULL,
+    "ActorDiscordID" BIGINT NOT NULL,
+    "TargetDiscordID" BIGINT NOT NULL,
+    "Value" INTEGER NOT NULL,
+    "OldDescription" TEXT NOT NULL,
+    -- NULL for retractions
+    "NewDescription" TEXT,
+    "At" TIMESTAMP NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS "IX_AuditLog_GuildID_At" ON public."AuditLog" ("GuildID", "At" DESC);
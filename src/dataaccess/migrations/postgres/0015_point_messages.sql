@@ -0,0 +1,6 @@
+-- The reply that announced a bbp/gbp, so /show can link to it. NULL for points
+-- given before this and for bbps the jury confirmed, whose message is the vote.
+ALTER TABLE public."Bbps" ADD COLUMN IF NOT EXISTS "ChannelID" BIGINT;
+ALTER TABLE public."Bbps" ADD COLUMN IF NOT EXISTS "MessageID" BIGINT;
+ALTER TABLE public."Gbps" ADD COLUMN IF NOT EXISTS "ChannelID" BIGINT;
+ALTER TABLE public."Gbps" ADD COLUMN IF NOT EXISTS "MessageID" BIGINT;
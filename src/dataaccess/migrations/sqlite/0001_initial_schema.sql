@@ -0,0 +1,102 @@
+-- SQLite counterpart of postgres/0001_initial_schema.sql
+CREATE TABLE IF NOT EXISTS "Users" (
+    "UserID" INTEGER PRIMARY KEY AUTOINCREMENT,
+    "Username" TEXT,
+    "DiscordUsername" TEXT,
+    "DiscordMention" TEXT,
+    "DiscordID" INTEGER NOT NULL,
+    "FriendlyName" TEXT,
+    "Points" INTEGER NOT NULL DEFAULT 0,
+    "BbpsIssued" INTEGER NOT NULL DEFAULT 0,
+    "GbpsIssued" INTEGER NOT NULL DEFAULT 0
+);
+
+CREATE TABLE IF NOT EXISTS "Bbps" (
+    "BbpID" INTEGER PRIMARY KEY AUTOINCREMENT,
+    "UserID" INTEGER NOT NULL REFERENCES "Users" ("UserID"),
+    "Value" INTEGER NOT NULL DEFAULT 1,
+    "Description" TEXT NOT NULL,
+    "Timestamp" TEXT NOT NULL,
+    "IssuerID" INTEGER NOT NULL REFERENCES "Users" ("UserID"),
+    "Forgiven" INTEGER NOT NULL DEFAULT 0
+);
+
+CREATE TABLE IF NOT EXISTS "Gbps" (
+    "GbpID" INTEGER PRIMARY KEY AUTOINCREMENT,
+    "UserID" INTEGER NOT NULL REFERENCES "Users" ("UserID"),
+    "Value" INTEGER NOT NULL DEFAULT 1,
+    "Description" TEXT NOT NULL,
+    "Timestamp" TEXT NOT NULL,
+    "IssuerID" INTEGER NOT NULL REFERENCES "Users" ("UserID")
+);
+
+CREATE UNIQUE INDEX IF NOT EXISTS "IX_Users_DiscordID" ON "Users" ("DiscordID");
+CREATE INDEX IF NOT EXISTS "IX_Users_Points" ON "Users" ("Points" DESC);
+CREATE INDEX IF NOT EXISTS "IX_Bbps_UserID_Timestamp" ON "Bbps" ("UserID", "Timestamp" DESC);
+CREATE INDEX IF NOT EXISTS "IX_Bbps_IssuerID_UserID" ON "Bbps" ("IssuerID", "UserID") WHERE "Forgiven" = 0;
+CREATE INDEX IF NOT EXISTS "IX_Gbps_UserID_Timestamp" ON "Gbps" ("UserID", "Timestamp" DESC);
+CREATE INDEX IF NOT EXISTS "IX_Gbps_IssuerID" ON "Gbps" ("IssuerID");
+
+-- SQLite has no stored functions, the triggers share the points calculation
+-- through this view instead.
+CREATE VIEW IF NOT EXISTS "UserTotals" AS
+SELECT u."UserID",
+       COALESCE((SELECT SUM(b."Value") FROM "Bbps" b WHERE b."UserID" = u."UserID" AND b."Forgiven" = 0), 0)
+     - COALESCE((SELECT SUM(g."Value") FROM "Gbps" g WHERE g."UserID" = u."UserID"), 0) AS "Points",
+       (SELECT COUNT(*) FROM "Bbps" b WHERE b."IssuerID" = u."UserID") AS "BbpsIssued",
+       (SELECT COUNT(*) FROM "Gbps" g WHERE g."IssuerID" = u."UserID") AS "GbpsIssued"
+FROM "Users" u;
+
+CREATE TRIGGER IF NOT EXISTS "TR_Bbps_Insert" AFTER INSERT ON "Bbps"
+BEGIN
+    UPDATE "Users" SET
+        "Points" = (SELECT t."Points" FROM "UserTotals" t WHERE t."UserID" = "Users"."UserID"),
+        "BbpsIssued" = (SELECT t."BbpsIssued" FROM "UserTotals" t WHERE t."UserID" = "Users"."UserID"),
+        "GbpsIssued" = (SELECT t."GbpsIssued" FROM "UserTotals" t WHERE t."UserID" = "Users"."UserID")
+    WHERE "UserID" IN (NEW."UserID", NEW."IssuerID");
+END;
+
+CREATE TRIGGER IF NOT EXISTS "TR_Bbps_Update" AFTER UPDATE ON "Bbps"
+BEGIN
+    UPDATE "Users" SET
+        "Points" = (SELECT t."Points" FROM "UserTotals" t WHERE t."UserID" = "Users"."UserID"),
+        "BbpsIssued" = (SELECT t."BbpsIssued" FROM "UserTotals" t WHERE t."UserID" = "Users"."UserID"),
+        "GbpsIssued" = (SELECT t."GbpsIssued" FROM "UserTotals" t WHERE t."UserID" = "Users"."UserID")
+    WHERE "UserID" IN (NEW."UserID", NEW."IssuerID", OLD."UserID", OLD."IssuerID");
+END;
+
+CREATE TRIGGER IF NOT EXISTS "TR_Bbps_Delete" AFTER DELETE ON "Bbps"
+BEGIN
+    UPDATE "Users" SET
+        "Points" = (SELECT t."Points" FROM "UserTotals" t WHERE t."UserID" = "Users"."UserID"),
+        "BbpsIssued" = (SELECT t."BbpsIssued" FROM "UserTotals" t WHERE t."UserID" = "Users"."UserID"),
+        "GbpsIssued" = (SELECT t."GbpsIssued" FROM "UserTotals" t WHERE t."UserID" = "Users"."UserID")
+    WHERE "UserID" IN (OLD."UserID", OLD."IssuerID");
+END;
+
+CREATE TRIGGER IF NOT EXISTS "TR_Gbps_Insert" AFTER INSERT ON "Gbps"
+BEGIN
+    UPDATE "Users" SET
+        "Points" = (SELECT t."Points" FROM "UserTotals" t WHERE t."UserID" = "Users"."UserID"),
+        "BbpsIssued" = (SELECT t."BbpsIssued" FROM "UserTotals" t WHERE t."UserID" = "Users"."UserID"),
+        "GbpsIssued" = (SELECT t."GbpsIssued" FROM "UserTotals" t WHERE t."UserID" = "Users"."UserID")
+    WHERE "UserID" IN (NEW."UserID", NEW."IssuerID");
+END;
+
+CREATE TRIGGER IF NOT EXISTS "TR_Gbps_Update" AFTER UPDATE ON "Gbps"
+BEGIN
+    UPDATE "Users" SET
+        "Points" = (SELECT t."Points" FROM "UserTotals" t WHERE t."UserID" = "Users"."UserID"),
+        "BbpsIssued" = (SELECT t."BbpsIssued" FROM "UserTotals" t WHERE t."UserID" = "Users"."UserID"),
+        "GbpsIssued" = (SELECT t."GbpsIssued" FROM "UserTotals" t WHERE t."UserID" = "Users"."UserID")
+    WHERE "UserID" IN (NEW."UserID", NEW."IssuerID", OLD."UserID", OLD."IssuerID");
+END;
+
+CREATE TRIGGER IF NOT EXISTS "TR_Gbps_Delete" AFTER DELETE ON "Gbps"
+BEGIN
+    UPDATE "Users" SET
+        "Points" = (SELECT t."Points" FROM "UserTotals" t WHERE t."UserID" = "Users"."UserID"),
+        "BbpsIssued" = (SELECT t."BbpsIssued" FROM "UserTotals" t WHERE t."UserID" = "Users"."UserID"),
+        "GbpsIssued" = (SELECT t."GbpsIssued" FROM "UserTotals" t WHERE t."UserID" = "Users"."UserID")
+    WHERE "UserID" IN (OLD."UserID", OLD."IssuerID");
+END;
@@ -0,0 +1,11 @@
+-- SQLite counterpart of postgres/0002_guild_scoping.sql
+ALTER TABLE "Users" ADD COLUMN "GuildID" INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE "Bbps" ADD COLUMN "GuildID" INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE "Gbps" ADD COLUMN "GuildID" INTEGER NOT NULL DEFAULT 0;
+
+DROP INDEX IF EXISTS "IX_Users_DiscordID";
+DROP INDEX IF EXISTS "IX_Users_Points";
+CREATE UNIQUE INDEX IF NOT EXISTS "IX_Users_GuildID_DiscordID" ON "Users" ("GuildID", "DiscordID");
+CREATE INDEX IF NOT EXISTS "IX_Users_GuildID_Points" ON "Users" ("GuildID", "Points" DESC);
+CREATE INDEX IF NOT EXISTS "IX_Bbps_GuildID_Timestamp" ON "Bbps" ("GuildID", "Timestamp" DESC);
+CREATE INDEX IF NOT EXISTS "IX_Gbps_GuildID_Timestamp" ON "Gbps" ("GuildID", "Timestamp" DESC);
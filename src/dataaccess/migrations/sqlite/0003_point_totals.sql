@@ -0,0 +1,92 @@
+-- SQLite counterpart of postgres/0003_point_totals.sql
+ALTER TABLE "Users" ADD COLUMN "BbpTotal" INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE "Users" ADD COLUMN "GbpTotal" INTEGER NOT NULL DEFAULT 0;
+
+DROP TRIGGER IF EXISTS "TR_Bbps_Insert";
+DROP TRIGGER IF EXISTS "TR_Bbps_Update";
+DROP TRIGGER IF EXISTS "TR_Bbps_Delete";
+DROP TRIGGER IF EXISTS "TR_Gbps_Insert";
+DROP TRIGGER IF EXISTS "TR_Gbps_Update";
+DROP TRIGGER IF EXISTS "TR_Gbps_Delete";
+DROP VIEW IF EXISTS "UserTotals";
+
+CREATE VIEW "UserTotals" AS
+SELECT "UserID", "BbpTotal", "GbpTotal", "BbpTotal" - "GbpTotal" AS "Points", "BbpsIssued", "GbpsIssued"
+FROM (
+    SELECT u."UserID",
+           COALESCE((SELECT SUM(b."Value") FROM "Bbps" b WHERE b."UserID" = u."UserID" AND b."Forgiven" = 0), 0) AS "BbpTotal",
+           COALESCE((SELECT SUM(g."Value") FROM "Gbps" g WHERE g."UserID" = u."UserID"), 0) AS "GbpTotal",
+           (SELECT COUNT(*) FROM "Bbps" b WHERE b."IssuerID" = u."UserID") AS "BbpsIssued",
+           (SELECT COUNT(*) FROM "Gbps" g WHERE g."IssuerID" = u."UserID") AS "GbpsIssued"
+    FROM "Users" u
+);
+
+CREATE TRIGGER "TR_Bbps_Insert" AFTER INSERT ON "Bbps"
+BEGIN
+    UPDATE "Users" SET
+        "BbpTotal" = (SELECT t."BbpTotal" FROM "UserTotals" t WHERE t."UserID" = "Users"."UserID"),
+        "GbpTotal" = (SELECT t."GbpTotal" FROM "UserTotals" t WHERE t."UserID" = "Users"."UserID"),
+        "Points" = (SELECT t."Points" FROM "UserTotals" t WHERE t."UserID" = "Users"."UserID"),
+        "BbpsIssued" = (SELECT t."BbpsIssued" FROM "UserTotals" t WHERE t."UserID" = "Users"."UserID"),
+        "GbpsIssued" = (SELECT t."GbpsIssued" FROM "UserTotals" t WHERE t."UserID" = "Users"."UserID")
+    WHERE "UserID" IN (NEW."UserID", NEW."IssuerID");
+END;
+
+CREATE TRIGGER "TR_Bbps_Update" AFTER UPDATE ON "Bbps"
+BEGIN
+    UPDATE "Users" SET
+        "BbpTotal" = (SELECT t."BbpTotal" FROM "UserTotals" t WHERE t."UserID" = "Users"."UserID"),
+        "GbpTotal" = (SELECT t."GbpTotal" FROM "UserTotals" t WHERE t."UserID" = "Users"."UserID"),
+        "Points" = (SELECT t."Points" FROM "UserTotals" t WHERE t."UserID" = "Users"."UserID"),
+        "BbpsIssued" = (SELECT t."BbpsIssued" FROM "UserTotals" t WHERE t."UserID" = "Users"."UserID"),
+        "GbpsIssued" = (SELECT t."GbpsIssued" FROM "UserTotals" t WHERE t."UserID" = "Users"."UserID")
+    WHERE "UserID" IN (NEW."UserID", NEW."IssuerID", OLD."UserID", OLD."IssuerID");
+END;
+
+CREATE TRIGGER "TR_Bbps_Delete" AFTER DELETE ON "Bbps"
+BEGIN
+    UPDATE "Users" SET
+        "BbpTotal" = (SELECT t."BbpTotal" FROM "UserTotals" t WHERE t."UserID" = "Users"."UserID"),
+        "GbpTotal" = (SELECT t."GbpTotal" FROM "UserTotals" t WHERE t."UserID" = "Users"."UserID"),
+        "Points" = (SELECT t."Points" FROM "UserTotals" t WHERE t."UserID" = "Users"."UserID"),
+        "BbpsIssued" = (SELECT t."BbpsIssued" FROM "UserTotals" t WHERE t."UserID" = "Users"."UserID"),
+        "GbpsIssued" = (SELECT t."GbpsIssued" FROM "UserTotals" t WHERE t."UserID" = "Users"."UserID")
+    WHERE "UserID" IN (OLD."UserID", OLD."IssuerID");
+END;
+
+CREATE TRIGGER "TR_Gbps_Insert" AFTER INSERT ON "Gbps"
+BEGIN
+    UPDATE "Users" SET
+        "BbpTotal" = (SELECT t."BbpTotal" FROM "UserTotals" t WHERE t."UserID" = "Users"."UserID"),
+        "GbpTotal" = (SELECT t."GbpTotal" FROM "UserTotals" t WHERE t."UserID" = "Users"."UserID"),
+        "Points" = (SELECT t."Points" FROM "UserTotals" t WHERE t."UserID" = "Users"."UserID"),
+        "BbpsIssued" = (SELECT t."BbpsIssued" FROM "UserTotals" t WHERE t."UserID" = "Users"."UserID"),
+        "GbpsIssued" = (SELECT t."GbpsIssued" FROM "UserTotals" t WHERE t."UserID" = "Users"."UserID")
+    WHERE "UserID" IN (NEW."UserID", NEW."IssuerID");
+END;
+
+CREATE TRIGGER "TR_Gbps_Update" AFTER UPDATE ON "Gbps"
+BEGIN
+    UPDATE "Users" SET
+        "BbpTotal" = (SELECT t."BbpTotal" FROM "UserTotals" t WHERE t."UserID" = "Users"."UserID"),
+        "GbpTotal" = (SELECT t."GbpTotal" FROM "UserTotals" t WHERE t."UserID" = "Users"."UserID"),
+        "Points" = (SELECT t."Points" FROM "UserTotals" t WHERE t."UserID" = "Users"."UserID"),
+        "BbpsIssued" = (SELECT t."BbpsIssued" FROM "UserTotals" t WHERE t."UserID" = "Users"."UserID"),
+        "GbpsIssued" = (SELECT t."GbpsIssued" FROM "UserTotals" t WHERE t."UserID" = "Users"."UserID")
+    WHERE "UserID" IN (NEW."UserID", NEW."IssuerID", OLD."UserID", OLD."IssuerID");
+END;
+
+CREATE TRIGGER "TR_Gbps_Delete" AFTER DELETE ON "Gbps"
+BEGIN
+    UPDATE "Users" SET
+        "BbpTotal" = (SELECT t."BbpTotal" FROM "UserTotals" t WHERE t."UserID" = "Users"."UserID"),
+        "GbpTotal" = (SELECT t."GbpTotal" FROM "UserTotals" t WHERE t."UserID" = "Users"."UserID"),
+        "Points" = (SELECT t."Points" FROM "UserTotals" t WHERE t."UserID" = "Users"."UserID"),
+        "BbpsIssued" = (SELECT t."BbpsIssued" FROM "UserTotals" t WHERE t."UserID" = "Users"."UserID"),
+        "GbpsIssued" = (SELECT t."GbpsIssued" FROM "UserTotals" t WHERE t."UserID" = "Users"."UserID")
+    WHERE "UserID" IN (OLD."UserID", OLD."IssuerID");
+END;
+
+UPDATE "Users" SET
+    "BbpTotal" = (SELECT t."BbpTotal" FROM "UserTotals" t WHERE t."UserID" = "Users"."UserID"),
+    "GbpTotal" = (SELECT t."GbpTotal" FROM "UserTotals" t WHERE t."UserID" = "Users"."UserID");
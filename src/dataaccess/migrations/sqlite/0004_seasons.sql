@@ -0,0 +1,42 @@
+-- SQLite counterpart of postgres/0004_seasons.sql
+CREATE TABLE IF NOT EXISTS "Seasons" (
+    "SeasonID" INTEGER PRIMARY KEY AUTOINCREMENT,
+    "GuildID" INTEGER NOT NULL,
+    "Number" INTEGER NOT NULL,
+    "StartedAt" TEXT NOT NULL,
+    "EndedAt" TEXT
+);
+
+CREATE UNIQUE INDEX IF NOT EXISTS "IX_Seasons_GuildID_Number" ON "Seasons" ("GuildID", "Number");
+CREATE UNIQUE INDEX IF NOT EXISTS "IX_Seasons_GuildID_Running" ON "Seasons" ("GuildID") WHERE "EndedAt" IS NULL;
+
+CREATE TABLE IF NOT EXISTS "SeasonStandings" (
+    "SeasonID" INTEGER NOT NULL REFERENCES "Seasons" ("SeasonID"),
+    "UserID" INTEGER NOT NULL REFERENCES "Users" ("UserID"),
+    "Rank" INTEGER NOT NULL,
+    "Points" INTEGER NOT NULL,
+    "BbpTotal" INTEGER NOT NULL,
+    "GbpTotal" INTEGER NOT NULL,
+    "BbpsIssued" INTEGER NOT NULL,
+    "GbpsIssued" INTEGER NOT NULL,
+    PRIMARY KEY ("SeasonID", "UserID")
+);
+
+ALTER TABLE "Bbps" ADD COLUMN "SeasonID" INTEGER REFERENCES "Seasons" ("SeasonID");
+ALTER TABLE "Gbps" ADD COLUMN "SeasonID" INTEGER REFERENCES "Seasons" ("SeasonID");
+
+-- The triggers look the view up by name, so replacing it is enough
+DROP VIEW IF EXISTS "UserTotals";
+
+CREATE VIEW "UserTotals" AS
+SELECT "UserID", "BbpTotal", "GbpTotal", "BbpTotal" - "GbpTotal" AS "Points", "BbpsIssued", "GbpsIssued"
+FROM (
+    SELECT u."UserID",
+           COALESCE((SELECT SUM(b."Value") FROM "Bbps" b
+                     WHERE b."UserID" = u."UserID" AND b."Forgiven" = 0 AND b."SeasonID" IS NULL), 0) AS "BbpTotal",
+           COALESCE((SELECT SUM(g."Value") FROM "Gbps" g
+                     WHERE g."UserID" = u."UserID" AND g."SeasonID" IS NULL), 0) AS "GbpTotal",
+           (SELECT COUNT(*) FROM "Bbps" b WHERE b."IssuerID" = u."UserID" AND b."SeasonID" IS NULL) AS "BbpsIssued",
+           (SELECT COUNT(*) FROM "Gbps" g WHERE g."IssuerID" = u."UserID" AND g."SeasonID" IS NULL) AS "GbpsIssued"
+    FROM "Users" u
+);
@@ -0,0 +1,11 @@
+-- SQLite counterpart of postgres/0005_guild_settings.sql. SQLite has no power(),
+-- so SqliteService registers decay_weight on its connection instead.
+CREATE TABLE IF NOT EXISTS "GuildSettings" (
+    "GuildID" INTEGER PRIMARY KEY,
+    "DecayMode" TEXT NOT NULL DEFAULT 'off',
+    "DecayDays" INTEGER NOT NULL DEFAULT 0
+);
+
+-- Archived standings keep the score they were ranked by
+ALTER TABLE "SeasonStandings" ADD COLUMN "Score" REAL NOT NULL DEFAULT 0;
+UPDATE "SeasonStandings" SET "Score" = "Points";
@@ -0,0 +1,4 @@
+-- SQLite counterpart of postgres/0006_point_values.sql
+ALTER TABLE "GuildSettings" ADD COLUMN "MaxValue" INTEGER NOT NULL DEFAULT 1;
+ALTER TABLE "GuildSettings" ADD COLUMN "ModeratorMaxValue" INTEGER NOT NULL DEFAULT 3;
+ALTER TABLE "GuildSettings" ADD COLUMN "ModeratorRoleID" INTEGER;
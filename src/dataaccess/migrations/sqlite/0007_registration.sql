@@ -0,0 +1,13 @@
+-- SQLite counterpart of postgres/0007_registration.sql
+ALTER TABLE "GuildSettings" ADD COLUMN "RegistrationPolicy" TEXT NOT NULL DEFAULT 'owner_only';
+ALTER TABLE "GuildSettings" ADD COLUMN "ModeratorChannelID" INTEGER;
+ALTER TABLE "GuildSettings" ADD COLUMN "AutoRegister" INTEGER NOT NULL DEFAULT 0;
+
+CREATE TABLE IF NOT EXISTS "RegistrationRequests" (
+    "GuildID" INTEGER NOT NULL,
+    "DiscordID" INTEGER NOT NULL,
+    "DiscordUsername" TEXT NOT NULL,
+    "FriendlyName" TEXT NOT NULL,
+    "RequestedAt" TEXT NOT NULL,
+    PRIMARY KEY ("GuildID", "DiscordID")
+);
@@ -0,0 +1,2 @@
+-- SQLite counterpart of postgres/0008_member_sync.sql
+ALTER TABLE "GuildSettings" ADD COLUMN "EnrolNewMembers" INTEGER NOT NULL DEFAULT 0;
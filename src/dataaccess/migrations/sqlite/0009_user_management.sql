@@ -0,0 +1,2 @@
+-- SQLite counterpart of postgres/0009_user_management.sql
+ALTER TABLE "Users" ADD COLUMN "Active" INTEGER NOT NULL DEFAULT 1;
@@ -0,0 +1,28 @@
+-- SQLite counterpart of postgres/0010_jury.sql
+ALTER TABLE "GuildSettings" ADD COLUMN "JuryQuorum" INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE "GuildSettings" ADD COLUMN "JuryHours" INTEGER NOT NULL DEFAULT 0;
+
+CREATE TABLE IF NOT EXISTS "PendingBbps" (
+    "PendingID" INTEGER PRIMARY KEY AUTOINCREMENT,
+    "GuildID" INTEGER NOT NULL,
+    "UserID" INTEGER NOT NULL REFERENCES "Users" ("UserID"),
+    "IssuerID" INTEGER NOT NULL REFERENCES "Users" ("UserID"),
+    "Value" INTEGER NOT NULL,
+    "Description" TEXT NOT NULL,
+    "Quorum" INTEGER NOT NULL,
+    "Status" TEXT NOT NULL DEFAULT 'pending',
+    "CreatedAt" TEXT NOT NULL,
+    "ExpiresAt" TEXT NOT NULL,
+    "ChannelID" INTEGER,
+    "MessageID" INTEGER
+);
+
+CREATE INDEX IF NOT EXISTS "IX_PendingBbps_ExpiresAt" ON "PendingBbps" ("ExpiresAt") WHERE "Status" = 'pending';
+
+CREATE TABLE IF NOT EXISTS "PendingBbpVotes" (
+    "PendingID" INTEGER NOT NULL REFERENCES "PendingBbps" ("PendingID"),
+    "VoterID" INTEGER NOT NULL REFERENCES "Users" ("UserID"),
+    "InFavour" INTEGER NOT NULL,
+    "VotedAt" TEXT NOT NULL,
+    PRIMARY KEY ("PendingID", "VoterID")
+);
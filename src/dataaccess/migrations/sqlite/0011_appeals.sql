@@ -0,0 +1,21 @@
+-- SQLite counterpart of postgres/0011_appeals.sql
+CREATE TABLE IF NOT EXISTS "Appeals" (
+    "AppealID" INTEGER PRIMARY KEY AUTOINCREMENT,
+    "BbpID" INTEGER NOT NULL UNIQUE REFERENCES "Bbps" ("BbpID"),
+    "Statement" TEXT NOT NULL,
+    "Status" TEXT NOT NULL DEFAULT 'open',
+    "Quorum" INTEGER NOT NULL,
+    "OpenedAt" TEXT NOT NULL,
+    "ResolvedAt" TEXT,
+    "ResolvedByDiscordID" INTEGER,
+    "ChannelID" INTEGER,
+    "MessageID" INTEGER
+);
+
+CREATE TABLE IF NOT EXISTS "AppealVotes" (
+    "AppealID" INTEGER NOT NULL REFERENCES "Appeals" ("AppealID"),
+    "VoterID" INTEGER NOT NULL REFERENCES "Users" ("UserID"),
+    "Overturn" INTEGER NOT NULL,
+    "VotedAt" TEXT NOT NULL,
+    PRIMARY KEY ("AppealID", "VoterID")
+);
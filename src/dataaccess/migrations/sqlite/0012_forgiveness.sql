@@ -0,0 +1,3 @@
+-- SQLite counterpart of postgres/0012_forgiveness.sql
+ALTER TABLE "Bbps" ADD COLUMN "ForgivenByDiscordID" INTEGER;
+ALTER TABLE "Bbps" ADD COLUMN "ForgivenAt" TEXT;
@@ -0,0 +1,4 @@
+-- SQLite counterpart of postgres/0013_point_links.sql
+ALTER TABLE "PendingBbps" ADD COLUMN "BbpID" INTEGER REFERENCES "Bbps" ("BbpID");
+
+CREATE UNIQUE INDEX IF NOT EXISTS "IX_PendingBbps_BbpID" ON "PendingBbps" ("BbpID") WHERE "BbpID" IS NOT NULL;
@@ -0,0 +1,18 @@
+-- SQLite counterpart of postgres/0014_retractions.sql
+ALTER TABLE "GuildSettings" ADD COLUMN "GraceMinutes" INTEGER NOT NULL DEFAULT 10;
+
+CREATE TABLE IF NOT EXISTS "AuditLog" (
+    "AuditID" INTEGER PRIMARY KEY AUTOINCREMENT,
+    "GuildID" INTEGER NOT NULL,
+    "Action" TEXT NOT NULL,
+    "Kind" TEXT NOT NULL,
+    "PointID" INTEGER NOT NULL,
+    "ActorDiscordID" INTEGER NOT NULL,
+    "TargetDiscordID" INTEGER NOT NULL,
+    "Value" INTEGER NOT NULL,
+    "OldDescription" TEXT NOT NULL,
+    "NewDescription" TEXT,
+    "At" TEXT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS "IX_AuditLog_GuildID_At" ON "AuditLog" ("GuildID", "At" DESC);
@@ -0,0 +1,5 @@
+-- SQLite counterpart of postgres/0015_point_messages.sql
+ALTER TABLE "Bbps" ADD COLUMN "ChannelID" INTEGER;
+ALTER TABLE "Bbps" ADD COLUMN "MessageID" INTEGER;
+ALTER TABLE "Gbps" ADD COLUMN "ChannelID" INTEGER;
+ALTER TABLE "Gbps" ADD COLUMN "MessageID" INTEGER;
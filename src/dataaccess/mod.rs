@@ -1 +1,11 @@
-pub mod postgres_service;
\ No newline at end of file
+pub mod bbp_store;
+pub mod clock;
+pub mod memory_store;
+pub mod migrations;
+pub mod models;
+pub mod postgres_service;
+#[cfg(feature = "sqlite")]
+pub mod sqlite_service;
+#[cfg(test)]
+pub mod store_cases;
+pub mod store_error;
@@ -0,0 +1,752 @@
+#[derive(Debug, Clone)]
+#[allow(dead_code)]
+pub struct User {
+    pub user_id: i32,
+    pub guild_id: i64,
+    pub username: Option<String>,
+    pub discord_username: Option<String>,
+    pub discord_mention: Option<String>,
+    pub discord_id: i64,
+    pub friendly_name: Option<String>,
+    pub points: i32,
+    /// Unforgiven bbps received
+    pub bbp_total: i32,
+    pub gbp_total: i32,
+    pub bbps_issued: i32,
+    pub gbps_issued: i32,
+    pub rank: Option<i64>,
+    /// The net score after decay, set along with `rank`
+    pub score: Option<f64>,
+    /// Deactivated users keep their history but aren't ranked and can't give or get points
+    pub active: bool,
+}
+
+#[derive(Debug, Clone)]
+#[allow(dead_code)]
+pub struct LeaderboardUser {
+    pub user_id: i32,
+    pub discord_username: Option<String>,
+    pub discord_mention: Option<String>,
+    pub discord_id: i64,
+    pub friendly_name: Option<String>,
+    pub points: i32,
+    pub bbp_total: i32,
+    pub gbp_total: i32,
+    pub bbps_issued: i32,
+    pub gbps_issued: i32,
+    pub rank: i64,
+    /// The net score after decay, which is what `rank` orders by
+    pub score: f64,
+}
+
+#[derive(Debug, Clone)]
+#[allow(dead_code)]
+pub  struct HistoryRecord {
+    pub  kind: PointKind,
+    /// The `BbpID` or `GbpID`, depending on `kind`
+    pub  point_id: i32,
+    pub  value: i32,
+    /// Always false for gbps
+    pub  forgiven: bool,
+    pub  issuer_discord_id: i64,
+    pub  issuer_friendly_name: String,
+    pub  description: String,
+    pub  timestamp: chrono::NaiveDateTime,
+    /// Where an appeal of the bbp stands, if it was appealed
+    pub  appeal: Option<AppealStatus>,
+}
+
+/// Career numbers for `/profile`, across every season.
+#[derive(Debug, Clone, Default)]
+pub struct UserStats {
+    /// Bbps received that were forgiven since
+    pub forgiven: i32,
+    /// Whoever gave the user the most bbp points, at most three
+    pub top_issuers: Vec<PointTally>,
+    /// Whoever the user gave the most bbp points
+    pub favourite_target: Option<PointTally>,
+    /// When the user's unforgiven bbps were issued, oldest first
+    pub offences: Vec<chrono::NaiveDateTime>,
+}
+
+/// Unforgiven bbp points between the user and someone else.
+#[derive(Debug, Clone)]
+#[allow(dead_code)]
+pub struct PointTally {
+    pub discord_id: i64,
+    pub friendly_name: Option<String>,
+    pub points: i32,
+}
+
+/// Everything two users gave each other, across every season, for `/versus`.
+#[derive(Debug, Clone, Default)]
+pub struct Rivalry {
+    /// What the first user gave the second
+    pub a_to_b: Exchange,
+    /// What the second user gave the first
+    pub b_to_a: Exchange,
+    /// The latest bbps and gbps between them in either direction, newest first
+    pub recent: Vec<HistoryRecord>,
+}
+
+/// Points one user gave another.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Exchange {
+    /// Bbp points, forgiven ones included
+    pub bbps: i32,
+    /// The part of `bbps` that was forgiven since
+    pub forgiven: i32,
+    pub gbps: i32,
+}
+
+impl Exchange {
+    /// Unforgiven bbp points, the ones that still count.
+    pub fn standing_bbps(&self) -> i32 {
+        self.bbps - self.forgiven
+    }
+
+    /// The share of bbp points that got forgiven, `None` if there were none.
+    pub fn forgiveness_rate(&self) -> Option<f64> {
+        (self.bbps > 0).then(|| self.forgiven as f64 / self.bbps as f64)
+    }
+}
+
+/// Only bbps and gbps timestamped in `[from, to)` count. A `None` bound leaves
+/// that side open. Unless `all_seasons` is set only the running season counts,
+/// so the default gives the same standings as `User.points`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TimeWindow {
+    pub from: Option<chrono::NaiveDateTime>,
+    pub to: Option<chrono::NaiveDateTime>,
+    pub all_seasons: bool,
+}
+
+impl TimeWindow {
+    pub fn contains(&self, timestamp: chrono::NaiveDateTime, season_id: Option<i32>) -> bool {
+        (self.all_seasons || season_id.is_none())
+            && self.from.is_none_or(|from| timestamp >= from)
+            && self.to.is_none_or(|to| timestamp < to)
+    }
+}
+
+/// What a leaderboard ranks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Standings {
+    Live(TimeWindow),
+    /// The snapshot taken when season number `n` ended
+    Season(i32),
+}
+
+#[derive(Debug, Clone)]
+#[allow(dead_code)]
+pub struct Season {
+    pub season_id: i32,
+    pub guild_id: i64,
+    /// Counts up from 1 in every guild
+    pub number: i32,
+    pub started_at: chrono::NaiveDateTime,
+    /// `None` while the season is running
+    pub ended_at: Option<chrono::NaiveDateTime>,
+}
+
+/// A season that was just ended, with the standings it was archived with.
+#[derive(Debug)]
+pub struct ClosedSeason {
+    pub season: Season,
+    /// Active users only, see `BbpStore::end_season`
+    pub standings: Vec<LeaderboardUser>,
+}
+
+impl ClosedSeason {
+    /// The best behaved user, with the lowest score.
+    pub fn champion(&self) -> Option<&LeaderboardUser> {
+        self.standings.iter().min_by(|a, b| a.score.total_cmp(&b.score).then(a.user_id.cmp(&b.user_id)))
+    }
+
+    /// Whoever collected the most bbps.
+    pub fn most_bbps(&self) -> Option<&LeaderboardUser> {
+        self.standings.iter().filter(|u| u.bbp_total > 0).min_by_key(|u| (std::cmp::Reverse(u.bbp_total), u.user_id))
+    }
+}
+
+/// A window into a longer list, e.g. one page of the leaderboard.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Page {
+    pub offset: i64,
+    pub limit: i64,
+}
+
+/// Narrows `get_user_history`. The default returns both kinds from every issuer
+/// across all seasons.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct HistoryFilter {
+    pub kind: Option<PointKind>,
+    pub issuer_discord_id: Option<i64>,
+    /// Only the bbps and gbps archived with this season number
+    pub season: Option<i32>,
+}
+
+/// A user's score: the unforgiven bbps they've received minus their gbps. The
+/// stores keep `User.points` equal to this, see `postgres/queries.sql`.
+pub fn net_score(bbp_total: i32, gbp_total: i32) -> i32 {
+    bbp_total - gbp_total
+}
+
+/// How quickly bbps stop counting towards a guild's scores. Gbps never decay.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DecayPolicy {
+    #[default]
+    Off,
+    /// A bbp counts half as much every `days` days
+    HalfLife { days: i32 },
+    /// A bbp counts fully until it is `days` days old, then not at all
+    Expiry { days: i32 },
+}
+
+impl DecayPolicy {
+    /// Reads the `DecayMode`/`DecayDays` columns. Unknown modes mean no decay.
+    pub fn from_parts(mode: &str, days: i32) -> DecayPolicy {
+        match mode {
+            "half_life" if days > 0 => DecayPolicy::HalfLife { days },
+            "expiry" if days > 0 => DecayPolicy::Expiry { days },
+            _ => DecayPolicy::Off,
+        }
+    }
+
+    pub fn mode(self) -> &'static str {
+        match self {
+            DecayPolicy::Off => "off",
+            DecayPolicy::HalfLife { .. } => "half_life",
+            DecayPolicy::Expiry { .. } => "expiry",
+        }
+    }
+
+    pub fn days(self) -> i32 {
+        match self {
+            DecayPolicy::Off => 0,
+            DecayPolicy::HalfLife { days } | DecayPolicy::Expiry { days } => days,
+        }
+    }
+
+    /// What a bbp issued at `issued_at` is worth at `now`, between 0 and 1. Must
+    /// agree with `decay_weight` in `postgres/0005_guild_settings.sql`.
+    pub fn weight(self, issued_at: chrono::NaiveDateTime, now: chrono::NaiveDateTime) -> f64 {
+        let age = (now - issued_at).max(chrono::Duration::zero());
+
+        // Like the SQL, a policy without days doesn't decay
+        match self {
+            DecayPolicy::Off => 1.0,
+            DecayPolicy::HalfLife { days } | DecayPolicy::Expiry { days } if days <= 0 => 1.0,
+            DecayPolicy::HalfLife { days } => 0.5f64.powf(age.num_seconds() as f64 / (days as f64 * 86400.0)),
+            DecayPolicy::Expiry { days } if age < chrono::Duration::days(days as i64) => 1.0,
+            DecayPolicy::Expiry { .. } => 0.0,
+        }
+    }
+}
+
+/// Per-guild configuration. Guilds that never changed anything get the default.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GuildSettings {
+    pub guild_id: i64,
+    pub decay: DecayPolicy,
+    /// The most a single bbp or gbp can be worth
+    pub max_value: i32,
+    /// Same for members with the moderator role
+    pub moderator_max_value: i32,
+    pub moderator_role_id: Option<i64>,
+    pub registration: RegistrationPolicy,
+    /// Where registration requests are posted for approval
+    pub moderator_channel_id: Option<i64>,
+    /// Register members on their first command, under `RegistrationPolicy::Open`
+    pub auto_register: bool,
+    /// Register members as soon as they join the server, or ask the moderators
+    /// to under `RegistrationPolicy::Approval`
+    pub enrol_new_members: bool,
+    pub jury: JuryPolicy,
+    /// How long issuers can edit or retract what they gave, 0 for not at all
+    pub grace_minutes: i32,
+}
+
+impl Default for GuildSettings {
+    fn default() -> Self {
+        GuildSettings {
+            guild_id: 0,
+            decay: DecayPolicy::Off,
+            max_value: 1,
+            moderator_max_value: 3,
+            moderator_role_id: None,
+            registration: RegistrationPolicy::OwnerOnly,
+            moderator_channel_id: None,
+            auto_register: false,
+            enrol_new_members: false,
+            jury: JuryPolicy::Off,
+            grace_minutes: 10,
+        }
+    }
+}
+
+/// Whether `/bbp` needs the guild to agree before the bbp counts.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum JuryPolicy {
+    /// Bbps are issued right away
+    #[default]
+    Off,
+    /// Bbps wait until `quorum` members vote for them, or against them, within `hours`
+    On { quorum: i32, hours: i32 },
+}
+
+impl JuryPolicy {
+    /// Reads the `JuryQuorum`/`JuryHours` columns. A quorum of 0 means no jury.
+    pub fn from_parts(quorum: i32, hours: i32) -> JuryPolicy {
+        if quorum > 0 && hours > 0 {
+            JuryPolicy::On { quorum, hours }
+        } else {
+            JuryPolicy::Off
+        }
+    }
+
+    pub fn quorum(self) -> i32 {
+        match self {
+            JuryPolicy::Off => 0,
+            JuryPolicy::On { quorum, .. } => quorum,
+        }
+    }
+
+    pub fn hours(self) -> i32 {
+        match self {
+            JuryPolicy::Off => 0,
+            JuryPolicy::On { hours, .. } => hours,
+        }
+    }
+}
+
+/// A `/bbp` put before the jury. It only becomes a bbp once confirmed.
+#[derive(Debug, Clone)]
+#[allow(dead_code)]
+pub struct PendingBbp {
+    pub pending_id: i32,
+    pub guild_id: i64,
+    pub issuer_discord_id: i64,
+    pub target_discord_id: i64,
+    pub value: i32,
+    pub description: String,
+    /// Votes either way it takes to settle it, fixed when it was put up
+    pub quorum: i32,
+    pub votes_for: i32,
+    pub votes_against: i32,
+    pub status: JuryStatus,
+    pub created_at: chrono::NaiveDateTime,
+    pub expires_at: chrono::NaiveDateTime,
+    /// The message with the vote buttons, once it was posted
+    pub channel_id: Option<i64>,
+    pub message_id: Option<i64>,
+    /// The bbp it became, once confirmed
+    pub bbp_id: Option<i32>,
+}
+
+impl PendingBbp {
+    /// What the votes so far add up to. A tie that reached the quorum on both
+    /// sides throws the bbp out.
+    pub fn verdict(&self) -> JuryStatus {
+        if self.votes_against >= self.quorum && self.votes_against >= self.votes_for {
+            JuryStatus::Vetoed
+        } else if self.votes_for >= self.quorum && self.votes_for > self.votes_against {
+            JuryStatus::Confirmed
+        } else {
+            JuryStatus::Pending
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JuryStatus {
+    Pending,
+    /// Issued as a bbp
+    Confirmed,
+    Vetoed,
+    /// Nobody reached the quorum in time
+    Expired,
+}
+
+impl JuryStatus {
+    /// Reads the `Status` column. Unknown values count as expired, so they never get issued.
+    pub fn parse(value: &str) -> JuryStatus {
+        match value {
+            "pending" => JuryStatus::Pending,
+            "confirmed" => JuryStatus::Confirmed,
+            "vetoed" => JuryStatus::Vetoed,
+            _ => JuryStatus::Expired,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            JuryStatus::Pending => "pending",
+            JuryStatus::Confirmed => "confirmed",
+            JuryStatus::Vetoed => "vetoed",
+            JuryStatus::Expired => "expired",
+        }
+    }
+}
+
+/// A vote that was just cast. `issued` is set when it confirmed the bbp.
+#[derive(Debug)]
+pub struct JuryVote {
+    pub pending: PendingBbp,
+    pub issued: Option<IssuedPoint>,
+}
+
+/// A single bbp, for commands that act on one.
+#[derive(Debug, Clone)]
+#[allow(dead_code)]
+pub struct Bbp {
+    pub bbp_id: i32,
+    pub guild_id: i64,
+    pub target_discord_id: i64,
+    pub issuer_discord_id: i64,
+    pub issuer_friendly_name: String,
+    pub value: i32,
+    pub description: String,
+    pub timestamp: chrono::NaiveDateTime,
+    pub forgiven: bool,
+    /// Who forgave it, `None` when it isn't forgiven, was overturned by vote or
+    /// was forgiven before this was recorded
+    pub forgiven_by_discord_id: Option<i64>,
+    pub forgiven_at: Option<chrono::NaiveDateTime>,
+    /// Set once the season it was given in ended
+    pub season_id: Option<i32>,
+}
+
+/// Everything about one bbp/gbp, for `/show`.
+#[derive(Debug, Clone)]
+#[allow(dead_code)]
+pub struct PointDetails {
+    pub kind: PointKind,
+    pub point_id: i32,
+    pub guild_id: i64,
+    pub target_discord_id: i64,
+    pub target_friendly_name: String,
+    pub issuer_discord_id: i64,
+    pub issuer_friendly_name: String,
+    pub value: i32,
+    pub description: String,
+    pub timestamp: chrono::NaiveDateTime,
+    /// Always false for gbps, like the forgiveness fields below
+    pub forgiven: bool,
+    pub forgiven_by_discord_id: Option<i64>,
+    pub forgiven_at: Option<chrono::NaiveDateTime>,
+    /// Set once the season it was given in ended
+    pub season_id: Option<i32>,
+    /// The reply that announced it, if it was remembered
+    pub channel_id: Option<i64>,
+    pub message_id: Option<i64>,
+    /// The jury vote that confirmed the bbp, if it went before one
+    pub jury: Option<PendingBbp>,
+    pub appeal: Option<Appeal>,
+}
+
+/// The target contesting a bbp. Overturning it forgives the bbp.
+#[derive(Debug, Clone)]
+#[allow(dead_code)]
+pub struct Appeal {
+    pub appeal_id: i32,
+    pub bbp: Bbp,
+    pub statement: String,
+    pub status: AppealStatus,
+    /// Votes either way it takes the community to settle it, 0 when only moderators decide
+    pub quorum: i32,
+    pub votes_overturn: i32,
+    pub votes_uphold: i32,
+    pub opened_at: chrono::NaiveDateTime,
+    pub resolved_at: Option<chrono::NaiveDateTime>,
+    /// The moderator who decided it, `None` while open or when the community did
+    pub resolved_by_discord_id: Option<i64>,
+    /// The message with the vote buttons, once it was posted
+    pub channel_id: Option<i64>,
+    pub message_id: Option<i64>,
+}
+
+impl Appeal {
+    /// What the community votes so far add up to. A tie that reached the quorum
+    /// on both sides lets the bbp stand.
+    pub fn verdict(&self) -> AppealStatus {
+        if self.quorum <= 0 {
+            AppealStatus::Open
+        } else if self.votes_overturn >= self.quorum && self.votes_overturn > self.votes_uphold {
+            AppealStatus::Overturned
+        } else if self.votes_uphold >= self.quorum && self.votes_uphold >= self.votes_overturn {
+            AppealStatus::Upheld
+        } else {
+            AppealStatus::Open
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AppealStatus {
+    Open,
+    /// The bbp stands
+    Upheld,
+    /// The bbp was forgiven
+    Overturned,
+}
+
+impl AppealStatus {
+    /// Reads the `Status` column. Unknown values count as upheld, so they never forgive anything.
+    pub fn parse(value: &str) -> AppealStatus {
+        match value {
+            "open" => AppealStatus::Open,
+            "overturned" => AppealStatus::Overturned,
+            _ => AppealStatus::Upheld,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            AppealStatus::Open => "open",
+            AppealStatus::Upheld => "upheld",
+            AppealStatus::Overturned => "overturned",
+        }
+    }
+}
+
+/// An issuer changing their mind about a bbp/gbp, kept in the audit log.
+#[derive(Debug, Clone)]
+#[allow(dead_code)]
+pub struct AuditEntry {
+    pub audit_id: i32,
+    pub guild_id: i64,
+    pub action: AuditAction,
+    pub kind: PointKind,
+    pub point_id: i32,
+    /// The issuer, who is the only one allowed to edit or retract
+    pub actor_discord_id: i64,
+    pub target_discord_id: i64,
+    pub value: i32,
+    pub old_description: String,
+    /// `None` for retractions
+    pub new_description: Option<String>,
+    pub at: chrono::NaiveDateTime,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AuditAction {
+    /// The description was corrected
+    Edit,
+    /// The bbp/gbp was taken back and no longer exists
+    Retract,
+}
+
+impl AuditAction {
+    /// Reads the `Action` column. Unknown values count as edits, which changed nothing that counts.
+    pub fn parse(value: &str) -> AuditAction {
+        match value {
+            "retract" => AuditAction::Retract,
+            _ => AuditAction::Edit,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            AuditAction::Edit => "edit",
+            AuditAction::Retract => "retract",
+        }
+    }
+}
+
+/// Who gets to register with `/join`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum RegistrationPolicy {
+    /// Anyone is registered right away
+    Open,
+    /// A moderator approves every request first
+    Approval,
+    /// Only owners add users, with `/add-user`
+    #[default]
+    OwnerOnly,
+}
+
+impl RegistrationPolicy {
+    /// Reads the `RegistrationPolicy` column. Unknown values keep registration closed.
+    pub fn parse(value: &str) -> RegistrationPolicy {
+        match value {
+            "open" => RegistrationPolicy::Open,
+            "approval" => RegistrationPolicy::Approval,
+            _ => RegistrationPolicy::OwnerOnly,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            RegistrationPolicy::Open => "open",
+            RegistrationPolicy::Approval => "approval",
+            RegistrationPolicy::OwnerOnly => "owner_only",
+        }
+    }
+}
+
+/// A `/join` waiting for a moderator.
+#[derive(Debug, Clone)]
+#[allow(dead_code)]
+pub struct RegistrationRequest {
+    pub guild_id: i64,
+    pub discord_id: i64,
+    pub discord_username: String,
+    pub friendly_name: String,
+    pub requested_at: chrono::NaiveDateTime,
+}
+
+impl GuildSettings {
+    /// Whether someone with these roles has the moderator role.
+    pub fn is_moderator(&self, role_ids: &[i64]) -> bool {
+        self.moderator_role_id.is_some_and(|role| role_ids.contains(&role))
+    }
+
+    pub fn max_value_for(&self, moderator: bool) -> i32 {
+        if moderator {
+            self.moderator_max_value.max(self.max_value)
+        } else {
+            self.max_value
+        }
+    }
+
+    /// How long after giving a bbp/gbp its issuer can still edit or retract it.
+    pub fn grace_period(&self) -> chrono::Duration {
+        chrono::Duration::minutes(self.grace_minutes.max(0) as i64)
+    }
+}
+
+impl PartialEq for User {
+    fn eq(&self, other: &Self) -> bool {
+        self.user_id == other.user_id
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PointKind {
+    Bbp,
+    Gbp,
+}
+
+impl PointKind {
+    /// The name used in queries and replies
+    pub fn as_str(self) -> &'static str {
+        match self {
+            PointKind::Bbp => "bbp",
+            PointKind::Gbp => "gbp",
+        }
+    }
+
+    pub fn parse(kind: &str) -> Option<PointKind> {
+        match kind {
+            "bbp" => Some(PointKind::Bbp),
+            "gbp" => Some(PointKind::Gbp),
+            _ => None,
+        }
+    }
+
+    /// How people refer to a bbp/gbp, e.g. "B12" or "G7"
+    pub fn public_id(self, point_id: i32) -> String {
+        match self {
+            PointKind::Bbp => format!("B{}", point_id),
+            PointKind::Gbp => format!("G{}", point_id),
+        }
+    }
+
+    /// Reads back a `public_id`, in either case.
+    pub fn parse_public_id(public_id: &str) -> Option<(PointKind, i32)> {
+        let public_id = public_id.trim();
+        let kind = match public_id.chars().next()? {
+            'B' | 'b' => PointKind::Bbp,
+            'G' | 'g' => PointKind::Gbp,
+            _ => return None,
+        };
+        // Only digits, `parse` alone would also take a sign
+        let digits = &public_id[1..];
+        if !digits.bytes().all(|b| b.is_ascii_digit()) {
+            return None;
+        }
+        let point_id = digits.parse().ok().filter(|id| *id > 0)?;
+
+        Some((kind, point_id))
+    }
+}
+
+/// A bbp/gbp that was just issued. `ranked` is the target's standing as seen by
+/// the same transaction that inserted the point.
+#[derive(Debug)]
+pub struct IssuedPoint {
+    /// The `BbpID` or `GbpID` it got
+    pub point_id: i32,
+    pub issuer: User,
+    pub target: User,
+    pub ranked: User,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(day: u32, hour: u32) -> chrono::NaiveDateTime {
+        chrono::NaiveDate::from_ymd_opt(2024, 5, day).unwrap().and_hms_opt(hour, 0, 0).unwrap()
+    }
+
+    #[test]
+    fn half_life_halves_the_weight_every_period() {
+        let decay = DecayPolicy::HalfLife { days: 2 };
+
+        assert_eq!(decay.weight(at(1, 0), at(1, 0)), 1.0);
+        assert_eq!(decay.weight(at(1, 0), at(3, 0)), 0.5);
+        assert_eq!(decay.weight(at(1, 0), at(5, 0)), 0.25);
+        assert!((decay.weight(at(1, 0), at(2, 0)) - 0.5f64.sqrt()).abs() < 1e-9);
+    }
+
+    #[test]
+    fn expiry_drops_the_weight_at_exactly_the_age() {
+        let decay = DecayPolicy::Expiry { days: 3 };
+
+        assert_eq!(decay.weight(at(1, 0), at(1, 0)), 1.0);
+        assert_eq!(decay.weight(at(1, 0), at(3, 23)), 1.0);
+        assert_eq!(decay.weight(at(1, 0), at(4, 0)), 0.0);
+        assert_eq!(decay.weight(at(1, 0), at(20, 0)), 0.0);
+    }
+
+    #[test]
+    fn policies_without_days_never_decay() {
+        for decay in [DecayPolicy::Off, DecayPolicy::HalfLife { days: 0 }, DecayPolicy::Expiry { days: 0 }] {
+            assert_eq!(decay.weight(at(1, 0), at(1, 0)), 1.0, "{:?}", decay);
+            assert_eq!(decay.weight(at(1, 0), at(30, 0)), 1.0, "{:?}", decay);
+        }
+    }
+
+    #[test]
+    fn bbps_from_the_future_count_fully() {
+        assert_eq!(DecayPolicy::HalfLife { days: 1 }.weight(at(2, 0), at(1, 0)), 1.0);
+        assert_eq!(DecayPolicy::Expiry { days: 1 }.weight(at(2, 0), at(1, 0)), 1.0);
+    }
+
+    #[test]
+    fn decay_columns_round_trip() {
+        for decay in [DecayPolicy::Off, DecayPolicy::HalfLife { days: 30 }, DecayPolicy::Expiry { days: 7 }] {
+            assert_eq!(DecayPolicy::from_parts(decay.mode(), decay.days()), decay);
+        }
+        assert_eq!(DecayPolicy::from_parts("half_life", 0), DecayPolicy::Off);
+    }
+
+    #[test]
+    fn net_score_of_nothing_is_zero() {
+        assert_eq!(net_score(0, 0), 0);
+        assert_eq!(net_score(4, 4), 0);
+    }
+
+    #[test]
+    fn net_score_is_positive_with_more_bbps() {
+        assert_eq!(net_score(5, 2), 3);
+    }
+
+    #[test]
+    fn net_score_is_negative_with_more_gbps() {
+        assert_eq!(net_score(1, 3), -2);
+        assert_eq!(net_score(0, 2), -2);
+    }
+}
@@ -1,62 +1,27 @@
-use std::{sync::Arc, time::Duration};
+use std::sync::Arc;
+use std::time::Duration;
 
+use async_trait::async_trait;
 use bb8_postgres::{PostgresConnectionManager, bb8::Pool};
-use serenity::prelude::TypeMapKey;
-use tokio::sync::Mutex;
-use tokio_postgres::Error;
+use log::{debug, info};
 use openssl::ssl::{SslConnector, SslMethod};
 use postgres_openssl::MakeTlsConnector;
+use tokio_postgres::types::ToSql;
 
+use crate::dataaccess::bbp_store::BbpStore;
+use crate::dataaccess::clock::{Clock, SystemClock};
+use crate::dataaccess::migrations;
+use crate::dataaccess::models::{Appeal, AppealStatus, AuditAction, AuditEntry, Bbp, ClosedSeason, DecayPolicy, Exchange, GuildSettings, HistoryFilter, HistoryRecord, IssuedPoint, JuryPolicy, JuryStatus, JuryVote, LeaderboardUser, Page, PendingBbp, PointDetails, PointKind, PointTally, RegistrationPolicy, RegistrationRequest, Rivalry, Season, Standings, TimeWindow, User, UserStats};
+use crate::dataaccess::store_error::{Existing, StoreError, UserRole};
+
+#[derive(Clone)]
 pub struct PostgresService {
     pub pool: Pool<PostgresConnectionManager<MakeTlsConnector>>,
-}
-
-#[derive(Debug)]
-pub struct User {
-    pub user_id: i32,
-    pub username: Option<String>,
-    pub discord_username: Option<String>,
-    pub discord_mention: Option<String>,
-    pub discord_id: i64,
-    pub friendly_name: Option<String>,
-    pub points: i32,
-    pub bbps_issued: i32,
-    pub gbps_issued: i32,
-    pub rank: Option<i64>
-}
-
-#[derive(Debug)]
-pub struct LeaderboardUser {
-    pub user_id: i32,
-    pub discord_username: Option<String>,
-    pub discord_mention: Option<String>,
-    pub discord_id: i64,
-    pub friendly_name: Option<String>,
-    pub points: i32,
-    pub bbps_issued: i32,
-    pub gbps_issued: i32,
-    pub rank: i64
-}
-
-#[derive(Debug)]
-pub  struct HistoryRecord {
-    pub  issuer_friendly_name: String,
-    pub  description: String,
-    pub  timestamp: chrono::NaiveDateTime,
-}
-
-impl PartialEq for User {
-    fn eq(&self, other: &Self) -> bool {
-        self.user_id == other.user_id
-    }
-}
-
-impl TypeMapKey for PostgresService {
-    type Value = Arc<Mutex<PostgresService>>;
+    clock: Arc<dyn Clock>,
 }
 
 impl PostgresService {
-    pub async fn new(conn_str: &str) -> Result<PostgresService, Error> {
+    pub async fn new(conn_str: &str) -> Result<PostgresService, Box<dyn std::error::Error + Send + Sync>> {
         let mut builder = SslConnector::builder(SslMethod::tls()).unwrap();
         builder.set_verify(openssl::ssl::SslVerifyMode::NONE);
 
@@ -68,199 +33,1483 @@ impl PostgresService {
             .build(manager)
             .await?;
 
-        Ok(PostgresService { pool })
+        let mut conn = pool.get().await?;
+        let applied = migrations::run_postgres_migrations(&mut conn).await?;
+        if !applied.is_empty() {
+            info!("Applied database migrations {:?}", applied);
+        }
+        drop(conn);
+
+        Ok(PostgresService { pool, clock: Arc::new(SystemClock) })
+    }
+
+    pub fn with_clock(self, clock: Arc<dyn Clock>) -> PostgresService {
+        PostgresService { clock, ..self }
+    }
+
+    fn handle_query_result(rows: &[tokio_postgres::Row]) -> Result<Option<User>, StoreError> {
+        match rows.len() {
+            0 => Ok(None),
+            1 => {
+                let user = PostgresService::row_to_user(&rows[0]);
+                Ok(Some(user))
+            },
+            _ => Err(StoreError::Integrity("Multiple users found for a single Discord mention".to_string())),
+        }
+    }
+
+    fn row_to_user(row: &tokio_postgres::Row) -> User {
+        User {
+            user_id: row.get("UserID"),
+            guild_id: row.get("GuildID"),
+            username: None, // This columns isnt currently used
+            discord_username: row.try_get("DiscordUsername").ok(),
+            discord_mention: row.try_get("DiscordMention").ok(),
+            discord_id: row.get("DiscordID"), 
+            friendly_name: row.try_get("FriendlyName").ok(),
+            points: row.get("Points"),
+            bbp_total: row.get("BbpTotal"),
+            gbp_total: row.get("GbpTotal"),
+            bbps_issued: row.get("BbpsIssued"),
+            gbps_issued: row.get("GbpsIssued"),
+            rank: row.try_get("Rank").ok(),
+            score: row.try_get("Score").ok(),
+            active: row.get("Active"),
+        }
+    }
+
+    fn row_to_leaderboard_user(row: &tokio_postgres::Row) -> LeaderboardUser {
+        LeaderboardUser {
+            user_id: row.get("UserID"),
+            discord_username: row.try_get("DiscordUsername").ok(),
+            discord_mention: row.try_get("DiscordMention").ok(),
+            discord_id: row.get("DiscordID"),
+            friendly_name: row.try_get("FriendlyName").ok(),
+            points: row.get("Points"),
+            bbp_total: row.get("BbpTotal"),
+            gbp_total: row.get("GbpTotal"),
+            bbps_issued: row.get("BbpsIssued"),
+            gbps_issued: row.get("GbpsIssued"),
+            rank: row.get("Rank"),
+            score: row.get("Score"),
+        }
+    }
+
+    fn row_to_season(row: &tokio_postgres::Row) -> Season {
+        Season {
+            season_id: row.get("SeasonID"),
+            guild_id: row.get("GuildID"),
+            number: row.get("Number"),
+            started_at: row.get("StartedAt"),
+            ended_at: row.get("EndedAt"),
+        }
+    }
+
+    fn row_to_history_record(row: &tokio_postgres::Row) -> Result<HistoryRecord, StoreError> {
+        let kind: &str = row.get("Kind");
+        Ok(HistoryRecord {
+            kind: PointKind::parse(kind).ok_or_else(|| StoreError::Integrity(format!("Unknown point kind '{}'", kind)))?,
+            point_id: row.get("PointID"),
+            value: row.get("Value"),
+            forgiven: row.get("Forgiven"),
+            issuer_discord_id: row.get("IssuerDiscordID"),
+            issuer_friendly_name: row.get::<_, Option<String>>("FriendlyName").unwrap_or_default(),
+            description: row.get("Description"),
+            timestamp: row.get("Timestamp"),
+            appeal: row.get::<_, Option<&str>>("AppealStatus").map(AppealStatus::parse),
+        })
+    }
+
+    fn row_to_audit_entry(row: &tokio_postgres::Row) -> Result<AuditEntry, StoreError> {
+        let kind: &str = row.get("Kind");
+        Ok(AuditEntry {
+            audit_id: row.get("AuditID"),
+            guild_id: row.get("GuildID"),
+            action: AuditAction::parse(row.get("Action")),
+            kind: PointKind::parse(kind).ok_or_else(|| StoreError::Integrity(format!("Unknown point kind '{}'", kind)))?,
+            point_id: row.get("PointID"),
+            actor_discord_id: row.get("ActorDiscordID"),
+            target_discord_id: row.get("TargetDiscordID"),
+            value: row.get("Value"),
+            old_description: row.get("OldDescription"),
+            new_description: row.get("NewDescription"),
+            at: row.get("At"),
+        })
+    }
+
+    fn row_to_exchange(row: &tokio_postgres::Row) -> Exchange {
+        Exchange {
+            bbps: row.get("Bbps"),
+            forgiven: row.get("Forgiven"),
+            gbps: row.get("Gbps"),
+        }
+    }
+
+    fn row_to_point_tally(row: &tokio_postgres::Row) -> PointTally {
+        PointTally {
+            discord_id: row.get("DiscordID"),
+            friendly_name: row.try_get("FriendlyName").ok(),
+            points: row.get("Points"),
+        }
+    }
+
+    fn row_to_guild_settings(row: &tokio_postgres::Row) -> GuildSettings {
+        GuildSettings {
+            guild_id: row.get("GuildID"),
+            decay: DecayPolicy::from_parts(row.get("DecayMode"), row.get("DecayDays")),
+            max_value: row.get("MaxValue"),
+            moderator_max_value: row.get("ModeratorMaxValue"),
+            moderator_role_id: row.get("ModeratorRoleID"),
+            registration: RegistrationPolicy::parse(row.get("RegistrationPolicy")),
+            moderator_channel_id: row.get("ModeratorChannelID"),
+            auto_register: row.get("AutoRegister"),
+            enrol_new_members: row.get("EnrolNewMembers"),
+            jury: JuryPolicy::from_parts(row.get("JuryQuorum"), row.get("JuryHours")),
+            grace_minutes: row.get("GraceMinutes"),
+        }
+    }
+
+    fn row_to_pending_bbp(row: &tokio_postgres::Row) -> PendingBbp {
+        PendingBbp {
+            pending_id: row.get("PendingID"),
+            guild_id: row.get("GuildID"),
+            issuer_discord_id: row.get("IssuerDiscordID"),
+            target_discord_id: row.get("TargetDiscordID"),
+            value: row.get("Value"),
+            description: row.get("Description"),
+            quorum: row.get("Quorum"),
+            votes_for: row.get("VotesFor"),
+            votes_against: row.get("VotesAgainst"),
+            status: JuryStatus::parse(row.get("Status")),
+            created_at: row.get("CreatedAt"),
+            expires_at: row.get("ExpiresAt"),
+            channel_id: row.get("ChannelID"),
+            message_id: row.get("MessageID"),
+            bbp_id: row.get("BbpID"),
+        }
+    }
+
+    fn row_to_bbp(row: &tokio_postgres::Row) -> Bbp {
+        Bbp {
+            bbp_id: row.get("BbpID"),
+            guild_id: row.get("GuildID"),
+            target_discord_id: row.get("TargetDiscordID"),
+            issuer_discord_id: row.get("IssuerDiscordID"),
+            issuer_friendly_name: row.get::<_, Option<String>>("IssuerFriendlyName").unwrap_or_default(),
+            value: row.get("Value"),
+            description: row.get("Description"),
+            timestamp: row.get("Timestamp"),
+            forgiven: row.get("Forgiven"),
+            forgiven_by_discord_id: row.get("ForgivenByDiscordID"),
+            forgiven_at: row.get("ForgivenAt"),
+            season_id: row.get("SeasonID"),
+        }
+    }
+
+    fn row_to_appeal(row: &tokio_postgres::Row) -> Appeal {
+        Appeal {
+            appeal_id: row.get("AppealID"),
+            bbp: Self::row_to_bbp(row),
+            statement: row.get("Statement"),
+            status: AppealStatus::parse(row.get("Status")),
+            quorum: row.get("Quorum"),
+            votes_overturn: row.get("VotesOverturn"),
+            votes_uphold: row.get("VotesUphold"),
+            opened_at: row.get("OpenedAt"),
+            resolved_at: row.get("ResolvedAt"),
+            resolved_by_discord_id: row.get("ResolvedByDiscordID"),
+            channel_id: row.get("ChannelID"),
+            message_id: row.get("MessageID"),
+        }
+    }
+
+    fn row_to_registration_request(row: &tokio_postgres::Row) -> RegistrationRequest {
+        RegistrationRequest {
+            guild_id: row.get("GuildID"),
+            discord_id: row.get("DiscordID"),
+            discord_username: row.get("DiscordUsername"),
+            friendly_name: row.get("FriendlyName"),
+            requested_at: row.get("RequestedAt"),
+        }
+    }
+
+    // A running season has no snapshot yet, its standings are the live ones.
+    async fn resolve_standings(conn: &tokio_postgres::Client, guild_id: i64, standings: Standings, now: chrono::NaiveDateTime) -> Result<StandingsSource, StoreError> {
+        match standings {
+            Standings::Live(window) => Ok(StandingsSource::Live(window, now)),
+            Standings::Season(number) => {
+                let season = conn
+                    .query_opt("SELECT * FROM public.\"Seasons\" WHERE \"GuildID\" = $1 AND \"Number\" = $2", &[&guild_id, &number])
+                    .await?
+                    .map(|row| Self::row_to_season(&row))
+                    .ok_or(StoreError::SeasonNotFound(number))?;
+
+                Ok(match season.ended_at {
+                    Some(_) => StandingsSource::Snapshot(season.season_id),
+                    None => StandingsSource::Live(TimeWindow::default(), now),
+                })
+            }
+        }
+    }
+
+    // Locks the appeal so concurrent votes take turns, and reads it. Settled
+    // appeals and those of archived bbps are closed.
+    async fn lock_open_appeal(tx: &tokio_postgres::Transaction<'_>, appeal_id: i32) -> Result<Appeal, StoreError> {
+        tx.query_opt("SELECT 1 FROM public.\"Appeals\" WHERE \"AppealID\" = $1 FOR UPDATE", &[&appeal_id]).await?;
+        let appeal = tx
+            .query_opt(&format!("{} WHERE a.\"AppealID\" = $1", APPEALS), &[&appeal_id])
+            .await?
+            .map(|row| Self::row_to_appeal(&row))
+            .ok_or(StoreError::VotingClosed)?;
+        if appeal.status != AppealStatus::Open || appeal.bbp.season_id.is_some() {
+            return Err(StoreError::VotingClosed);
+        }
+
+        Ok(appeal)
+    }
+
+    // Closes the appeal, forgiving the bbp if it was overturned. The points
+    // trigger recomputes the target's totals.
+    async fn settle_appeal(tx: &tokio_postgres::Transaction<'_>, appeal: &mut Appeal, status: AppealStatus, resolved_by: Option<i64>, now: chrono::NaiveDateTime) -> Result<(), StoreError> {
+        tx.execute(
+                "UPDATE public.\"Appeals\" SET \"Status\" = $2, \"ResolvedAt\" = $3, \"ResolvedByDiscordID\" = $4 WHERE \"AppealID\" = $1",
+                &[&appeal.appeal_id, &status.as_str(), &now, &resolved_by])
+            .await?;
+        if status == AppealStatus::Overturned {
+            tx.execute(
+                    "UPDATE public.\"Bbps\" SET \"Forgiven\" = true, \"ForgivenByDiscordID\" = $2, \"ForgivenAt\" = $3 WHERE \"BbpID\" = $1",
+                    &[&appeal.bbp.bbp_id, &resolved_by, &now])
+                .await?;
+            appeal.bbp.forgiven = true;
+            appeal.bbp.forgiven_by_discord_id = resolved_by;
+            appeal.bbp.forgiven_at = Some(now);
+        }
+
+        appeal.status = status;
+        appeal.resolved_at = Some(now);
+        appeal.resolved_by_discord_id = resolved_by;
+        Ok(())
+    }
+
+    // `Some(forgiver)` forgives the picked bbp, `None` unforgives it. The points
+    // trigger recomputes the target's totals.
+    async fn change_forgiven(&self, guild_id: i64, target_discord_id: i64, issuer_discord_id: Option<i64>, bbp_id: Option<i32>, forgiver_discord_id: Option<i64>) -> Result<Option<Bbp>, StoreError> {
+        let conn = self.pool.get().await?;
+        let forgiven = forgiver_discord_id.is_none();
+        let forgiven_at = forgiver_discord_id.map(|_| self.clock.now());
+
+        let changed = conn
+            .query_opt(
+                &format!(
+                    "UPDATE public.\"Bbps\"
+                     SET \"Forgiven\" = NOT $4, \"ForgivenByDiscordID\" = $6, \"ForgivenAt\" = $7
+                     WHERE \"Forgiven\" = $4 AND \"BbpID\" = (
+                         SELECT b.\"BbpID\"
+                         FROM public.\"Bbps\" b
+                         JOIN public.\"Users\" t ON t.\"UserID\" = b.\"UserID\"
+                         JOIN public.\"Users\" i ON i.\"UserID\" = b.\"IssuerID\"
+                         WHERE {} AND ($5::INTEGER IS NULL OR b.\"BbpID\" = $5)
+                         ORDER BY b.\"Timestamp\" DESC, b.\"BbpID\" DESC
+                         LIMIT 1
+                     )
+                     RETURNING \"BbpID\"",
+                    RECENT_BBPS),
+                &[&guild_id, &target_discord_id, &issuer_discord_id, &forgiven, &bbp_id, &forgiver_discord_id, &forgiven_at])
+            .await?;
+        let Some(changed) = changed else {
+            return Ok(None);
+        };
+
+        let row = conn
+            .query_one(&format!("{} WHERE b.\"BbpID\" = $1", BBPS), &[&changed.get::<_, i32>("BbpID")])
+            .await?;
+
+        Ok(Some(Self::row_to_bbp(&row)))
+    }
+
+    // Edits the point when given a description and retracts it otherwise, then
+    // logs what happened in the same transaction. Deleting it lets the points
+    // trigger take it off both users' totals.
+    async fn change_own_point(&self, guild_id: i64, kind: PointKind, point_id: i32, issuer_discord_id: i64, description: Option<&str>, grace: chrono::Duration) -> Result<AuditEntry, StoreError> {
+        let now = self.clock.now();
+        let mut conn = self.pool.get().await?;
+        let tx = conn.transaction().await?;
+
+        let (table, id_column, contested) = match kind {
+            PointKind::Bbp => ("Bbps", "BbpID",
+                               "EXISTS (SELECT 1 FROM public.\"PendingBbps\" j WHERE j.\"BbpID\" = p.\"BbpID\")
+                                OR EXISTS (SELECT 1 FROM public.\"Appeals\" a WHERE a.\"BbpID\" = p.\"BbpID\")"),
+            PointKind::Gbp => ("Gbps", "GbpID", "false"),
+        };
+        let point = tx
+            .query_opt(
+                &format!(
+                    "SELECT p.*, t.\"DiscordID\" AS \"TargetDiscordID\", i.\"DiscordID\" AS \"IssuerDiscordID\", {} AS \"Contested\"
+                     FROM public.\"{}\" p
+                     JOIN public.\"Users\" t ON t.\"UserID\" = p.\"UserID\"
+                     JOIN public.\"Users\" i ON i.\"UserID\" = p.\"IssuerID\"
+                     WHERE p.\"{}\" = $1 AND p.\"GuildID\" = $2
+                     FOR UPDATE OF p",
+                    contested, table, id_column),
+                &[&point_id, &guild_id])
+            .await?
+            .ok_or(StoreError::PointNotFound)?;
+        let target_discord_id: i64 = point.get("TargetDiscordID");
+        if point.get::<_, i64>("IssuerDiscordID") != issuer_discord_id || target_discord_id == issuer_discord_id
+            || point.get::<_, bool>("Contested") || point.get::<_, Option<i32>>("SeasonID").is_some() {
+            return Err(StoreError::NotEditable);
+        }
+        if now - point.get::<_, chrono::NaiveDateTime>("Timestamp") >= grace {
+            return Err(StoreError::GracePeriodOver { minutes: grace.num_minutes() as i32 });
+        }
+
+        let action = match description {
+            Some(description) => {
+                tx.execute(&format!("UPDATE public.\"{}\" SET \"Description\" = $1 WHERE \"{}\" = $2", table, id_column), &[&description, &point_id]).await?;
+                AuditAction::Edit
+            }
+            None => {
+                tx.execute(&format!("DELETE FROM public.\"{}\" WHERE \"{}\" = $1", table, id_column), &[&point_id]).await?;
+                AuditAction::Retract
+            }
+        };
+        let row = tx
+            .query_one(
+                "INSERT INTO public.\"AuditLog\" (\"GuildID\", \"Action\", \"Kind\", \"PointID\", \"ActorDiscordID\", \"TargetDiscordID\",
+                                                \"Value\", \"OldDescription\", \"NewDescription\", \"At\")
+                 VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
+                 RETURNING *",
+                &[&guild_id, &action.as_str(), &kind.as_str(), &point_id, &issuer_discord_id, &target_discord_id,
+                  &point.get::<_, i32>("Value"), &point.get::<_, String>("Description"), &description, &now])
+            .await?;
+
+        tx.commit().await?;
+        Self::row_to_audit_entry(&row)
+    }
+
+    // Locking both rows (in UserID order to avoid deadlocks) makes concurrent
+    // issuances against the same users wait, so the points trigger always sees
+    // every committed point.
+    async fn lock_users(tx: &tokio_postgres::Transaction<'_>, guild_id: i64, issuer_discord_id: i64, target_discord_id: i64) -> Result<(User, User), StoreError> {
+        let rows = tx
+            .query(
+                "SELECT * FROM public.\"Users\"
+                 WHERE \"GuildID\" = $1 AND \"DiscordID\" IN ($2, $3)
+                 ORDER BY \"UserID\"
+                 FOR UPDATE",
+                &[&guild_id, &issuer_discord_id, &target_discord_id])
+            .await?;
+        let users: Vec<User> = rows.iter().map(PostgresService::row_to_user).collect();
+
+        let issuer = match users.iter().find(|u| u.discord_id == issuer_discord_id) {
+            Some(user) => user.clone(),
+            None => return Err(StoreError::UserNotFound(UserRole::Issuer)),
+        };
+        let target = match users.iter().find(|u| u.discord_id == target_discord_id) {
+            Some(user) => user.clone(),
+            None => return Err(StoreError::UserNotFound(UserRole::Target)),
+        };
+        if !issuer.active {
+            return Err(StoreError::UserInactive(UserRole::Issuer));
+        }
+        if !target.active {
+            return Err(StoreError::UserInactive(UserRole::Target));
+        }
+
+        Ok((issuer, target))
+    }
+
+    // Inserts the point and reads back the target's live rank. Expects lock_users
+    // to have locked both users.
+    #[allow(clippy::too_many_arguments)]
+    async fn insert_point(tx: &tokio_postgres::Transaction<'_>, guild_id: i64, kind: PointKind, value: i32, issuer: User, target: User, description: &str, timestamp: chrono::NaiveDateTime) -> Result<IssuedPoint, StoreError> {
+        let insert = match kind {
+            PointKind::Bbp => "INSERT INTO public.\"Bbps\" (\"GuildID\", \"UserID\", \"Value\", \"Description\", \"Timestamp\", \"IssuerID\") VALUES ($1, $2, $6, $3, $4, $5) RETURNING \"BbpID\"",
+            PointKind::Gbp => "INSERT INTO public.\"Gbps\" (\"GuildID\", \"UserID\", \"Value\", \"Description\", \"Timestamp\", \"IssuerID\") VALUES ($1, $2, $6, $3, $4, $5) RETURNING \"GbpID\"",
+        };
+        let point_id: i32 = tx.query_one(insert, &[&guild_id, &target.user_id, &description, &timestamp, &issuer.user_id, &value]).await?.get(0);
+
+        let running = StandingsSource::Live(TimeWindow::default(), timestamp);
+        let (_, mut params) = running.query(&guild_id);
+        params.push(&target.discord_id);
+        let rows = tx.query(&ranked_user_query(), &params).await?;
+        let ranked = match Self::handle_query_result(&rows)? {
+            Some(user) => user,
+            None => return Err(StoreError::Integrity("Ranked user not found".to_string())),
+        };
+
+        Ok(IssuedPoint { point_id, issuer, target, ranked })
     }
+}
+
+enum StandingsSource {
+    /// Scored as of the given time
+    Live(TimeWindow, chrono::NaiveDateTime),
+    Snapshot(i32),
+}
+
+impl StandingsSource {
+    /// The ranked users query and its parameters. Callers number their own
+    /// parameters after these.
+    fn query<'a>(&'a self, guild_id: &'a i64) -> (&'static str, Vec<&'a (dyn ToSql + Sync)>) {
+        match self {
+            StandingsSource::Live(window, now) => (RANKED_USERS_IN_WINDOW, vec![guild_id, &window.from, &window.to, &window.all_seasons, now]),
+            StandingsSource::Snapshot(season_id) => (SEASON_STANDINGS, vec![season_id]),
+        }
+    }
+}
+
+// Every active user of guild $1 with the totals of their bbps and gbps timestamped in
+// [$2, $3), ranked by their score as of $5. A NULL bound leaves that side open;
+// rows of closed seasons only count when $4 is true.
+const RANKED_USERS_IN_WINDOW: &str = "
+    SELECT *,
+           RANK() OVER (ORDER BY \"Score\" DESC) AS \"Rank\",
+           ROW_NUMBER() OVER (ORDER BY \"Score\" DESC, \"UserID\") - 1 AS \"Position\"
+    FROM (
+        SELECT *, \"BbpTotal\" - \"GbpTotal\" AS \"Points\", \"DecayedBbpTotal\" - \"GbpTotal\" AS \"Score\"
+        FROM (
+            SELECT u.\"UserID\", u.\"DiscordUsername\", u.\"DiscordMention\", u.\"DiscordID\", u.\"FriendlyName\",
+                   COALESCE((SELECT SUM(b.\"Value\") FROM public.\"Bbps\" b
+                             WHERE b.\"UserID\" = u.\"UserID\" AND b.\"Forgiven\" = false
+                               AND ($2::TIMESTAMP IS NULL OR b.\"Timestamp\" >= $2)
+                               AND ($3::TIMESTAMP IS NULL OR b.\"Timestamp\" < $3)
+                               AND ($4 OR b.\"SeasonID\" IS NULL)), 0)::INTEGER AS \"BbpTotal\",
+                   COALESCE((SELECT SUM(b.\"Value\" * public.decay_weight(gs.\"DecayMode\", gs.\"DecayDays\", b.\"Timestamp\", $5)) FROM public.\"Bbps\" b
+                             WHERE b.\"UserID\" = u.\"UserID\" AND b.\"Forgiven\" = false
+                               AND ($2::TIMESTAMP IS NULL OR b.\"Timestamp\" >= $2)
+                               AND ($3::TIMESTAMP IS NULL OR b.\"Timestamp\" < $3)
+                               AND ($4 OR b.\"SeasonID\" IS NULL)), 0)::DOUBLE PRECISION AS \"DecayedBbpTotal\",
+                   COALESCE((SELECT SUM(g.\"Value\") FROM public.\"Gbps\" g
+                             WHERE g.\"UserID\" = u.\"UserID\"
+                               AND ($2::TIMESTAMP IS NULL OR g.\"Timestamp\" >= $2)
+                               AND ($3::TIMESTAMP IS NULL OR g.\"Timestamp\" < $3)
+                               AND ($4 OR g.\"SeasonID\" IS NULL)), 0)::INTEGER AS \"GbpTotal\",
+                   (SELECT COUNT(*) FROM public.\"Bbps\" b
+                    WHERE b.\"IssuerID\" = u.\"UserID\"
+                      AND ($2::TIMESTAMP IS NULL OR b.\"Timestamp\" >= $2)
+                      AND ($3::TIMESTAMP IS NULL OR b.\"Timestamp\" < $3)
+                      AND ($4 OR b.\"SeasonID\" IS NULL))::INTEGER AS \"BbpsIssued\",
+                   (SELECT COUNT(*) FROM public.\"Gbps\" g
+                    WHERE g.\"IssuerID\" = u.\"UserID\"
+                      AND ($2::TIMESTAMP IS NULL OR g.\"Timestamp\" >= $2)
+                      AND ($3::TIMESTAMP IS NULL OR g.\"Timestamp\" < $3)
+                      AND ($4 OR g.\"SeasonID\" IS NULL))::INTEGER AS \"GbpsIssued\"
+            FROM public.\"Users\" u
+            LEFT JOIN public.\"GuildSettings\" gs ON gs.\"GuildID\" = u.\"GuildID\"
+            WHERE u.\"GuildID\" = $1 AND u.\"Active\"
+        ) totals
+    ) scored";
+
+// The standings archived for season $1, shaped like RANKED_USERS_IN_WINDOW
+const SEASON_STANDINGS: &str = "
+    SELECT s.*, u.\"DiscordUsername\", u.\"DiscordMention\", u.\"DiscordID\", u.\"FriendlyName\",
+           ROW_NUMBER() OVER (ORDER BY s.\"Rank\", s.\"UserID\") - 1 AS \"Position\"
+    FROM public.\"SeasonStandings\" s
+    JOIN public.\"Users\" u ON u.\"UserID\" = s.\"UserID\"
+    WHERE s.\"SeasonID\" = $1";
+
+// Points $1 gave $2, across every season
+const EXCHANGE: &str = "
+    SELECT
+        (SELECT COALESCE(SUM(\"Value\"), 0) FROM public.\"Bbps\" WHERE \"IssuerID\" = $1 AND \"UserID\" = $2)::INTEGER AS \"Bbps\",
+        (SELECT COALESCE(SUM(\"Value\"), 0) FROM public.\"Bbps\" WHERE \"IssuerID\" = $1 AND \"UserID\" = $2 AND \"Forgiven\" = true)::INTEGER AS \"Forgiven\",
+        (SELECT COALESCE(SUM(\"Value\"), 0) FROM public.\"Gbps\" WHERE \"IssuerID\" = $1 AND \"UserID\" = $2)::INTEGER AS \"Gbps\"";
 
-    pub async fn get_user_by_discord_id(&self, discord_id: i64) -> Result<Option<User>, Box<dyn std::error::Error + Send + Sync>> {
+// Re-ranks the archived standings of every closed season of guild $1 by score,
+// after lines were combined or removed
+const RERANK_SEASON_STANDINGS: &str = "
+    UPDATE public.\"SeasonStandings\" s
+    SET \"Rank\" = r.\"Rank\"
+    FROM (
+        SELECT \"SeasonID\", \"UserID\", RANK() OVER (PARTITION BY \"SeasonID\" ORDER BY \"Score\" DESC) AS \"Rank\"
+        FROM public.\"SeasonStandings\"
+        WHERE \"SeasonID\" IN (SELECT \"SeasonID\" FROM public.\"Seasons\" WHERE \"GuildID\" = $1)
+    ) r
+    WHERE s.\"SeasonID\" = r.\"SeasonID\" AND s.\"UserID\" = r.\"UserID\"";
+
+// Pending bbps with their users' Discord IDs and the votes so far, callers add the WHERE
+const PENDING_BBPS: &str = "
+    SELECT p.*, t.\"DiscordID\" AS \"TargetDiscordID\", i.\"DiscordID\" AS \"IssuerDiscordID\",
+           (SELECT COUNT(*) FROM public.\"PendingBbpVotes\" v WHERE v.\"PendingID\" = p.\"PendingID\" AND v.\"InFavour\")::INTEGER AS \"VotesFor\",
+           (SELECT COUNT(*) FROM public.\"PendingBbpVotes\" v WHERE v.\"PendingID\" = p.\"PendingID\" AND NOT v.\"InFavour\")::INTEGER AS \"VotesAgainst\"
+    FROM public.\"PendingBbps\" p
+    JOIN public.\"Users\" t ON t.\"UserID\" = p.\"UserID\"
+    JOIN public.\"Users\" i ON i.\"UserID\" = p.\"IssuerID\"";
+
+// Bbps with their users' Discord IDs, callers add the WHERE
+const BBPS: &str = "
+    SELECT b.*, t.\"DiscordID\" AS \"TargetDiscordID\", i.\"DiscordID\" AS \"IssuerDiscordID\", i.\"FriendlyName\" AS \"IssuerFriendlyName\"
+    FROM public.\"Bbps\" b
+    JOIN public.\"Users\" t ON t.\"UserID\" = b.\"UserID\"
+    JOIN public.\"Users\" i ON i.\"UserID\" = b.\"IssuerID\"";
+
+// Picks bbps from BBPS: the target's ($2) from the running season with the
+// forgiven state $4, only the issuer's ($3) if there is one. Bbps overturned on
+// appeal stay forgiven, so they never qualify.
+const RECENT_BBPS: &str = "
+    b.\"GuildID\" = $1 AND t.\"DiscordID\" = $2 AND ($3::BIGINT IS NULL OR i.\"DiscordID\" = $3)
+    AND b.\"Forgiven\" = $4 AND b.\"SeasonID\" IS NULL
+    AND NOT EXISTS (SELECT 1 FROM public.\"Appeals\" a WHERE a.\"BbpID\" = b.\"BbpID\" AND a.\"Status\" = 'overturned')";
+
+// Appeals with their bbp, shaped like BBPS, and the votes so far. Callers add the WHERE
+const APPEALS: &str = "
+    SELECT a.*, b.*, t.\"DiscordID\" AS \"TargetDiscordID\", i.\"DiscordID\" AS \"IssuerDiscordID\", i.\"FriendlyName\" AS \"IssuerFriendlyName\",
+           (SELECT COUNT(*) FROM public.\"AppealVotes\" v WHERE v.\"AppealID\" = a.\"AppealID\" AND v.\"Overturn\")::INTEGER AS \"VotesOverturn\",
+           (SELECT COUNT(*) FROM public.\"AppealVotes\" v WHERE v.\"AppealID\" = a.\"AppealID\" AND NOT v.\"Overturn\")::INTEGER AS \"VotesUphold\"
+    FROM public.\"Appeals\" a
+    JOIN public.\"Bbps\" b ON b.\"BbpID\" = a.\"BbpID\"
+    JOIN public.\"Users\" t ON t.\"UserID\" = b.\"UserID\"
+    JOIN public.\"Users\" i ON i.\"UserID\" = b.\"IssuerID\"";
+
+// The user with DiscordID $6 and their live rank and score, see RANKED_USERS_IN_WINDOW.
+// Deactivated users aren't ranked and get NULLs.
+fn ranked_user_query() -> String {
+    format!(
+        "SELECT u.*, ranked_users.\"Rank\", ranked_users.\"Score\"
+         FROM public.\"Users\" u
+         LEFT JOIN ({}) ranked_users ON ranked_users.\"UserID\" = u.\"UserID\"
+         WHERE u.\"GuildID\" = $1 AND u.\"DiscordID\" = $6",
+        RANKED_USERS_IN_WINDOW)
+}
+
+#[async_trait]
+impl BbpStore for PostgresService {
+    fn now(&self) -> chrono::NaiveDateTime {
+        self.clock.now()
+    }
+
+    async fn get_user_by_discord_id(&self, guild_id: i64, discord_id: i64) -> Result<Option<User>, StoreError> {
         // Get a connection from the pool
         let conn = self.pool.get().await?;
         if conn.is_closed() {
-            print!("Attempted to use a connection that is closed.")
+            debug!("Attempted to use a connection that is closed.");
         }
         
         let rows = conn
-            .query("SELECT * FROM public.\"Users\" WHERE \"DiscordID\" = $1", &[&discord_id])
+            .query("SELECT * FROM public.\"Users\" WHERE \"GuildID\" = $1 AND \"DiscordID\" = $2", &[&guild_id, &discord_id])
             .await?;
 
         Self::handle_query_result(&rows)
     }
 
-    pub async fn get_user_by_discord_id_with_rank(&self, discord_mention: i64) -> Result<Option<User>, Box<dyn std::error::Error + Send + Sync>> {
+    async fn get_user_by_discord_id_with_rank(&self, guild_id: i64, discord_mention: i64) -> Result<Option<User>, StoreError> {
         let conn = self.pool.get().await?;
-        
-        let rows = conn
-            .query("SELECT * FROM (
-                        SELECT *, RANK() OVER (ORDER BY \"Points\" DESC) AS \"Rank\"
-                        FROM public.\"Users\"
-                    ) ranked_users
-                    WHERE \"DiscordID\" = $1", &[&discord_mention])
-            .await?;
+        let running = StandingsSource::Live(TimeWindow::default(), self.clock.now());
+        let (_, mut params) = running.query(&guild_id);
+        params.push(&discord_mention);
+
+        let rows = conn.query(&ranked_user_query(), &params).await?;
 
         Self::handle_query_result(&rows)
     }
 
-    pub async fn add_user(&self, discord_id: i64, discord_username: &str, friendly_name: &str) -> Result<Option<User>, Box<dyn std::error::Error + Send + Sync>> {
+    async fn add_user(&self, guild_id: i64, discord_id: i64, discord_username: &str, friendly_name: &str) -> Result<User, StoreError> {
         let conn = self.pool.get().await?;
         let discord_mention = format!("<@{}>", discord_id);
         
         let rows = conn
             .query(
-                "INSERT INTO public.\"Users\" (\"DiscordID\",\"DiscordUsername\",\"DiscordMention\",\"FriendlyName\") \
-                VALUES ($1,$2,$3,$4)\
-                RETURNING *", &[&discord_id, &discord_username, &discord_mention, &friendly_name])
-            .await?;
+                "INSERT INTO public.\"Users\" (\"GuildID\",\"DiscordID\",\"DiscordUsername\",\"DiscordMention\",\"FriendlyName\") \
+                VALUES ($1,$2,$3,$4,$5)\
+                RETURNING *", &[&guild_id, &discord_id, &discord_username, &discord_mention, &friendly_name])
+            .await
+            .map_err(|e| match StoreError::from(e) {
+                StoreError::Duplicate(_) => StoreError::Duplicate(Existing::User),
+                e => e,
+            })?;
 
-        Self::handle_query_result(&rows)
+        match Self::handle_query_result(&rows)? {
+            Some(user) => Ok(user),
+            None => Err(StoreError::Integrity("Inserted user was not returned".to_string())),
+        }
     }
 
-    pub async fn add_bbp_to_user(&self, target: &User, issuer: &User, description: &str) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-        let timestamp = chrono::Utc::now().naive_utc();
+    async fn rename_user(&self, guild_id: i64, discord_id: i64, friendly_name: &str) -> Result<User, StoreError> {
         let conn = self.pool.get().await?;
 
-        let _ = conn
+        let row = conn
+            .query_opt(
+                "UPDATE public.\"Users\" SET \"FriendlyName\" = $3 WHERE \"GuildID\" = $1 AND \"DiscordID\" = $2 RETURNING *",
+                &[&guild_id, &discord_id, &friendly_name])
+            .await?
+            .ok_or(StoreError::UserNotFound(UserRole::Target))?;
+
+        Ok(Self::row_to_user(&row))
+    }
+
+    async fn set_user_active(&self, guild_id: i64, discord_id: i64, active: bool) -> Result<User, StoreError> {
+        let conn = self.pool.get().await?;
+
+        let row = conn
+            .query_opt(
+                "UPDATE public.\"Users\" SET \"Active\" = $3 WHERE \"GuildID\" = $1 AND \"DiscordID\" = $2 RETURNING *",
+                &[&guild_id, &discord_id, &active])
+            .await?
+            .ok_or(StoreError::UserNotFound(UserRole::Target))?;
+
+        Ok(Self::row_to_user(&row))
+    }
+
+    async fn merge_users(&self, guild_id: i64, from_discord_id: i64, into_discord_id: i64) -> Result<User, StoreError> {
+        if from_discord_id == into_discord_id {
+            return Err(StoreError::Integrity("Can't merge a user into themselves".to_string()));
+        }
+        let mut conn = self.pool.get().await?;
+        let tx = conn.transaction().await?;
+
+        // Same locking order as issue_point, so issuances against either user wait
+        let rows = tx
             .query(
-                "INSERT INTO public.\"Bbps\" (\"UserID\", \"Value\", \"Description\", \"Timestamp\", \"IssuerID\") VALUES ($1, 1, $2, $3, $4)",
-                &[&target.user_id, &description, &timestamp, &issuer.user_id]
-            )
+                "SELECT * FROM public.\"Users\"
+                 WHERE \"GuildID\" = $1 AND \"DiscordID\" IN ($2, $3)
+                 ORDER BY \"UserID\"
+                 FOR UPDATE",
+                &[&guild_id, &from_discord_id, &into_discord_id])
+            .await?;
+        let users: Vec<User> = rows.iter().map(PostgresService::row_to_user).collect();
+        let from = users.iter().find(|u| u.discord_id == from_discord_id).ok_or(StoreError::UserNotFound(UserRole::MergeFrom))?.user_id;
+        let into = users.iter().find(|u| u.discord_id == into_discord_id).ok_or(StoreError::UserNotFound(UserRole::MergeInto))?.user_id;
+
+        // The points trigger recomputes both users' totals as the rows move
+        tx.execute("UPDATE public.\"Bbps\" SET \"UserID\" = $2 WHERE \"UserID\" = $1", &[&from, &into]).await?;
+        tx.execute("UPDATE public.\"Bbps\" SET \"IssuerID\" = $2 WHERE \"IssuerID\" = $1", &[&from, &into]).await?;
+        tx.execute("UPDATE public.\"Gbps\" SET \"UserID\" = $2 WHERE \"UserID\" = $1", &[&from, &into]).await?;
+        tx.execute("UPDATE public.\"Gbps\" SET \"IssuerID\" = $2 WHERE \"IssuerID\" = $1", &[&from, &into]).await?;
+        tx.execute("UPDATE public.\"PendingBbps\" SET \"UserID\" = $2 WHERE \"UserID\" = $1", &[&from, &into]).await?;
+        tx.execute("UPDATE public.\"PendingBbps\" SET \"IssuerID\" = $2 WHERE \"IssuerID\" = $1", &[&from, &into]).await?;
+
+        // Where both users voted on the same pending bbp or appeal only the second user's vote counts
+        tx.execute(
+            "DELETE FROM public.\"PendingBbpVotes\"
+             WHERE \"VoterID\" = $1
+               AND \"PendingID\" IN (SELECT \"PendingID\" FROM public.\"PendingBbpVotes\" WHERE \"VoterID\" = $2)",
+            &[&from, &into])
+            .await?;
+        tx.execute("UPDATE public.\"PendingBbpVotes\" SET \"VoterID\" = $2 WHERE \"VoterID\" = $1", &[&from, &into]).await?;
+        tx.execute(
+            "DELETE FROM public.\"AppealVotes\"
+             WHERE \"VoterID\" = $1
+               AND \"AppealID\" IN (SELECT \"AppealID\" FROM public.\"AppealVotes\" WHERE \"VoterID\" = $2)",
+            &[&from, &into])
+            .await?;
+        tx.execute("UPDATE public.\"AppealVotes\" SET \"VoterID\" = $2 WHERE \"VoterID\" = $1", &[&from, &into]).await?;
+
+        // Seasons both users were ranked in get one combined line, re-ranked below
+        tx.execute(
+            "UPDATE public.\"SeasonStandings\" s
+             SET \"Points\" = s.\"Points\" + f.\"Points\",
+                 \"Score\" = s.\"Score\" + f.\"Score\",
+                 \"BbpTotal\" = s.\"BbpTotal\" + f.\"BbpTotal\",
+                 \"GbpTotal\" = s.\"GbpTotal\" + f.\"GbpTotal\",
+                 \"BbpsIssued\" = s.\"BbpsIssued\" + f.\"BbpsIssued\",
+                 \"GbpsIssued\" = s.\"GbpsIssued\" + f.\"GbpsIssued\"
+             FROM public.\"SeasonStandings\" f
+             WHERE f.\"SeasonID\" = s.\"SeasonID\" AND f.\"UserID\" = $1 AND s.\"UserID\" = $2",
+            &[&from, &into])
+            .await?;
+        tx.execute(
+            "DELETE FROM public.\"SeasonStandings\"
+             WHERE \"UserID\" = $1
+               AND \"SeasonID\" IN (SELECT \"SeasonID\" FROM public.\"SeasonStandings\" WHERE \"UserID\" = $2)",
+            &[&from, &into])
+            .await?;
+        tx.execute("UPDATE public.\"SeasonStandings\" SET \"UserID\" = $2 WHERE \"UserID\" = $1", &[&from, &into]).await?;
+        tx.execute(RERANK_SEASON_STANDINGS, &[&guild_id]).await?;
+
+        tx.execute("DELETE FROM public.\"Users\" WHERE \"UserID\" = $1", &[&from]).await?;
+        let merged = tx.query_one("SELECT * FROM public.\"Users\" WHERE \"UserID\" = $1", &[&into]).await?;
+
+        tx.commit().await?;
+        Ok(Self::row_to_user(&merged))
+    }
+
+    async fn remove_user(&self, guild_id: i64, discord_id: i64) -> Result<User, StoreError> {
+        let mut conn = self.pool.get().await?;
+        let tx = conn.transaction().await?;
+
+        let user = tx
+            .query_opt("SELECT * FROM public.\"Users\" WHERE \"GuildID\" = $1 AND \"DiscordID\" = $2 FOR UPDATE", &[&guild_id, &discord_id])
+            .await?
+            .map(|row| Self::row_to_user(&row))
+            .ok_or(StoreError::UserNotFound(UserRole::Target))?;
+
+        let history = tx
+            .query_opt(
+                "SELECT 1 FROM public.\"Bbps\" WHERE $1 IN (\"UserID\", \"IssuerID\")
+                 UNION ALL
+                 SELECT 1 FROM public.\"Gbps\" WHERE $1 IN (\"UserID\", \"IssuerID\")
+                 UNION ALL
+                 SELECT 1 FROM public.\"PendingBbps\" WHERE $1 IN (\"UserID\", \"IssuerID\")
+                 UNION ALL
+                 SELECT 1 FROM public.\"PendingBbpVotes\" WHERE \"VoterID\" = $1
+                 UNION ALL
+                 SELECT 1 FROM public.\"AppealVotes\" WHERE \"VoterID\" = $1
+                 LIMIT 1",
+                &[&user.user_id])
             .await?;
-    
+        if history.is_some() {
+            return Err(StoreError::UserHasHistory);
+        }
+
+        // Without any bbps or gbps their archived lines are all zeroes
+        tx.execute("DELETE FROM public.\"SeasonStandings\" WHERE \"UserID\" = $1", &[&user.user_id]).await?;
+        tx.execute(RERANK_SEASON_STANDINGS, &[&guild_id]).await?;
+        tx.execute("DELETE FROM public.\"Users\" WHERE \"UserID\" = $1", &[&user.user_id]).await?;
+
+        tx.commit().await?;
+        Ok(user)
+    }
+
+    async fn issue_point(&self, guild_id: i64, kind: PointKind, value: i32, issuer_discord_id: i64, target_discord_id: i64, description: &str) -> Result<IssuedPoint, StoreError> {
+        let timestamp = self.clock.now();
+        let mut conn = self.pool.get().await?;
+        let tx = conn.transaction().await?;
+
+        let (issuer, target) = Self::lock_users(&tx, guild_id, issuer_discord_id, target_discord_id).await?;
+        let issued = Self::insert_point(&tx, guild_id, kind, value, issuer, target, description, timestamp).await?;
+
+        tx.commit().await?;
+        Ok(issued)
+    }
+
+    async fn set_point_message(&self, guild_id: i64, kind: PointKind, point_id: i32, channel_id: i64, message_id: i64) -> Result<(), StoreError> {
+        let conn = self.pool.get().await?;
+
+        let (table, id_column) = match kind {
+            PointKind::Bbp => ("Bbps", "BbpID"),
+            PointKind::Gbp => ("Gbps", "GbpID"),
+        };
+        conn.execute(
+                &format!("UPDATE public.\"{}\" SET \"ChannelID\" = $3, \"MessageID\" = $4 WHERE \"{}\" = $1 AND \"GuildID\" = $2", table, id_column),
+                &[&point_id, &guild_id, &channel_id, &message_id])
+            .await?;
+
         Ok(())
     }
 
-    pub async fn add_gbp_to_user(&self, target: &User, issuer: &User, description: &str) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-        let timestamp = chrono::Utc::now().naive_utc();
+    async fn create_pending_bbp(&self, guild_id: i64, value: i32, issuer_discord_id: i64, target_discord_id: i64, description: &str, quorum: i32, lifetime: chrono::Duration) -> Result<PendingBbp, StoreError> {
+        let created_at = self.clock.now();
+        let expires_at = created_at + lifetime;
+        let mut conn = self.pool.get().await?;
+        let tx = conn.transaction().await?;
+
+        let (issuer, target) = Self::lock_users(&tx, guild_id, issuer_discord_id, target_discord_id).await?;
+        let row = tx
+            .query_one(
+                "INSERT INTO public.\"PendingBbps\" (\"GuildID\", \"UserID\", \"IssuerID\", \"Value\", \"Description\", \"Quorum\", \"CreatedAt\", \"ExpiresAt\")
+                 VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+                 RETURNING \"PendingID\"",
+                &[&guild_id, &target.user_id, &issuer.user_id, &value, &description, &quorum, &created_at, &expires_at])
+            .await?;
+        let pending_id: i32 = row.get("PendingID");
+        let row = tx.query_one(&format!("{} WHERE p.\"PendingID\" = $1", PENDING_BBPS), &[&pending_id]).await?;
+
+        tx.commit().await?;
+        Ok(Self::row_to_pending_bbp(&row))
+    }
+
+    async fn set_pending_bbp_message(&self, pending_id: i32, channel_id: i64, message_id: i64) -> Result<(), StoreError> {
         let conn = self.pool.get().await?;
 
-        let _ = conn
-            .query(
-                "INSERT INTO public.\"Gbps\" (\"UserID\", \"Value\", \"Description\", \"Timestamp\", \"IssuerID\") VALUES ($1, 1, $2, $3, $4)",
-                &[&target.user_id, &description, &timestamp, &issuer.user_id]
-            )
+        conn.execute(
+                "UPDATE public.\"PendingBbps\" SET \"ChannelID\" = $2, \"MessageID\" = $3 WHERE \"PendingID\" = $1",
+                &[&pending_id, &channel_id, &message_id])
             .await?;
-    
+
         Ok(())
     }
 
-    pub async fn forgive_user(&self, target: &User, issuer: &User) -> Result<Option<String>, Box<dyn std::error::Error + Send + Sync>> {
+    async fn vote_on_pending_bbp(&self, pending_id: i32, voter_discord_id: i64, in_favour: bool) -> Result<JuryVote, StoreError> {
+        let now = self.clock.now();
+        let mut conn = self.pool.get().await?;
+        let tx = conn.transaction().await?;
+
+        // Locking the pending bbp makes concurrent votes on it take turns, so only one of them settles it
+        let row = tx
+            .query_opt("SELECT * FROM public.\"PendingBbps\" WHERE \"PendingID\" = $1 FOR UPDATE", &[&pending_id])
+            .await?
+            .ok_or(StoreError::VotingClosed)?;
+        let status: String = row.get("Status");
+        let expires_at: chrono::NaiveDateTime = row.get("ExpiresAt");
+        if JuryStatus::parse(&status) != JuryStatus::Pending || now >= expires_at {
+            return Err(StoreError::VotingClosed);
+        }
+        let guild_id: i64 = row.get("GuildID");
+        let accused: i32 = row.get("UserID");
+        let accuser: i32 = row.get("IssuerID");
+
+        let voter = tx
+            .query_opt("SELECT * FROM public.\"Users\" WHERE \"GuildID\" = $1 AND \"DiscordID\" = $2", &[&guild_id, &voter_discord_id])
+            .await?
+            .map(|row| Self::row_to_user(&row))
+            .ok_or(StoreError::UserNotFound(UserRole::Issuer))?;
+        if !voter.active {
+            return Err(StoreError::UserInactive(UserRole::Issuer));
+        }
+        if voter.user_id == accused || voter.user_id == accuser {
+            return Err(StoreError::OwnAccusation);
+        }
+
+        tx.execute(
+                "INSERT INTO public.\"PendingBbpVotes\" (\"PendingID\", \"VoterID\", \"InFavour\", \"VotedAt\")
+                 VALUES ($1, $2, $3, $4)
+                 ON CONFLICT (\"PendingID\", \"VoterID\") DO UPDATE
+                 SET \"InFavour\" = EXCLUDED.\"InFavour\", \"VotedAt\" = EXCLUDED.\"VotedAt\"",
+                &[&pending_id, &voter.user_id, &in_favour, &now])
+            .await?;
+
+        let row = tx.query_one(&format!("{} WHERE p.\"PendingID\" = $1", PENDING_BBPS), &[&pending_id]).await?;
+        let mut pending = Self::row_to_pending_bbp(&row);
+        let verdict = pending.verdict();
+        let issued = match verdict {
+            JuryStatus::Confirmed => {
+                let (issuer, target) = Self::lock_users(&tx, guild_id, pending.issuer_discord_id, pending.target_discord_id).await?;
+                Some(Self::insert_point(&tx, guild_id, PointKind::Bbp, pending.value, issuer, target, &pending.description, now).await?)
+            },
+            _ => None,
+        };
+        if verdict != JuryStatus::Pending {
+            let bbp_id = issued.as_ref().map(|issued| issued.point_id);
+            tx.execute(
+                    "UPDATE public.\"PendingBbps\" SET \"Status\" = $2, \"BbpID\" = $3 WHERE \"PendingID\" = $1",
+                    &[&pending_id, &verdict.as_str(), &bbp_id])
+                .await?;
+            pending.status = verdict;
+            pending.bbp_id = bbp_id;
+        }
+
+        tx.commit().await?;
+        Ok(JuryVote { pending, issued })
+    }
+
+    async fn expire_pending_bbps(&self) -> Result<Vec<PendingBbp>, StoreError> {
+        let mut conn = self.pool.get().await?;
+        let tx = conn.transaction().await?;
+
+        let expired: Vec<i32> = tx
+            .query(
+                "UPDATE public.\"PendingBbps\" SET \"Status\" = 'expired'
+                 WHERE \"Status\" = 'pending' AND \"ExpiresAt\" <= $1
+                 RETURNING \"PendingID\"",
+                &[&self.clock.now()])
+            .await?
+            .iter()
+            .map(|row| row.get("PendingID"))
+            .collect();
+        let rows = tx
+            .query(&format!("{} WHERE p.\"PendingID\" = ANY($1) ORDER BY p.\"PendingID\"", PENDING_BBPS), &[&expired])
+            .await?;
+
+        tx.commit().await?;
+        Ok(rows.iter().map(Self::row_to_pending_bbp).collect())
+    }
+
+    async fn get_recent_bbps(&self, guild_id: i64, target_discord_id: i64, issuer_discord_id: Option<i64>, forgiven: bool, limit: i64) -> Result<Vec<Bbp>, StoreError> {
         let conn = self.pool.get().await?;
 
         let rows = conn
             .query(
-                "WITH most_recent AS (
-                    SELECT \"BbpID\"
-                    FROM public.\"Bbps\"
-                    WHERE \"IssuerID\" = $1 AND \"UserID\" = $2 AND \"Forgiven\" = false
-                    ORDER BY \"Timestamp\" DESC
-                    LIMIT 1
-                )
-                UPDATE public.\"Bbps\"
-                SET \"Forgiven\" = true
-                FROM most_recent
-                WHERE \"Bbps\".\"BbpID\" = most_recent.\"BbpID\"
-                RETURNING *;",
-                &[&issuer.user_id, &target.user_id]
-            )
+                &format!(
+                    "{} WHERE {}
+                     ORDER BY b.\"Timestamp\" DESC, b.\"BbpID\" DESC
+                     LIMIT $5",
+                    BBPS, RECENT_BBPS),
+                &[&guild_id, &target_discord_id, &issuer_discord_id, &forgiven, &limit])
             .await?;
-    
-        match rows.len() {
-            0 => Ok(None),
-            1 => match rows[0].try_get::<_, Option<String>>("Description") {
-                    Ok(description) => Ok(description),
-                    Err(_) => Err("Couldn't get the description for the forgive.".into())
-                },
-            _ => Err("Multiple users found for a single Discord mention".into()),
+
+        Ok(rows.iter().map(Self::row_to_bbp).collect())
+    }
+
+    async fn open_appeal(&self, guild_id: i64, bbp_id: i32, appellant_discord_id: i64, statement: &str, quorum: i32) -> Result<Appeal, StoreError> {
+        let conn = self.pool.get().await?;
+
+        let bbp = conn
+            .query_opt(&format!("{} WHERE b.\"BbpID\" = $1 AND b.\"GuildID\" = $2", BBPS), &[&bbp_id, &guild_id])
+            .await?
+            .map(|row| Self::row_to_bbp(&row))
+            .ok_or(StoreError::BbpNotFound)?;
+        if bbp.target_discord_id != appellant_discord_id || bbp.forgiven || bbp.season_id.is_some() {
+            return Err(StoreError::NotAppealable);
+        }
+
+        // The unique BbpID turns away a second appeal, even a concurrent one
+        let row = conn
+            .query_opt(
+                "INSERT INTO public.\"Appeals\" (\"BbpID\", \"Statement\", \"Quorum\", \"OpenedAt\")
+                 VALUES ($1, $2, $3, $4)
+                 ON CONFLICT (\"BbpID\") DO NOTHING
+                 RETURNING \"AppealID\"",
+                &[&bbp_id, &statement, &quorum, &self.clock.now()])
+            .await?
+            .ok_or(StoreError::NotAppealable)?;
+        let appeal_id: i32 = row.get("AppealID");
+        let row = conn.query_one(&format!("{} WHERE a.\"AppealID\" = $1", APPEALS), &[&appeal_id]).await?;
+
+        Ok(Self::row_to_appeal(&row))
+    }
+
+    async fn set_appeal_message(&self, appeal_id: i32, channel_id: i64, message_id: i64) -> Result<(), StoreError> {
+        let conn = self.pool.get().await?;
+
+        conn.execute(
+                "UPDATE public.\"Appeals\" SET \"ChannelID\" = $2, \"MessageID\" = $3 WHERE \"AppealID\" = $1",
+                &[&appeal_id, &channel_id, &message_id])
+            .await?;
+
+        Ok(())
+    }
+
+    async fn vote_on_appeal(&self, appeal_id: i32, voter_discord_id: i64, overturn: bool) -> Result<Appeal, StoreError> {
+        let now = self.clock.now();
+        let mut conn = self.pool.get().await?;
+        let tx = conn.transaction().await?;
+
+        let appeal = Self::lock_open_appeal(&tx, appeal_id).await?;
+        if appeal.quorum <= 0 {
+            return Err(StoreError::NoCommunityVote);
+        }
+        let voter = tx
+            .query_opt("SELECT * FROM public.\"Users\" WHERE \"GuildID\" = $1 AND \"DiscordID\" = $2", &[&appeal.bbp.guild_id, &voter_discord_id])
+            .await?
+            .map(|row| Self::row_to_user(&row))
+            .ok_or(StoreError::UserNotFound(UserRole::Issuer))?;
+        if !voter.active {
+            return Err(StoreError::UserInactive(UserRole::Issuer));
+        }
+        if voter.discord_id == appeal.bbp.target_discord_id || voter.discord_id == appeal.bbp.issuer_discord_id {
+            return Err(StoreError::OwnAccusation);
+        }
+
+        tx.execute(
+                "INSERT INTO public.\"AppealVotes\" (\"AppealID\", \"VoterID\", \"Overturn\", \"VotedAt\")
+                 VALUES ($1, $2, $3, $4)
+                 ON CONFLICT (\"AppealID\", \"VoterID\") DO UPDATE
+                 SET \"Overturn\" = EXCLUDED.\"Overturn\", \"VotedAt\" = EXCLUDED.\"VotedAt\"",
+                &[&appeal_id, &voter.user_id, &overturn, &now])
+            .await?;
+
+        let row = tx.query_one(&format!("{} WHERE a.\"AppealID\" = $1", APPEALS), &[&appeal_id]).await?;
+        let mut appeal = Self::row_to_appeal(&row);
+        let verdict = appeal.verdict();
+        if verdict != AppealStatus::Open {
+            Self::settle_appeal(&tx, &mut appeal, verdict, None, now).await?;
+        }
+
+        tx.commit().await?;
+        Ok(appeal)
+    }
+
+    async fn resolve_appeal(&self, appeal_id: i32, moderator_discord_id: i64, overturn: bool) -> Result<Appeal, StoreError> {
+        let now = self.clock.now();
+        let mut conn = self.pool.get().await?;
+        let tx = conn.transaction().await?;
+
+        let mut appeal = Self::lock_open_appeal(&tx, appeal_id).await?;
+        if moderator_discord_id == appeal.bbp.target_discord_id || moderator_discord_id == appeal.bbp.issuer_discord_id {
+            return Err(StoreError::OwnAccusation);
         }
+        let status = if overturn { AppealStatus::Overturned } else { AppealStatus::Upheld };
+        Self::settle_appeal(&tx, &mut appeal, status, Some(moderator_discord_id), now).await?;
+
+        tx.commit().await?;
+        Ok(appeal)
+    }
+
+    async fn forgive_bbp(&self, guild_id: i64, target_discord_id: i64, issuer_discord_id: Option<i64>, bbp_id: Option<i32>, forgiver_discord_id: i64) -> Result<Bbp, StoreError> {
+        self.change_forgiven(guild_id, target_discord_id, issuer_discord_id, bbp_id, Some(forgiver_discord_id)).await?
+            .ok_or(StoreError::NothingToForgive)
     }
 
-    pub async fn get_leaderboard(&self) -> Result<Vec<LeaderboardUser>, Box<dyn std::error::Error + Send + Sync>> {
+    async fn unforgive_bbp(&self, guild_id: i64, target_discord_id: i64, issuer_discord_id: Option<i64>, bbp_id: Option<i32>) -> Result<Bbp, StoreError> {
+        self.change_forgiven(guild_id, target_discord_id, issuer_discord_id, bbp_id, None).await?
+            .ok_or(StoreError::NothingToUnforgive)
+    }
+
+    async fn get_point(&self, guild_id: i64, kind: PointKind, point_id: i32) -> Result<Option<PointDetails>, StoreError> {
+        let conn = self.pool.get().await?;
+
+        let query = match kind {
+            PointKind::Bbp => "SELECT p.*, p.\"BbpID\" AS \"PointID\"
+                               FROM public.\"Bbps\" p",
+            PointKind::Gbp => "SELECT p.*, p.\"GbpID\" AS \"PointID\", false AS \"Forgiven\", NULL::BIGINT AS \"ForgivenByDiscordID\", NULL::TIMESTAMP AS \"ForgivenAt\"
+                               FROM public.\"Gbps\" p",
+        };
+        let row = conn
+            .query_opt(
+                &format!(
+                    "SELECT h.*, t.\"DiscordID\" AS \"TargetDiscordID\", t.\"FriendlyName\" AS \"TargetFriendlyName\",
+                            i.\"DiscordID\" AS \"IssuerDiscordID\", i.\"FriendlyName\" AS \"IssuerFriendlyName\"
+                     FROM ({}) h
+                     JOIN public.\"Users\" t ON t.\"UserID\" = h.\"UserID\"
+                     JOIN public.\"Users\" i ON i.\"UserID\" = h.\"IssuerID\"
+                     WHERE h.\"PointID\" = $1 AND h.\"GuildID\" = $2",
+                    query),
+                &[&point_id, &guild_id])
+            .await?;
+        let Some(row) = row else {
+            return Ok(None);
+        };
+
+        let (jury, appeal) = match kind {
+            PointKind::Bbp => (
+                conn.query_opt(&format!("{} WHERE p.\"BbpID\" = $1", PENDING_BBPS), &[&point_id]).await?
+                    .map(|row| Self::row_to_pending_bbp(&row)),
+                conn.query_opt(&format!("{} WHERE a.\"BbpID\" = $1", APPEALS), &[&point_id]).await?
+                    .map(|row| Self::row_to_appeal(&row)),
+            ),
+            PointKind::Gbp => (None, None),
+        };
+
+        Ok(Some(PointDetails {
+            kind,
+            point_id,
+            guild_id,
+            target_discord_id: row.get("TargetDiscordID"),
+            target_friendly_name: row.get::<_, Option<String>>("TargetFriendlyName").unwrap_or_default(),
+            issuer_discord_id: row.get("IssuerDiscordID"),
+            issuer_friendly_name: row.get::<_, Option<String>>("IssuerFriendlyName").unwrap_or_default(),
+            value: row.get("Value"),
+            description: row.get("Description"),
+            timestamp: row.get("Timestamp"),
+            forgiven: row.get("Forgiven"),
+            forgiven_by_discord_id: row.get("ForgivenByDiscordID"),
+            forgiven_at: row.get("ForgivenAt"),
+            season_id: row.get("SeasonID"),
+            channel_id: row.get("ChannelID"),
+            message_id: row.get("MessageID"),
+            jury,
+            appeal,
+        }))
+    }
+
+    async fn edit_point(&self, guild_id: i64, kind: PointKind, point_id: i32, issuer_discord_id: i64, description: &str, grace: chrono::Duration) -> Result<AuditEntry, StoreError> {
+        self.change_own_point(guild_id, kind, point_id, issuer_discord_id, Some(description), grace).await
+    }
+
+    async fn retract_point(&self, guild_id: i64, kind: PointKind, point_id: i32, issuer_discord_id: i64, grace: chrono::Duration) -> Result<AuditEntry, StoreError> {
+        self.change_own_point(guild_id, kind, point_id, issuer_discord_id, None, grace).await
+    }
+
+    async fn get_audit_log(&self, guild_id: i64, page: Page) -> Result<Vec<AuditEntry>, StoreError> {
         let conn = self.pool.get().await?;
 
         let rows = conn
             .query(
-                "SELECT \"UserID\", \"DiscordUsername\", \"DiscordMention\", \"DiscordID\", \"FriendlyName\", \"Points\", \"BbpsIssued\", \"GbpsIssued\", \"Rank\"
-                 FROM (
-                     SELECT *, RANK() OVER (ORDER BY \"Points\" DESC) AS \"Rank\"
-                     FROM public.\"Users\"
-                 ) ranked_users
-                 ORDER BY \"Rank\"
-                 LIMIT 10",
-                &[]
-            )
+                "SELECT * FROM public.\"AuditLog\"
+                 WHERE \"GuildID\" = $1
+                 ORDER BY \"At\" DESC, \"AuditID\" DESC
+                 OFFSET $2
+                 LIMIT $3",
+                &[&guild_id, &page.offset, &page.limit])
             .await?;
 
-        let leaderboard = rows.iter().map(|row| LeaderboardUser {
-            user_id: row.get("UserID"),
-            discord_username: row.try_get("DiscordUsername").ok(),
-            discord_mention: row.try_get("DiscordMention").ok(),
-            discord_id: row.get("DiscordID"),
-            friendly_name: row.try_get("FriendlyName").ok(),
-            points: row.get("Points"),
-            bbps_issued: row.get("BbpsIssued"),
-            gbps_issued: row.get("GbpsIssued"),
-            rank: row.get("Rank"),
-        }).collect();
+        rows.iter().map(Self::row_to_audit_entry).collect()
+    }
+
+    async fn get_leaderboard(&self, guild_id: i64, standings: Standings, page: Page) -> Result<Vec<LeaderboardUser>, StoreError> {
+        let conn = self.pool.get().await?;
+
+        let source = Self::resolve_standings(&conn, guild_id, standings, self.clock.now()).await?;
+        let (ranked_users, mut params) = source.query(&guild_id);
+        let sql = format!(
+            "SELECT *
+             FROM ({}) ranked_users
+             ORDER BY \"Rank\", \"UserID\"
+             OFFSET ${}
+             LIMIT ${}",
+            ranked_users, params.len() + 1, params.len() + 2);
+        params.extend_from_slice(&[&page.offset, &page.limit]);
 
-        Ok(leaderboard)
+        let rows = conn.query(&sql, &params).await?;
+
+        Ok(rows.iter().map(Self::row_to_leaderboard_user).collect())
     }
 
-    pub async fn get_user_history(&self, discord_id: i64) -> Result<Vec<HistoryRecord>, Box<dyn std::error::Error + Send + Sync>> {
+    async fn get_leaderboard_position(&self, guild_id: i64, standings: Standings, discord_id: i64) -> Result<Option<i64>, StoreError> {
+        let conn = self.pool.get().await?;
+
+        let source = Self::resolve_standings(&conn, guild_id, standings, self.clock.now()).await?;
+        let (ranked_users, mut params) = source.query(&guild_id);
+        let sql = format!(
+            "SELECT \"Position\"
+             FROM ({}) ranked_users
+             WHERE \"DiscordID\" = ${}",
+            ranked_users, params.len() + 1);
+        params.push(&discord_id);
+
+        let row = conn.query_opt(&sql, &params).await?;
+
+        Ok(row.map(|row| row.get("Position")))
+    }
+
+    async fn get_user_history(&self, guild_id: i64, discord_id: i64, filter: HistoryFilter, page: Page) -> Result<Vec<HistoryRecord>, StoreError> {
         let conn = self.pool.get().await?;
 
         // First, get the UserID from the Users table using the DiscordID
         let user_id_row = conn
-            .query_one("SELECT \"UserID\" FROM public.\"Users\" WHERE \"DiscordID\" = $1", &[&discord_id])
-            .await?;
+            .query_opt("SELECT \"UserID\" FROM public.\"Users\" WHERE \"GuildID\" = $1 AND \"DiscordID\" = $2", &[&guild_id, &discord_id])
+            .await?
+            .ok_or(StoreError::UserNotFound(UserRole::Target))?;
 
         let user_id: i32 = user_id_row.get("UserID");
 
-        // Then, get the history records for the UserID
+        // A running season's rows aren't tagged yet
+        let season_id = match filter.season {
+            Some(number) => match Self::resolve_standings(&conn, guild_id, Standings::Season(number), self.clock.now()).await? {
+                StandingsSource::Snapshot(season_id) => Some(season_id),
+                StandingsSource::Live(..) => None,
+            },
+            None => None,
+        };
+
+        // Then, get the bbps and gbps for the UserID as one timeline
+        let kind = filter.kind.map(PointKind::as_str);
         let rows = conn
             .query(
-                "SELECT u.\"FriendlyName\", b.\"Description\", b.\"Timestamp\"
-                 FROM public.\"Bbps\" b
-                 JOIN public.\"Users\" u ON b.\"IssuerID\" = u.\"UserID\"
-                 WHERE b.\"UserID\" = $1
-                 ORDER BY b.\"Timestamp\" DESC
-                 LIMIT 10",
-                &[&user_id]
+                "SELECT h.*, u.\"FriendlyName\", u.\"DiscordID\" AS \"IssuerDiscordID\"
+                 FROM (
+                     SELECT 'bbp' AS \"Kind\", b.\"BbpID\" AS \"PointID\", b.\"Value\", b.\"Forgiven\", b.\"Description\", b.\"Timestamp\", b.\"IssuerID\", (SELECT a.\"Status\" FROM public.\"Appeals\" a WHERE a.\"BbpID\" = b.\"BbpID\") AS \"AppealStatus\", b.\"SeasonID\"
+                     FROM public.\"Bbps\" b
+                     WHERE b.\"UserID\" = $1
+                     UNION ALL
+                     SELECT 'gbp' AS \"Kind\", g.\"GbpID\", g.\"Value\", false, g.\"Description\", g.\"Timestamp\", g.\"IssuerID\", NULL::TEXT, g.\"SeasonID\"
+                     FROM public.\"Gbps\" g
+                     WHERE g.\"UserID\" = $1
+                 ) h
+                 JOIN public.\"Users\" u ON h.\"IssuerID\" = u.\"UserID\"
+                 WHERE ($2::TEXT IS NULL OR h.\"Kind\" = $2)
+                   AND ($3::BIGINT IS NULL OR u.\"DiscordID\" = $3)
+                   AND (NOT $6 OR h.\"SeasonID\" IS NOT DISTINCT FROM $7)
+                 ORDER BY h.\"Timestamp\" DESC, h.\"Kind\", h.\"PointID\" DESC
+                 OFFSET $4
+                 LIMIT $5",
+                &[&user_id, &kind, &filter.issuer_discord_id, &page.offset, &page.limit, &filter.season.is_some(), &season_id]
             )
             .await?;
 
-        let history = rows.iter().map(|row| {
-            HistoryRecord {
-                issuer_friendly_name: row.get("FriendlyName"),
-                description: row.get("Description"),
-                timestamp: row.get("Timestamp"),
-            }
-        }).collect();
+        rows.iter().map(Self::row_to_history_record).collect()
+    }
+
+    async fn get_user_stats(&self, guild_id: i64, discord_id: i64) -> Result<UserStats, StoreError> {
+        let conn = self.pool.get().await?;
+
+        let user_id: i32 = conn
+            .query_opt("SELECT \"UserID\" FROM public.\"Users\" WHERE \"GuildID\" = $1 AND \"DiscordID\" = $2", &[&guild_id, &discord_id])
+            .await?
+            .ok_or(StoreError::UserNotFound(UserRole::Target))?
+            .get("UserID");
+
+        let forgiven: i64 = conn
+            .query_one("SELECT COUNT(*) FROM public.\"Bbps\" WHERE \"UserID\" = $1 AND \"Forgiven\" = true", &[&user_id])
+            .await?
+            .get(0);
 
-        Ok(history)
+        let top_issuers = conn
+            .query(
+                "SELECT u.\"DiscordID\", u.\"FriendlyName\", SUM(b.\"Value\")::INTEGER AS \"Points\"
+                 FROM public.\"Bbps\" b
+                 JOIN public.\"Users\" u ON u.\"UserID\" = b.\"IssuerID\"
+                 WHERE b.\"UserID\" = $1 AND b.\"Forgiven\" = false
+                 GROUP BY u.\"UserID\"
+                 ORDER BY \"Points\" DESC, u.\"UserID\"
+                 LIMIT 3",
+                &[&user_id])
+            .await?
+            .iter()
+            .map(Self::row_to_point_tally)
+            .collect();
+
+        let favourite_target = conn
+            .query_opt(
+                "SELECT u.\"DiscordID\", u.\"FriendlyName\", SUM(b.\"Value\")::INTEGER AS \"Points\"
+                 FROM public.\"Bbps\" b
+                 JOIN public.\"Users\" u ON u.\"UserID\" = b.\"UserID\"
+                 WHERE b.\"IssuerID\" = $1 AND b.\"Forgiven\" = false
+                 GROUP BY u.\"UserID\"
+                 ORDER BY \"Points\" DESC, u.\"UserID\"
+                 LIMIT 1",
+                &[&user_id])
+            .await?
+            .map(|row| Self::row_to_point_tally(&row));
+
+        let offences = conn
+            .query(
+                "SELECT \"Timestamp\" FROM public.\"Bbps\" WHERE \"UserID\" = $1 AND \"Forgiven\" = false ORDER BY \"Timestamp\"",
+                &[&user_id])
+            .await?
+            .iter()
+            .map(|row| row.get("Timestamp"))
+            .collect();
+
+        Ok(UserStats { forgiven: forgiven as i32, top_issuers, favourite_target, offences })
     }
-    
-    fn handle_query_result(rows: &[tokio_postgres::Row]) -> Result<Option<User>, Box<dyn std::error::Error + Send + Sync>> {
-        match rows.len() {
-            0 => Ok(None),
-            1 => {
-                let user = PostgresService::row_to_user(&rows[0]);
-                Ok(Some(user))
-            },
-            _ => Err("Multiple users found for a single Discord mention".into()),
+
+    async fn get_rivalry(&self, guild_id: i64, a_discord_id: i64, b_discord_id: i64, recent: i64) -> Result<Rivalry, StoreError> {
+        let conn = self.pool.get().await?;
+
+        let mut user_ids = Vec::with_capacity(2);
+        for discord_id in [a_discord_id, b_discord_id] {
+            let user_id: i32 = conn
+                .query_opt("SELECT \"UserID\" FROM public.\"Users\" WHERE \"GuildID\" = $1 AND \"DiscordID\" = $2", &[&guild_id, &discord_id])
+                .await?
+                .ok_or(StoreError::UserNotFound(UserRole::Target))?
+                .get("UserID");
+            user_ids.push(user_id);
         }
+        let (a, b) = (user_ids[0], user_ids[1]);
+
+        let a_to_b = Self::row_to_exchange(&conn.query_one(EXCHANGE, &[&a, &b]).await?);
+        let b_to_a = Self::row_to_exchange(&conn.query_one(EXCHANGE, &[&b, &a]).await?);
+
+        let recent = conn
+            .query(
+                "SELECT h.*, u.\"FriendlyName\", u.\"DiscordID\" AS \"IssuerDiscordID\"
+                 FROM (
+                     SELECT 'bbp' AS \"Kind\", b.\"BbpID\" AS \"PointID\", b.\"Value\", b.\"Forgiven\", b.\"Description\", b.\"Timestamp\", b.\"IssuerID\", (SELECT a.\"Status\" FROM public.\"Appeals\" a WHERE a.\"BbpID\" = b.\"BbpID\") AS \"AppealStatus\"
+                     FROM public.\"Bbps\" b
+                     WHERE (b.\"IssuerID\" = $1 AND b.\"UserID\" = $2) OR (b.\"IssuerID\" = $2 AND b.\"UserID\" = $1)
+                     UNION ALL
+                     SELECT 'gbp' AS \"Kind\", g.\"GbpID\", g.\"Value\", false, g.\"Description\", g.\"Timestamp\", g.\"IssuerID\", NULL::TEXT
+                     FROM public.\"Gbps\" g
+                     WHERE (g.\"IssuerID\" = $1 AND g.\"UserID\" = $2) OR (g.\"IssuerID\" = $2 AND g.\"UserID\" = $1)
+                 ) h
+                 JOIN public.\"Users\" u ON h.\"IssuerID\" = u.\"UserID\"
+                 ORDER BY h.\"Timestamp\" DESC, h.\"Kind\", h.\"PointID\" DESC
+                 LIMIT $3",
+                &[&a, &b, &recent])
+            .await?
+            .iter()
+            .map(Self::row_to_history_record)
+            .collect::<Result<_, _>>()?;
+
+        Ok(Rivalry { a_to_b, b_to_a, recent })
     }
 
-    fn row_to_user(row: &tokio_postgres::Row) -> User {
-        User {
-            user_id: row.get("UserID"),
-            username: None, // This columns isnt currently used
-            discord_username: row.try_get("DiscordUsername").ok(),
-            discord_mention: row.try_get("DiscordMention").ok(),
-            discord_id: row.get("DiscordID"), 
-            friendly_name: row.try_get("FriendlyName").ok(),
-            points: row.get("Points"),
-            bbps_issued: row.get("BbpsIssued"),
-            gbps_issued: row.get("GbpsIssued"),
-            rank: row.try_get("Rank").ok()
+    async fn get_point_timeline(&self, guild_id: i64, discord_id: i64, window: TimeWindow) -> Result<Vec<HistoryRecord>, StoreError> {
+        let conn = self.pool.get().await?;
+
+        let user_id: i32 = conn
+            .query_opt("SELECT \"UserID\" FROM public.\"Users\" WHERE \"GuildID\" = $1 AND \"DiscordID\" = $2", &[&guild_id, &discord_id])
+            .await?
+            .ok_or(StoreError::UserNotFound(UserRole::Target))?
+            .get("UserID");
+
+        conn.query(
+                "SELECT h.*, u.\"FriendlyName\", u.\"DiscordID\" AS \"IssuerDiscordID\"
+                 FROM (
+                     SELECT 'bbp' AS \"Kind\", b.\"BbpID\" AS \"PointID\", b.\"Value\", b.\"Forgiven\", b.\"Description\", b.\"Timestamp\", b.\"IssuerID\", (SELECT a.\"Status\" FROM public.\"Appeals\" a WHERE a.\"BbpID\" = b.\"BbpID\") AS \"AppealStatus\", b.\"SeasonID\"
+                     FROM public.\"Bbps\" b
+                     WHERE b.\"UserID\" = $1
+                     UNION ALL
+                     SELECT 'gbp' AS \"Kind\", g.\"GbpID\", g.\"Value\", false, g.\"Description\", g.\"Timestamp\", g.\"IssuerID\", NULL::TEXT, g.\"SeasonID\"
+                     FROM public.\"Gbps\" g
+                     WHERE g.\"UserID\" = $1
+                 ) h
+                 JOIN public.\"Users\" u ON h.\"IssuerID\" = u.\"UserID\"
+                 WHERE ($2::TIMESTAMP IS NULL OR h.\"Timestamp\" >= $2)
+                   AND ($3::TIMESTAMP IS NULL OR h.\"Timestamp\" < $3)
+                   AND ($4 OR h.\"SeasonID\" IS NULL)
+                 ORDER BY h.\"Timestamp\", h.\"Kind\" DESC, h.\"PointID\"",
+                &[&user_id, &window.from, &window.to, &window.all_seasons])
+            .await?
+            .iter()
+            .map(Self::row_to_history_record)
+            .collect()
+    }
+
+    async fn start_season(&self, guild_id: i64) -> Result<Season, StoreError> {
+        let conn = self.pool.get().await?;
+        let started_at = self.clock.now();
+
+        let row = conn
+            .query_one(
+                "INSERT INTO public.\"Seasons\" (\"GuildID\", \"Number\", \"StartedAt\")
+                 SELECT $1, COALESCE(MAX(\"Number\"), 0) + 1, $2
+                 FROM public.\"Seasons\"
+                 WHERE \"GuildID\" = $1
+                 RETURNING *",
+                &[&guild_id, &started_at]
+            )
+            .await
+            .map_err(|e| match StoreError::from(e) {
+                StoreError::Duplicate(_) => StoreError::Duplicate(Existing::RunningSeason),
+                e => e,
+            })?;
+
+        Ok(Self::row_to_season(&row))
+    }
+
+    async fn end_season(&self, guild_id: i64) -> Result<ClosedSeason, StoreError> {
+        let mut conn = self.pool.get().await?;
+        let tx = conn.transaction().await?;
+        let ended_at = self.clock.now();
+
+        // issue_point locks its users first, so this waits out issuances in flight
+        // and keeps new ones from slipping in between the snapshot and the archive
+        tx.query("SELECT 1 FROM public.\"Users\" WHERE \"GuildID\" = $1 ORDER BY \"UserID\" FOR UPDATE", &[&guild_id]).await?;
+
+        let season_id: i32 = tx
+            .query_opt("SELECT \"SeasonID\" FROM public.\"Seasons\" WHERE \"GuildID\" = $1 AND \"EndedAt\" IS NULL FOR UPDATE", &[&guild_id])
+            .await?
+            .ok_or(StoreError::NoOpenSeason)?
+            .get("SeasonID");
+
+        let running = StandingsSource::Live(TimeWindow::default(), ended_at);
+        let (ranked_users, mut params) = running.query(&guild_id);
+        let snapshot = format!(
+            "INSERT INTO public.\"SeasonStandings\" (\"SeasonID\", \"UserID\", \"Rank\", \"Points\", \"Score\", \"BbpTotal\", \"GbpTotal\", \"BbpsIssued\", \"GbpsIssued\")
+             SELECT ${}, \"UserID\", \"Rank\", \"Points\", \"Score\", \"BbpTotal\", \"GbpTotal\", \"BbpsIssued\", \"GbpsIssued\"
+             FROM ({}) ranked_users",
+            params.len() + 1, ranked_users);
+        params.push(&season_id);
+        tx.execute(&snapshot, &params).await?;
+
+        // Archiving resets the cached totals through the points trigger
+        tx.execute("UPDATE public.\"Bbps\" SET \"SeasonID\" = $1 WHERE \"GuildID\" = $2 AND \"SeasonID\" IS NULL", &[&season_id, &guild_id]).await?;
+        tx.execute("UPDATE public.\"Gbps\" SET \"SeasonID\" = $1 WHERE \"GuildID\" = $2 AND \"SeasonID\" IS NULL", &[&season_id, &guild_id]).await?;
+
+        let season = tx
+            .query_one("UPDATE public.\"Seasons\" SET \"EndedAt\" = $2 WHERE \"SeasonID\" = $1 RETURNING *", &[&season_id, &ended_at])
+            .await?;
+        let standings = tx
+            .query(&format!("SELECT * FROM ({}) ranked_users ORDER BY \"Rank\", \"UserID\"", SEASON_STANDINGS), &[&season_id])
+            .await?;
+
+        tx.commit().await?;
+        Ok(ClosedSeason {
+            season: Self::row_to_season(&season),
+            standings: standings.iter().map(Self::row_to_leaderboard_user).collect(),
+        })
+    }
+
+    async fn get_seasons(&self, guild_id: i64) -> Result<Vec<Season>, StoreError> {
+        let conn = self.pool.get().await?;
+
+        let rows = conn
+            .query("SELECT * FROM public.\"Seasons\" WHERE \"GuildID\" = $1 ORDER BY \"Number\"", &[&guild_id])
+            .await?;
+
+        Ok(rows.iter().map(Self::row_to_season).collect())
+    }
+
+    async fn get_season(&self, guild_id: i64, number: i32) -> Result<Season, StoreError> {
+        let conn = self.pool.get().await?;
+
+        let row = conn
+            .query_opt("SELECT * FROM public.\"Seasons\" WHERE \"GuildID\" = $1 AND \"Number\" = $2", &[&guild_id, &number])
+            .await?
+            .ok_or(StoreError::SeasonNotFound(number))?;
+
+        Ok(Self::row_to_season(&row))
+    }
+
+    async fn get_guild_settings(&self, guild_id: i64) -> Result<GuildSettings, StoreError> {
+        let conn = self.pool.get().await?;
+
+        let row = conn
+            .query_opt("SELECT * FROM public.\"GuildSettings\" WHERE \"GuildID\" = $1", &[&guild_id])
+            .await?;
+
+        Ok(match row {
+            Some(row) => Self::row_to_guild_settings(&row),
+            None => GuildSettings { guild_id, ..GuildSettings::default() },
+        })
+    }
+
+    async fn save_guild_settings(&self, settings: GuildSettings) -> Result<GuildSettings, StoreError> {
+        let conn = self.pool.get().await?;
+
+        let row = conn
+            .query_one(
+                "INSERT INTO public.\"GuildSettings\" (\"GuildID\", \"DecayMode\", \"DecayDays\", \"MaxValue\", \"ModeratorMaxValue\", \"ModeratorRoleID\",
+                                                   \"RegistrationPolicy\", \"ModeratorChannelID\", \"AutoRegister\", \"EnrolNewMembers\",
+                                                   \"JuryQuorum\", \"JuryHours\", \"GraceMinutes\")
+                 VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13)
+                 ON CONFLICT (\"GuildID\") DO UPDATE
+                 SET \"DecayMode\" = EXCLUDED.\"DecayMode\",
+                     \"DecayDays\" = EXCLUDED.\"DecayDays\",
+                     \"MaxValue\" = EXCLUDED.\"MaxValue\",
+                     \"ModeratorMaxValue\" = EXCLUDED.\"ModeratorMaxValue\",
+                     \"ModeratorRoleID\" = EXCLUDED.\"ModeratorRoleID\",
+                     \"RegistrationPolicy\" = EXCLUDED.\"RegistrationPolicy\",
+                     \"ModeratorChannelID\" = EXCLUDED.\"ModeratorChannelID\",
+                     \"AutoRegister\" = EXCLUDED.\"AutoRegister\",
+                     \"EnrolNewMembers\" = EXCLUDED.\"EnrolNewMembers\",
+                     \"JuryQuorum\" = EXCLUDED.\"JuryQuorum\",
+                     \"JuryHours\" = EXCLUDED.\"JuryHours\",
+                     \"GraceMinutes\" = EXCLUDED.\"GraceMinutes\"
+                 RETURNING *",
+                &[&settings.guild_id, &settings.decay.mode(), &settings.decay.days(),
+                  &settings.max_value, &settings.moderator_max_value, &settings.moderator_role_id,
+                  &settings.registration.as_str(), &settings.moderator_channel_id, &settings.auto_register,
+                  &settings.enrol_new_members, &settings.jury.quorum(), &settings.jury.hours(), &settings.grace_minutes])
+            .await?;
+
+        Ok(Self::row_to_guild_settings(&row))
+    }
+
+    async fn update_discord_username(&self, discord_id: i64, discord_username: &str) -> Result<u64, StoreError> {
+        let conn = self.pool.get().await?;
+        let discord_mention = format!("<@{}>", discord_id);
+
+        let updated = conn
+            .execute(
+                "UPDATE public.\"Users\" SET \"DiscordUsername\" = $2, \"DiscordMention\" = $3
+                 WHERE \"DiscordID\" = $1
+                   AND (\"DiscordUsername\" IS DISTINCT FROM $2 OR \"DiscordMention\" IS DISTINCT FROM $3)",
+                &[&discord_id, &discord_username, &discord_mention])
+            .await?;
+
+        Ok(updated)
+    }
+
+    async fn request_registration(&self, guild_id: i64, discord_id: i64, discord_username: &str, friendly_name: &str) -> Result<RegistrationRequest, StoreError> {
+        let conn = self.pool.get().await?;
+
+        let registered = conn
+            .query_opt("SELECT 1 FROM public.\"Users\" WHERE \"GuildID\" = $1 AND \"DiscordID\" = $2", &[&guild_id, &discord_id])
+            .await?;
+        if registered.is_some() {
+            return Err(StoreError::Duplicate(Existing::User));
         }
+
+        let row = conn
+            .query_opt(
+                "INSERT INTO public.\"RegistrationRequests\" (\"GuildID\", \"DiscordID\", \"DiscordUsername\", \"FriendlyName\", \"RequestedAt\")
+                 VALUES ($1, $2, $3, $4, $5)
+                 ON CONFLICT DO NOTHING
+                 RETURNING *",
+                &[&guild_id, &discord_id, &discord_username, &friendly_name, &self.clock.now()])
+            .await?
+            .ok_or(StoreError::Duplicate(Existing::JoinRequest))?;
+
+        Ok(Self::row_to_registration_request(&row))
+    }
+
+    async fn take_registration_request(&self, guild_id: i64, discord_id: i64) -> Result<Option<RegistrationRequest>, StoreError> {
+        let conn = self.pool.get().await?;
+
+        let row = conn
+            .query_opt(
+                "DELETE FROM public.\"RegistrationRequests\" WHERE \"GuildID\" = $1 AND \"DiscordID\" = $2 RETURNING *",
+                &[&guild_id, &discord_id])
+            .await?;
+
+        Ok(row.map(|row| Self::row_to_registration_request(&row)))
     }
 
+    async fn claim_legacy_rows(&self, guild_id: i64) -> Result<u64, StoreError> {
+        let mut conn = self.pool.get().await?;
+        let tx = conn.transaction().await?;
+
+        let claimed = tx
+            .execute("UPDATE public.\"Users\" SET \"GuildID\" = $1 WHERE \"GuildID\" = 0", &[&guild_id])
+            .await?;
+        tx.execute("UPDATE public.\"Bbps\" SET \"GuildID\" = $1 WHERE \"GuildID\" = 0", &[&guild_id]).await?;
+        tx.execute("UPDATE public.\"Gbps\" SET \"GuildID\" = $1 WHERE \"GuildID\" = 0", &[&guild_id]).await?;
+
+        tx.commit().await?;
+        Ok(claimed)
+    }
 }
@@ -0,0 +1,1619 @@
+use std::sync::{Arc, Mutex};
+
+use async_trait::async_trait;
+use log::info;
+use rusqlite::functions::FunctionFlags;
+use rusqlite::{params, params_from_iter, Connection, OptionalExtension, ToSql};
+
+use crate::dataaccess::bbp_store::BbpStore;
+use crate::dataaccess::clock::{Clock, SystemClock};
+use crate::dataaccess::migrations;
+use crate::dataaccess::models::{Appeal, AppealStatus, AuditAction, AuditEntry, Bbp, ClosedSeason, DecayPolicy, Exchange, GuildSettings, HistoryFilter, HistoryRecord, IssuedPoint, JuryPolicy, JuryStatus, JuryVote, LeaderboardUser, Page, PendingBbp, PointDetails, PointKind, PointTally, RegistrationPolicy, RegistrationRequest, Rivalry, Season, Standings, TimeWindow, User, UserStats};
+use crate::dataaccess::store_error::{Existing, StoreError, UserRole};
+
+/// `BbpStore` backed by a single SQLite file, for deployments that don't want to
+/// run Postgres. rusqlite is blocking, so every query runs on the blocking pool.
+pub struct SqliteService {
+    conn: Arc<Mutex<Connection>>,
+    clock: Arc<dyn Clock>,
+}
+
+impl SqliteService {
+    pub async fn new(path: &str) -> Result<SqliteService, Box<dyn std::error::Error + Send + Sync>> {
+        let path = path.to_string();
+
+        let conn = tokio::task::spawn_blocking(move || -> Result<Connection, Box<dyn std::error::Error + Send + Sync>> {
+            let mut conn = Connection::open(&path)?;
+            conn.execute_batch("PRAGMA foreign_keys = ON; PRAGMA journal_mode = WAL;")?;
+            Self::register_functions(&conn)?;
+
+            let applied = migrations::run_sqlite_migrations(&mut conn)?;
+            if !applied.is_empty() {
+                info!("Applied database migrations {:?}", applied);
+            }
+
+            Ok(conn)
+        }).await??;
+
+        Ok(SqliteService { conn: Arc::new(Mutex::new(conn)), clock: Arc::new(SystemClock) })
+    }
+
+    pub fn with_clock(self, clock: Arc<dyn Clock>) -> SqliteService {
+        SqliteService { clock, ..self }
+    }
+
+    // Postgres defines decay_weight in SQL, SQLite lacks the math for it
+    fn register_functions(conn: &Connection) -> rusqlite::Result<()> {
+        conn.create_scalar_function(
+            "decay_weight",
+            4,
+            FunctionFlags::SQLITE_UTF8 | FunctionFlags::SQLITE_DETERMINISTIC,
+            |ctx| {
+                let mode = ctx.get::<Option<String>>(0)?.unwrap_or_default();
+                let days = ctx.get::<Option<i32>>(1)?.unwrap_or_default();
+                let policy = DecayPolicy::from_parts(&mode, days);
+
+                Ok(policy.weight(ctx.get(2)?, ctx.get(3)?))
+            })
+    }
+
+    async fn with_conn<T, F>(&self, f: F) -> Result<T, StoreError>
+    where
+        T: Send + 'static,
+        F: FnOnce(&mut Connection) -> Result<T, StoreError> + Send + 'static,
+    {
+        let conn = self.conn.clone();
+
+        tokio::task::spawn_blocking(move || {
+            let mut conn = conn.lock().map_err(|_| StoreError::Pool("SQLite connection mutex was poisoned".to_string()))?;
+            f(&mut conn)
+        }).await?
+    }
+
+    fn handle_query_result(users: Vec<User>) -> Result<Option<User>, StoreError> {
+        match users.len() {
+            0 => Ok(None),
+            1 => Ok(users.into_iter().next()),
+            _ => Err(StoreError::Integrity("Multiple users found for a single Discord mention".to_string())),
+        }
+    }
+
+    fn row_to_user(row: &rusqlite::Row) -> rusqlite::Result<User> {
+        Ok(User {
+            user_id: row.get("UserID")?,
+            guild_id: row.get("GuildID")?,
+            username: None, // This columns isnt currently used
+            discord_username: row.get("DiscordUsername")?,
+            discord_mention: row.get("DiscordMention")?,
+            discord_id: row.get("DiscordID")?,
+            friendly_name: row.get("FriendlyName")?,
+            points: row.get("Points")?,
+            bbp_total: row.get("BbpTotal")?,
+            gbp_total: row.get("GbpTotal")?,
+            bbps_issued: row.get("BbpsIssued")?,
+            gbps_issued: row.get("GbpsIssued")?,
+            rank: row.get("Rank").ok(),
+            score: row.get("Score").ok(),
+            active: row.get("Active")?,
+        })
+    }
+
+    fn row_to_leaderboard_user(row: &rusqlite::Row) -> rusqlite::Result<LeaderboardUser> {
+        Ok(LeaderboardUser {
+            user_id: row.get("UserID")?,
+            discord_username: row.get("DiscordUsername")?,
+            discord_mention: row.get("DiscordMention")?,
+            discord_id: row.get("DiscordID")?,
+            friendly_name: row.get("FriendlyName")?,
+            points: row.get("Points")?,
+            bbp_total: row.get("BbpTotal")?,
+            gbp_total: row.get("GbpTotal")?,
+            bbps_issued: row.get("BbpsIssued")?,
+            gbps_issued: row.get("GbpsIssued")?,
+            rank: row.get("Rank")?,
+            score: row.get("Score")?,
+        })
+    }
+
+    fn row_to_season(row: &rusqlite::Row) -> rusqlite::Result<Season> {
+        Ok(Season {
+            season_id: row.get("SeasonID")?,
+            guild_id: row.get("GuildID")?,
+            number: row.get("Number")?,
+            started_at: row.get("StartedAt")?,
+            ended_at: row.get("EndedAt")?,
+        })
+    }
+
+    // The kind is checked separately, rusqlite can't carry a StoreError out of a row mapper
+    fn row_to_history_record(row: &rusqlite::Row) -> rusqlite::Result<(String, HistoryRecord)> {
+        Ok((
+            row.get("Kind")?,
+            HistoryRecord {
+                kind: PointKind::Bbp,
+                point_id: row.get("PointID")?,
+                value: row.get("Value")?,
+                forgiven: row.get("Forgiven")?,
+                issuer_discord_id: row.get("IssuerDiscordID")?,
+                issuer_friendly_name: row.get::<_, Option<String>>("FriendlyName")?.unwrap_or_default(),
+                description: row.get("Description")?,
+                timestamp: row.get("Timestamp")?,
+                appeal: row.get::<_, Option<String>>("AppealStatus")?.as_deref().map(AppealStatus::parse),
+            }))
+    }
+
+    // The kind is checked separately, like for history records
+    fn row_to_audit_entry(row: &rusqlite::Row) -> rusqlite::Result<(String, AuditEntry)> {
+        Ok((
+            row.get("Kind")?,
+            AuditEntry {
+                audit_id: row.get("AuditID")?,
+                guild_id: row.get("GuildID")?,
+                action: AuditAction::parse(&row.get::<_, String>("Action")?),
+                kind: PointKind::Bbp,
+                point_id: row.get("PointID")?,
+                actor_discord_id: row.get("ActorDiscordID")?,
+                target_discord_id: row.get("TargetDiscordID")?,
+                value: row.get("Value")?,
+                old_description: row.get("OldDescription")?,
+                new_description: row.get("NewDescription")?,
+                at: row.get("At")?,
+            },
+        ))
+    }
+
+    fn check_audit_kind((kind, entry): (String, AuditEntry)) -> Result<AuditEntry, StoreError> {
+        Ok(AuditEntry {
+            kind: PointKind::parse(&kind).ok_or_else(|| StoreError::Integrity(format!("Unknown point kind '{}'", kind)))?,
+            ..entry
+        })
+    }
+
+    fn check_history_kind((kind, record): (String, HistoryRecord)) -> Result<HistoryRecord, StoreError> {
+        Ok(HistoryRecord {
+            kind: PointKind::parse(&kind).ok_or_else(|| StoreError::Integrity(format!("Unknown point kind '{}'", kind)))?,
+            ..record
+        })
+    }
+
+    fn row_to_exchange(row: &rusqlite::Row) -> rusqlite::Result<Exchange> {
+        Ok(Exchange {
+            bbps: row.get("Bbps")?,
+            forgiven: row.get("Forgiven")?,
+            gbps: row.get("Gbps")?,
+        })
+    }
+
+    fn row_to_point_tally(row: &rusqlite::Row) -> rusqlite::Result<PointTally> {
+        Ok(PointTally {
+            discord_id: row.get("DiscordID")?,
+            friendly_name: row.get("FriendlyName")?,
+            points: row.get("Points")?,
+        })
+    }
+
+    fn row_to_guild_settings(row: &rusqlite::Row) -> rusqlite::Result<GuildSettings> {
+        Ok(GuildSettings {
+            guild_id: row.get("GuildID")?,
+            decay: DecayPolicy::from_parts(&row.get::<_, String>("DecayMode")?, row.get("DecayDays")?),
+            max_value: row.get("MaxValue")?,
+            moderator_max_value: row.get("ModeratorMaxValue")?,
+            moderator_role_id: row.get("ModeratorRoleID")?,
+            registration: RegistrationPolicy::parse(&row.get::<_, String>("RegistrationPolicy")?),
+            moderator_channel_id: row.get("ModeratorChannelID")?,
+            auto_register: row.get("AutoRegister")?,
+            enrol_new_members: row.get("EnrolNewMembers")?,
+            jury: JuryPolicy::from_parts(row.get("JuryQuorum")?, row.get("JuryHours")?),
+            grace_minutes: row.get("GraceMinutes")?,
+        })
+    }
+
+    fn row_to_registration_request(row: &rusqlite::Row) -> rusqlite::Result<RegistrationRequest> {
+        Ok(RegistrationRequest {
+            guild_id: row.get("GuildID")?,
+            discord_id: row.get("DiscordID")?,
+            discord_username: row.get("DiscordUsername")?,
+            friendly_name: row.get("FriendlyName")?,
+            requested_at: row.get("RequestedAt")?,
+        })
+    }
+
+    fn row_to_pending_bbp(row: &rusqlite::Row) -> rusqlite::Result<PendingBbp> {
+        Ok(PendingBbp {
+            pending_id: row.get("PendingID")?,
+            guild_id: row.get("GuildID")?,
+            issuer_discord_id: row.get("IssuerDiscordID")?,
+            target_discord_id: row.get("TargetDiscordID")?,
+            value: row.get("Value")?,
+            description: row.get("Description")?,
+            quorum: row.get("Quorum")?,
+            votes_for: row.get("VotesFor")?,
+            votes_against: row.get("VotesAgainst")?,
+            status: JuryStatus::parse(&row.get::<_, String>("Status")?),
+            created_at: row.get("CreatedAt")?,
+            expires_at: row.get("ExpiresAt")?,
+            channel_id: row.get("ChannelID")?,
+            message_id: row.get("MessageID")?,
+            bbp_id: row.get("BbpID")?,
+        })
+    }
+
+    fn get_pending_bbp_blocking(conn: &Connection, pending_id: i32) -> Result<PendingBbp, StoreError> {
+        conn.query_row(
+                &format!("{} WHERE p.\"PendingID\" = ?1", PENDING_BBPS),
+                params![pending_id],
+                Self::row_to_pending_bbp)
+            .optional()?
+            .ok_or_else(|| StoreError::Integrity("Pending bbp not found".to_string()))
+    }
+
+    // Looks up both users of a point and checks they can still give and get one.
+    // Immediate transactions already keep other writers out while the point goes in.
+    fn users_for_point(conn: &Connection, guild_id: i64, issuer_discord_id: i64, target_discord_id: i64) -> Result<(User, User), StoreError> {
+        let users = conn
+            .prepare("SELECT * FROM \"Users\" WHERE \"GuildID\" = ?1 AND \"DiscordID\" IN (?2, ?3)")?
+            .query_map(params![guild_id, issuer_discord_id, target_discord_id], Self::row_to_user)?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let issuer = match users.iter().find(|u| u.discord_id == issuer_discord_id) {
+            Some(user) => user.clone(),
+            None => return Err(StoreError::UserNotFound(UserRole::Issuer)),
+        };
+        let target = match users.iter().find(|u| u.discord_id == target_discord_id) {
+            Some(user) => user.clone(),
+            None => return Err(StoreError::UserNotFound(UserRole::Target)),
+        };
+        if !issuer.active {
+            return Err(StoreError::UserInactive(UserRole::Issuer));
+        }
+        if !target.active {
+            return Err(StoreError::UserInactive(UserRole::Target));
+        }
+
+        Ok((issuer, target))
+    }
+
+    // Inserts the point and reads back the target's live rank
+    #[allow(clippy::too_many_arguments)]
+    fn insert_point(conn: &Connection, guild_id: i64, kind: PointKind, value: i32, issuer: User, target: User, description: &str, timestamp: chrono::NaiveDateTime) -> Result<IssuedPoint, StoreError> {
+        let insert = match kind {
+            PointKind::Bbp => "INSERT INTO \"Bbps\" (\"GuildID\", \"UserID\", \"Value\", \"Description\", \"Timestamp\", \"IssuerID\") VALUES (?1, ?2, ?6, ?3, ?4, ?5) RETURNING \"BbpID\"",
+            PointKind::Gbp => "INSERT INTO \"Gbps\" (\"GuildID\", \"UserID\", \"Value\", \"Description\", \"Timestamp\", \"IssuerID\") VALUES (?1, ?2, ?6, ?3, ?4, ?5) RETURNING \"GbpID\"",
+        };
+        let point_id: i32 = conn.query_row(insert, params![guild_id, target.user_id, description, timestamp, issuer.user_id, value], |row| row.get(0))?;
+
+        let running = StandingsSource::Live(TimeWindow::default(), timestamp);
+        let (_, mut params) = running.query(&guild_id);
+        params.push(&target.discord_id);
+        let ranked = conn
+            .prepare(&ranked_user_query())?
+            .query_map(params_from_iter(params), Self::row_to_user)?
+            .collect::<Result<Vec<_>, _>>()?;
+        let ranked = match Self::handle_query_result(ranked)? {
+            Some(user) => user,
+            None => return Err(StoreError::Integrity("Ranked user not found".to_string())),
+        };
+
+        Ok(IssuedPoint { point_id, issuer, target, ranked })
+    }
+
+    fn row_to_bbp(row: &rusqlite::Row) -> rusqlite::Result<Bbp> {
+        Ok(Bbp {
+            bbp_id: row.get("BbpID")?,
+            guild_id: row.get("GuildID")?,
+            target_discord_id: row.get("TargetDiscordID")?,
+            issuer_discord_id: row.get("IssuerDiscordID")?,
+            issuer_friendly_name: row.get::<_, Option<String>>("IssuerFriendlyName")?.unwrap_or_default(),
+            value: row.get("Value")?,
+            description: row.get("Description")?,
+            timestamp: row.get("Timestamp")?,
+            forgiven: row.get("Forgiven")?,
+            forgiven_by_discord_id: row.get("ForgivenByDiscordID")?,
+            forgiven_at: row.get("ForgivenAt")?,
+            season_id: row.get("SeasonID")?,
+        })
+    }
+
+    fn row_to_appeal(row: &rusqlite::Row) -> rusqlite::Result<Appeal> {
+        Ok(Appeal {
+            appeal_id: row.get("AppealID")?,
+            bbp: Self::row_to_bbp(row)?,
+            statement: row.get("Statement")?,
+            status: AppealStatus::parse(&row.get::<_, String>("Status")?),
+            quorum: row.get("Quorum")?,
+            votes_overturn: row.get("VotesOverturn")?,
+            votes_uphold: row.get("VotesUphold")?,
+            opened_at: row.get("OpenedAt")?,
+            resolved_at: row.get("ResolvedAt")?,
+            resolved_by_discord_id: row.get("ResolvedByDiscordID")?,
+            channel_id: row.get("ChannelID")?,
+            message_id: row.get("MessageID")?,
+        })
+    }
+
+    fn get_appeal_blocking(conn: &Connection, appeal_id: i32) -> Result<Option<Appeal>, StoreError> {
+        let appeal = conn
+            .query_row(
+                &format!("{} WHERE a.\"AppealID\" = ?1", APPEALS),
+                params![appeal_id],
+                Self::row_to_appeal)
+            .optional()?;
+
+        Ok(appeal)
+    }
+
+    // Settled appeals and those of archived bbps are closed
+    fn get_open_appeal_blocking(conn: &Connection, appeal_id: i32) -> Result<Appeal, StoreError> {
+        match Self::get_appeal_blocking(conn, appeal_id)? {
+            Some(appeal) if appeal.status == AppealStatus::Open && appeal.bbp.season_id.is_none() => Ok(appeal),
+            _ => Err(StoreError::VotingClosed),
+        }
+    }
+
+    // Closes the appeal, forgiving the bbp if it was overturned. The points
+    // triggers recompute the target's totals.
+    fn settle_appeal(conn: &Connection, appeal: &mut Appeal, status: AppealStatus, resolved_by: Option<i64>, now: chrono::NaiveDateTime) -> Result<(), StoreError> {
+        conn.execute(
+            "UPDATE \"Appeals\" SET \"Status\" = ?2, \"ResolvedAt\" = ?3, \"ResolvedByDiscordID\" = ?4 WHERE \"AppealID\" = ?1",
+            params![appeal.appeal_id, status.as_str(), now, resolved_by])?;
+        if status == AppealStatus::Overturned {
+            conn.execute(
+                "UPDATE \"Bbps\" SET \"Forgiven\" = 1, \"ForgivenByDiscordID\" = ?2, \"ForgivenAt\" = ?3 WHERE \"BbpID\" = ?1",
+                params![appeal.bbp.bbp_id, resolved_by, now])?;
+            appeal.bbp.forgiven = true;
+            appeal.bbp.forgiven_by_discord_id = resolved_by;
+            appeal.bbp.forgiven_at = Some(now);
+        }
+
+        appeal.status = status;
+        appeal.resolved_at = Some(now);
+        appeal.resolved_by_discord_id = resolved_by;
+        Ok(())
+    }
+
+    // `Some(forgiver)` forgives the picked bbp, `None` unforgives it. The points
+    // triggers recompute the target's totals.
+    fn change_forgiven_blocking(conn: &Connection, guild_id: i64, target_discord_id: i64, issuer_discord_id: Option<i64>, bbp_id: Option<i32>, forgiver_discord_id: Option<i64>, now: chrono::NaiveDateTime) -> Result<Option<Bbp>, StoreError> {
+        let forgiven = forgiver_discord_id.is_none();
+        let forgiven_at = forgiver_discord_id.map(|_| now);
+
+        let changed: Option<i32> = conn
+            .query_row(
+                &format!(
+                    "UPDATE \"Bbps\"
+                     SET \"Forgiven\" = NOT ?4, \"ForgivenByDiscordID\" = ?6, \"ForgivenAt\" = ?7
+                     WHERE \"Forgiven\" = ?4 AND \"BbpID\" = (
+                         SELECT b.\"BbpID\"
+                         FROM \"Bbps\" b
+                         JOIN \"Users\" t ON t.\"UserID\" = b.\"UserID\"
+                         JOIN \"Users\" i ON i.\"UserID\" = b.\"IssuerID\"
+                         WHERE {} AND (?5 IS NULL OR b.\"BbpID\" = ?5)
+                         ORDER BY b.\"Timestamp\" DESC, b.\"BbpID\" DESC
+                         LIMIT 1
+                     )
+                     RETURNING \"BbpID\"",
+                    RECENT_BBPS),
+                params![guild_id, target_discord_id, issuer_discord_id, forgiven, bbp_id, forgiver_discord_id, forgiven_at],
+                |row| row.get("BbpID"))
+            .optional()?;
+        let Some(changed) = changed else {
+            return Ok(None);
+        };
+
+        let bbp = conn.query_row(&format!("{} WHERE b.\"BbpID\" = ?1", BBPS), params![changed], Self::row_to_bbp)?;
+
+        Ok(Some(bbp))
+    }
+
+    // Edits the point when given a description and retracts it otherwise, then
+    // logs what happened in the same transaction. Deleting it lets the points
+    // trigger take it off both users' totals.
+    #[allow(clippy::too_many_arguments)]
+    fn change_own_point_blocking(conn: &mut Connection, guild_id: i64, kind: PointKind, point_id: i32, issuer_discord_id: i64, description: Option<String>, grace: chrono::Duration, now: chrono::NaiveDateTime) -> Result<AuditEntry, StoreError> {
+        let tx = conn.transaction_with_behavior(rusqlite::TransactionBehavior::Immediate)?;
+
+        let (table, id_column, contested) = match kind {
+            PointKind::Bbp => ("Bbps", "BbpID",
+                               "EXISTS (SELECT 1 FROM \"PendingBbps\" j WHERE j.\"BbpID\" = p.\"BbpID\")
+                                OR EXISTS (SELECT 1 FROM \"Appeals\" a WHERE a.\"BbpID\" = p.\"BbpID\")"),
+            PointKind::Gbp => ("Gbps", "GbpID", "0"),
+        };
+        let point = tx
+            .query_row(
+                &format!(
+                    "SELECT p.\"Value\", p.\"Description\", p.\"Timestamp\", p.\"SeasonID\",
+                            t.\"DiscordID\" AS \"TargetDiscordID\", i.\"DiscordID\" AS \"IssuerDiscordID\", {} AS \"Contested\"
+                     FROM \"{}\" p
+                     JOIN \"Users\" t ON t.\"UserID\" = p.\"UserID\"
+                     JOIN \"Users\" i ON i.\"UserID\" = p.\"IssuerID\"
+                     WHERE p.\"{}\" = ?1 AND p.\"GuildID\" = ?2",
+                    contested, table, id_column),
+                params![point_id, guild_id],
+                |row| Ok((
+                    row.get::<_, i32>("Value")?,
+                    row.get::<_, String>("Description")?,
+                    row.get::<_, chrono::NaiveDateTime>("Timestamp")?,
+                    row.get::<_, Option<i32>>("SeasonID")?,
+                    row.get::<_, i64>("TargetDiscordID")?,
+                    row.get::<_, i64>("IssuerDiscordID")?,
+                    row.get::<_, bool>("Contested")?,
+                )))
+            .optional()?;
+        let Some((value, old_description, timestamp, season_id, target_discord_id, point_issuer, contested)) = point else {
+            return Err(StoreError::PointNotFound);
+        };
+        if point_issuer != issuer_discord_id || target_discord_id == issuer_discord_id || contested || season_id.is_some() {
+            return Err(StoreError::NotEditable);
+        }
+        if now - timestamp >= grace {
+            return Err(StoreError::GracePeriodOver { minutes: grace.num_minutes() as i32 });
+        }
+
+        let action = match &description {
+            Some(description) => {
+                tx.execute(&format!("UPDATE \"{}\" SET \"Description\" = ?1 WHERE \"{}\" = ?2", table, id_column), params![description, point_id])?;
+                AuditAction::Edit
+            }
+            None => {
+                tx.execute(&format!("DELETE FROM \"{}\" WHERE \"{}\" = ?1", table, id_column), params![point_id])?;
+                AuditAction::Retract
+            }
+        };
+        let entry = tx.query_row(
+            "INSERT INTO \"AuditLog\" (\"GuildID\", \"Action\", \"Kind\", \"PointID\", \"ActorDiscordID\", \"TargetDiscordID\",
+                                     \"Value\", \"OldDescription\", \"NewDescription\", \"At\")
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)
+             RETURNING *",
+            params![guild_id, action.as_str(), kind.as_str(), point_id, issuer_discord_id, target_discord_id,
+                    value, old_description, description, now],
+            Self::row_to_audit_entry)?;
+
+        tx.commit()?;
+        Self::check_audit_kind(entry)
+    }
+
+    fn get_season_blocking(conn: &Connection, guild_id: i64, number: i32) -> Result<Season, StoreError> {
+        conn.query_row(
+                "SELECT * FROM \"Seasons\" WHERE \"GuildID\" = ?1 AND \"Number\" = ?2",
+                params![guild_id, number],
+                Self::row_to_season)
+            .optional()?
+            .ok_or(StoreError::SeasonNotFound(number))
+    }
+
+    // A running season has no snapshot yet, its standings are the live ones.
+    fn resolve_standings(conn: &Connection, guild_id: i64, standings: Standings, now: chrono::NaiveDateTime) -> Result<StandingsSource, StoreError> {
+        match standings {
+            Standings::Live(window) => Ok(StandingsSource::Live(window, now)),
+            Standings::Season(number) => {
+                let season = Self::get_season_blocking(conn, guild_id, number)?;
+
+                Ok(match season.ended_at {
+                    Some(_) => StandingsSource::Snapshot(season.season_id),
+                    None => StandingsSource::Live(TimeWindow::default(), now),
+                })
+            }
+        }
+    }
+}
+
+enum StandingsSource {
+    /// Scored as of the given time
+    Live(TimeWindow, chrono::NaiveDateTime),
+    Snapshot(i32),
+}
+
+impl StandingsSource {
+    /// The ranked users query and its parameters. Callers number their own
+    /// parameters after these.
+    fn query<'a>(&'a self, guild_id: &'a i64) -> (&'static str, Vec<&'a dyn ToSql>) {
+        match self {
+            StandingsSource::Live(window, now) => (RANKED_USERS_IN_WINDOW, vec![guild_id, &window.from, &window.to, &window.all_seasons, now]),
+            StandingsSource::Snapshot(season_id) => (SEASON_STANDINGS, vec![season_id]),
+        }
+    }
+}
+
+// Every active user of guild ?1 with the totals of their bbps and gbps timestamped in
+// [?2, ?3), ranked by their score as of ?5. A NULL bound leaves that side open;
+// rows of closed seasons only count when ?4 is true.
+const RANKED_USERS_IN_WINDOW: &str = "
+    SELECT *,
+           RANK() OVER (ORDER BY \"Score\" DESC) AS \"Rank\",
+           ROW_NUMBER() OVER (ORDER BY \"Score\" DESC, \"UserID\") - 1 AS \"Position\"
+    FROM (
+        SELECT *, \"BbpTotal\" - \"GbpTotal\" AS \"Points\", \"DecayedBbpTotal\" - \"GbpTotal\" AS \"Score\"
+        FROM (
+            SELECT u.\"UserID\", u.\"DiscordUsername\", u.\"DiscordMention\", u.\"DiscordID\", u.\"FriendlyName\",
+                   COALESCE((SELECT SUM(b.\"Value\") FROM \"Bbps\" b
+                             WHERE b.\"UserID\" = u.\"UserID\" AND b.\"Forgiven\" = 0
+                               AND (?2 IS NULL OR b.\"Timestamp\" >= ?2)
+                               AND (?3 IS NULL OR b.\"Timestamp\" < ?3)
+                               AND (?4 OR b.\"SeasonID\" IS NULL)), 0) AS \"BbpTotal\",
+                   COALESCE((SELECT SUM(b.\"Value\" * decay_weight(gs.\"DecayMode\", gs.\"DecayDays\", b.\"Timestamp\", ?5)) FROM \"Bbps\" b
+                             WHERE b.\"UserID\" = u.\"UserID\" AND b.\"Forgiven\" = 0
+                               AND (?2 IS NULL OR b.\"Timestamp\" >= ?2)
+                               AND (?3 IS NULL OR b.\"Timestamp\" < ?3)
+                               AND (?4 OR b.\"SeasonID\" IS NULL)), 0.0) AS \"DecayedBbpTotal\",
+                   COALESCE((SELECT SUM(g.\"Value\") FROM \"Gbps\" g
+                             WHERE g.\"UserID\" = u.\"UserID\"
+                               AND (?2 IS NULL OR g.\"Timestamp\" >= ?2)
+                               AND (?3 IS NULL OR g.\"Timestamp\" < ?3)
+                               AND (?4 OR g.\"SeasonID\" IS NULL)), 0) AS \"GbpTotal\",
+                   (SELECT COUNT(*) FROM \"Bbps\" b
+                    WHERE b.\"IssuerID\" = u.\"UserID\"
+                      AND (?2 IS NULL OR b.\"Timestamp\" >= ?2)
+                      AND (?3 IS NULL OR b.\"Timestamp\" < ?3)
+                      AND (?4 OR b.\"SeasonID\" IS NULL)) AS \"BbpsIssued\",
+                   (SELECT COUNT(*) FROM \"Gbps\" g
+                    WHERE g.\"IssuerID\" = u.\"UserID\"
+                      AND (?2 IS NULL OR g.\"Timestamp\" >= ?2)
+                      AND (?3 IS NULL OR g.\"Timestamp\" < ?3)
+                      AND (?4 OR g.\"SeasonID\" IS NULL)) AS \"GbpsIssued\"
+            FROM \"Users\" u
+            LEFT JOIN \"GuildSettings\" gs ON gs.\"GuildID\" = u.\"GuildID\"
+            WHERE u.\"GuildID\" = ?1 AND u.\"Active\"
+        ) totals
+    ) scored";
+
+// The standings archived for season ?1, shaped like RANKED_USERS_IN_WINDOW
+const SEASON_STANDINGS: &str = "
+    SELECT s.*, u.\"DiscordUsername\", u.\"DiscordMention\", u.\"DiscordID\", u.\"FriendlyName\",
+           ROW_NUMBER() OVER (ORDER BY s.\"Rank\", s.\"UserID\") - 1 AS \"Position\"
+    FROM \"SeasonStandings\" s
+    JOIN \"Users\" u ON u.\"UserID\" = s.\"UserID\"
+    WHERE s.\"SeasonID\" = ?1";
+
+// Points ?1 gave ?2, across every season
+const EXCHANGE: &str = "
+    SELECT
+        (SELECT COALESCE(SUM(\"Value\"), 0) FROM \"Bbps\" WHERE \"IssuerID\" = ?1 AND \"UserID\" = ?2) AS \"Bbps\",
+        (SELECT COALESCE(SUM(\"Value\"), 0) FROM \"Bbps\" WHERE \"IssuerID\" = ?1 AND \"UserID\" = ?2 AND \"Forgiven\" = 1) AS \"Forgiven\",
+        (SELECT COALESCE(SUM(\"Value\"), 0) FROM \"Gbps\" WHERE \"IssuerID\" = ?1 AND \"UserID\" = ?2) AS \"Gbps\"";
+
+// Re-ranks the archived standings of every closed season of guild ?1 by score,
+// after lines were combined or removed
+const RERANK_SEASON_STANDINGS: &str = "
+    UPDATE \"SeasonStandings\" AS s
+    SET \"Rank\" = r.\"Rank\"
+    FROM (
+        SELECT \"SeasonID\", \"UserID\", RANK() OVER (PARTITION BY \"SeasonID\" ORDER BY \"Score\" DESC) AS \"Rank\"
+        FROM \"SeasonStandings\"
+        WHERE \"SeasonID\" IN (SELECT \"SeasonID\" FROM \"Seasons\" WHERE \"GuildID\" = ?1)
+    ) AS r
+    WHERE s.\"SeasonID\" = r.\"SeasonID\" AND s.\"UserID\" = r.\"UserID\"";
+
+// Pending bbps with their users' Discord IDs and the votes so far, callers add the WHERE
+const PENDING_BBPS: &str = "
+    SELECT p.*, t.\"DiscordID\" AS \"TargetDiscordID\", i.\"DiscordID\" AS \"IssuerDiscordID\",
+           (SELECT COUNT(*) FROM \"PendingBbpVotes\" v WHERE v.\"PendingID\" = p.\"PendingID\" AND v.\"InFavour\") AS \"VotesFor\",
+           (SELECT COUNT(*) FROM \"PendingBbpVotes\" v WHERE v.\"PendingID\" = p.\"PendingID\" AND NOT v.\"InFavour\") AS \"VotesAgainst\"
+    FROM \"PendingBbps\" p
+    JOIN \"Users\" t ON t.\"UserID\" = p.\"UserID\"
+    JOIN \"Users\" i ON i.\"UserID\" = p.\"IssuerID\"";
+
+// Bbps with their users' Discord IDs, callers add the WHERE
+const BBPS: &str = "
+    SELECT b.*, t.\"DiscordID\" AS \"TargetDiscordID\", i.\"DiscordID\" AS \"IssuerDiscordID\", i.\"FriendlyName\" AS \"IssuerFriendlyName\"
+    FROM \"Bbps\" b
+    JOIN \"Users\" t ON t.\"UserID\" = b.\"UserID\"
+    JOIN \"Users\" i ON i.\"UserID\" = b.\"IssuerID\"";
+
+// Picks bbps from BBPS: the target's (?2) from the running season with the
+// forgiven state ?4, only the issuer's (?3) if there is one. Bbps overturned on
+// appeal stay forgiven, so they never qualify.
+const RECENT_BBPS: &str = "
+    b.\"GuildID\" = ?1 AND t.\"DiscordID\" = ?2 AND (?3 IS NULL OR i.\"DiscordID\" = ?3)
+    AND b.\"Forgiven\" = ?4 AND b.\"SeasonID\" IS NULL
+    AND NOT EXISTS (SELECT 1 FROM \"Appeals\" a WHERE a.\"BbpID\" = b.\"BbpID\" AND a.\"Status\" = 'overturned')";
+
+// Appeals with their bbp, shaped like BBPS, and the votes so far. Callers add the WHERE
+const APPEALS: &str = "
+    SELECT a.*, b.*, t.\"DiscordID\" AS \"TargetDiscordID\", i.\"DiscordID\" AS \"IssuerDiscordID\", i.\"FriendlyName\" AS \"IssuerFriendlyName\",
+           (SELECT COUNT(*) FROM \"AppealVotes\" v WHERE v.\"AppealID\" = a.\"AppealID\" AND v.\"Overturn\") AS \"VotesOverturn\",
+           (SELECT COUNT(*) FROM \"AppealVotes\" v WHERE v.\"AppealID\" = a.\"AppealID\" AND NOT v.\"Overturn\") AS \"VotesUphold\"
+    FROM \"Appeals\" a
+    JOIN \"Bbps\" b ON b.\"BbpID\" = a.\"BbpID\"
+    JOIN \"Users\" t ON t.\"UserID\" = b.\"UserID\"
+    JOIN \"Users\" i ON i.\"UserID\" = b.\"IssuerID\"";
+
+// The user with DiscordID ?6 and their live rank and score, see RANKED_USERS_IN_WINDOW.
+// Deactivated users aren't ranked and get NULLs.
+fn ranked_user_query() -> String {
+    format!(
+        "SELECT u.*, ranked_users.\"Rank\", ranked_users.\"Score\"
+         FROM \"Users\" u
+         LEFT JOIN ({}) ranked_users ON ranked_users.\"UserID\" = u.\"UserID\"
+         WHERE u.\"GuildID\" = ?1 AND u.\"DiscordID\" = ?6",
+        RANKED_USERS_IN_WINDOW)
+}
+
+#[async_trait]
+impl BbpStore for SqliteService {
+    fn now(&self) -> chrono::NaiveDateTime {
+        self.clock.now()
+    }
+
+    async fn get_user_by_discord_id(&self, guild_id: i64, discord_id: i64) -> Result<Option<User>, StoreError> {
+        self.with_conn(move |conn| {
+            let users = conn
+                .prepare("SELECT * FROM \"Users\" WHERE \"GuildID\" = ?1 AND \"DiscordID\" = ?2")?
+                .query_map(params![guild_id, discord_id], Self::row_to_user)?
+                .collect::<Result<Vec<_>, _>>()?;
+
+            Self::handle_query_result(users)
+        }).await
+    }
+
+    async fn get_user_by_discord_id_with_rank(&self, guild_id: i64, discord_id: i64) -> Result<Option<User>, StoreError> {
+        let now = self.clock.now();
+
+        self.with_conn(move |conn| {
+            let running = StandingsSource::Live(TimeWindow::default(), now);
+            let (_, mut params) = running.query(&guild_id);
+            params.push(&discord_id);
+
+            let users = conn
+                .prepare(&ranked_user_query())?
+                .query_map(params_from_iter(params), Self::row_to_user)?
+                .collect::<Result<Vec<_>, _>>()?;
+
+            Self::handle_query_result(users)
+        }).await
+    }
+
+    async fn add_user(&self, guild_id: i64, discord_id: i64, discord_username: &str, friendly_name: &str) -> Result<User, StoreError> {
+        let discord_username = discord_username.to_string();
+        let friendly_name = friendly_name.to_string();
+        let discord_mention = format!("<@{}>", discord_id);
+
+        self.with_conn(move |conn| {
+            let user = conn
+                .query_row(
+                    "INSERT INTO \"Users\" (\"GuildID\",\"DiscordID\",\"DiscordUsername\",\"DiscordMention\",\"FriendlyName\") \
+                    VALUES (?1,?2,?3,?4,?5) \
+                    RETURNING *",
+                    params![guild_id, discord_id, discord_username, discord_mention, friendly_name],
+                    Self::row_to_user)
+                .map_err(|e| match StoreError::from(e) {
+                    StoreError::Duplicate(_) => StoreError::Duplicate(Existing::User),
+                    e => e,
+                })?;
+
+            Ok(user)
+        }).await
+    }
+
+    async fn rename_user(&self, guild_id: i64, discord_id: i64, friendly_name: &str) -> Result<User, StoreError> {
+        let friendly_name = friendly_name.to_string();
+
+        self.with_conn(move |conn| {
+            conn.query_row(
+                    "UPDATE \"Users\" SET \"FriendlyName\" = ?3 WHERE \"GuildID\" = ?1 AND \"DiscordID\" = ?2 RETURNING *",
+                    params![guild_id, discord_id, friendly_name],
+                    Self::row_to_user)
+                .optional()?
+                .ok_or(StoreError::UserNotFound(UserRole::Target))
+        }).await
+    }
+
+    async fn set_user_active(&self, guild_id: i64, discord_id: i64, active: bool) -> Result<User, StoreError> {
+        self.with_conn(move |conn| {
+            conn.query_row(
+                    "UPDATE \"Users\" SET \"Active\" = ?3 WHERE \"GuildID\" = ?1 AND \"DiscordID\" = ?2 RETURNING *",
+                    params![guild_id, discord_id, active],
+                    Self::row_to_user)
+                .optional()?
+                .ok_or(StoreError::UserNotFound(UserRole::Target))
+        }).await
+    }
+
+    async fn merge_users(&self, guild_id: i64, from_discord_id: i64, into_discord_id: i64) -> Result<User, StoreError> {
+        if from_discord_id == into_discord_id {
+            return Err(StoreError::Integrity("Can't merge a user into themselves".to_string()));
+        }
+
+        self.with_conn(move |conn| {
+            let tx = conn.transaction_with_behavior(rusqlite::TransactionBehavior::Immediate)?;
+
+            let user_id = |discord_id: i64, role: UserRole| -> Result<i32, StoreError> {
+                tx.query_row(
+                        "SELECT \"UserID\" FROM \"Users\" WHERE \"GuildID\" = ?1 AND \"DiscordID\" = ?2",
+                        params![guild_id, discord_id],
+                        |row| row.get("UserID"))
+                    .optional()?
+                    .ok_or(StoreError::UserNotFound(role))
+            };
+            let (from, into) = (user_id(from_discord_id, UserRole::MergeFrom)?, user_id(into_discord_id, UserRole::MergeInto)?);
+
+            // The points triggers recompute both users' totals as the rows move
+            tx.execute("UPDATE \"Bbps\" SET \"UserID\" = ?2 WHERE \"UserID\" = ?1", params![from, into])?;
+            tx.execute("UPDATE \"Bbps\" SET \"IssuerID\" = ?2 WHERE \"IssuerID\" = ?1", params![from, into])?;
+            tx.execute("UPDATE \"Gbps\" SET \"UserID\" = ?2 WHERE \"UserID\" = ?1", params![from, into])?;
+            tx.execute("UPDATE \"Gbps\" SET \"IssuerID\" = ?2 WHERE \"IssuerID\" = ?1", params![from, into])?;
+            tx.execute("UPDATE \"PendingBbps\" SET \"UserID\" = ?2 WHERE \"UserID\" = ?1", params![from, into])?;
+            tx.execute("UPDATE \"PendingBbps\" SET \"IssuerID\" = ?2 WHERE \"IssuerID\" = ?1", params![from, into])?;
+
+            // Where both users voted on the same pending bbp or appeal only the second user's vote counts
+            tx.execute(
+                "DELETE FROM \"PendingBbpVotes\"
+                 WHERE \"VoterID\" = ?1
+                   AND \"PendingID\" IN (SELECT \"PendingID\" FROM \"PendingBbpVotes\" WHERE \"VoterID\" = ?2)",
+                params![from, into])?;
+            tx.execute("UPDATE \"PendingBbpVotes\" SET \"VoterID\" = ?2 WHERE \"VoterID\" = ?1", params![from, into])?;
+            tx.execute(
+                "DELETE FROM \"AppealVotes\"
+                 WHERE \"VoterID\" = ?1
+                   AND \"AppealID\" IN (SELECT \"AppealID\" FROM \"AppealVotes\" WHERE \"VoterID\" = ?2)",
+                params![from, into])?;
+            tx.execute("UPDATE \"AppealVotes\" SET \"VoterID\" = ?2 WHERE \"VoterID\" = ?1", params![from, into])?;
+
+            // Seasons both users were ranked in get one combined line, re-ranked below
+            tx.execute(
+                "UPDATE \"SeasonStandings\" AS s
+                 SET \"Points\" = s.\"Points\" + f.\"Points\",
+                     \"Score\" = s.\"Score\" + f.\"Score\",
+                     \"BbpTotal\" = s.\"BbpTotal\" + f.\"BbpTotal\",
+                     \"GbpTotal\" = s.\"GbpTotal\" + f.\"GbpTotal\",
+                     \"BbpsIssued\" = s.\"BbpsIssued\" + f.\"BbpsIssued\",
+                     \"GbpsIssued\" = s.\"GbpsIssued\" + f.\"GbpsIssued\"
+                 FROM \"SeasonStandings\" AS f
+                 WHERE f.\"SeasonID\" = s.\"SeasonID\" AND f.\"UserID\" = ?1 AND s.\"UserID\" = ?2",
+                params![from, into])?;
+            tx.execute(
+                "DELETE FROM \"SeasonStandings\"
+                 WHERE \"UserID\" = ?1
+                   AND \"SeasonID\" IN (SELECT \"SeasonID\" FROM \"SeasonStandings\" WHERE \"UserID\" = ?2)",
+                params![from, into])?;
+            tx.execute("UPDATE \"SeasonStandings\" SET \"UserID\" = ?2 WHERE \"UserID\" = ?1", params![from, into])?;
+            tx.execute(RERANK_SEASON_STANDINGS, params![guild_id])?;
+
+            tx.execute("DELETE FROM \"Users\" WHERE \"UserID\" = ?1", params![from])?;
+            let merged = tx.query_row("SELECT * FROM \"Users\" WHERE \"UserID\" = ?1", params![into], Self::row_to_user)?;
+
+            tx.commit()?;
+            Ok(merged)
+        }).await
+    }
+
+    async fn remove_user(&self, guild_id: i64, discord_id: i64) -> Result<User, StoreError> {
+        self.with_conn(move |conn| {
+            let tx = conn.transaction_with_behavior(rusqlite::TransactionBehavior::Immediate)?;
+
+            let user = tx
+                .query_row(
+                    "SELECT * FROM \"Users\" WHERE \"GuildID\" = ?1 AND \"DiscordID\" = ?2",
+                    params![guild_id, discord_id],
+                    Self::row_to_user)
+                .optional()?
+                .ok_or(StoreError::UserNotFound(UserRole::Target))?;
+
+            let history = tx
+                .query_row(
+                    "SELECT 1 FROM \"Bbps\" WHERE ?1 IN (\"UserID\", \"IssuerID\")
+                     UNION ALL
+                     SELECT 1 FROM \"Gbps\" WHERE ?1 IN (\"UserID\", \"IssuerID\")
+                     UNION ALL
+                     SELECT 1 FROM \"PendingBbps\" WHERE ?1 IN (\"UserID\", \"IssuerID\")
+                     UNION ALL
+                     SELECT 1 FROM \"PendingBbpVotes\" WHERE \"VoterID\" = ?1
+                     UNION ALL
+                     SELECT 1 FROM \"AppealVotes\" WHERE \"VoterID\" = ?1
+                     LIMIT 1",
+                    params![user.user_id],
+                    |_| Ok(()))
+                .optional()?;
+            if history.is_some() {
+                return Err(StoreError::UserHasHistory);
+            }
+
+            // Without any bbps or gbps their archived lines are all zeroes
+            tx.execute("DELETE FROM \"SeasonStandings\" WHERE \"UserID\" = ?1", params![user.user_id])?;
+            tx.execute(RERANK_SEASON_STANDINGS, params![guild_id])?;
+            tx.execute("DELETE FROM \"Users\" WHERE \"UserID\" = ?1", params![user.user_id])?;
+
+            tx.commit()?;
+            Ok(user)
+        }).await
+    }
+
+    async fn issue_point(&self, guild_id: i64, kind: PointKind, value: i32, issuer_discord_id: i64, target_discord_id: i64, description: &str) -> Result<IssuedPoint, StoreError> {
+        let timestamp = self.clock.now();
+        let description = description.to_string();
+
+        self.with_conn(move |conn| {
+            let tx = conn.transaction_with_behavior(rusqlite::TransactionBehavior::Immediate)?;
+
+            let (issuer, target) = Self::users_for_point(&tx, guild_id, issuer_discord_id, target_discord_id)?;
+            let issued = Self::insert_point(&tx, guild_id, kind, value, issuer, target, &description, timestamp)?;
+
+            tx.commit()?;
+            Ok(issued)
+        }).await
+    }
+
+    async fn set_point_message(&self, guild_id: i64, kind: PointKind, point_id: i32, channel_id: i64, message_id: i64) -> Result<(), StoreError> {
+        self.with_conn(move |conn| {
+            let (table, id_column) = match kind {
+                PointKind::Bbp => ("Bbps", "BbpID"),
+                PointKind::Gbp => ("Gbps", "GbpID"),
+            };
+            conn.execute(
+                &format!("UPDATE \"{}\" SET \"ChannelID\" = ?3, \"MessageID\" = ?4 WHERE \"{}\" = ?1 AND \"GuildID\" = ?2", table, id_column),
+                params![point_id, guild_id, channel_id, message_id])?;
+
+            Ok(())
+        }).await
+    }
+
+    async fn create_pending_bbp(&self, guild_id: i64, value: i32, issuer_discord_id: i64, target_discord_id: i64, description: &str, quorum: i32, lifetime: chrono::Duration) -> Result<PendingBbp, StoreError> {
+        let created_at = self.clock.now();
+        let expires_at = created_at + lifetime;
+        let description = description.to_string();
+
+        self.with_conn(move |conn| {
+            let tx = conn.transaction_with_behavior(rusqlite::TransactionBehavior::Immediate)?;
+
+            let (issuer, target) = Self::users_for_point(&tx, guild_id, issuer_discord_id, target_discord_id)?;
+            let pending_id: i32 = tx.query_row(
+                "INSERT INTO \"PendingBbps\" (\"GuildID\", \"UserID\", \"IssuerID\", \"Value\", \"Description\", \"Quorum\", \"CreatedAt\", \"ExpiresAt\")
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)
+                 RETURNING \"PendingID\"",
+                params![guild_id, target.user_id, issuer.user_id, value, description, quorum, created_at, expires_at],
+                |row| row.get("PendingID"))?;
+            let pending = Self::get_pending_bbp_blocking(&tx, pending_id)?;
+
+            tx.commit()?;
+            Ok(pending)
+        }).await
+    }
+
+    async fn set_pending_bbp_message(&self, pending_id: i32, channel_id: i64, message_id: i64) -> Result<(), StoreError> {
+        self.with_conn(move |conn| {
+            conn.execute(
+                "UPDATE \"PendingBbps\" SET \"ChannelID\" = ?2, \"MessageID\" = ?3 WHERE \"PendingID\" = ?1",
+                params![pending_id, channel_id, message_id])?;
+
+            Ok(())
+        }).await
+    }
+
+    async fn vote_on_pending_bbp(&self, pending_id: i32, voter_discord_id: i64, in_favour: bool) -> Result<JuryVote, StoreError> {
+        let now = self.clock.now();
+
+        self.with_conn(move |conn| {
+            let tx = conn.transaction_with_behavior(rusqlite::TransactionBehavior::Immediate)?;
+
+            let (status, expires_at, guild_id, accused, accuser) = tx
+                .query_row(
+                    "SELECT * FROM \"PendingBbps\" WHERE \"PendingID\" = ?1",
+                    params![pending_id],
+                    |row| Ok((
+                        row.get::<_, String>("Status")?,
+                        row.get::<_, chrono::NaiveDateTime>("ExpiresAt")?,
+                        row.get::<_, i64>("GuildID")?,
+                        row.get::<_, i32>("UserID")?,
+                        row.get::<_, i32>("IssuerID")?)))
+                .optional()?
+                .ok_or(StoreError::VotingClosed)?;
+            if JuryStatus::parse(&status) != JuryStatus::Pending || now >= expires_at {
+                return Err(StoreError::VotingClosed);
+            }
+
+            let voter = tx
+                .query_row(
+                    "SELECT * FROM \"Users\" WHERE \"GuildID\" = ?1 AND \"DiscordID\" = ?2",
+                    params![guild_id, voter_discord_id],
+                    Self::row_to_user)
+                .optional()?
+                .ok_or(StoreError::UserNotFound(UserRole::Issuer))?;
+            if !voter.active {
+                return Err(StoreError::UserInactive(UserRole::Issuer));
+            }
+            if voter.user_id == accused || voter.user_id == accuser {
+                return Err(StoreError::OwnAccusation);
+            }
+
+            tx.execute(
+                "INSERT INTO \"PendingBbpVotes\" (\"PendingID\", \"VoterID\", \"InFavour\", \"VotedAt\")
+                 VALUES (?1, ?2, ?3, ?4)
+                 ON CONFLICT (\"PendingID\", \"VoterID\") DO UPDATE
+                 SET \"InFavour\" = excluded.\"InFavour\", \"VotedAt\" = excluded.\"VotedAt\"",
+                params![pending_id, voter.user_id, in_favour, now])?;
+
+            let mut pending = Self::get_pending_bbp_blocking(&tx, pending_id)?;
+            let verdict = pending.verdict();
+            let issued = match verdict {
+                JuryStatus::Confirmed => {
+                    let (issuer, target) = Self::users_for_point(&tx, guild_id, pending.issuer_discord_id, pending.target_discord_id)?;
+                    Some(Self::insert_point(&tx, guild_id, PointKind::Bbp, pending.value, issuer, target, &pending.description, now)?)
+                },
+                _ => None,
+            };
+            if verdict != JuryStatus::Pending {
+                let bbp_id = issued.as_ref().map(|issued| issued.point_id);
+                tx.execute(
+                    "UPDATE \"PendingBbps\" SET \"Status\" = ?2, \"BbpID\" = ?3 WHERE \"PendingID\" = ?1",
+                    params![pending_id, verdict.as_str(), bbp_id])?;
+                pending.status = verdict;
+                pending.bbp_id = bbp_id;
+            }
+
+            tx.commit()?;
+            Ok(JuryVote { pending, issued })
+        }).await
+    }
+
+    async fn expire_pending_bbps(&self) -> Result<Vec<PendingBbp>, StoreError> {
+        let now = self.clock.now();
+
+        self.with_conn(move |conn| {
+            let tx = conn.transaction_with_behavior(rusqlite::TransactionBehavior::Immediate)?;
+
+            let expired = tx
+                .prepare(
+                    "UPDATE \"PendingBbps\" SET \"Status\" = 'expired'
+                     WHERE \"Status\" = 'pending' AND \"ExpiresAt\" <= ?1
+                     RETURNING \"PendingID\"")?
+                .query_map(params![now], |row| row.get::<_, i32>("PendingID"))?
+                .collect::<Result<Vec<_>, _>>()?;
+            let mut pending = expired
+                .into_iter()
+                .map(|pending_id| Self::get_pending_bbp_blocking(&tx, pending_id))
+                .collect::<Result<Vec<_>, _>>()?;
+            pending.sort_by_key(|p| p.pending_id);
+
+            tx.commit()?;
+            Ok(pending)
+        }).await
+    }
+
+    async fn get_recent_bbps(&self, guild_id: i64, target_discord_id: i64, issuer_discord_id: Option<i64>, forgiven: bool, limit: i64) -> Result<Vec<Bbp>, StoreError> {
+        self.with_conn(move |conn| {
+            let bbps = conn
+                .prepare(&format!(
+                    "{} WHERE {}
+                     ORDER BY b.\"Timestamp\" DESC, b.\"BbpID\" DESC
+                     LIMIT ?5",
+                    BBPS, RECENT_BBPS))?
+                .query_map(params![guild_id, target_discord_id, issuer_discord_id, forgiven, limit], Self::row_to_bbp)?
+                .collect::<Result<Vec<_>, _>>()?;
+
+            Ok(bbps)
+        }).await
+    }
+
+    async fn open_appeal(&self, guild_id: i64, bbp_id: i32, appellant_discord_id: i64, statement: &str, quorum: i32) -> Result<Appeal, StoreError> {
+        let statement = statement.to_string();
+        let now = self.clock.now();
+
+        self.with_conn(move |conn| {
+            let tx = conn.transaction_with_behavior(rusqlite::TransactionBehavior::Immediate)?;
+
+            let bbp = tx
+                .query_row(
+                    &format!("{} WHERE b.\"BbpID\" = ?1 AND b.\"GuildID\" = ?2", BBPS),
+                    params![bbp_id, guild_id],
+                    Self::row_to_bbp)
+                .optional()?
+                .ok_or(StoreError::BbpNotFound)?;
+            if bbp.target_discord_id != appellant_discord_id || bbp.forgiven || bbp.season_id.is_some() {
+                return Err(StoreError::NotAppealable);
+            }
+
+            let appeal_id: i32 = tx
+                .query_row(
+                    "INSERT INTO \"Appeals\" (\"BbpID\", \"Statement\", \"Quorum\", \"OpenedAt\")
+                     VALUES (?1, ?2, ?3, ?4)
+                     ON CONFLICT (\"BbpID\") DO NOTHING
+                     RETURNING \"AppealID\"",
+                    params![bbp_id, statement, quorum, now],
+                    |row| row.get("AppealID"))
+                .optional()?
+                .ok_or(StoreError::NotAppealable)?;
+            let appeal = Self::get_appeal_blocking(&tx, appeal_id)?
+                .ok_or_else(|| StoreError::Integrity("Appeal not found".to_string()))?;
+
+            tx.commit()?;
+            Ok(appeal)
+        }).await
+    }
+
+    async fn set_appeal_message(&self, appeal_id: i32, channel_id: i64, message_id: i64) -> Result<(), StoreError> {
+        self.with_conn(move |conn| {
+            conn.execute(
+                "UPDATE \"Appeals\" SET \"ChannelID\" = ?2, \"MessageID\" = ?3 WHERE \"AppealID\" = ?1",
+                params![appeal_id, channel_id, message_id])?;
+
+            Ok(())
+        }).await
+    }
+
+    async fn vote_on_appeal(&self, appeal_id: i32, voter_discord_id: i64, overturn: bool) -> Result<Appeal, StoreError> {
+        let now = self.clock.now();
+
+        self.with_conn(move |conn| {
+            let tx = conn.transaction_with_behavior(rusqlite::TransactionBehavior::Immediate)?;
+
+            let appeal = Self::get_open_appeal_blocking(&tx, appeal_id)?;
+            if appeal.quorum <= 0 {
+                return Err(StoreError::NoCommunityVote);
+            }
+            let voter = tx
+                .query_row(
+                    "SELECT * FROM \"Users\" WHERE \"GuildID\" = ?1 AND \"DiscordID\" = ?2",
+                    params![appeal.bbp.guild_id, voter_discord_id],
+                    Self::row_to_user)
+                .optional()?
+                .ok_or(StoreError::UserNotFound(UserRole::Issuer))?;
+            if !voter.active {
+                return Err(StoreError::UserInactive(UserRole::Issuer));
+            }
+            if voter.discord_id == appeal.bbp.target_discord_id || voter.discord_id == appeal.bbp.issuer_discord_id {
+                return Err(StoreError::OwnAccusation);
+            }
+
+            tx.execute(
+                "INSERT INTO \"AppealVotes\" (\"AppealID\", \"VoterID\", \"Overturn\", \"VotedAt\")
+                 VALUES (?1, ?2, ?3, ?4)
+                 ON CONFLICT (\"AppealID\", \"VoterID\") DO UPDATE
+                 SET \"Overturn\" = excluded.\"Overturn\", \"VotedAt\" = excluded.\"VotedAt\"",
+                params![appeal_id, voter.user_id, overturn, now])?;
+
+            let mut appeal = Self::get_open_appeal_blocking(&tx, appeal_id)?;
+            let verdict = appeal.verdict();
+            if verdict != AppealStatus::Open {
+                Self::settle_appeal(&tx, &mut appeal, verdict, None, now)?;
+            }
+
+            tx.commit()?;
+            Ok(appeal)
+        }).await
+    }
+
+    async fn resolve_appeal(&self, appeal_id: i32, moderator_discord_id: i64, overturn: bool) -> Result<Appeal, StoreError> {
+        let now = self.clock.now();
+
+        self.with_conn(move |conn| {
+            let tx = conn.transaction_with_behavior(rusqlite::TransactionBehavior::Immediate)?;
+
+            let mut appeal = Self::get_open_appeal_blocking(&tx, appeal_id)?;
+            if moderator_discord_id == appeal.bbp.target_discord_id || moderator_discord_id == appeal.bbp.issuer_discord_id {
+                return Err(StoreError::OwnAccusation);
+            }
+            let status = if overturn { AppealStatus::Overturned } else { AppealStatus::Upheld };
+            Self::settle_appeal(&tx, &mut appeal, status, Some(moderator_discord_id), now)?;
+
+            tx.commit()?;
+            Ok(appeal)
+        }).await
+    }
+
+    async fn forgive_bbp(&self, guild_id: i64, target_discord_id: i64, issuer_discord_id: Option<i64>, bbp_id: Option<i32>, forgiver_discord_id: i64) -> Result<Bbp, StoreError> {
+        let now = self.clock.now();
+
+        self.with_conn(move |conn| {
+            Self::change_forgiven_blocking(conn, guild_id, target_discord_id, issuer_discord_id, bbp_id, Some(forgiver_discord_id), now)?
+                .ok_or(StoreError::NothingToForgive)
+        }).await
+    }
+
+    async fn unforgive_bbp(&self, guild_id: i64, target_discord_id: i64, issuer_discord_id: Option<i64>, bbp_id: Option<i32>) -> Result<Bbp, StoreError> {
+        let now = self.clock.now();
+
+        self.with_conn(move |conn| {
+            Self::change_forgiven_blocking(conn, guild_id, target_discord_id, issuer_discord_id, bbp_id, None, now)?
+                .ok_or(StoreError::NothingToUnforgive)
+        }).await
+    }
+
+    async fn get_point(&self, guild_id: i64, kind: PointKind, point_id: i32) -> Result<Option<PointDetails>, StoreError> {
+        self.with_conn(move |conn| {
+            let query = match kind {
+                PointKind::Bbp => "SELECT p.*, p.\"BbpID\" AS \"PointID\"
+                                   FROM \"Bbps\" p",
+                PointKind::Gbp => "SELECT p.*, p.\"GbpID\" AS \"PointID\", 0 AS \"Forgiven\", NULL AS \"ForgivenByDiscordID\", NULL AS \"ForgivenAt\"
+                                   FROM \"Gbps\" p",
+            };
+            let point = conn
+                .query_row(
+                    &format!(
+                        "SELECT h.*, t.\"DiscordID\" AS \"TargetDiscordID\", t.\"FriendlyName\" AS \"TargetFriendlyName\",
+                                i.\"DiscordID\" AS \"IssuerDiscordID\", i.\"FriendlyName\" AS \"IssuerFriendlyName\"
+                         FROM ({}) h
+                         JOIN \"Users\" t ON t.\"UserID\" = h.\"UserID\"
+                         JOIN \"Users\" i ON i.\"UserID\" = h.\"IssuerID\"
+                         WHERE h.\"PointID\" = ?1 AND h.\"GuildID\" = ?2",
+                        query),
+                    params![point_id, guild_id],
+                    |row| Ok(PointDetails {
+                        kind,
+                        point_id,
+                        guild_id,
+                        target_discord_id: row.get("TargetDiscordID")?,
+                        target_friendly_name: row.get::<_, Option<String>>("TargetFriendlyName")?.unwrap_or_default(),
+                        issuer_discord_id: row.get("IssuerDiscordID")?,
+                        issuer_friendly_name: row.get::<_, Option<String>>("IssuerFriendlyName")?.unwrap_or_default(),
+                        value: row.get("Value")?,
+                        description: row.get("Description")?,
+                        timestamp: row.get("Timestamp")?,
+                        forgiven: row.get("Forgiven")?,
+                        forgiven_by_discord_id: row.get("ForgivenByDiscordID")?,
+                        forgiven_at: row.get("ForgivenAt")?,
+                        season_id: row.get("SeasonID")?,
+                        channel_id: row.get("ChannelID")?,
+                        message_id: row.get("MessageID")?,
+                        jury: None,
+                        appeal: None,
+                    }))
+                .optional()?;
+            let Some(mut point) = point else {
+                return Ok(None);
+            };
+
+            if kind == PointKind::Bbp {
+                point.jury = conn
+                    .query_row(&format!("{} WHERE p.\"BbpID\" = ?1", PENDING_BBPS), params![point_id], Self::row_to_pending_bbp)
+                    .optional()?;
+                point.appeal = conn
+                    .query_row(&format!("{} WHERE a.\"BbpID\" = ?1", APPEALS), params![point_id], Self::row_to_appeal)
+                    .optional()?;
+            }
+
+            Ok(Some(point))
+        }).await
+    }
+
+    async fn edit_point(&self, guild_id: i64, kind: PointKind, point_id: i32, issuer_discord_id: i64, description: &str, grace: chrono::Duration) -> Result<AuditEntry, StoreError> {
+        let description = description.to_string();
+        let now = self.clock.now();
+
+        self.with_conn(move |conn| {
+            Self::change_own_point_blocking(conn, guild_id, kind, point_id, issuer_discord_id, Some(description), grace, now)
+        }).await
+    }
+
+    async fn retract_point(&self, guild_id: i64, kind: PointKind, point_id: i32, issuer_discord_id: i64, grace: chrono::Duration) -> Result<AuditEntry, StoreError> {
+        let now = self.clock.now();
+
+        self.with_conn(move |conn| {
+            Self::change_own_point_blocking(conn, guild_id, kind, point_id, issuer_discord_id, None, grace, now)
+        }).await
+    }
+
+    async fn get_audit_log(&self, guild_id: i64, page: Page) -> Result<Vec<AuditEntry>, StoreError> {
+        self.with_conn(move |conn| {
+            let mut stmt = conn.prepare(
+                "SELECT * FROM \"AuditLog\"
+                 WHERE \"GuildID\" = ?1
+                 ORDER BY \"At\" DESC, \"AuditID\" DESC
+                 LIMIT ?3 OFFSET ?2")?;
+            let rows = stmt
+                .query_map(params![guild_id, page.offset, page.limit], Self::row_to_audit_entry)?
+                .collect::<Result<Vec<_>, _>>()?;
+
+            rows.into_iter().map(Self::check_audit_kind).collect()
+        }).await
+    }
+
+    async fn get_leaderboard(&self, guild_id: i64, standings: Standings, page: Page) -> Result<Vec<LeaderboardUser>, StoreError> {
+        let now = self.clock.now();
+
+        self.with_conn(move |conn| {
+            let source = Self::resolve_standings(conn, guild_id, standings, now)?;
+            let (ranked_users, mut params) = source.query(&guild_id);
+            let sql = format!(
+                "SELECT *
+                 FROM ({}) ranked_users
+                 ORDER BY \"Rank\", \"UserID\"
+                 LIMIT ?{} OFFSET ?{}",
+                ranked_users, params.len() + 2, params.len() + 1);
+            params.extend_from_slice(&[&page.offset, &page.limit]);
+
+            let leaderboard = conn
+                .prepare(&sql)?
+                .query_map(params_from_iter(params), Self::row_to_leaderboard_user)?
+                .collect::<Result<Vec<_>, _>>()?;
+
+            Ok(leaderboard)
+        }).await
+    }
+
+    async fn get_leaderboard_position(&self, guild_id: i64, standings: Standings, discord_id: i64) -> Result<Option<i64>, StoreError> {
+        let now = self.clock.now();
+
+        self.with_conn(move |conn| {
+            let source = Self::resolve_standings(conn, guild_id, standings, now)?;
+            let (ranked_users, mut params) = source.query(&guild_id);
+            let sql = format!(
+                "SELECT \"Position\"
+                 FROM ({}) ranked_users
+                 WHERE \"DiscordID\" = ?{}",
+                ranked_users, params.len() + 1);
+            params.push(&discord_id);
+
+            let position = conn
+                .query_row(&sql, params_from_iter(params), |row| row.get("Position"))
+                .optional()?;
+
+            Ok(position)
+        }).await
+    }
+
+    async fn get_user_history(&self, guild_id: i64, discord_id: i64, filter: HistoryFilter, page: Page) -> Result<Vec<HistoryRecord>, StoreError> {
+        let now = self.clock.now();
+
+        self.with_conn(move |conn| {
+            // First, get the UserID from the Users table using the DiscordID
+            let user_id: i32 = conn
+                .query_row(
+                    "SELECT \"UserID\" FROM \"Users\" WHERE \"GuildID\" = ?1 AND \"DiscordID\" = ?2",
+                    params![guild_id, discord_id],
+                    |row| row.get("UserID"))
+                .optional()?
+                .ok_or(StoreError::UserNotFound(UserRole::Target))?;
+
+            // A running season's rows aren't tagged yet
+            let season_id = match filter.season {
+                Some(number) => match Self::resolve_standings(conn, guild_id, Standings::Season(number), now)? {
+                    StandingsSource::Snapshot(season_id) => Some(season_id),
+                    StandingsSource::Live(..) => None,
+                },
+                None => None,
+            };
+
+            // Then, get the bbps and gbps for the UserID as one timeline
+            let kind = filter.kind.map(PointKind::as_str);
+            let rows = conn
+                .prepare(
+                    "SELECT h.*, u.\"FriendlyName\", u.\"DiscordID\" AS \"IssuerDiscordID\"
+                     FROM (
+                         SELECT 'bbp' AS \"Kind\", b.\"BbpID\" AS \"PointID\", b.\"Value\", b.\"Forgiven\", b.\"Description\", b.\"Timestamp\", b.\"IssuerID\", (SELECT a.\"Status\" FROM \"Appeals\" a WHERE a.\"BbpID\" = b.\"BbpID\") AS \"AppealStatus\", b.\"SeasonID\"
+                         FROM \"Bbps\" b
+                         WHERE b.\"UserID\" = ?1
+                         UNION ALL
+                         SELECT 'gbp' AS \"Kind\", g.\"GbpID\", g.\"Value\", 0, g.\"Description\", g.\"Timestamp\", g.\"IssuerID\", NULL, g.\"SeasonID\"
+                         FROM \"Gbps\" g
+                         WHERE g.\"UserID\" = ?1
+                     ) h
+                     JOIN \"Users\" u ON h.\"IssuerID\" = u.\"UserID\"
+                     WHERE (?2 IS NULL OR h.\"Kind\" = ?2)
+                       AND (?3 IS NULL OR u.\"DiscordID\" = ?3)
+                       AND (NOT ?6 OR h.\"SeasonID\" IS ?7)
+                     ORDER BY h.\"Timestamp\" DESC, h.\"Kind\", h.\"PointID\" DESC
+                     LIMIT ?5 OFFSET ?4")?
+                .query_map(params![user_id, kind, filter.issuer_discord_id, page.offset, page.limit, filter.season.is_some(), season_id], Self::row_to_history_record)?
+                .collect::<Result<Vec<_>, _>>()?;
+
+            rows.into_iter().map(Self::check_history_kind).collect()
+        }).await
+    }
+
+    async fn get_user_stats(&self, guild_id: i64, discord_id: i64) -> Result<UserStats, StoreError> {
+        self.with_conn(move |conn| {
+            let user_id: i32 = conn
+                .query_row(
+                    "SELECT \"UserID\" FROM \"Users\" WHERE \"GuildID\" = ?1 AND \"DiscordID\" = ?2",
+                    params![guild_id, discord_id],
+                    |row| row.get("UserID"))
+                .optional()?
+                .ok_or(StoreError::UserNotFound(UserRole::Target))?;
+
+            let forgiven = conn.query_row(
+                "SELECT COUNT(*) FROM \"Bbps\" WHERE \"UserID\" = ?1 AND \"Forgiven\" = 1",
+                params![user_id],
+                |row| row.get(0))?;
+
+            let top_issuers = conn
+                .prepare(
+                    "SELECT u.\"DiscordID\", u.\"FriendlyName\", SUM(b.\"Value\") AS \"Points\"
+                     FROM \"Bbps\" b
+                     JOIN \"Users\" u ON u.\"UserID\" = b.\"IssuerID\"
+                     WHERE b.\"UserID\" = ?1 AND b.\"Forgiven\" = 0
+                     GROUP BY u.\"UserID\"
+                     ORDER BY \"Points\" DESC, u.\"UserID\"
+                     LIMIT 3")?
+                .query_map(params![user_id], Self::row_to_point_tally)?
+                .collect::<Result<Vec<_>, _>>()?;
+
+            let favourite_target = conn
+                .query_row(
+                    "SELECT u.\"DiscordID\", u.\"FriendlyName\", SUM(b.\"Value\") AS \"Points\"
+                     FROM \"Bbps\" b
+                     JOIN \"Users\" u ON u.\"UserID\" = b.\"UserID\"
+                     WHERE b.\"IssuerID\" = ?1 AND b.\"Forgiven\" = 0
+                     GROUP BY u.\"UserID\"
+                     ORDER BY \"Points\" DESC, u.\"UserID\"
+                     LIMIT 1",
+                    params![user_id],
+                    Self::row_to_point_tally)
+                .optional()?;
+
+            let offences = conn
+                .prepare("SELECT \"Timestamp\" FROM \"Bbps\" WHERE \"UserID\" = ?1 AND \"Forgiven\" = 0 ORDER BY \"Timestamp\"")?
+                .query_map(params![user_id], |row| row.get("Timestamp"))?
+                .collect::<Result<Vec<_>, _>>()?;
+
+            Ok(UserStats { forgiven, top_issuers, favourite_target, offences })
+        }).await
+    }
+
+    async fn get_rivalry(&self, guild_id: i64, a_discord_id: i64, b_discord_id: i64, recent: i64) -> Result<Rivalry, StoreError> {
+        self.with_conn(move |conn| {
+            let mut user_ids = Vec::with_capacity(2);
+            for discord_id in [a_discord_id, b_discord_id] {
+                let user_id: i32 = conn
+                    .query_row(
+                        "SELECT \"UserID\" FROM \"Users\" WHERE \"GuildID\" = ?1 AND \"DiscordID\" = ?2",
+                        params![guild_id, discord_id],
+                        |row| row.get("UserID"))
+                    .optional()?
+                    .ok_or(StoreError::UserNotFound(UserRole::Target))?;
+                user_ids.push(user_id);
+            }
+            let (a, b) = (user_ids[0], user_ids[1]);
+
+            let a_to_b = conn.query_row(EXCHANGE, params![a, b], Self::row_to_exchange)?;
+            let b_to_a = conn.query_row(EXCHANGE, params![b, a], Self::row_to_exchange)?;
+
+            let rows = conn
+                .prepare(
+                    "SELECT h.*, u.\"FriendlyName\", u.\"DiscordID\" AS \"IssuerDiscordID\"
+                     FROM (
+                         SELECT 'bbp' AS \"Kind\", b.\"BbpID\" AS \"PointID\", b.\"Value\", b.\"Forgiven\", b.\"Description\", b.\"Timestamp\", b.\"IssuerID\", (SELECT a.\"Status\" FROM \"Appeals\" a WHERE a.\"BbpID\" = b.\"BbpID\") AS \"AppealStatus\"
+                         FROM \"Bbps\" b
+                         WHERE (b.\"IssuerID\" = ?1 AND b.\"UserID\" = ?2) OR (b.\"IssuerID\" = ?2 AND b.\"UserID\" = ?1)
+                         UNION ALL
+                         SELECT 'gbp' AS \"Kind\", g.\"GbpID\", g.\"Value\", 0, g.\"Description\", g.\"Timestamp\", g.\"IssuerID\", NULL
+                         FROM \"Gbps\" g
+                         WHERE (g.\"IssuerID\" = ?1 AND g.\"UserID\" = ?2) OR (g.\"IssuerID\" = ?2 AND g.\"UserID\" = ?1)
+                     ) h
+                     JOIN \"Users\" u ON h.\"IssuerID\" = u.\"UserID\"
+                     ORDER BY h.\"Timestamp\" DESC, h.\"Kind\", h.\"PointID\" DESC
+                     LIMIT ?3")?
+                .query_map(params![a, b, recent], Self::row_to_history_record)?
+                .collect::<Result<Vec<_>, _>>()?;
+            let recent = rows.into_iter().map(Self::check_history_kind).collect::<Result<_, _>>()?;
+
+            Ok(Rivalry { a_to_b, b_to_a, recent })
+        }).await
+    }
+
+    async fn get_point_timeline(&self, guild_id: i64, discord_id: i64, window: TimeWindow) -> Result<Vec<HistoryRecord>, StoreError> {
+        self.with_conn(move |conn| {
+            let user_id: i32 = conn
+                .query_row(
+                    "SELECT \"UserID\" FROM \"Users\" WHERE \"GuildID\" = ?1 AND \"DiscordID\" = ?2",
+                    params![guild_id, discord_id],
+                    |row| row.get("UserID"))
+                .optional()?
+                .ok_or(StoreError::UserNotFound(UserRole::Target))?;
+
+            let rows = conn
+                .prepare(
+                    "SELECT h.*, u.\"FriendlyName\", u.\"DiscordID\" AS \"IssuerDiscordID\"
+                     FROM (
+                         SELECT 'bbp' AS \"Kind\", b.\"BbpID\" AS \"PointID\", b.\"Value\", b.\"Forgiven\", b.\"Description\", b.\"Timestamp\", b.\"IssuerID\", (SELECT a.\"Status\" FROM \"Appeals\" a WHERE a.\"BbpID\" = b.\"BbpID\") AS \"AppealStatus\", b.\"SeasonID\"
+                         FROM \"Bbps\" b
+                         WHERE b.\"UserID\" = ?1
+                         UNION ALL
+                         SELECT 'gbp' AS \"Kind\", g.\"GbpID\", g.\"Value\", 0, g.\"Description\", g.\"Timestamp\", g.\"IssuerID\", NULL, g.\"SeasonID\"
+                         FROM \"Gbps\" g
+                         WHERE g.\"UserID\" = ?1
+                     ) h
+                     JOIN \"Users\" u ON h.\"IssuerID\" = u.\"UserID\"
+                     WHERE (?2 IS NULL OR h.\"Timestamp\" >= ?2)
+                       AND (?3 IS NULL OR h.\"Timestamp\" < ?3)
+                       AND (?4 OR h.\"SeasonID\" IS NULL)
+                     ORDER BY h.\"Timestamp\", h.\"Kind\" DESC, h.\"PointID\"")?
+                .query_map(params![user_id, window.from, window.to, window.all_seasons], Self::row_to_history_record)?
+                .collect::<Result<Vec<_>, _>>()?;
+
+            rows.into_iter().map(Self::check_history_kind).collect()
+        }).await
+    }
+
+    async fn start_season(&self, guild_id: i64) -> Result<Season, StoreError> {
+        let started_at = self.clock.now();
+
+        self.with_conn(move |conn| {
+            let season = conn
+                .query_row(
+                    "INSERT INTO \"Seasons\" (\"GuildID\", \"Number\", \"StartedAt\")
+                     SELECT ?1, COALESCE(MAX(\"Number\"), 0) + 1, ?2
+                     FROM \"Seasons\"
+                     WHERE \"GuildID\" = ?1
+                     RETURNING *",
+                    params![guild_id, started_at],
+                    Self::row_to_season)
+                .map_err(|e| match StoreError::from(e) {
+                    StoreError::Duplicate(_) => StoreError::Duplicate(Existing::RunningSeason),
+                    e => e,
+                })?;
+
+            Ok(season)
+        }).await
+    }
+
+    async fn end_season(&self, guild_id: i64) -> Result<ClosedSeason, StoreError> {
+        let ended_at = self.clock.now();
+
+        self.with_conn(move |conn| {
+            let tx = conn.transaction_with_behavior(rusqlite::TransactionBehavior::Immediate)?;
+
+            let season_id: i32 = tx
+                .query_row(
+                    "SELECT \"SeasonID\" FROM \"Seasons\" WHERE \"GuildID\" = ?1 AND \"EndedAt\" IS NULL",
+                    params![guild_id],
+                    |row| row.get("SeasonID"))
+                .optional()?
+                .ok_or(StoreError::NoOpenSeason)?;
+
+            let running = StandingsSource::Live(TimeWindow::default(), ended_at);
+            let (ranked_users, mut params) = running.query(&guild_id);
+            let snapshot = format!(
+                "INSERT INTO \"SeasonStandings\" (\"SeasonID\", \"UserID\", \"Rank\", \"Points\", \"Score\", \"BbpTotal\", \"GbpTotal\", \"BbpsIssued\", \"GbpsIssued\")
+                 SELECT ?{}, \"UserID\", \"Rank\", \"Points\", \"Score\", \"BbpTotal\", \"GbpTotal\", \"BbpsIssued\", \"GbpsIssued\"
+                 FROM ({}) ranked_users",
+                params.len() + 1, ranked_users);
+            params.push(&season_id);
+            tx.execute(&snapshot, params_from_iter(params))?;
+
+            // Archiving resets the cached totals through the points triggers
+            tx.execute("UPDATE \"Bbps\" SET \"SeasonID\" = ?1 WHERE \"GuildID\" = ?2 AND \"SeasonID\" IS NULL", params![season_id, guild_id])?;
+            tx.execute("UPDATE \"Gbps\" SET \"SeasonID\" = ?1 WHERE \"GuildID\" = ?2 AND \"SeasonID\" IS NULL", params![season_id, guild_id])?;
+
+            let season = tx.query_row(
+                "UPDATE \"Seasons\" SET \"EndedAt\" = ?2 WHERE \"SeasonID\" = ?1 RETURNING *",
+                params![season_id, ended_at],
+                Self::row_to_season)?;
+            let standings = tx
+                .prepare(&format!("SELECT * FROM ({}) ranked_users ORDER BY \"Rank\", \"UserID\"", SEASON_STANDINGS))?
+                .query_map(params![season_id], Self::row_to_leaderboard_user)?
+                .collect::<Result<Vec<_>, _>>()?;
+
+            tx.commit()?;
+            Ok(ClosedSeason { season, standings })
+        }).await
+    }
+
+    async fn get_seasons(&self, guild_id: i64) -> Result<Vec<Season>, StoreError> {
+        self.with_conn(move |conn| {
+            let seasons = conn
+                .prepare("SELECT * FROM \"Seasons\" WHERE \"GuildID\" = ?1 ORDER BY \"Number\"")?
+                .query_map(params![guild_id], Self::row_to_season)?
+                .collect::<Result<Vec<_>, _>>()?;
+
+            Ok(seasons)
+        }).await
+    }
+
+    async fn get_season(&self, guild_id: i64, number: i32) -> Result<Season, StoreError> {
+        self.with_conn(move |conn| Self::get_season_blocking(conn, guild_id, number)).await
+    }
+
+    async fn get_guild_settings(&self, guild_id: i64) -> Result<GuildSettings, StoreError> {
+        self.with_conn(move |conn| {
+            let settings = conn
+                .query_row(
+                    "SELECT * FROM \"GuildSettings\" WHERE \"GuildID\" = ?1",
+                    params![guild_id],
+                    Self::row_to_guild_settings)
+                .optional()?;
+
+            Ok(settings.unwrap_or(GuildSettings { guild_id, ..GuildSettings::default() }))
+        }).await
+    }
+
+    async fn save_guild_settings(&self, settings: GuildSettings) -> Result<GuildSettings, StoreError> {
+        self.with_conn(move |conn| {
+            let settings = conn.query_row(
+                "INSERT INTO \"GuildSettings\" (\"GuildID\", \"DecayMode\", \"DecayDays\", \"MaxValue\", \"ModeratorMaxValue\", \"ModeratorRoleID\",
+                                              \"RegistrationPolicy\", \"ModeratorChannelID\", \"AutoRegister\", \"EnrolNewMembers\",
+                                              \"JuryQuorum\", \"JuryHours\", \"GraceMinutes\")
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13)
+                 ON CONFLICT (\"GuildID\") DO UPDATE
+                 SET \"DecayMode\" = excluded.\"DecayMode\",
+                     \"DecayDays\" = excluded.\"DecayDays\",
+                     \"MaxValue\" = excluded.\"MaxValue\",
+                     \"ModeratorMaxValue\" = excluded.\"ModeratorMaxValue\",
+                     \"ModeratorRoleID\" = excluded.\"ModeratorRoleID\",
+                     \"RegistrationPolicy\" = excluded.\"RegistrationPolicy\",
+                     \"ModeratorChannelID\" = excluded.\"ModeratorChannelID\",
+                     \"AutoRegister\" = excluded.\"AutoRegister\",
+                     \"EnrolNewMembers\" = excluded.\"EnrolNewMembers\",
+                     \"JuryQuorum\" = excluded.\"JuryQuorum\",
+                     \"JuryHours\" = excluded.\"JuryHours\",
+                     \"GraceMinutes\" = excluded.\"GraceMinutes\"
+                 RETURNING *",
+                params![settings.guild_id, settings.decay.mode(), settings.decay.days(),
+                        settings.max_value, settings.moderator_max_value, settings.moderator_role_id,
+                        settings.registration.as_str(), settings.moderator_channel_id, settings.auto_register,
+                        settings.enrol_new_members, settings.jury.quorum(), settings.jury.hours(), settings.grace_minutes],
+                Self::row_to_guild_settings)?;
+
+            Ok(settings)
+        }).await
+    }
+
+    async fn update_discord_username(&self, discord_id: i64, discord_username: &str) -> Result<u64, StoreError> {
+        let discord_username = discord_username.to_string();
+        let discord_mention = format!("<@{}>", discord_id);
+
+        self.with_conn(move |conn| {
+            let updated = conn.execute(
+                "UPDATE \"Users\" SET \"DiscordUsername\" = ?2, \"DiscordMention\" = ?3
+                 WHERE \"DiscordID\" = ?1
+                   AND (\"DiscordUsername\" IS NOT ?2 OR \"DiscordMention\" IS NOT ?3)",
+                params![discord_id, discord_username, discord_mention])?;
+
+            Ok(updated as u64)
+        }).await
+    }
+
+    async fn request_registration(&self, guild_id: i64, discord_id: i64, discord_username: &str, friendly_name: &str) -> Result<RegistrationRequest, StoreError> {
+        let discord_username = discord_username.to_string();
+        let friendly_name = friendly_name.to_string();
+        let now = self.clock.now();
+
+        self.with_conn(move |conn| {
+            let registered = conn
+                .query_row(
+                    "SELECT 1 FROM \"Users\" WHERE \"GuildID\" = ?1 AND \"DiscordID\" = ?2",
+                    params![guild_id, discord_id],
+                    |_| Ok(()))
+                .optional()?;
+            if registered.is_some() {
+                return Err(StoreError::Duplicate(Existing::User));
+            }
+
+            conn.query_row(
+                    "INSERT INTO \"RegistrationRequests\" (\"GuildID\", \"DiscordID\", \"DiscordUsername\", \"FriendlyName\", \"RequestedAt\")
+                     VALUES (?1, ?2, ?3, ?4, ?5)
+                     ON CONFLICT DO NOTHING
+                     RETURNING *",
+                    params![guild_id, discord_id, discord_username, friendly_name, now],
+                    Self::row_to_registration_request)
+                .optional()?
+                .ok_or(StoreError::Duplicate(Existing::JoinRequest))
+        }).await
+    }
+
+    async fn take_registration_request(&self, guild_id: i64, discord_id: i64) -> Result<Option<RegistrationRequest>, StoreError> {
+        self.with_conn(move |conn| {
+            let request = conn
+                .query_row(
+                    "DELETE FROM \"RegistrationRequests\" WHERE \"GuildID\" = ?1 AND \"DiscordID\" = ?2 RETURNING *",
+                    params![guild_id, discord_id],
+                    Self::row_to_registration_request)
+                .optional()?;
+
+            Ok(request)
+        }).await
+    }
+
+    async fn claim_legacy_rows(&self, guild_id: i64) -> Result<u64, StoreError> {
+        self.with_conn(move |conn| {
+            let tx = conn.transaction()?;
+
+            let claimed = tx.execute("UPDATE \"Users\" SET \"GuildID\" = ?1 WHERE \"GuildID\" = 0", params![guild_id])?;
+            tx.execute("UPDATE \"Bbps\" SET \"GuildID\" = ?1 WHERE \"GuildID\" = 0", params![guild_id])?;
+            tx.execute("UPDATE \"Gbps\" SET \"GuildID\" = ?1 WHERE \"GuildID\" = 0", params![guild_id])?;
+
+            tx.commit()?;
+            Ok(claimed as u64)
+        }).await
+    }
+}
+
+#[cfg(all(test, feature = "sqlite"))]
+mod tests {
+    use super::*;
+    use crate::dataaccess::store_cases::store_cases;
+
+    async fn open(clock: Arc<dyn Clock>) -> SqliteService {
+        SqliteService::new(":memory:").await.unwrap().with_clock(clock)
+    }
+
+    store_cases!(open);
+}
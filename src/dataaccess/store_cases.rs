@@ -0,0 +1,681 @@
+//! Behaviour every `BbpStore` has to share, whatever its backend. The stores run
+//! these through `store_cases!` from their own test modules, so an in-process
+//! store that drifts from the SQL fails the same test as the SQL would.
+use chrono::{Duration, NaiveDateTime};
+
+use crate::dataaccess::bbp_store::BbpStore;
+use crate::dataaccess::clock::FixedClock;
+use crate::dataaccess::models::{AppealStatus, AuditAction, DecayPolicy, GuildSettings, HistoryFilter, JuryStatus, Page, PointKind, Standings, TimeWindow, User};
+use crate::dataaccess::store_error::{StoreError, UserRole};
+
+pub const GUILD: i64 = 1;
+pub const ALICE: i64 = 10;
+pub const BOB: i64 = 20;
+pub const CAROL: i64 = 30;
+/// Jurors, for the cases that need more voters than Carol
+pub const DAVE: i64 = 40;
+pub const ERIN: i64 = 50;
+/// A second guild the same people are in
+pub const OTHER_GUILD: i64 = 2;
+
+/// Where every store's clock starts.
+pub fn start() -> NaiveDateTime {
+    chrono::NaiveDate::from_ymd_opt(2024, 5, 1).unwrap().and_hms_opt(12, 0, 0).unwrap()
+}
+
+/// Runs every case below against the store `$open(clock).await` returns.
+macro_rules! store_cases {
+    ($open:path) => {
+        $crate::dataaccess::store_cases::store_cases!(@cases $open;
+            points_are_issued_at_the_clock_time,
+            issuing_updates_both_users,
+            half_life_decays_the_score_but_not_the_points,
+            gbps_never_decay,
+            expired_bbps_drop_out_of_the_ranking,
+            forgive_takes_the_most_recent_unforgiven_bbp,
+            unforgive_restores_the_most_recently_forgiven_bbp,
+            forgiving_by_id_only_picks_a_matching_bbp,
+            leaderboard_ties_share_a_rank,
+            leaderboard_window_only_counts_points_inside_it,
+            history_lists_newest_first,
+            history_filters_by_kind_and_issuer,
+            history_pages_points_with_the_same_timestamp_once,
+            points_stay_in_their_guild,
+            points_remember_the_message_that_announced_them,
+            claiming_legacy_rows_moves_guild_zero_into_the_guild,
+            ending_a_season_archives_its_points_and_standings,
+            merging_users_combines_their_points_and_season_standings,
+            merging_names_the_missing_user,
+            deactivated_users_leave_the_leaderboard,
+            jury_confirms_a_bbp_once_the_quorum_votes_for_it,
+            jury_vetoes_a_bbp_the_quorum_votes_against,
+            pending_bbps_expire_when_their_time_runs_out,
+            moderators_settle_appeals_right_away,
+            appeals_without_a_quorum_are_for_moderators_only,
+            the_community_quorum_settles_an_appeal,
+            edits_and_retractions_stop_when_the_grace_period_ends,
+            only_your_own_uncontested_points_are_editable,
+            retracting_takes_the_point_off_the_totals_and_logs_it,
+        );
+    };
+    (@cases $open:path; $($case:ident),* $(,)?) => {
+        $(
+            #[tokio::test]
+            async fn $case() {
+                let clock = ::std::sync::Arc::new($crate::dataaccess::clock::FixedClock::at($crate::dataaccess::store_cases::start()));
+                let db = $open(clock.clone()).await;
+                $crate::dataaccess::store_cases::$case(&db, &clock).await;
+            }
+        )*
+    };
+}
+pub(crate) use store_cases;
+
+async fn register(db: &dyn BbpStore, decay: DecayPolicy) {
+    for (discord_id, name) in [(ALICE, "Alice"), (BOB, "Bob"), (CAROL, "Carol")] {
+        db.add_user(GUILD, discord_id, &name.to_lowercase(), name).await.unwrap();
+    }
+    db.save_guild_settings(GuildSettings { guild_id: GUILD, decay, ..GuildSettings::default() }).await.unwrap();
+}
+
+async fn ranked(db: &dyn BbpStore, discord_id: i64) -> User {
+    db.get_user_by_discord_id_with_rank(GUILD, discord_id).await.unwrap().unwrap()
+}
+
+async fn live_leaderboard(db: &dyn BbpStore, window: TimeWindow) -> Vec<(i64, i64, f64)> {
+    db.get_leaderboard(GUILD, Standings::Live(window), Page { offset: 0, limit: 10 }).await.unwrap()
+        .into_iter()
+        .map(|u| (u.discord_id, u.rank, u.score))
+        .collect()
+}
+
+async fn register_jurors(db: &dyn BbpStore) {
+    for (discord_id, name) in [(DAVE, "Dave"), (ERIN, "Erin")] {
+        db.add_user(GUILD, discord_id, &name.to_lowercase(), name).await.unwrap();
+    }
+}
+
+fn everything() -> Page {
+    Page { offset: 0, limit: 50 }
+}
+
+pub async fn points_are_issued_at_the_clock_time(db: &dyn BbpStore, clock: &FixedClock) {
+    register(db, DecayPolicy::Off).await;
+    clock.advance(Duration::hours(5));
+
+    db.issue_point(GUILD, PointKind::Bbp, 1, ALICE, BOB, "late").await.unwrap();
+
+    let history = db.get_user_history(GUILD, BOB, HistoryFilter::default(), everything()).await.unwrap();
+    assert_eq!(history[0].timestamp, start() + Duration::hours(5));
+}
+
+pub async fn issuing_updates_both_users(db: &dyn BbpStore, _clock: &FixedClock) {
+    register(db, DecayPolicy::Off).await;
+
+    let first = db.issue_point(GUILD, PointKind::Bbp, 3, ALICE, BOB, "late").await.unwrap();
+    let second = db.issue_point(GUILD, PointKind::Gbp, 1, CAROL, BOB, "donuts").await.unwrap();
+
+    assert_eq!((first.point_id, second.point_id), (1, 1));
+    // `target` is the user as looked up, `ranked` already counts the point
+    assert_eq!((second.target.discord_id, second.target.gbp_total), (BOB, 0));
+    assert_eq!((second.ranked.points, second.ranked.bbp_total, second.ranked.gbp_total), (2, 3, 1));
+    assert_eq!((second.ranked.rank, second.ranked.score), (Some(1), Some(2.0)));
+    let (alice, carol) = (ranked(db, ALICE).await, ranked(db, CAROL).await);
+    assert_eq!((alice.bbps_issued, alice.gbps_issued), (1, 0));
+    assert_eq!((carol.bbps_issued, carol.gbps_issued), (0, 1));
+}
+
+pub async fn half_life_decays_the_score_but_not_the_points(db: &dyn BbpStore, clock: &FixedClock) {
+    register(db, DecayPolicy::HalfLife { days: 1 }).await;
+    db.issue_point(GUILD, PointKind::Bbp, 2, ALICE, BOB, "late").await.unwrap();
+    assert_eq!(ranked(db, BOB).await.score, Some(2.0));
+
+    clock.advance(Duration::days(1));
+    let bob = ranked(db, BOB).await;
+    assert_eq!((bob.points, bob.bbp_total, bob.score), (2, 2, Some(1.0)));
+
+    clock.advance(Duration::days(1));
+    assert_eq!(ranked(db, BOB).await.score, Some(0.5));
+}
+
+pub async fn gbps_never_decay(db: &dyn BbpStore, clock: &FixedClock) {
+    register(db, DecayPolicy::Expiry { days: 1 }).await;
+    db.issue_point(GUILD, PointKind::Bbp, 1, ALICE, BOB, "late").await.unwrap();
+    db.issue_point(GUILD, PointKind::Gbp, 1, ALICE, BOB, "donuts").await.unwrap();
+
+    clock.advance(Duration::days(1));
+
+    let bob = ranked(db, BOB).await;
+    assert_eq!((bob.points, bob.score), (0, Some(-1.0)));
+}
+
+pub async fn expired_bbps_drop_out_of_the_ranking(db: &dyn BbpStore, clock: &FixedClock) {
+    register(db, DecayPolicy::Expiry { days: 5 }).await;
+    db.issue_point(GUILD, PointKind::Bbp, 2, ALICE, BOB, "old news").await.unwrap();
+    clock.advance(Duration::days(3));
+    db.issue_point(GUILD, PointKind::Bbp, 1, ALICE, CAROL, "fresh").await.unwrap();
+
+    clock.advance(Duration::days(1));
+    assert_eq!((ranked(db, BOB).await.rank, ranked(db, CAROL).await.rank), (Some(1), Some(2)));
+
+    // Bob's bbps turn exactly 5 days old and he ties with Alice at nothing
+    clock.advance(Duration::days(1));
+    let (bob, carol) = (ranked(db, BOB).await, ranked(db, CAROL).await);
+    assert_eq!((bob.points, bob.score, bob.rank), (2, Some(0.0), Some(2)));
+    assert_eq!((carol.score, carol.rank), (Some(1.0), Some(1)));
+    assert_eq!(live_leaderboard(db, TimeWindow::default()).await, [(CAROL, 1, 1.0), (ALICE, 2, 0.0), (BOB, 2, 0.0)]);
+}
+
+pub async fn forgive_takes_the_most_recent_unforgiven_bbp(db: &dyn BbpStore, clock: &FixedClock) {
+    register(db, DecayPolicy::Off).await;
+    db.issue_point(GUILD, PointKind::Bbp, 1, ALICE, BOB, "late").await.unwrap();
+    clock.advance(Duration::hours(1));
+    db.issue_point(GUILD, PointKind::Bbp, 2, CAROL, BOB, "ate my lunch").await.unwrap();
+    clock.advance(Duration::hours(1));
+    db.issue_point(GUILD, PointKind::Bbp, 3, ALICE, BOB, "missed standup").await.unwrap();
+
+    let newest = db.forgive_bbp(GUILD, BOB, Some(ALICE), None, ALICE).await.unwrap();
+    let older = db.forgive_bbp(GUILD, BOB, Some(ALICE), None, ALICE).await.unwrap();
+    let none_left = db.forgive_bbp(GUILD, BOB, Some(ALICE), None, ALICE).await;
+
+    assert_eq!((newest.bbp_id, newest.forgiven, newest.forgiven_by_discord_id), (3, true, Some(ALICE)));
+    assert_eq!(older.bbp_id, 1);
+    assert!(matches!(none_left, Err(StoreError::NothingToForgive)));
+    let bob = ranked(db, BOB).await;
+    assert_eq!((bob.points, bob.bbp_total), (2, 2));
+}
+
+pub async fn unforgive_restores_the_most_recently_forgiven_bbp(db: &dyn BbpStore, clock: &FixedClock) {
+    register(db, DecayPolicy::Off).await;
+    db.issue_point(GUILD, PointKind::Bbp, 1, ALICE, BOB, "late").await.unwrap();
+    clock.advance(Duration::hours(1));
+    db.issue_point(GUILD, PointKind::Bbp, 2, ALICE, BOB, "missed standup").await.unwrap();
+    db.forgive_bbp(GUILD, BOB, None, Some(1), ALICE).await.unwrap();
+    db.forgive_bbp(GUILD, BOB, None, Some(2), ALICE).await.unwrap();
+    assert_eq!(ranked(db, BOB).await.points, 0);
+
+    let restored = db.unforgive_bbp(GUILD, BOB, Some(ALICE), None).await.unwrap();
+
+    assert_eq!((restored.bbp_id, restored.forgiven, restored.forgiven_by_discord_id), (2, false, None));
+    assert_eq!(ranked(db, BOB).await.points, 2);
+}
+
+pub async fn forgiving_by_id_only_picks_a_matching_bbp(db: &dyn BbpStore, _clock: &FixedClock) {
+    register(db, DecayPolicy::Off).await;
+    db.issue_point(GUILD, PointKind::Bbp, 1, ALICE, BOB, "late").await.unwrap();
+    db.issue_point(GUILD, PointKind::Bbp, 2, CAROL, BOB, "ate my lunch").await.unwrap();
+    db.issue_point(GUILD, PointKind::Bbp, 3, ALICE, CAROL, "missed standup").await.unwrap();
+
+    // Someone else's bbp, or another target's, doesn't match
+    let not_alices = db.forgive_bbp(GUILD, BOB, Some(ALICE), Some(2), ALICE).await;
+    let not_bobs = db.forgive_bbp(GUILD, BOB, None, Some(3), ALICE).await;
+    assert!(matches!(not_alices, Err(StoreError::NothingToForgive)), "{:?}", not_alices);
+    assert!(matches!(not_bobs, Err(StoreError::NothingToForgive)), "{:?}", not_bobs);
+
+    let forgiven = db.forgive_bbp(GUILD, BOB, None, Some(2), ALICE).await.unwrap();
+    let twice = db.forgive_bbp(GUILD, BOB, None, Some(2), ALICE).await;
+
+    assert_eq!((forgiven.bbp_id, forgiven.forgiven_by_discord_id), (2, Some(ALICE)));
+    assert!(matches!(twice, Err(StoreError::NothingToForgive)), "{:?}", twice);
+    assert_eq!(ranked(db, BOB).await.points, 1);
+    let still_counts = db.unforgive_bbp(GUILD, BOB, None, Some(1)).await;
+    assert!(matches!(still_counts, Err(StoreError::NothingToUnforgive)), "{:?}", still_counts);
+    let restored = db.unforgive_bbp(GUILD, BOB, Some(CAROL), Some(2)).await.unwrap();
+    assert_eq!((restored.bbp_id, restored.forgiven), (2, false));
+    assert_eq!(ranked(db, BOB).await.points, 3);
+}
+
+pub async fn leaderboard_ties_share_a_rank(db: &dyn BbpStore, _clock: &FixedClock) {
+    register(db, DecayPolicy::Off).await;
+    db.issue_point(GUILD, PointKind::Bbp, 2, ALICE, CAROL, "late").await.unwrap();
+    db.issue_point(GUILD, PointKind::Bbp, 2, ALICE, BOB, "late").await.unwrap();
+
+    assert_eq!(live_leaderboard(db, TimeWindow::default()).await, [(BOB, 1, 2.0), (CAROL, 1, 2.0), (ALICE, 3, 0.0)]);
+    assert_eq!((ranked(db, CAROL).await.rank, ranked(db, ALICE).await.rank), (Some(1), Some(3)));
+    let positions = (
+        db.get_leaderboard_position(GUILD, Standings::Live(TimeWindow::default()), CAROL).await.unwrap(),
+        db.get_leaderboard_position(GUILD, Standings::Live(TimeWindow::default()), ALICE).await.unwrap(),
+    );
+    assert_eq!(positions, (Some(1), Some(2)));
+}
+
+pub async fn leaderboard_window_only_counts_points_inside_it(db: &dyn BbpStore, clock: &FixedClock) {
+    register(db, DecayPolicy::Off).await;
+    db.issue_point(GUILD, PointKind::Bbp, 3, ALICE, BOB, "last week").await.unwrap();
+    clock.advance(Duration::days(2));
+    db.issue_point(GUILD, PointKind::Bbp, 1, ALICE, CAROL, "today").await.unwrap();
+    db.issue_point(GUILD, PointKind::Gbp, 1, CAROL, BOB, "today").await.unwrap();
+
+    // `from` is inclusive and `to` exclusive, down to the second
+    let window = TimeWindow { from: Some(start() + Duration::days(2)), to: Some(start() + Duration::days(2) + Duration::seconds(1)), all_seasons: false };
+    assert_eq!(live_leaderboard(db, window).await, [(CAROL, 1, 1.0), (ALICE, 2, 0.0), (BOB, 3, -1.0)]);
+
+    let before = TimeWindow { to: Some(start() + Duration::days(2)), ..TimeWindow::default() };
+    assert_eq!(live_leaderboard(db, before).await, [(BOB, 1, 3.0), (ALICE, 2, 0.0), (CAROL, 2, 0.0)]);
+    let timeline = db.get_point_timeline(GUILD, BOB, window).await.unwrap();
+    assert_eq!(timeline.iter().map(|r| r.kind).collect::<Vec<_>>(), [PointKind::Gbp]);
+}
+
+pub async fn history_lists_newest_first(db: &dyn BbpStore, clock: &FixedClock) {
+    register(db, DecayPolicy::Off).await;
+    db.issue_point(GUILD, PointKind::Bbp, 1, ALICE, BOB, "late").await.unwrap();
+    clock.advance(Duration::hours(1));
+    db.issue_point(GUILD, PointKind::Gbp, 1, CAROL, BOB, "donuts").await.unwrap();
+    clock.advance(Duration::hours(1));
+    db.issue_point(GUILD, PointKind::Bbp, 2, CAROL, BOB, "ate my lunch").await.unwrap();
+
+    let history = db.get_user_history(GUILD, BOB, HistoryFilter::default(), everything()).await.unwrap();
+    let second_page = db.get_user_history(GUILD, BOB, HistoryFilter::default(), Page { offset: 1, limit: 1 }).await.unwrap();
+
+    let listed = |records: &[crate::dataaccess::models::HistoryRecord]| records.iter().map(|r| (r.kind, r.point_id, r.description.clone())).collect::<Vec<_>>();
+    assert_eq!(listed(&history), [
+        (PointKind::Bbp, 2, "ate my lunch".to_string()),
+        (PointKind::Gbp, 1, "donuts".to_string()),
+        (PointKind::Bbp, 1, "late".to_string()),
+    ]);
+    assert_eq!(listed(&second_page), [(PointKind::Gbp, 1, "donuts".to_string())]);
+    assert_eq!(history[0].issuer_friendly_name, "Carol");
+}
+
+pub async fn history_filters_by_kind_and_issuer(db: &dyn BbpStore, clock: &FixedClock) {
+    register(db, DecayPolicy::Off).await;
+    db.issue_point(GUILD, PointKind::Bbp, 1, ALICE, BOB, "late").await.unwrap();
+    clock.advance(Duration::hours(1));
+    db.issue_point(GUILD, PointKind::Gbp, 1, CAROL, BOB, "donuts").await.unwrap();
+    clock.advance(Duration::hours(1));
+    db.issue_point(GUILD, PointKind::Bbp, 2, CAROL, BOB, "ate my lunch").await.unwrap();
+
+    let bbps = db.get_user_history(GUILD, BOB, HistoryFilter { kind: Some(PointKind::Bbp), ..HistoryFilter::default() }, everything()).await.unwrap();
+    let from_carol = db.get_user_history(GUILD, BOB, HistoryFilter { issuer_discord_id: Some(CAROL), ..HistoryFilter::default() }, everything()).await.unwrap();
+
+    assert_eq!(bbps.iter().map(|r| r.point_id).collect::<Vec<_>>(), [2, 1]);
+    assert_eq!(from_carol.iter().map(|r| r.kind).collect::<Vec<_>>(), [PointKind::Bbp, PointKind::Gbp]);
+}
+
+pub async fn history_pages_points_with_the_same_timestamp_once(db: &dyn BbpStore, _clock: &FixedClock) {
+    register(db, DecayPolicy::Off).await;
+    db.issue_point(GUILD, PointKind::Bbp, 1, ALICE, BOB, "late").await.unwrap();
+    db.issue_point(GUILD, PointKind::Gbp, 1, CAROL, BOB, "donuts").await.unwrap();
+    db.issue_point(GUILD, PointKind::Bbp, 1, CAROL, BOB, "ate my lunch").await.unwrap();
+    db.issue_point(GUILD, PointKind::Gbp, 1, ALICE, BOB, "coffee").await.unwrap();
+
+    let mut paged = Vec::new();
+    for offset in 0..4 {
+        paged.extend(db.get_user_history(GUILD, BOB, HistoryFilter::default(), Page { offset, limit: 1 }).await.unwrap());
+    }
+    let recent = db.get_rivalry(GUILD, ALICE, BOB, 10).await.unwrap().recent;
+
+    let listed = |records: &[crate::dataaccess::models::HistoryRecord]| records.iter().map(|r| (r.kind, r.point_id)).collect::<Vec<_>>();
+    assert_eq!(listed(&paged), [(PointKind::Bbp, 2), (PointKind::Bbp, 1), (PointKind::Gbp, 2), (PointKind::Gbp, 1)]);
+    assert_eq!(listed(&recent), [(PointKind::Bbp, 1), (PointKind::Gbp, 2)]);
+}
+
+pub async fn points_stay_in_their_guild(db: &dyn BbpStore, _clock: &FixedClock) {
+    register(db, DecayPolicy::Off).await;
+    for (discord_id, name) in [(ALICE, "Alice"), (BOB, "Bob")] {
+        db.add_user(OTHER_GUILD, discord_id, &name.to_lowercase(), name).await.unwrap();
+    }
+    db.issue_point(GUILD, PointKind::Bbp, 2, ALICE, BOB, "late").await.unwrap();
+    db.issue_point(OTHER_GUILD, PointKind::Bbp, 1, BOB, ALICE, "rude").await.unwrap();
+    db.issue_point(OTHER_GUILD, PointKind::Gbp, 1, ALICE, BOB, "donuts").await.unwrap();
+
+    let leaderboard = |guild_id| async move {
+        db.get_leaderboard(guild_id, Standings::Live(TimeWindow::default()), everything()).await.unwrap()
+            .into_iter()
+            .map(|u| (u.discord_id, u.rank, u.points))
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(leaderboard(GUILD).await, [(BOB, 1, 2), (ALICE, 2, 0), (CAROL, 2, 0)]);
+    assert_eq!(leaderboard(OTHER_GUILD).await, [(ALICE, 1, 1), (BOB, 2, -1)]);
+
+    let bob_here = db.get_user_by_discord_id_with_rank(GUILD, BOB).await.unwrap().unwrap();
+    let bob_there = db.get_user_by_discord_id_with_rank(OTHER_GUILD, BOB).await.unwrap().unwrap();
+    assert_eq!((bob_here.rank, bob_here.bbp_total, bob_here.gbp_total, bob_here.bbps_issued), (Some(1), 2, 0, 0));
+    assert_eq!((bob_there.rank, bob_there.bbp_total, bob_there.gbp_total, bob_there.bbps_issued), (Some(2), 0, 1, 1));
+
+    let history = db.get_user_history(GUILD, BOB, HistoryFilter::default(), everything()).await.unwrap();
+    let elsewhere = db.get_user_history(OTHER_GUILD, BOB, HistoryFilter::default(), everything()).await.unwrap();
+    assert_eq!(history.iter().map(|r| r.description.as_str()).collect::<Vec<_>>(), ["late"]);
+    assert_eq!(elsewhere.iter().map(|r| r.description.as_str()).collect::<Vec<_>>(), ["donuts"]);
+    assert!(db.get_user_by_discord_id(OTHER_GUILD, CAROL).await.unwrap().is_none());
+}
+
+pub async fn points_remember_the_message_that_announced_them(db: &dyn BbpStore, _clock: &FixedClock) {
+    register(db, DecayPolicy::Off).await;
+    db.issue_point(GUILD, PointKind::Bbp, 1, ALICE, BOB, "late").await.unwrap();
+    db.issue_point(GUILD, PointKind::Gbp, 1, ALICE, BOB, "donuts").await.unwrap();
+
+    db.set_point_message(GUILD, PointKind::Bbp, 1, 500, 600).await.unwrap();
+    // Another guild's point with the same ID is left alone
+    db.set_point_message(OTHER_GUILD, PointKind::Gbp, 1, 500, 700).await.unwrap();
+
+    let message = |point: crate::dataaccess::models::PointDetails| (point.channel_id, point.message_id);
+    assert_eq!(message(db.get_point(GUILD, PointKind::Bbp, 1).await.unwrap().unwrap()), (Some(500), Some(600)));
+    assert_eq!(message(db.get_point(GUILD, PointKind::Gbp, 1).await.unwrap().unwrap()), (None, None));
+}
+
+pub async fn claiming_legacy_rows_moves_guild_zero_into_the_guild(db: &dyn BbpStore, _clock: &FixedClock) {
+    for (discord_id, name) in [(ALICE, "Alice"), (BOB, "Bob")] {
+        db.add_user(0, discord_id, &name.to_lowercase(), name).await.unwrap();
+    }
+    db.issue_point(0, PointKind::Bbp, 2, ALICE, BOB, "before guilds").await.unwrap();
+    db.issue_point(0, PointKind::Gbp, 1, BOB, ALICE, "also before").await.unwrap();
+    db.add_user(OTHER_GUILD, CAROL, "carol", "Carol").await.unwrap();
+
+    let claimed = db.claim_legacy_rows(GUILD).await.unwrap();
+
+    assert_eq!(claimed, 2);
+    assert!(db.get_user_by_discord_id(0, BOB).await.unwrap().is_none());
+    let bob = db.get_user_by_discord_id_with_rank(GUILD, BOB).await.unwrap().unwrap();
+    assert_eq!((bob.guild_id, bob.points, bob.rank), (GUILD, 2, Some(1)));
+    let history = db.get_user_history(GUILD, ALICE, HistoryFilter::default(), everything()).await.unwrap();
+    assert_eq!(history.iter().map(|r| r.description.as_str()).collect::<Vec<_>>(), ["also before"]);
+    assert!(db.get_user_by_discord_id(OTHER_GUILD, CAROL).await.unwrap().is_some());
+    assert_eq!(db.claim_legacy_rows(GUILD).await.unwrap(), 0);
+}
+
+pub async fn ending_a_season_archives_its_points_and_standings(db: &dyn BbpStore, clock: &FixedClock) {
+    register(db, DecayPolicy::Off).await;
+    let season = db.start_season(GUILD).await.unwrap();
+    db.issue_point(GUILD, PointKind::Bbp, 2, ALICE, BOB, "late").await.unwrap();
+    db.issue_point(GUILD, PointKind::Gbp, 1, CAROL, ALICE, "donuts").await.unwrap();
+    db.issue_point(GUILD, PointKind::Bbp, 3, BOB, CAROL, "ate my lunch").await.unwrap();
+    db.set_user_active(GUILD, CAROL, false).await.unwrap();
+    clock.advance(Duration::days(1));
+
+    let closed = db.end_season(GUILD).await.unwrap();
+
+    assert_eq!((closed.season.number, closed.season.ended_at), (1, Some(start() + Duration::days(1))));
+    // Deactivated users aren't ranked, so they're left out of the snapshot
+    let standings = |users: &[crate::dataaccess::models::LeaderboardUser]| users.iter().map(|u| (u.discord_id, u.rank, u.points, u.bbp_total, u.gbp_total)).collect::<Vec<_>>();
+    assert_eq!(standings(&closed.standings), [(BOB, 1, 2, 2, 0), (ALICE, 2, -1, 0, 1)]);
+    assert_eq!(closed.champion().map(|u| u.discord_id), Some(ALICE));
+    assert_eq!(closed.most_bbps().map(|u| u.discord_id), Some(BOB));
+    let archived = db.get_leaderboard(GUILD, Standings::Season(1), everything()).await.unwrap();
+    assert_eq!(standings(&archived), standings(&closed.standings));
+
+    // The points carry the season and no longer count live
+    for (kind, point_id) in [(PointKind::Bbp, 1), (PointKind::Gbp, 1), (PointKind::Bbp, 2)] {
+        let point = db.get_point(GUILD, kind, point_id).await.unwrap().unwrap();
+        assert_eq!(point.season_id, Some(season.season_id));
+    }
+    assert!(db.get_recent_bbps(GUILD, BOB, None, false, 10).await.unwrap().is_empty());
+    let in_season = db.get_user_history(GUILD, BOB, HistoryFilter { season: Some(1), ..HistoryFilter::default() }, everything()).await.unwrap();
+    assert_eq!(in_season.len(), 1);
+    let bob = ranked(db, BOB).await;
+    assert_eq!((bob.points, bob.bbp_total, bob.rank), (0, 0, Some(1)));
+    assert_eq!(live_leaderboard(db, TimeWindow::default()).await, [(ALICE, 1, 0.0), (BOB, 1, 0.0)]);
+    assert!(matches!(db.end_season(GUILD).await, Err(StoreError::NoOpenSeason)));
+}
+
+pub async fn merging_users_combines_their_points_and_season_standings(db: &dyn BbpStore, _clock: &FixedClock) {
+    register(db, DecayPolicy::Off).await;
+    db.start_season(GUILD).await.unwrap();
+    db.issue_point(GUILD, PointKind::Bbp, 2, BOB, ALICE, "rude").await.unwrap();
+    db.issue_point(GUILD, PointKind::Bbp, 1, ALICE, BOB, "late").await.unwrap();
+    db.issue_point(GUILD, PointKind::Bbp, 1, ALICE, CAROL, "late too").await.unwrap();
+    db.end_season(GUILD).await.unwrap();
+    db.issue_point(GUILD, PointKind::Bbp, 3, ALICE, CAROL, "ate my lunch").await.unwrap();
+    db.issue_point(GUILD, PointKind::Gbp, 1, CAROL, ALICE, "donuts").await.unwrap();
+    db.issue_point(GUILD, PointKind::Bbp, 1, ALICE, BOB, "missed standup").await.unwrap();
+
+    // Carol turns out to be Bob's second account
+    let merged = db.merge_users(GUILD, CAROL, BOB).await.unwrap();
+
+    assert_eq!((merged.discord_id, merged.points, merged.bbp_total, merged.gbps_issued), (BOB, 4, 4, 1));
+    assert!(db.get_user_by_discord_id(GUILD, CAROL).await.unwrap().is_none());
+    assert_eq!(live_leaderboard(db, TimeWindow::default()).await, [(BOB, 1, 4.0), (ALICE, 2, -1.0)]);
+    let history = db.get_user_history(GUILD, BOB, HistoryFilter::default(), everything()).await.unwrap();
+    assert_eq!(history.len(), 4);
+
+    let season = db.get_leaderboard(GUILD, Standings::Season(1), everything()).await.unwrap();
+    let season: Vec<_> = season.iter().map(|u| (u.discord_id, u.rank, u.points, u.bbp_total, u.bbps_issued)).collect();
+    assert_eq!(season, [(ALICE, 1, 2, 2, 2), (BOB, 1, 2, 2, 1)]);
+}
+
+pub async fn merging_names_the_missing_user(db: &dyn BbpStore, _clock: &FixedClock) {
+    register(db, DecayPolicy::Off).await;
+
+    let from = db.merge_users(GUILD, 99, BOB).await;
+    let into = db.merge_users(GUILD, BOB, 99).await;
+
+    assert!(matches!(from, Err(StoreError::UserNotFound(UserRole::MergeFrom))), "{:?}", from);
+    assert!(matches!(into, Err(StoreError::UserNotFound(UserRole::MergeInto))), "{:?}", into);
+    assert!(db.get_user_by_discord_id(GUILD, BOB).await.unwrap().is_some());
+}
+
+pub async fn deactivated_users_leave_the_leaderboard(db: &dyn BbpStore, _clock: &FixedClock) {
+    register(db, DecayPolicy::Off).await;
+    db.issue_point(GUILD, PointKind::Bbp, 2, ALICE, BOB, "late").await.unwrap();
+    db.issue_point(GUILD, PointKind::Bbp, 1, ALICE, CAROL, "late").await.unwrap();
+
+    let bob = db.set_user_active(GUILD, BOB, false).await.unwrap();
+
+    assert!(!bob.active);
+    assert_eq!(live_leaderboard(db, TimeWindow::default()).await, [(CAROL, 1, 1.0), (ALICE, 2, 0.0)]);
+    let bob = ranked(db, BOB).await;
+    assert_eq!((bob.points, bob.rank), (2, None));
+    assert_eq!(db.get_leaderboard_position(GUILD, Standings::Live(TimeWindow::default()), BOB).await.unwrap(), None);
+    assert_eq!(db.get_user_history(GUILD, BOB, HistoryFilter::default(), everything()).await.unwrap().len(), 1);
+    let issued = db.issue_point(GUILD, PointKind::Bbp, 1, ALICE, BOB, "again").await;
+    assert!(matches!(issued, Err(StoreError::UserInactive(UserRole::Target))));
+
+    db.set_user_active(GUILD, BOB, true).await.unwrap();
+    assert_eq!(ranked(db, BOB).await.rank, Some(1));
+}
+
+pub async fn jury_confirms_a_bbp_once_the_quorum_votes_for_it(db: &dyn BbpStore, _clock: &FixedClock) {
+    register(db, DecayPolicy::Off).await;
+    register_jurors(db).await;
+    let pending = db.create_pending_bbp(GUILD, 2, ALICE, BOB, "late", 2, Duration::days(1)).await.unwrap();
+    assert_eq!((pending.status, pending.expires_at, pending.bbp_id), (JuryStatus::Pending, start() + Duration::days(1), None));
+
+    // The accuser and the accused don't get a say
+    for voter in [ALICE, BOB] {
+        let vote = db.vote_on_pending_bbp(pending.pending_id, voter, true).await;
+        assert!(matches!(vote, Err(StoreError::OwnAccusation)), "{:?}", vote);
+    }
+    // Changing your mind replaces your vote rather than adding to it
+    db.vote_on_pending_bbp(pending.pending_id, CAROL, false).await.unwrap();
+    let changed = db.vote_on_pending_bbp(pending.pending_id, CAROL, true).await.unwrap();
+    assert_eq!((changed.pending.votes_for, changed.pending.votes_against, changed.pending.status), (1, 0, JuryStatus::Pending));
+    assert!(changed.issued.is_none());
+    assert_eq!(ranked(db, BOB).await.points, 0);
+
+    let confirmed = db.vote_on_pending_bbp(pending.pending_id, DAVE, true).await.unwrap();
+
+    assert_eq!((confirmed.pending.votes_for, confirmed.pending.status, confirmed.pending.bbp_id), (2, JuryStatus::Confirmed, Some(1)));
+    let issued = confirmed.issued.unwrap();
+    assert_eq!((issued.point_id, issued.issuer.discord_id, issued.ranked.points), (1, ALICE, 2));
+    assert_eq!(db.get_point(GUILD, PointKind::Bbp, 1).await.unwrap().unwrap().description, "late");
+    let late = db.vote_on_pending_bbp(pending.pending_id, ERIN, false).await;
+    assert!(matches!(late, Err(StoreError::VotingClosed)), "{:?}", late);
+}
+
+pub async fn jury_vetoes_a_bbp_the_quorum_votes_against(db: &dyn BbpStore, _clock: &FixedClock) {
+    register(db, DecayPolicy::Off).await;
+    register_jurors(db).await;
+    let pending = db.create_pending_bbp(GUILD, 1, ALICE, BOB, "late", 2, Duration::days(1)).await.unwrap();
+
+    db.vote_on_pending_bbp(pending.pending_id, CAROL, false).await.unwrap();
+    db.vote_on_pending_bbp(pending.pending_id, DAVE, true).await.unwrap();
+    // Two against reaches the quorum and outvotes the one for
+    let vetoed = db.vote_on_pending_bbp(pending.pending_id, ERIN, false).await.unwrap();
+
+    assert_eq!((vetoed.pending.votes_for, vetoed.pending.votes_against), (1, 2));
+    assert_eq!((vetoed.pending.status, vetoed.pending.bbp_id), (JuryStatus::Vetoed, None));
+    assert!(vetoed.issued.is_none());
+    assert_eq!(ranked(db, BOB).await.points, 0);
+    assert!(db.get_point(GUILD, PointKind::Bbp, 1).await.unwrap().is_none());
+    let again = db.vote_on_pending_bbp(pending.pending_id, CAROL, true).await;
+    assert!(matches!(again, Err(StoreError::VotingClosed)), "{:?}", again);
+}
+
+pub async fn pending_bbps_expire_when_their_time_runs_out(db: &dyn BbpStore, clock: &FixedClock) {
+    register(db, DecayPolicy::Off).await;
+    let pending = db.create_pending_bbp(GUILD, 1, ALICE, BOB, "late", 2, Duration::hours(1)).await.unwrap();
+    db.set_pending_bbp_message(pending.pending_id, 500, 600).await.unwrap();
+    db.vote_on_pending_bbp(pending.pending_id, CAROL, true).await.unwrap();
+
+    clock.advance(Duration::minutes(59));
+    assert!(db.expire_pending_bbps().await.unwrap().is_empty());
+
+    clock.advance(Duration::minutes(1));
+    let late = db.vote_on_pending_bbp(pending.pending_id, CAROL, false).await;
+    let expired = db.expire_pending_bbps().await.unwrap();
+
+    assert!(matches!(late, Err(StoreError::VotingClosed)), "{:?}", late);
+    let expired: Vec<_> = expired.iter().map(|p| (p.pending_id, p.status, p.votes_for, p.channel_id, p.message_id)).collect();
+    assert_eq!(expired, [(pending.pending_id, JuryStatus::Expired, 1, Some(500), Some(600))]);
+    assert!(db.expire_pending_bbps().await.unwrap().is_empty());
+    assert_eq!(ranked(db, BOB).await.points, 0);
+}
+
+pub async fn moderators_settle_appeals_right_away(db: &dyn BbpStore, clock: &FixedClock) {
+    register(db, DecayPolicy::Off).await;
+    register_jurors(db).await;
+    db.issue_point(GUILD, PointKind::Bbp, 2, ALICE, BOB, "late").await.unwrap();
+    db.issue_point(GUILD, PointKind::Bbp, 1, ALICE, BOB, "missed standup").await.unwrap();
+    let not_theirs = db.open_appeal(GUILD, 1, CAROL, "it wasn't me", 2).await;
+    assert!(matches!(not_theirs, Err(StoreError::NotAppealable)), "{:?}", not_theirs);
+
+    let first = db.open_appeal(GUILD, 1, BOB, "the bus was late", 2).await.unwrap();
+    let second = db.open_appeal(GUILD, 2, BOB, "nobody told me", 2).await.unwrap();
+    assert_eq!((first.status, first.quorum, first.opened_at), (AppealStatus::Open, 2, start()));
+    // A moderator doesn't have to wait for the community vote, or count towards it
+    db.vote_on_appeal(first.appeal_id, DAVE, false).await.unwrap();
+    clock.advance(Duration::hours(1));
+    let own = db.resolve_appeal(first.appeal_id, ALICE, false).await;
+    let overturned = db.resolve_appeal(first.appeal_id, CAROL, true).await.unwrap();
+    let upheld = db.resolve_appeal(second.appeal_id, CAROL, false).await.unwrap();
+
+    assert!(matches!(own, Err(StoreError::OwnAccusation)), "{:?}", own);
+    assert_eq!((overturned.status, overturned.resolved_by_discord_id, overturned.resolved_at), (AppealStatus::Overturned, Some(CAROL), Some(start() + Duration::hours(1))));
+    assert_eq!((overturned.votes_overturn, overturned.votes_uphold), (0, 1));
+    assert!(overturned.bbp.forgiven);
+    assert_eq!((upheld.status, upheld.bbp.forgiven), (AppealStatus::Upheld, false));
+    assert_eq!(ranked(db, BOB).await.points, 1);
+    let again = db.resolve_appeal(first.appeal_id, DAVE, false).await;
+    let vote = db.vote_on_appeal(second.appeal_id, ERIN, true).await;
+    let reopened = db.open_appeal(GUILD, 2, BOB, "please", 2).await;
+    assert!(matches!(again, Err(StoreError::VotingClosed)), "{:?}", again);
+    assert!(matches!(vote, Err(StoreError::VotingClosed)), "{:?}", vote);
+    assert!(matches!(reopened, Err(StoreError::NotAppealable)), "{:?}", reopened);
+}
+
+pub async fn appeals_without_a_quorum_are_for_moderators_only(db: &dyn BbpStore, _clock: &FixedClock) {
+    register(db, DecayPolicy::Off).await;
+    db.issue_point(GUILD, PointKind::Bbp, 1, ALICE, BOB, "late").await.unwrap();
+    let appeal = db.open_appeal(GUILD, 1, BOB, "the bus was late", 0).await.unwrap();
+
+    let vote = db.vote_on_appeal(appeal.appeal_id, CAROL, true).await;
+
+    assert!(matches!(vote, Err(StoreError::NoCommunityVote)), "{:?}", vote);
+    let appeal = db.resolve_appeal(appeal.appeal_id, CAROL, true).await.unwrap();
+    assert_eq!((appeal.status, appeal.votes_overturn), (AppealStatus::Overturned, 0));
+    assert_eq!(ranked(db, BOB).await.points, 0);
+}
+
+pub async fn the_community_quorum_settles_an_appeal(db: &dyn BbpStore, _clock: &FixedClock) {
+    register(db, DecayPolicy::Off).await;
+    register_jurors(db).await;
+    db.issue_point(GUILD, PointKind::Bbp, 2, ALICE, BOB, "late").await.unwrap();
+    db.issue_point(GUILD, PointKind::Bbp, 1, ALICE, BOB, "missed standup").await.unwrap();
+    let first = db.open_appeal(GUILD, 1, BOB, "the bus was late", 2).await.unwrap();
+    let second = db.open_appeal(GUILD, 2, BOB, "nobody told me", 2).await.unwrap();
+
+    let own = db.vote_on_appeal(first.appeal_id, BOB, true).await;
+    let open = db.vote_on_appeal(first.appeal_id, CAROL, true).await.unwrap();
+    db.vote_on_appeal(first.appeal_id, DAVE, false).await.unwrap();
+    let overturned = db.vote_on_appeal(first.appeal_id, ERIN, true).await.unwrap();
+    db.vote_on_appeal(second.appeal_id, CAROL, false).await.unwrap();
+    let upheld = db.vote_on_appeal(second.appeal_id, DAVE, false).await.unwrap();
+
+    assert!(matches!(own, Err(StoreError::OwnAccusation)), "{:?}", own);
+    assert_eq!((open.status, open.votes_overturn, open.votes_uphold), (AppealStatus::Open, 1, 0));
+    assert_eq!((overturned.status, overturned.votes_overturn, overturned.votes_uphold), (AppealStatus::Overturned, 2, 1));
+    assert_eq!((overturned.resolved_by_discord_id, overturned.bbp.forgiven), (None, true));
+    assert_eq!((upheld.status, upheld.votes_uphold, upheld.bbp.forgiven), (AppealStatus::Upheld, 2, false));
+    assert_eq!(ranked(db, BOB).await.points, 1);
+}
+
+pub async fn edits_and_retractions_stop_when_the_grace_period_ends(db: &dyn BbpStore, clock: &FixedClock) {
+    register(db, DecayPolicy::Off).await;
+    let grace = Duration::minutes(10);
+    db.issue_point(GUILD, PointKind::Gbp, 1, ALICE, BOB, "donuts").await.unwrap();
+
+    clock.advance(Duration::minutes(10) - Duration::seconds(1));
+    let edited = db.edit_point(GUILD, PointKind::Gbp, 1, ALICE, "bagels", grace).await.unwrap();
+
+    assert_eq!((edited.action, edited.old_description.as_str(), edited.new_description.as_deref()), (AuditAction::Edit, "donuts", Some("bagels")));
+    assert_eq!(edited.at, start() + Duration::minutes(10) - Duration::seconds(1));
+    // The grace period ends exactly 10 minutes after the point was given
+    clock.advance(Duration::seconds(1));
+    let edit = db.edit_point(GUILD, PointKind::Gbp, 1, ALICE, "croissants", grace).await;
+    let retract = db.retract_point(GUILD, PointKind::Gbp, 1, ALICE, grace).await;
+    assert!(matches!(edit, Err(StoreError::GracePeriodOver { minutes: 10 })), "{:?}", edit);
+    assert!(matches!(retract, Err(StoreError::GracePeriodOver { minutes: 10 })), "{:?}", retract);
+
+    // No grace period at all turns both off
+    db.issue_point(GUILD, PointKind::Gbp, 1, ALICE, BOB, "coffee").await.unwrap();
+    let right_away = db.retract_point(GUILD, PointKind::Gbp, 2, ALICE, Duration::zero()).await;
+    assert!(matches!(right_away, Err(StoreError::GracePeriodOver { minutes: 0 })), "{:?}", right_away);
+    assert_eq!(db.get_point(GUILD, PointKind::Gbp, 1).await.unwrap().unwrap().description, "bagels");
+    assert_eq!(db.get_audit_log(GUILD, everything()).await.unwrap().len(), 1);
+}
+
+pub async fn only_your_own_uncontested_points_are_editable(db: &dyn BbpStore, _clock: &FixedClock) {
+    register(db, DecayPolicy::Off).await;
+    let grace = Duration::minutes(10);
+    db.start_season(GUILD).await.unwrap();
+    db.issue_point(GUILD, PointKind::Bbp, 1, ALICE, BOB, "archived").await.unwrap();
+    db.end_season(GUILD).await.unwrap();
+    db.issue_point(GUILD, PointKind::Bbp, 1, ALICE, BOB, "appealed").await.unwrap();
+    db.open_appeal(GUILD, 2, BOB, "the bus was late", 0).await.unwrap();
+    let pending = db.create_pending_bbp(GUILD, 1, ALICE, BOB, "before the jury", 1, Duration::days(1)).await.unwrap();
+    db.vote_on_pending_bbp(pending.pending_id, CAROL, true).await.unwrap();
+    // Like the bbp for giving yourself a gbp
+    db.issue_point(GUILD, PointKind::Bbp, 1, ALICE, ALICE, "self").await.unwrap();
+    db.issue_point(GUILD, PointKind::Bbp, 1, ALICE, BOB, "plain").await.unwrap();
+
+    for bbp_id in 1..=4 {
+        let edit = db.edit_point(GUILD, PointKind::Bbp, bbp_id, ALICE, "changed", grace).await;
+        let retract = db.retract_point(GUILD, PointKind::Bbp, bbp_id, ALICE, grace).await;
+        assert!(matches!(edit, Err(StoreError::NotEditable)), "B{}: {:?}", bbp_id, edit);
+        assert!(matches!(retract, Err(StoreError::NotEditable)), "B{}: {:?}", bbp_id, retract);
+    }
+    let not_the_issuer = db.edit_point(GUILD, PointKind::Bbp, 5, CAROL, "changed", grace).await;
+    let elsewhere = db.edit_point(OTHER_GUILD, PointKind::Bbp, 5, ALICE, "changed", grace).await;
+    let missing = db.retract_point(GUILD, PointKind::Gbp, 1, ALICE, grace).await;
+    assert!(matches!(not_the_issuer, Err(StoreError::NotEditable)), "{:?}", not_the_issuer);
+    assert!(matches!(elsewhere, Err(StoreError::PointNotFound)), "{:?}", elsewhere);
+    assert!(matches!(missing, Err(StoreError::PointNotFound)), "{:?}", missing);
+    assert!(db.edit_point(GUILD, PointKind::Bbp, 5, ALICE, "changed", grace).await.is_ok());
+    assert_eq!(db.get_audit_log(GUILD, everything()).await.unwrap().len(), 1);
+}
+
+pub async fn retracting_takes_the_point_off_the_totals_and_logs_it(db: &dyn BbpStore, clock: &FixedClock) {
+    register(db, DecayPolicy::Off).await;
+    let grace = Duration::minutes(10);
+    db.issue_point(GUILD, PointKind::Bbp, 2, ALICE, BOB, "late").await.unwrap();
+    db.issue_point(GUILD, PointKind::Bbp, 1, CAROL, BOB, "ate my lunch").await.unwrap();
+    db.edit_point(GUILD, PointKind::Bbp, 2, CAROL, "ate my sandwich", grace).await.unwrap();
+    clock.advance(Duration::minutes(1));
+
+    let retracted = db.retract_point(GUILD, PointKind::Bbp, 1, ALICE, grace).await.unwrap();
+
+    assert_eq!((retracted.action, retracted.point_id, retracted.value, retracted.target_discord_id), (AuditAction::Retract, 1, 2, BOB));
+    assert_eq!((retracted.old_description.as_str(), retracted.new_description, retracted.at), ("late", None, start() + Duration::minutes(1)));
+    assert!(db.get_point(GUILD, PointKind::Bbp, 1).await.unwrap().is_none());
+    let (alice, bob) = (ranked(db, ALICE).await, ranked(db, BOB).await);
+    assert_eq!((bob.points, bob.bbp_total, alice.bbps_issued), (1, 1, 0));
+    assert_eq!(live_leaderboard(db, TimeWindow::default()).await, [(BOB, 1, 1.0), (ALICE, 2, 0.0), (CAROL, 2, 0.0)]);
+    let history = db.get_user_history(GUILD, BOB, HistoryFilter::default(), everything()).await.unwrap();
+    assert_eq!(history.iter().map(|r| r.point_id).collect::<Vec<_>>(), [2]);
+
+    let log = db.get_audit_log(GUILD, everything()).await.unwrap();
+    assert_eq!(log.iter().map(|e| (e.action, e.point_id, e.actor_discord_id)).collect::<Vec<_>>(), [(AuditAction::Retract, 1, ALICE), (AuditAction::Edit, 2, CAROL)]);
+    assert!(db.get_audit_log(OTHER_GUILD, everything()).await.unwrap().is_empty());
+    // The retracted ID isn't handed out again
+    assert_eq!(db.issue_point(GUILD, PointKind::Bbp, 1, ALICE, BOB, "again").await.unwrap().point_id, 3);
+}
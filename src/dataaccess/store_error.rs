@@ -0,0 +1,143 @@
+use std::fmt;
+
+use bb8_postgres::bb8::RunError;
+use log::error;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UserRole {
+    Issuer,
+    Target,
+    /// The user `merge_users` merges away
+    MergeFrom,
+    /// The user `merge_users` keeps
+    MergeInto,
+}
+
+/// What a `StoreError::Duplicate` ran into.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Existing {
+    User,
+    RunningSeason,
+    JoinRequest,
+    /// A unique constraint the store didn't expect to hit
+    Row,
+}
+
+/// Errors returned by every `BbpStore`. The variants are what commands branch on,
+/// the strings are details for the logs.
+#[derive(Debug)]
+pub enum StoreError {
+    UserNotFound(UserRole),
+    /// The user was deactivated and can't take part until reactivated
+    UserInactive(UserRole),
+    /// Only users without any bbps or gbps can be removed outright
+    UserHasHistory,
+    /// The pending bbp or appeal was already settled, expired or doesn't exist
+    VotingClosed,
+    /// The issuer and the target of a pending bbp or an appeal don't get a say in it
+    OwnAccusation,
+    /// No bbp with this ID in the guild
+    BbpNotFound,
+    /// No bbp or gbp with this ID in the guild
+    PointNotFound,
+    /// Only the issuer can edit or retract a bbp/gbp, and not once a jury or an
+    /// appeal was involved or its season ended
+    NotEditable,
+    /// The guild's grace period of `minutes` for edits and retractions is over
+    GracePeriodOver { minutes: i32 },
+    /// Only the target can appeal a bbp, once, while it still counts
+    NotAppealable,
+    /// The guild has no jury, so only moderators decide appeals
+    NoCommunityVote,
+    /// A unique row already exists, e.g. registering the same user twice
+    Duplicate(Existing),
+    NothingToForgive,
+    /// No forgiven bbp to take back. Bbps overturned on appeal stay forgiven
+    NothingToUnforgive,
+    /// A bbp/gbp worth more than the issuer may give, which is at most `max`
+    ValueNotAllowed { max: i32 },
+    /// A bbp/gbp worth nothing or less
+    ValueTooLow,
+    /// `end_season` without a running season
+    NoOpenSeason,
+    /// No season with this number
+    SeasonNotFound(i32),
+    /// No connection could be handed out
+    Pool(String),
+    Query(String),
+    /// The data broke an assumption, e.g. several rows where one was expected
+    Integrity(String),
+}
+
+impl fmt::Display for StoreError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StoreError::UserNotFound(UserRole::Issuer) => write!(f, "Issuing user not found"),
+            StoreError::UserNotFound(UserRole::Target) => write!(f, "Target user not found"),
+            StoreError::UserNotFound(UserRole::MergeFrom) => write!(f, "User to merge not found"),
+            StoreError::UserNotFound(UserRole::MergeInto) => write!(f, "User to merge into not found"),
+            StoreError::UserInactive(UserRole::Issuer) => write!(f, "Issuing user is deactivated"),
+            StoreError::UserInactive(_) => write!(f, "Target user is deactivated"),
+            StoreError::UserHasHistory => write!(f, "User has bbps or gbps"),
+            StoreError::VotingClosed => write!(f, "Voting on the pending bbp is closed"),
+            StoreError::OwnAccusation => write!(f, "Vote on a pending bbp or appeal the user is part of"),
+            StoreError::BbpNotFound => write!(f, "Bbp not found"),
+            StoreError::PointNotFound => write!(f, "Bbp or gbp not found"),
+            StoreError::NotEditable => write!(f, "Bbp or gbp can't be changed by the user"),
+            StoreError::GracePeriodOver { minutes } => write!(f, "Grace period of {} minutes is over", minutes),
+            StoreError::NotAppealable => write!(f, "Bbp can't be appealed"),
+            StoreError::NoCommunityVote => write!(f, "Appeals are decided by moderators only"),
+            StoreError::Duplicate(what) => write!(f, "Duplicate: {:?}", what),
+            StoreError::NothingToForgive => write!(f, "There is nothing to forgive"),
+            StoreError::NothingToUnforgive => write!(f, "There is nothing to unforgive"),
+            StoreError::ValueNotAllowed { max } => write!(f, "Value above the issuer's limit of {}", max),
+            StoreError::ValueTooLow => write!(f, "Value below 1"),
+            StoreError::NoOpenSeason => write!(f, "No season is running"),
+            StoreError::SeasonNotFound(number) => write!(f, "Season {} not found", number),
+            StoreError::Pool(e) => write!(f, "Connection pool error: {}", e),
+            StoreError::Query(e) => write!(f, "Query error: {}", e),
+            StoreError::Integrity(e) => write!(f, "Integrity error: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for StoreError {}
+
+impl From<tokio_postgres::Error> for StoreError {
+    fn from(e: tokio_postgres::Error) -> Self {
+        // The driver's text names tables and constraints, it only goes to the logs
+        if e.code() == Some(&tokio_postgres::error::SqlState::UNIQUE_VIOLATION) {
+            error!("Unique constraint violated: {}", e);
+            return StoreError::Duplicate(Existing::Row);
+        }
+        StoreError::Query(e.to_string())
+    }
+}
+
+impl From<RunError<tokio_postgres::Error>> for StoreError {
+    fn from(e: RunError<tokio_postgres::Error>) -> Self {
+        match e {
+            RunError::User(e) => StoreError::Pool(e.to_string()),
+            RunError::TimedOut => StoreError::Pool("Timed out waiting for a connection".to_string()),
+        }
+    }
+}
+
+impl From<tokio::task::JoinError> for StoreError {
+    fn from(e: tokio::task::JoinError) -> Self {
+        StoreError::Pool(e.to_string())
+    }
+}
+
+#[cfg(feature = "sqlite")]
+impl From<rusqlite::Error> for StoreError {
+    fn from(e: rusqlite::Error) -> Self {
+        match e {
+            rusqlite::Error::SqliteFailure(err, _) if err.extended_code == rusqlite::ffi::SQLITE_CONSTRAINT_UNIQUE => {
+                error!("Unique constraint violated: {}", e);
+                StoreError::Duplicate(Existing::Row)
+            }
+            _ => StoreError::Query(e.to_string()),
+        }
+    }
+}
@@ -0,0 +1,52 @@
+use log::{error, info};
+use poise::serenity_prelude as serenity;
+
+use crate::{Data, Error};
+use crate::commands::bbp_commands::display_name;
+use crate::commands::{appeal_commands, jury_commands, registration_commands};
+use crate::commands::registration_commands::JoinOutcome;
+
+/// Gateway events the bot reacts to outside of commands.
+pub async fn event_handler(
+    ctx: &serenity::Context,
+    event: &serenity::FullEvent,
+    framework: poise::FrameworkContext<'_, Data, Error>,
+    data: &Data,
+) -> Result<(), Error> {
+    match event {
+        serenity::FullEvent::InteractionCreate { interaction: serenity::Interaction::Component(press) } => {
+            registration_commands::handle_button(ctx, press, data, &framework.options().owners).await?;
+            jury_commands::handle_button(ctx, press, data).await?;
+            appeal_commands::handle_button(ctx, press, data, &framework.options().owners).await?;
+        }
+        serenity::FullEvent::GuildMemberAddition { new_member } if !new_member.user.bot => {
+            let guild_id = new_member.guild_id.get() as i64;
+            let user = &new_member.user;
+            let db = data.db.as_ref();
+            match registration_commands::member_joined(db, guild_id, user.id.get() as i64, &user.name, new_member.display_name()).await {
+                Ok(Some(JoinOutcome::Registered(added))) => info!("Enrolled {} in guild {} as they joined", display_name(&added), guild_id),
+                Ok(Some(JoinOutcome::Pending { request, channel_id })) => {
+                    if let Err(e) = registration_commands::post_request(ctx, db, &request, channel_id).await {
+                        error!("Dropping the request to join of {} in guild {} failed: {}", user.name, guild_id, e);
+                    }
+                }
+                Ok(Some(JoinOutcome::Refused(_)) | None) => {}
+                Err(e) => error!("Handling {} joining guild {} failed: {}", user.name, guild_id, e),
+            }
+        }
+        // Username changes reach bots through the member updates of every guild they
+        // share, UserUpdate is only ever sent for the bot's own account. The event
+        // carries the member's user whether or not the member was cached
+        serenity::FullEvent::GuildMemberUpdate { event, .. } => sync_username(data, &event.user).await,
+        _ => {}
+    }
+
+    Ok(())
+}
+
+/// Usernames are only stored for display, so a failed update is just logged.
+async fn sync_username(data: &Data, user: &serenity::User) {
+    if let Err(e) = data.db.update_discord_username(user.id.get() as i64, &user.name).await {
+        error!("Updating the username of {} failed: {}", user.id, e);
+    }
+}
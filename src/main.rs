@@ -1,22 +1,44 @@
+mod charts;
 mod commands;
 mod dataaccess;
+mod events;
 
 use poise::serenity_prelude as serenity;
 use std::env;
 use std::sync::Arc;
-use poise::futures_util::lock::Mutex;
-use serenity::prelude::TypeMapKey;
+use log::{info, warn};
+use crate::dataaccess::bbp_store::BbpStore;
+use crate::dataaccess::clock::{Clock, SystemClock};
+use crate::dataaccess::memory_store::MemoryStore;
 use crate::dataaccess::postgres_service::PostgresService;
 
-struct PostgresServiceContainer;
-
-impl TypeMapKey for PostgresServiceContainer{
-    type Value = Arc<Mutex<PostgresService>>;
-}
 type Error = Box<dyn std::error::Error + Send + Sync>;
 type Context<'a> = poise::Context<'a, Data, Error>;
 pub struct Data {
-    db: Arc<Mutex<PostgresService>>,
+    // Every store handles its own pooling/locking, so commands share it directly
+    db: Arc<dyn BbpStore>,
+}
+
+async fn connect_store(database_url: &str, clock: Arc<dyn Clock>) -> Result<Arc<dyn BbpStore>, Error> {
+    if database_url.starts_with("memory://") {
+        warn!("Using the in-memory store, nothing will be persisted.");
+        return Ok(Arc::new(MemoryStore::new().with_clock(clock)));
+    }
+
+    if let Some(path) = database_url.strip_prefix("sqlite://") {
+        #[cfg(feature = "sqlite")]
+        {
+            let db = crate::dataaccess::sqlite_service::SqliteService::new(path).await?;
+            return Ok(Arc::new(db.with_clock(clock)));
+        }
+        #[cfg(not(feature = "sqlite"))]
+        {
+            return Err(format!("Can't open '{}', this build doesn't include the sqlite feature.", path).into());
+        }
+    }
+
+    let db = PostgresService::new(database_url).await?;
+    Ok(Arc::new(db.with_clock(clock)))
 }
 
 #[tokio::main]
@@ -24,8 +46,25 @@ async fn main() {
     env_logger::init();
     dotenv::dotenv().ok();
     let token = env::var("DISCORD_TOKEN").expect("Expected a token in the environment");
-    let constr = env::var("PG_CONNECTION_STRING").expect("Expected a connection string for postgres.");
-    let intents = serenity::GatewayIntents::non_privileged();
+    // DATABASE_URL takes precedence, PG_CONNECTION_STRING is kept for existing deployments
+    let database_url = env::var("DATABASE_URL")
+        .or_else(|_| env::var("PG_CONNECTION_STRING"))
+        .expect("Expected a DATABASE_URL or a connection string for postgres.");
+    // Guild that owns the rows created before the bot was guild aware
+    let legacy_guild_id = env::var("LEGACY_GUILD_ID").ok()
+        .map(|id| id.parse::<i64>().expect("LEGACY_GUILD_ID must be a Discord guild id"));
+    // Member events drive username sync and enrolling new members. They need the
+    // GUILD_MEMBERS intent, which is privileged and has to be enabled for the bot
+    // in the developer portal, so it's only requested when asked for
+    let sync_members = env::var("SYNC_MEMBERS").ok()
+        .map(|sync| sync.parse::<bool>().expect("SYNC_MEMBERS must be true or false"))
+        .unwrap_or(false);
+    let intents = if sync_members {
+        serenity::GatewayIntents::non_privileged() | serenity::GatewayIntents::GUILD_MEMBERS
+    } else {
+        info!("SYNC_MEMBERS is off, usernames won't be kept in sync and new members won't be enrolled.");
+        serenity::GatewayIntents::non_privileged()
+    };
 
     let framework = poise::Framework::builder()
         .options(poise::FrameworkOptions {
@@ -33,19 +72,41 @@ async fn main() {
                 commands::bbp_commands::bbp_add_command(),
                 commands::bbp_commands::gbp_add_command(),
                 commands::bbp_commands::add_user_command(),
+                commands::user_commands::user_command(),
+                commands::registration_commands::join_command(),
                 commands::bbp_commands::bbp_forgive_command(),
+                commands::bbp_commands::bbp_unforgive_command(),
+                commands::edit_commands::point_edit_command(),
+                commands::edit_commands::point_retract_command(),
+                commands::appeal_commands::appeal_command(),
                 commands::bbp_commands::leaderboard_command(),
                 commands::bbp_commands::history_command(),
+                commands::show_commands::show_command(),
+                commands::edit_commands::audit_command(),
+                commands::profile_commands::profile_command(),
+                commands::profile_commands::versus_command(),
+                commands::chart_commands::chart_command(),
+                commands::season_commands::season_command(),
+                commands::settings_commands::settings_command(),
             ],
             initialize_owners: true,
+            pre_command: |ctx| Box::pin(commands::registration_commands::auto_register_author(ctx)),
+            event_handler: |ctx, event, framework, data| Box::pin(events::event_handler(ctx, event, framework, data)),
             ..Default::default()
         })
-        .setup(|ctx, _ready, framework| {
+        .setup(move |ctx, _ready, framework| {
             Box::pin(async move {
-                let db = PostgresService::new(&constr).await
+                let db = connect_store(&database_url, Arc::new(SystemClock)).await
                     .expect("Couldn't build database connection");
+                if let Some(guild_id) = legacy_guild_id {
+                    let claimed = db.claim_legacy_rows(guild_id).await?;
+                    if claimed > 0 {
+                        info!("Moved {} pre-guild users into guild {}", claimed, guild_id);
+                    }
+                }
+                tokio::spawn(commands::jury_commands::sweep_expired(ctx.http.clone(), db.clone()));
                 let data = Data {
-                    db: Arc::new(Mutex::new(db)),
+                    db,
                 };
                 poise::builtins::register_globally(ctx, &framework.options().commands).await?;
                 Ok(data)